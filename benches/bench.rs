@@ -1,6 +1,11 @@
 #![allow(missing_docs)]
 
-use alloy_trie::nodes::encode_path_leaf;
+use alloy_primitives::{keccak256, B256};
+use alloy_trie::{
+    nibbles::unpack_b256,
+    nodes::encode_path_leaf,
+    root::{indexed_trie_root, ordered_trie_root},
+};
 use criterion::{
     criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup, Criterion,
 };
@@ -23,6 +28,117 @@ pub fn nibbles_path_encoding(c: &mut Criterion) {
     }
 }
 
+/// Benchmarks the root computation of tries keyed by the RLP encoded item indexes.
+pub fn indexed_trie_roots(c: &mut Criterion) {
+    let lengths = [100usize, 1000, 5000];
+
+    let mut g = group(c, "indexed_trie_root");
+    for len in lengths {
+        let items = (0..len as u64).map(|i| vec![i as u8; 100]).collect::<Vec<_>>();
+        g.throughput(criterion::Throughput::Elements(len as u64));
+        g.bench_function(criterion::BenchmarkId::new("hash_builder", len), |b| {
+            b.iter(|| ordered_trie_root(black_box(&items)))
+        });
+        g.bench_function(criterion::BenchmarkId::new("indexed", len), |b| {
+            b.iter(|| indexed_trie_root(black_box(&items)))
+        });
+    }
+}
+
+/// Benchmarks unpacking hashed keys into nibbles.
+pub fn hashed_key_unpacking(c: &mut Criterion) {
+    let keys = hashed_keys(1000);
+
+    let mut g = group(c, "unpack_b256");
+    g.throughput(criterion::Throughput::Elements(keys.len() as u64));
+    g.bench_function("nybbles", |b| {
+        b.iter(|| keys.iter().map(|key| Nibbles::unpack(black_box(key)).len()).sum::<usize>())
+    });
+    g.bench_function("swar", |b| {
+        b.iter(|| keys.iter().map(|key| unpack_b256(black_box(key)).len()).sum::<usize>())
+    });
+}
+
+/// Benchmarks building many small tries, like the storage tries of the accounts, with new buffers
+/// for every trie against reusing the buffers of the previous trie through a [`BufferPool`].
+///
+/// [`BufferPool`]: alloy_trie::hash_builder::BufferPool
+pub fn hash_builder_allocator(c: &mut Criterion) {
+    use alloy_trie::{hash_builder::BufferPool, HashBuilder};
+
+    let tries = 1000usize;
+    let mut keys = hashed_keys(8).iter().map(Nibbles::unpack).collect::<Vec<_>>();
+    keys.sort_unstable();
+    let value = [0x01; 32];
+
+    let mut g = group(c, "hash_builder_allocator");
+    g.throughput(criterion::Throughput::Elements((tries * keys.len()) as u64));
+    g.bench_function("default", |b| {
+        b.iter(|| {
+            let mut root = B256::ZERO;
+            for _ in 0..tries {
+                let mut hb = HashBuilder::default();
+                for key in black_box(&keys) {
+                    hb.add_leaf(key.clone(), &value, false);
+                }
+                root = hb.root();
+            }
+            root
+        })
+    });
+    g.bench_function("buffer_pool", |b| {
+        b.iter(|| {
+            let mut root = B256::ZERO;
+            let mut pool = BufferPool::new(2, 128);
+            for _ in 0..tries {
+                let mut hb = HashBuilder::default().with_allocator(pool);
+                for key in black_box(&keys) {
+                    hb.add_leaf(key.clone(), &value, false);
+                }
+                root = hb.root();
+                pool = hb.into_allocator();
+            }
+            root
+        })
+    });
+}
+
+/// Benchmarks the state root computation against a hash builder unpacking the hashed addresses
+/// with [`Nibbles::unpack`].
+#[cfg(feature = "ethereum")]
+pub fn state_roots(c: &mut Criterion) {
+    use alloy_trie::{root::state_root, HashBuilder, TrieAccount};
+
+    let lengths = [1000usize, 10_000];
+
+    let mut g = group(c, "state_root");
+    for len in lengths {
+        let accounts = hashed_keys(len)
+            .into_iter()
+            .enumerate()
+            .map(|(i, key)| (key, TrieAccount { nonce: i as u64, ..Default::default() }))
+            .collect::<std::collections::BTreeMap<_, _>>();
+        g.throughput(criterion::Throughput::Elements(len as u64));
+        g.bench_function(criterion::BenchmarkId::new("nybbles", len), |b| {
+            b.iter(|| {
+                let mut hb = HashBuilder::default();
+                let mut account_rlp = Vec::new();
+                for (hashed_address, account) in black_box(&accounts) {
+                    account_rlp.clear();
+                    account.encode_into(&mut account_rlp);
+                    hb.add_leaf(Nibbles::unpack(hashed_address), &account_rlp, false);
+                }
+                hb.root()
+            })
+        });
+        g.bench_function(criterion::BenchmarkId::new("swar", len), |b| {
+            b.iter(|| {
+                state_root(black_box(&accounts).iter().map(|(key, account)| (*key, *account)))
+            })
+        });
+    }
+}
+
 fn group<'c>(c: &'c mut Criterion, name: &str) -> BenchmarkGroup<'c, WallTime> {
     let mut g = c.benchmark_group(name);
     g.warm_up_time(Duration::from_secs(1));
@@ -30,6 +146,10 @@ fn group<'c>(c: &'c mut Criterion, name: &str) -> BenchmarkGroup<'c, WallTime> {
     g
 }
 
+fn hashed_keys(len: usize) -> Vec<B256> {
+    (0..len as u64).map(|i| keccak256(i.to_be_bytes())).collect()
+}
+
 fn get_nibbles(len: usize) -> Nibbles {
     proptest::arbitrary::any_with::<Nibbles>(len.into())
         .new_tree(&mut Default::default())
@@ -37,5 +157,16 @@ fn get_nibbles(len: usize) -> Nibbles {
         .current()
 }
 
-criterion_group!(benches, nibbles_path_encoding);
+criterion_group!(
+    benches,
+    nibbles_path_encoding,
+    indexed_trie_roots,
+    hashed_key_unpacking,
+    hash_builder_allocator
+);
+#[cfg(feature = "ethereum")]
+criterion_group!(ethereum_benches, state_roots);
+#[cfg(feature = "ethereum")]
+criterion_main!(benches, ethereum_benches);
+#[cfg(not(feature = "ethereum"))]
 criterion_main!(benches);
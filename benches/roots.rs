@@ -0,0 +1,96 @@
+#![allow(missing_docs)]
+
+use alloy_primitives::Bytes;
+use alloy_rlp::encode_fixed_size;
+use alloy_trie::{
+    proof::{verify_proof, ProofRetainer},
+    root::{ordered_trie_root, state_root, storage_root},
+    test_utils::{hashed_accounts, hashed_storage, ordered_items},
+    HashBuilder,
+};
+use criterion::{
+    criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup, BenchmarkId, Criterion,
+    Throughput,
+};
+use nybbles::Nibbles;
+use std::{hint::black_box, time::Duration};
+
+/// Benchmarks the root computation of ordered tries, such as the transaction and receipt tries.
+pub fn ordered_trie_roots(c: &mut Criterion) {
+    let mut g = group(c, "ordered_trie_root");
+    for len in [128usize, 10_000] {
+        let items = ordered_items(len, 100);
+        g.throughput(Throughput::Elements(len as u64));
+        g.bench_function(BenchmarkId::from_parameter(len), |b| {
+            b.iter(|| ordered_trie_root(black_box(&items)))
+        });
+    }
+}
+
+/// Benchmarks the state root computation from sorted hashed accounts.
+pub fn state_roots(c: &mut Criterion) {
+    let mut g = group(c, "state_root");
+    g.sample_size(10);
+    for len in [100_000usize, 1_000_000] {
+        let accounts = hashed_accounts(len);
+        g.throughput(Throughput::Elements(len as u64));
+        g.bench_function(BenchmarkId::from_parameter(len), |b| {
+            b.iter(|| state_root(black_box(&accounts).iter().copied()))
+        });
+    }
+}
+
+/// Benchmarks the storage root computation from sorted hashed slots.
+pub fn storage_roots(c: &mut Criterion) {
+    let mut g = group(c, "storage_root");
+    for len in [100usize, 10_000] {
+        let storage = hashed_storage(len);
+        g.throughput(Throughput::Elements(len as u64));
+        g.bench_function(BenchmarkId::from_parameter(len), |b| {
+            b.iter(|| storage_root(black_box(&storage).iter().copied()))
+        });
+    }
+}
+
+/// Benchmarks the verification of storage proofs.
+pub fn proof_verification(c: &mut Criterion) {
+    let mut g = group(c, "verify_proof");
+    for len in [100usize, 10_000] {
+        let storage = hashed_storage(len);
+        let (target, (value, is_private)) = storage[len / 2];
+        let key = Nibbles::unpack(target);
+        let mut hb =
+            HashBuilder::default().with_proof_retainer(ProofRetainer::from_iter([key.clone()]));
+        for (hashed_slot, (value, is_private)) in &storage {
+            hb.add_leaf(
+                Nibbles::unpack(hashed_slot),
+                encode_fixed_size(value).as_ref(),
+                *is_private,
+            );
+        }
+        let root = hb.root();
+        let proof: Vec<Bytes> = hb.take_proof_nodes().matching_nodes_for(&key);
+        let value = encode_fixed_size(&value).to_vec();
+        g.bench_function(BenchmarkId::from_parameter(len), |b| {
+            b.iter(|| {
+                verify_proof(
+                    black_box(root),
+                    key.clone(),
+                    Some(value.clone()),
+                    is_private,
+                    black_box(&proof),
+                )
+            })
+        });
+    }
+}
+
+fn group<'c>(c: &'c mut Criterion, name: &str) -> BenchmarkGroup<'c, WallTime> {
+    let mut g = c.benchmark_group(name);
+    g.warm_up_time(Duration::from_secs(1));
+    g.noise_threshold(0.02);
+    g
+}
+
+criterion_group!(benches, ordered_trie_roots, state_roots, storage_roots, proof_verification);
+criterion_main!(benches);
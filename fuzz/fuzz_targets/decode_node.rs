@@ -0,0 +1,19 @@
+//! Decodes arbitrary bytes as trie nodes, checking that decoding never panics and that decoded
+//! nodes survive an encoding round trip.
+
+#![no_main]
+
+use alloy_rlp::Decodable;
+use alloy_trie::nodes::{BranchNode, ExtensionNode, LeafNode, TrieNode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(node) = TrieNode::decode(&mut &data[..]) {
+        let encoded = alloy_rlp::encode(&node);
+        assert_eq!(TrieNode::decode(&mut &encoded[..]), Ok(node));
+    }
+
+    let _ = BranchNode::decode(&mut &data[..]);
+    let _ = ExtensionNode::decode(&mut &data[..]);
+    let _ = LeafNode::decode(&mut &data[..]);
+});
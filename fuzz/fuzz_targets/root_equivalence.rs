@@ -0,0 +1,18 @@
+//! Compares the root computed by the hash builder with the naive reference implementation.
+
+#![no_main]
+
+use alloy_trie::{test_utils::triehash_reference_with_privacy, HashBuilder, Nibbles};
+use libfuzzer_sys::fuzz_target;
+use std::collections::BTreeMap;
+
+fuzz_target!(|leaves: BTreeMap<[u8; 32], (Vec<u8>, bool)>| {
+    let mut hb = HashBuilder::default();
+    for (key, (value, is_private)) in &leaves {
+        hb.add_leaf(Nibbles::unpack(key), value, *is_private);
+    }
+    let expected = triehash_reference_with_privacy(
+        leaves.iter().map(|(key, (value, is_private))| (key, value, *is_private)),
+    );
+    assert_eq!(hb.root(), expected);
+});
@@ -0,0 +1,54 @@
+//! Verifies arbitrary proofs, checking that verification never panics, and checks that the proofs
+//! retained by the hash builder for arbitrary tries are verified.
+
+#![no_main]
+
+use alloy_primitives::{Bytes, B256};
+use alloy_trie::{
+    proof::{verify_proof, ProofRetainer},
+    HashBuilder, Nibbles,
+};
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use std::collections::BTreeMap;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    root: [u8; 32],
+    key: [u8; 32],
+    value: Option<Vec<u8>>,
+    is_private: bool,
+    proof: Vec<Vec<u8>>,
+    leaves: BTreeMap<[u8; 32], (Vec<u8>, bool)>,
+    target: [u8; 32],
+}
+
+fuzz_target!(|input: Input| {
+    let proof = input.proof.into_iter().map(Bytes::from).collect::<Vec<_>>();
+    let _ = verify_proof(
+        B256::from(input.root),
+        Nibbles::unpack(input.key),
+        input.value,
+        input.is_private,
+        &proof,
+    );
+
+    let leaves = input
+        .leaves
+        .into_iter()
+        .filter(|(_, (value, _))| !value.is_empty())
+        .collect::<BTreeMap<_, _>>();
+    let target = Nibbles::unpack(input.target);
+    let mut hb =
+        HashBuilder::default().with_proof_retainer(ProofRetainer::from_iter([target.clone()]));
+    for (key, (value, is_private)) in &leaves {
+        hb.add_leaf(Nibbles::unpack(key), value, *is_private);
+    }
+    let root = hb.root();
+    let proof = hb.take_proof_nodes().matching_nodes_for(&target);
+    let (value, is_private) = match leaves.get(&input.target) {
+        Some((value, is_private)) => (Some(value.clone()), *is_private),
+        None => (None, false),
+    };
+    assert!(verify_proof(root, target, value, is_private, &proof).is_ok());
+});
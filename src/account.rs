@@ -1,10 +1,22 @@
-use crate::{EMPTY_ROOT_HASH, KECCAK_EMPTY};
-use alloy_primitives::{keccak256, B256, U256};
+use crate::{
+    is_empty_code_hash, is_empty_root,
+    root::{storage_root_unhashed, FlaggedStorageValue},
+    EMPTY_ROOT_HASH, KECCAK_EMPTY,
+};
+use alloc::{collections::BTreeMap, vec::Vec};
+#[cfg(feature = "account-extensions")]
+use alloy_primitives::Address;
+use alloy_primitives::{keccak256, Bytes, B256, U256};
+#[cfg(feature = "account-extensions")]
+use alloy_rlp::{length_of_length, BufMut, Header};
+use alloy_rlp::{Decodable, Encodable};
+#[cfg(not(feature = "account-extensions"))]
 use alloy_rlp::{RlpDecodable, RlpEncodable};
 
 /// Represents an TrieAccount in the account trie.
-#[derive(Copy, Clone, Debug, PartialEq, Eq, RlpDecodable, RlpEncodable)]
-#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(not(feature = "account-extensions"), derive(RlpDecodable, RlpEncodable))]
+#[cfg_attr(feature = "arbitrary", derive(derive_arbitrary::Arbitrary, proptest_derive::Arbitrary))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct TrieAccount {
@@ -17,6 +29,13 @@ pub struct TrieAccount {
     pub storage_root: B256,
     /// The hash of the code of the account.
     pub code_hash: B256,
+    /// The extra fields of the account, encoded after the Ethereum ones.
+    #[cfg(feature = "account-extensions")]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "AccountExtension::is_none")
+    )]
+    pub extension: AccountExtension,
 }
 
 impl Default for TrieAccount {
@@ -26,19 +45,361 @@ impl Default for TrieAccount {
             balance: U256::ZERO,
             storage_root: EMPTY_ROOT_HASH,
             code_hash: KECCAK_EMPTY,
+            #[cfg(feature = "account-extensions")]
+            extension: AccountExtension::None,
         }
     }
 }
 
+/// The extra fields of a [TrieAccount], for experimenting with the Seismic account model.
+///
+/// An account without extension is encoded as an Ethereum account. Otherwise the type of the
+/// extension and its value are appended to the fields of the account.
+#[cfg(feature = "account-extensions")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(derive_arbitrary::Arbitrary, proptest_derive::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum AccountExtension {
+    /// No extra fields.
+    #[default]
+    None,
+    /// The size of the code of the account, so that stateless clients do not need the code to
+    /// charge for it.
+    CodeSize(#[cfg_attr(feature = "serde", serde(with = "quantity"))] u64),
+    /// The address the code of the account is delegated to, as by an EIP-7702 delegation
+    /// designator.
+    Delegation(Address),
+}
+
+#[cfg(feature = "account-extensions")]
+impl AccountExtension {
+    /// The type of the [AccountExtension::CodeSize] extension in the encoding.
+    pub const CODE_SIZE_TYPE: u8 = 1;
+
+    /// The type of the [AccountExtension::Delegation] extension in the encoding.
+    pub const DELEGATION_TYPE: u8 = 2;
+
+    /// Returns `true` if the account has no extra fields.
+    pub const fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
+
+    /// Returns the length of the encoded fields, appended to the payload of the account.
+    fn fields_length(&self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::CodeSize(size) => Self::CODE_SIZE_TYPE.length() + size.length(),
+            Self::Delegation(address) => Self::DELEGATION_TYPE.length() + address.length(),
+        }
+    }
+
+    fn encode_fields(&self, out: &mut dyn BufMut) {
+        match self {
+            Self::None => {}
+            Self::CodeSize(size) => {
+                Self::CODE_SIZE_TYPE.encode(out);
+                size.encode(out);
+            }
+            Self::Delegation(address) => {
+                Self::DELEGATION_TYPE.encode(out);
+                address.encode(out);
+            }
+        }
+    }
+
+    fn decode_fields(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        if buf.is_empty() {
+            return Ok(Self::None);
+        }
+        match u8::decode(buf)? {
+            Self::CODE_SIZE_TYPE => Ok(Self::CodeSize(u64::decode(buf)?)),
+            Self::DELEGATION_TYPE => Ok(Self::Delegation(Address::decode(buf)?)),
+            _ => Err(alloy_rlp::Error::Custom("unknown account extension type")),
+        }
+    }
+}
+
+#[cfg(feature = "account-extensions")]
+impl Encodable for TrieAccount {
+    fn encode(&self, out: &mut dyn BufMut) {
+        Header { list: true, payload_length: self.rlp_payload_length() }.encode(out);
+        self.nonce.encode(out);
+        self.balance.encode(out);
+        self.storage_root.encode(out);
+        self.code_hash.encode(out);
+        self.extension.encode_fields(out);
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.rlp_payload_length();
+        payload_length + length_of_length(payload_length)
+    }
+}
+
+#[cfg(feature = "account-extensions")]
+impl Decodable for TrieAccount {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let mut bytes = Header::decode_bytes(buf, true)?;
+        let account = Self {
+            nonce: u64::decode(&mut bytes)?,
+            balance: U256::decode(&mut bytes)?,
+            storage_root: B256::decode(&mut bytes)?,
+            code_hash: B256::decode(&mut bytes)?,
+            extension: AccountExtension::decode_fields(&mut bytes)?,
+        };
+        if !bytes.is_empty() {
+            return Err(alloy_rlp::Error::UnexpectedLength);
+        }
+        Ok(account)
+    }
+}
+
 impl TrieAccount {
+    /// Sets the extra fields of the account.
+    #[cfg(feature = "account-extensions")]
+    pub const fn with_extension(mut self, extension: AccountExtension) -> Self {
+        self.extension = extension;
+        self
+    }
+
+    #[cfg(feature = "account-extensions")]
+    fn rlp_payload_length(&self) -> usize {
+        self.nonce.length()
+            + self.balance.length()
+            + self.storage_root.length()
+            + self.code_hash.length()
+            + self.extension.fields_length()
+    }
+
+    /// Creates an account from its parts, computing the storage root from the unhashed storage
+    /// slots and the code hash from the code.
+    ///
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn from_parts<T: FlaggedStorageValue>(
+        nonce: u64,
+        balance: U256,
+        storage: impl IntoIterator<Item = (B256, T)>,
+        code: &[u8],
+    ) -> Self {
+        Self {
+            nonce,
+            balance,
+            storage_root: storage_root_unhashed(storage),
+            code_hash: keccak256(code),
+            #[cfg(feature = "account-extensions")]
+            extension: AccountExtension::None,
+        }
+    }
+
+    /// Creates an account from the fields of a genesis allocation, where every field but the
+    /// balance is optional.
+    ///
+    /// Missing storage and code result in [EMPTY_ROOT_HASH] and [KECCAK_EMPTY] respectively.
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn from_genesis_account<T: FlaggedStorageValue>(
+        nonce: Option<u64>,
+        balance: U256,
+        storage: Option<impl IntoIterator<Item = (B256, T)>>,
+        code: Option<&[u8]>,
+    ) -> Self {
+        Self {
+            nonce: nonce.unwrap_or_default(),
+            balance,
+            storage_root: storage.map_or(EMPTY_ROOT_HASH, storage_root_unhashed),
+            code_hash: code.map_or(KECCAK_EMPTY, keccak256),
+            #[cfg(feature = "account-extensions")]
+            extension: AccountExtension::None,
+        }
+    }
+
+    /// Returns `true` if the account is empty as defined by Ethereum: it has no nonce, balance,
+    /// code or storage.
+    ///
+    /// Empty accounts are not stored in the state trie.
+    pub fn is_empty(&self) -> bool {
+        self.nonce == 0
+            && self.balance.is_zero()
+            && is_empty_code_hash(self.code_hash)
+            && is_empty_root(self.storage_root)
+    }
+
+    /// Returns the length of the RLP encoding of the account.
+    pub fn rlp_encoded_length(&self) -> usize {
+        Encodable::length(self)
+    }
+
+    /// Appends the RLP encoding of the account to the buffer, reserving its exact length first.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.reserve(self.rlp_encoded_length());
+        Encodable::encode(self, buf);
+    }
+
+    /// Decodes an RLP encoded account, e.g. the value of an account leaf in a proof.
+    pub fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        <Self as Decodable>::decode(buf)
+    }
+
     /// Compute  hash as committed to in the MPT trie without memorizing.
     pub fn trie_hash_slow(&self) -> B256 {
         keccak256(alloy_rlp::encode(self))
     }
 }
 
+/// An account of a genesis allocation, where every field but the balance is optional.
+///
+/// The storage values carry their privacy, e.g. as `(U256, bool)` pairs in the Seismic genesis
+/// format.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GenesisAccount<T = (U256, bool)> {
+    /// The account's nonce.
+    pub nonce: Option<u64>,
+    /// The account's balance.
+    pub balance: U256,
+    /// The code of the account.
+    pub code: Option<Bytes>,
+    /// The storage of the account, keyed by unhashed slot.
+    pub storage: Option<BTreeMap<B256, T>>,
+}
+
+impl<T: FlaggedStorageValue> GenesisAccount<T> {
+    /// Returns the account as committed to in the state trie, computing its storage root and
+    /// code hash.
+    ///
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn trie_account(&self) -> TrieAccount {
+        TrieAccount::from_genesis_account(
+            self.nonce,
+            self.balance,
+            self.storage.as_ref().map(|storage| storage.iter().map(|(slot, value)| (*slot, value))),
+            self.code.as_ref().map(|code| &code[..]),
+        )
+    }
+}
+
+impl<T: FlaggedStorageValue> From<&GenesisAccount<T>> for TrieAccount {
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    fn from(account: &GenesisAccount<T>) -> Self {
+        account.trie_account()
+    }
+}
+
+#[cfg(feature = "alloy-genesis")]
+impl From<alloy_genesis::GenesisAccount> for GenesisAccount<U256> {
+    /// Converts an Ethereum genesis allocation, whose storage slots are all public.
+    fn from(account: alloy_genesis::GenesisAccount) -> Self {
+        Self {
+            nonce: account.nonce,
+            balance: account.balance,
+            code: account.code,
+            storage: account.storage.map(|storage| {
+                storage
+                    .into_iter()
+                    .map(|(slot, value)| (slot, U256::from_be_bytes(value.0)))
+                    .collect()
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "alloy-genesis")]
+impl From<alloy_genesis::GenesisAccount> for TrieAccount {
+    /// Converts an Ethereum genesis allocation, whose storage slots are all public.
+    fn from(account: alloy_genesis::GenesisAccount) -> Self {
+        GenesisAccount::from(account).trie_account()
+    }
+}
+
+#[cfg(feature = "alloy-consensus")]
+impl From<alloy_consensus::TrieAccount> for TrieAccount {
+    fn from(account: alloy_consensus::TrieAccount) -> Self {
+        Self {
+            nonce: account.nonce,
+            balance: account.balance,
+            storage_root: account.storage_root,
+            code_hash: account.code_hash,
+            #[cfg(feature = "account-extensions")]
+            extension: AccountExtension::None,
+        }
+    }
+}
+
+#[cfg(all(feature = "alloy-consensus", not(feature = "account-extensions")))]
+impl From<TrieAccount> for alloy_consensus::TrieAccount {
+    fn from(account: TrieAccount) -> Self {
+        Self {
+            nonce: account.nonce,
+            balance: account.balance,
+            storage_root: account.storage_root,
+            code_hash: account.code_hash,
+        }
+    }
+}
+
+#[cfg(all(feature = "alloy-consensus", feature = "account-extensions"))]
+impl TryFrom<TrieAccount> for alloy_consensus::TrieAccount {
+    /// The extension of the account, which Ethereum accounts cannot hold.
+    type Error = AccountExtension;
+
+    fn try_from(account: TrieAccount) -> Result<Self, Self::Error> {
+        if !account.extension.is_none() {
+            return Err(account.extension);
+        }
+        Ok(Self {
+            nonce: account.nonce,
+            balance: account.balance,
+            storage_root: account.storage_root,
+            code_hash: account.code_hash,
+        })
+    }
+}
+
+/// The fields of an account kept by the EVM besides its storage, shaped like revm's
+/// `AccountInfo` without the code.
+///
+/// Converts to a [TrieAccount] given the storage root of the account. It is defined here rather
+/// than converted from revm, so unlike the `alloy-genesis` and `alloy-consensus` conversions it
+/// needs no feature of its own. It carries no account extension either, the converted
+/// account having none, so it does not depend on `account-extensions`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccountInfo {
+    /// The account's balance.
+    pub balance: U256,
+    /// The account's nonce.
+    pub nonce: u64,
+    /// The hash of the account's code.
+    pub code_hash: B256,
+}
+
+impl Default for AccountInfo {
+    fn default() -> Self {
+        Self { balance: U256::ZERO, nonce: 0, code_hash: KECCAK_EMPTY }
+    }
+}
+
+impl From<TrieAccount> for AccountInfo {
+    fn from(account: TrieAccount) -> Self {
+        Self { balance: account.balance, nonce: account.nonce, code_hash: account.code_hash }
+    }
+}
+
+impl From<(AccountInfo, B256)> for TrieAccount {
+    /// Creates an account from its info and its storage root.
+    fn from((info, storage_root): (AccountInfo, B256)) -> Self {
+        Self {
+            nonce: info.nonce,
+            balance: info.balance,
+            storage_root,
+            code_hash: info.code_hash,
+            #[cfg(feature = "account-extensions")]
+            extension: AccountExtension::None,
+        }
+    }
+}
+
 #[cfg(feature = "serde")]
-mod quantity {
+pub(crate) mod quantity {
     use alloy_primitives::U64;
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -63,7 +424,6 @@ mod quantity {
 mod tests {
     use super::*;
     use alloy_primitives::{hex, U256};
-    use alloy_rlp::Decodable;
 
     #[test]
     fn test_account_encoding() {
@@ -74,12 +434,49 @@ mod tests {
                 "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
             )),
             code_hash: keccak256(hex!("5a465a905090036002900360015500")),
+            #[cfg(feature = "account-extensions")]
+            extension: AccountExtension::None,
         };
 
         let encoded = alloy_rlp::encode(account);
 
         let decoded = TrieAccount::decode(&mut &encoded[..]).unwrap();
         assert_eq!(account, decoded);
+
+        let mut buf = vec![0x01];
+        account.encode_into(&mut buf);
+        assert_eq!(account.rlp_encoded_length(), encoded.len());
+        assert_eq!(buf[1..], encoded[..]);
+    }
+
+    #[test]
+    #[cfg(feature = "account-extensions")]
+    fn test_account_extension_encoding() {
+        let account = TrieAccount { nonce: 1, balance: U256::from(1000), ..Default::default() };
+
+        // Without extension the account is encoded as an Ethereum account.
+        let mut ethereum = Vec::new();
+        alloy_rlp::encode_list::<_, dyn Encodable>(
+            &[
+                &account.nonce as &dyn Encodable,
+                &account.balance,
+                &account.storage_root,
+                &account.code_hash,
+            ],
+            &mut ethereum,
+        );
+        assert_eq!(alloy_rlp::encode(account), ethereum);
+
+        for extension in [
+            AccountExtension::CodeSize(1234),
+            AccountExtension::Delegation(Address::repeat_byte(1)),
+        ] {
+            let extended = account.with_extension(extension);
+            let encoded = alloy_rlp::encode(extended);
+            assert_eq!(extended.rlp_encoded_length(), encoded.len());
+            assert_eq!(TrieAccount::decode(&mut &encoded[..]).unwrap(), extended);
+            assert_ne!(extended.trie_hash_slow(), account.trie_hash_slow());
+        }
     }
 
     #[test]
@@ -91,10 +488,62 @@ mod tests {
                 "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
             )),
             code_hash: keccak256(hex!("5a465a905090036002900360015500")),
+            #[cfg(feature = "account-extensions")]
+            extension: AccountExtension::None,
         };
 
         let expected_hash = keccak256(alloy_rlp::encode(account));
         let actual_hash = account.trie_hash_slow();
         assert_eq!(expected_hash, actual_hash);
     }
+
+    #[test]
+    fn test_from_parts() {
+        let code = hex!("5a465a905090036002900360015500");
+        let storage = [(B256::with_last_byte(1), (U256::from(2), true))];
+        let account = TrieAccount::from_parts(1, U256::from(1000), storage, &code);
+        assert_eq!(account.storage_root, storage_root_unhashed(storage));
+        assert_eq!(account.code_hash, keccak256(code));
+
+        let genesis = TrieAccount::from_genesis_account(
+            Some(1),
+            U256::from(1000),
+            Some(storage),
+            Some(&code[..]),
+        );
+        assert_eq!(genesis, account);
+
+        let empty =
+            TrieAccount::from_genesis_account(None, U256::ZERO, None::<[(B256, U256); 0]>, None);
+        assert_eq!(empty, TrieAccount::default());
+        assert!(empty.is_empty());
+        assert!(!account.is_empty());
+        assert!(!TrieAccount { code_hash: keccak256(code), ..empty }.is_empty());
+        assert!(!TrieAccount { storage_root: account.storage_root, ..empty }.is_empty());
+    }
+
+    #[test]
+    fn test_account_conversions() {
+        let genesis = GenesisAccount {
+            nonce: Some(2),
+            balance: U256::from(10),
+            code: Some(Bytes::from_static(&[0x60, 0x00])),
+            storage: Some(BTreeMap::from([(B256::with_last_byte(1), (U256::from(3), true))])),
+        };
+        let account = TrieAccount::from(&genesis);
+        assert_eq!(account, genesis.trie_account());
+
+        let info = AccountInfo::from(account);
+        assert_eq!(info.nonce, 2);
+        assert_eq!(info.code_hash, account.code_hash);
+        assert_eq!(TrieAccount::from((info, account.storage_root)), account);
+        assert_eq!(
+            TrieAccount::from((AccountInfo::default(), EMPTY_ROOT_HASH)),
+            TrieAccount::default()
+        );
+        assert_eq!(
+            TrieAccount::from_parts(0, U256::ZERO, [(B256::ZERO, U256::ZERO); 0], &[]),
+            TrieAccount::default()
+        );
+    }
 }
@@ -0,0 +1,506 @@
+//! Incremental state and storage root computation on top of async cursors, see
+//! [`cursor::asynchronous`](crate::cursor::asynchronous).
+//!
+//! These mirror [StateRoot](crate::StateRoot), [StorageRoot](crate::StorageRoot) and
+//! [account_proof](crate::proof::account_proof), awaiting the cursors instead of blocking on them,
+//! so that roots and proofs can be computed against remote state providers without tying up a
+//! thread per computation. The walk over the stored nodes is shared with the synchronous variants,
+//! only the cursor calls are awaited here.
+
+use crate::{
+    cursor::{
+        asynchronous::{
+            AsyncHashedCursor, AsyncHashedCursorFactory, AsyncHashedStorageCursor, AsyncTrieCursor,
+            AsyncTrieCursorFactory,
+        },
+        DatabaseError,
+    },
+    hash_builder::TrieObserver,
+    nibbles::unpack_b256,
+    node_iter::{NodeIterState, NodeIterStep, TrieElement},
+    prefix_set::{PrefixSet, TriePrefixSets},
+    proof::{AccountProof, AccountProofCollector, StorageProofCollector},
+    updates::{StateTrieUpdates, TrieUpdates},
+    walker::TrieWalker,
+    witness::expand_paths_to,
+    HashBuilder, Nibbles, EMPTY_ROOT_HASH,
+};
+use alloc::{sync::Arc, vec::Vec};
+use alloy_primitives::{keccak256, Address, B256};
+
+/// Computes the state root from async cursors over the hashed state and the stored account and
+/// storage trie nodes, see [StateRoot](crate::StateRoot).
+#[derive(Clone, Debug)]
+pub struct AsyncStateRoot<T, H> {
+    /// Factory of cursors over the stored trie nodes.
+    trie_cursor_factory: T,
+    /// Factory of cursors over the hashed state.
+    hashed_cursor_factory: H,
+    /// The prefix sets of the changed keys.
+    prefix_sets: TriePrefixSets,
+    /// Observer of the hashing work.
+    observer: Option<Arc<dyn TrieObserver>>,
+}
+
+impl<T, H> AsyncStateRoot<T, H>
+where
+    T: AsyncTrieCursorFactory + Clone,
+    H: AsyncHashedCursorFactory + Clone,
+{
+    /// Creates a new state root computer.
+    ///
+    /// With no prefix sets set, the stored nodes are assumed to be up to date.
+    pub fn new(trie_cursor_factory: T, hashed_cursor_factory: H) -> Self {
+        Self {
+            trie_cursor_factory,
+            hashed_cursor_factory,
+            prefix_sets: TriePrefixSets::default(),
+            observer: None,
+        }
+    }
+
+    /// Sets the prefix sets of the changed keys.
+    pub fn with_prefix_sets(mut self, prefix_sets: TriePrefixSets) -> Self {
+        self.prefix_sets = prefix_sets;
+        self
+    }
+
+    /// Sets the observer of the hashing work of the account and storage tries.
+    pub fn with_observer(mut self, observer: Arc<dyn TrieObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Computes the state root.
+    pub async fn root(self) -> Result<B256, DatabaseError> {
+        self.calculate(false).await.map(|(root, _)| root)
+    }
+
+    /// Computes the state root along with the updates of the stored account and storage trie
+    /// nodes.
+    pub async fn root_with_updates(self) -> Result<(B256, StateTrieUpdates), DatabaseError> {
+        self.calculate(true).await
+    }
+
+    async fn calculate(
+        self,
+        retain_updates: bool,
+    ) -> Result<(B256, StateTrieUpdates), DatabaseError> {
+        let TriePrefixSets { account_prefix_set, mut storage_prefix_sets } = self.prefix_sets;
+        let mut updates = StateTrieUpdates::default();
+
+        let trie_cursor = self.trie_cursor_factory.account_trie_cursor().await?;
+        let hashed_account_cursor = self.hashed_cursor_factory.hashed_account_cursor().await?;
+        let walker = trie_walker(trie_cursor, account_prefix_set)
+            .await?
+            .with_deletions_retained(retain_updates);
+        let mut account_node_iter = AsyncTrieNodeIter::new(walker, hashed_account_cursor);
+        let mut hash_builder = HashBuilder::default().with_updates(retain_updates);
+        hash_builder.observer = self.observer.clone();
+        let mut account_rlp = Vec::new();
+
+        while let Some(element) = account_node_iter.try_next().await? {
+            match element {
+                TrieElement::Branch { key, hash, children_are_in_trie, is_private } => {
+                    hash_builder.add_branch_with_privacy(
+                        key,
+                        hash,
+                        children_are_in_trie,
+                        is_private,
+                    );
+                }
+                TrieElement::Leaf(hashed_address, mut account) => {
+                    let mut storage_root = AsyncStorageRoot::new(
+                        self.trie_cursor_factory.clone(),
+                        self.hashed_cursor_factory.clone(),
+                        hashed_address,
+                    )
+                    .with_prefix_set(
+                        storage_prefix_sets.remove(&hashed_address).unwrap_or_default(),
+                    );
+                    storage_root.observer = self.observer.clone();
+                    let (root, storage_updates) = storage_root.calculate(retain_updates).await?;
+                    if !storage_updates.is_empty() {
+                        updates.storage_tries.insert(hashed_address, storage_updates);
+                    }
+                    account.storage_root = root;
+
+                    account_rlp.clear();
+                    account.encode_into(&mut account_rlp);
+                    // account nodes are always public
+                    hash_builder.add_leaf(unpack_b256(&hashed_address), &account_rlp, false);
+                }
+            }
+        }
+
+        let (root, hash_builder_updates) = hash_builder.root_with_updates();
+        updates.account_nodes.finalize(
+            hash_builder_updates.updated_nodes,
+            account_node_iter.walker.take_removed_keys(),
+        );
+
+        Ok((root, updates))
+    }
+}
+
+/// Computes the storage root of a single account from async cursors over its hashed storage and
+/// its stored storage trie nodes, see [StorageRoot](crate::StorageRoot).
+///
+/// SEISMIC WARNING: Ensure that the storage values are flagged correctly by the hashed storage
+/// cursor.
+#[derive(Clone, Debug)]
+pub struct AsyncStorageRoot<T, H> {
+    /// Factory of cursors over the stored trie nodes.
+    trie_cursor_factory: T,
+    /// Factory of cursors over the hashed state.
+    hashed_cursor_factory: H,
+    /// The hashed address of the account.
+    hashed_address: B256,
+    /// The prefix set of the changed storage keys.
+    prefix_set: PrefixSet,
+    /// Observer of the hashing work.
+    observer: Option<Arc<dyn TrieObserver>>,
+}
+
+impl<T, H> AsyncStorageRoot<T, H>
+where
+    T: AsyncTrieCursorFactory,
+    H: AsyncHashedCursorFactory,
+{
+    /// Creates a new storage root computer for the account with the given hashed address.
+    pub fn new(trie_cursor_factory: T, hashed_cursor_factory: H, hashed_address: B256) -> Self {
+        Self {
+            trie_cursor_factory,
+            hashed_cursor_factory,
+            hashed_address,
+            prefix_set: PrefixSet::default(),
+            observer: None,
+        }
+    }
+
+    /// Sets the prefix set of the changed storage keys.
+    pub fn with_prefix_set(mut self, prefix_set: PrefixSet) -> Self {
+        self.prefix_set = prefix_set;
+        self
+    }
+
+    /// Sets the observer of the hashing work of the storage trie.
+    pub fn with_observer(mut self, observer: Arc<dyn TrieObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Computes the storage root.
+    pub async fn root(self) -> Result<B256, DatabaseError> {
+        self.calculate(false).await.map(|(root, _)| root)
+    }
+
+    /// Computes the storage root along with the updates of the stored storage trie nodes.
+    pub async fn root_with_updates(self) -> Result<(B256, TrieUpdates), DatabaseError> {
+        self.calculate(true).await
+    }
+
+    async fn calculate(self, retain_updates: bool) -> Result<(B256, TrieUpdates), DatabaseError> {
+        let mut updates = TrieUpdates::default();
+        let mut trie_cursor =
+            self.trie_cursor_factory.storage_trie_cursor(self.hashed_address).await?;
+        let mut hashed_storage_cursor =
+            self.hashed_cursor_factory.hashed_storage_cursor(self.hashed_address).await?;
+
+        // Short circuit on wiped storage, all stored nodes are removed.
+        if hashed_storage_cursor.is_storage_empty().await? {
+            if retain_updates {
+                let mut entry = trie_cursor.seek(Nibbles::default()).await?;
+                while let Some((path, _)) = entry {
+                    updates.insert_removed(path);
+                    entry = trie_cursor.next().await?;
+                }
+            }
+            if let Some(observer) = &self.observer {
+                observer.on_storage_root_computed();
+            }
+            return Ok((EMPTY_ROOT_HASH, updates));
+        }
+
+        let walker = trie_walker(trie_cursor, self.prefix_set)
+            .await?
+            .with_deletions_retained(retain_updates);
+        let mut storage_node_iter = AsyncTrieNodeIter::new(walker, hashed_storage_cursor);
+        let mut hash_builder = HashBuilder::default().with_updates(retain_updates);
+        hash_builder.observer = self.observer.clone();
+
+        while let Some(element) = storage_node_iter.try_next().await? {
+            match element {
+                TrieElement::Branch { key, hash, children_are_in_trie, is_private } => {
+                    hash_builder.add_branch_with_privacy(
+                        key,
+                        hash,
+                        children_are_in_trie,
+                        is_private,
+                    );
+                }
+                TrieElement::Leaf(hashed_slot, (value, is_private)) => {
+                    hash_builder.add_leaf(
+                        unpack_b256(&hashed_slot),
+                        alloy_rlp::encode_fixed_size(&value).as_ref(),
+                        is_private,
+                    );
+                }
+            }
+        }
+
+        let (root, hash_builder_updates) = hash_builder.root_with_updates();
+        updates.finalize(
+            hash_builder_updates.updated_nodes,
+            storage_node_iter.walker.take_removed_keys(),
+        );
+        if let Some(observer) = &self.observer {
+            observer.on_storage_root_computed();
+        }
+
+        Ok((root, updates))
+    }
+}
+
+/// Builds the [AccountProof] of the given address and storage slots from async cursors over the
+/// stored trie nodes and the hashed state, see [account_proof](crate::proof::account_proof).
+pub async fn async_account_proof<T, H>(
+    trie_cursor_factory: T,
+    hashed_cursor_factory: H,
+    address: Address,
+    slots: &[B256],
+) -> Result<AccountProof, DatabaseError>
+where
+    T: AsyncTrieCursorFactory + Clone,
+    H: AsyncHashedCursorFactory + Clone,
+{
+    let hashed_address = keccak256(address);
+    let mut storage_collector = StorageProofCollector::new(slots);
+    let walker = trie_walker(
+        trie_cursor_factory.storage_trie_cursor(hashed_address).await?,
+        expand_paths_to(&storage_collector.targets),
+    )
+    .await?;
+    let mut storage_node_iter = AsyncTrieNodeIter::new(
+        walker,
+        hashed_cursor_factory.hashed_storage_cursor(hashed_address).await?,
+    );
+    while let Some(element) = storage_node_iter.try_next().await? {
+        storage_collector.add(element);
+    }
+
+    let mut collector = AccountProofCollector::new(address, storage_collector.finish(slots));
+    let walker = trie_walker(
+        trie_cursor_factory.account_trie_cursor().await?,
+        expand_paths_to(&collector.targets),
+    )
+    .await?;
+    let mut account_node_iter =
+        AsyncTrieNodeIter::new(walker, hashed_cursor_factory.hashed_account_cursor().await?);
+    while let Some(mut element) = account_node_iter.try_next().await? {
+        if let TrieElement::Leaf(leaf_address, account) = &mut element {
+            if *leaf_address != hashed_address {
+                account.storage_root = AsyncStorageRoot::new(
+                    trie_cursor_factory.clone(),
+                    hashed_cursor_factory.clone(),
+                    *leaf_address,
+                )
+                .root()
+                .await?;
+            }
+        }
+        collector.add(element);
+    }
+
+    Ok(collector.finish())
+}
+
+/// Creates a [TrieWalker] over an [AsyncTrieCursor], see [TrieWalker::new].
+async fn trie_walker<C: AsyncTrieCursor>(
+    mut cursor: C,
+    changes: PrefixSet,
+) -> Result<TrieWalker<C>, DatabaseError> {
+    let root = cursor.seek_exact(Nibbles::default()).await?;
+    Ok(TrieWalker::with_root(cursor, root, changes))
+}
+
+/// Advances the [TrieWalker] over an [AsyncTrieCursor], see [TrieWalker::advance].
+async fn advance_walker<C: AsyncTrieCursor>(
+    walker: &mut TrieWalker<C>,
+) -> Result<(), DatabaseError> {
+    let mut seek = walker.start_advance();
+    while let Some(key) = seek {
+        let entry = walker.cursor_mut().seek(key).await?;
+        seek = walker.consume_node(entry);
+    }
+    walker.update_skip_node();
+    Ok(())
+}
+
+/// Async variant of the [TrieNodeIter](crate::TrieNodeIter) over an [AsyncHashedCursor].
+#[derive(Debug)]
+struct AsyncTrieNodeIter<C, H: AsyncHashedCursor> {
+    /// The walker over the stored branch nodes.
+    walker: TrieWalker<C>,
+    /// The cursor over the hashed entries.
+    hashed_cursor: H,
+    /// The position of the iteration over the hashed entries.
+    state: NodeIterState<H::Value>,
+}
+
+impl<C: AsyncTrieCursor, H: AsyncHashedCursor> AsyncTrieNodeIter<C, H> {
+    fn new(walker: TrieWalker<C>, hashed_cursor: H) -> Self {
+        Self { walker, hashed_cursor, state: NodeIterState::default() }
+    }
+
+    async fn try_next(&mut self) -> Result<Option<TrieElement<H::Value>>, DatabaseError> {
+        loop {
+            match self.state.step(&self.walker) {
+                NodeIterStep::Branch(element) => return Ok(Some(element)),
+                NodeIterStep::Leaf(hashed_key, value) => {
+                    self.state.current_hashed_entry = self.hashed_cursor.next().await?;
+                    return Ok(Some(TrieElement::Leaf(hashed_key, value)));
+                }
+                NodeIterStep::Resume(last_hashed_key) => {
+                    self.hashed_cursor.seek(last_hashed_key).await?;
+                    self.state.current_hashed_entry = self.hashed_cursor.next().await?;
+                }
+                NodeIterStep::Seek(seek_key) => {
+                    self.state.current_hashed_entry = self.hashed_cursor.seek(seek_key).await?;
+                    advance_walker(&mut self.walker).await?;
+                }
+                NodeIterStep::Done => return Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cursor::in_memory::{InMemoryHashedState, InMemoryTrieNodes},
+        prefix_set::TriePrefixSetsMut,
+        proof::account_proof,
+        StateRoot, TrieAccount,
+    };
+    use alloc::collections::BTreeMap;
+    use alloy_primitives::U256;
+    use core::{
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+    use std::{sync::Arc as StdArc, task::Wake};
+
+    /// Polls the future to completion, the in-memory cursors never being pending.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        struct NoopWaker;
+        impl Wake for NoopWaker {
+            fn wake(self: StdArc<Self>) {}
+        }
+        let waker = Waker::from(StdArc::new(NoopWaker));
+        let mut future = pin!(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn async_root_matches_sync_root() {
+        let mut state = InMemoryHashedState::default();
+        for i in 0..50u64 {
+            let hashed_address = alloy_primitives::keccak256(i.to_be_bytes());
+            state.accounts.insert(hashed_address, TrieAccount { nonce: i, ..Default::default() });
+            if i % 5 == 0 {
+                let storage = (1..=10u64)
+                    .map(|slot| {
+                        let hashed_slot = alloy_primitives::keccak256(slot.to_be_bytes());
+                        (hashed_slot, (U256::from(slot + i), slot % 2 == 0))
+                    })
+                    .collect::<BTreeMap<_, _>>();
+                state.storages.insert(hashed_address, storage);
+            }
+        }
+        let mut nodes = InMemoryTrieNodes::default();
+
+        // From scratch, with every key changed.
+        let (root, updates) = StateRoot::new(&nodes, &state).root_with_updates().unwrap();
+        let (async_root, async_updates) =
+            block_on(AsyncStateRoot::new(&nodes, &state).root_with_updates()).unwrap();
+        assert_eq!(async_root, root);
+        assert_eq!(async_updates, updates);
+
+        nodes.account_nodes.extend(updates.account_nodes.updated_nodes);
+        for (hashed_address, storage_updates) in updates.storage_tries {
+            nodes
+                .storage_nodes
+                .entry(hashed_address)
+                .or_default()
+                .extend(storage_updates.updated_nodes);
+        }
+        assert_eq!(block_on(AsyncStateRoot::new(&nodes, &state).root()), Ok(root));
+
+        // Incrementally, on top of the stored nodes.
+        let mut prefix_sets = TriePrefixSetsMut::default();
+        let changed = *state.accounts.keys().nth(10).unwrap();
+        state.accounts.get_mut(&changed).unwrap().nonce += 1;
+        prefix_sets.account_prefix_set.insert(Nibbles::unpack(changed));
+        let prefix_sets = prefix_sets.freeze();
+        let expected = StateRoot::new(&nodes, &state)
+            .with_prefix_sets(prefix_sets.clone())
+            .root_with_updates()
+            .unwrap();
+        assert_ne!(expected.0, root);
+        assert_eq!(
+            block_on(
+                AsyncStateRoot::new(&nodes, &state)
+                    .with_prefix_sets(prefix_sets)
+                    .root_with_updates()
+            ),
+            Ok(expected)
+        );
+    }
+
+    #[test]
+    fn async_account_proof_matches_sync_proof() {
+        let mut state = InMemoryHashedState::default();
+        let slots = Vec::from_iter((1..=10u64).map(|slot| B256::with_last_byte(slot as u8)));
+        for i in 0..50u8 {
+            let hashed_address = keccak256(Address::with_last_byte(i));
+            state
+                .accounts
+                .insert(hashed_address, TrieAccount { nonce: i as u64, ..Default::default() });
+            if i % 5 == 0 {
+                let storage = slots
+                    .iter()
+                    .enumerate()
+                    .map(|(j, slot)| (keccak256(slot), (U256::from(j + 1), j % 2 == 0)))
+                    .collect::<BTreeMap<_, _>>();
+                state.storages.insert(hashed_address, storage);
+            }
+        }
+        let mut nodes = InMemoryTrieNodes::default();
+        let (root, updates) = StateRoot::new(&nodes, &state).root_with_updates().unwrap();
+        nodes.account_nodes.extend(updates.account_nodes.updated_nodes);
+        for (hashed_address, storage_updates) in updates.storage_tries {
+            nodes
+                .storage_nodes
+                .entry(hashed_address)
+                .or_default()
+                .extend(storage_updates.updated_nodes);
+        }
+
+        // An account with storage, one without and a missing one.
+        for address in [0, 1, 100].map(Address::with_last_byte) {
+            let proof = account_proof(&nodes, &state, address, &slots[..3]).unwrap();
+            proof.verify(root).unwrap();
+            assert_eq!(
+                block_on(async_account_proof(&nodes, &state, address, &slots[..3])),
+                Ok(proof)
+            );
+        }
+    }
+}
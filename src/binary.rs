@@ -0,0 +1,335 @@
+//! Experimental binary Merkle trie over 32-byte keys.
+//!
+//! Every internal node has two children selected by the next bit of the key, and a subtrie
+//! holding a single leaf is replaced by the leaf itself, so the trie has no extension nodes.
+//! Nodes are hashed with fixed-size preimages instead of RLP:
+//!
+//! - empty subtrie: [B256::ZERO]
+//! - leaf: `hash(flag || key || hash(value))`, where the flag is [LEAF_PUBLIC] or [LEAF_PRIVATE]
+//! - internal node: `hash(INTERNAL || left || right)`
+//!
+//! As in the hexary trie, the privacy of a leaf is part of its hash.
+
+use crate::hash_builder::{KeccakHasher, TrieHasher};
+use alloc::vec::Vec;
+use alloy_primitives::B256;
+use core::marker::PhantomData;
+
+/// The flag of a public leaf.
+pub const LEAF_PUBLIC: u8 = 0x00;
+
+/// The flag of a private leaf.
+pub const LEAF_PRIVATE: u8 = 0x01;
+
+/// The flag of an internal node.
+pub const INTERNAL: u8 = 0x02;
+
+/// A leaf of the binary trie, with the hash of its value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BinaryLeaf {
+    /// The key of the leaf.
+    pub key: B256,
+    /// The hash of the value of the leaf.
+    pub value_hash: B256,
+    /// Whether the leaf is private.
+    pub is_private: bool,
+}
+
+impl BinaryLeaf {
+    /// Returns the hash of the leaf node.
+    pub fn hash<H: TrieHasher>(&self) -> B256 {
+        let mut preimage = [0u8; 65];
+        preimage[0] = if self.is_private { LEAF_PRIVATE } else { LEAF_PUBLIC };
+        preimage[1..33].copy_from_slice(self.key.as_slice());
+        preimage[33..].copy_from_slice(self.value_hash.as_slice());
+        H::hash(&preimage)
+    }
+}
+
+/// The proof of a key in a [BinaryTrie], see [BinaryTrie::proof].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BinaryProof {
+    /// The hashes of the siblings along the path of the key, ordered from the root.
+    pub siblings: Vec<B256>,
+    /// The leaf at the end of the path, which is another leaf if the key is not in the trie, or
+    /// [None] if the path ends in an empty subtrie.
+    pub leaf: Option<BinaryLeaf>,
+}
+
+impl BinaryProof {
+    /// Verifies the proof of the given key against the root, returning the leaf of the key if it
+    /// is in the trie.
+    ///
+    /// Returns [None] if the proof does not match the root.
+    pub fn verify<H: TrieHasher>(&self, root: B256, key: B256) -> Option<Option<BinaryLeaf>> {
+        if self.siblings.len() > 256 {
+            return None;
+        }
+        // Another leaf must share the proven path with the key.
+        if let Some(leaf) = self.leaf.filter(|leaf| leaf.key != key) {
+            if (0..self.siblings.len()).any(|depth| bit(&leaf.key, depth) != bit(&key, depth)) {
+                return None;
+            }
+        }
+
+        let mut hash = self.leaf.map_or(B256::ZERO, |leaf| leaf.hash::<H>());
+        for (depth, sibling) in self.siblings.iter().enumerate().rev() {
+            hash = if bit(&key, depth) {
+                internal_hash::<H>(sibling, &hash)
+            } else {
+                internal_hash::<H>(&hash, sibling)
+            };
+        }
+        (hash == root).then(|| self.leaf.filter(|leaf| leaf.key == key))
+    }
+}
+
+/// An in-memory binary Merkle trie.
+#[derive(Clone, Debug)]
+pub struct BinaryTrie<H = KeccakHasher> {
+    /// The leaves sorted by key.
+    leaves: Vec<BinaryLeaf>,
+    _hasher: PhantomData<H>,
+}
+
+impl<H> Default for BinaryTrie<H> {
+    fn default() -> Self {
+        Self { leaves: Vec::new(), _hasher: PhantomData }
+    }
+}
+
+impl<H: TrieHasher> BinaryTrie<H> {
+    /// Creates a trie from the given leaves, the last value of a key taking precedence.
+    ///
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn from_leaves<V: AsRef<[u8]>>(leaves: impl IntoIterator<Item = (B256, V, bool)>) -> Self {
+        let mut trie = Self::default();
+        for (key, value, is_private) in leaves {
+            trie.insert(key, value.as_ref(), is_private);
+        }
+        trie
+    }
+
+    /// Returns the number of leaves.
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Returns `true` if the trie has no leaves.
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Returns the leaf of the given key.
+    pub fn get(&self, key: &B256) -> Option<&BinaryLeaf> {
+        self.leaves.binary_search_by(|leaf| leaf.key.cmp(key)).ok().map(|index| &self.leaves[index])
+    }
+
+    /// Inserts or replaces the value of the given key.
+    ///
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn insert(&mut self, key: B256, value: &[u8], is_private: bool) {
+        let leaf = BinaryLeaf { key, value_hash: H::hash(value), is_private };
+        match self.leaves.binary_search_by(|leaf| leaf.key.cmp(&key)) {
+            Ok(index) => self.leaves[index] = leaf,
+            Err(index) => self.leaves.insert(index, leaf),
+        }
+    }
+
+    /// Removes the given key, returning its leaf.
+    pub fn remove(&mut self, key: &B256) -> Option<BinaryLeaf> {
+        let index = self.leaves.binary_search_by(|leaf| leaf.key.cmp(key)).ok()?;
+        Some(self.leaves.remove(index))
+    }
+
+    /// Returns the root of the trie.
+    pub fn root(&self) -> B256 {
+        subtrie_hash::<H>(&self.leaves, 0)
+    }
+
+    /// Returns the proof of the given key, proving either its leaf or its absence.
+    pub fn proof(&self, key: &B256) -> BinaryProof {
+        let mut siblings = Vec::new();
+        let mut leaves = &self.leaves[..];
+        let mut depth = 0;
+        while leaves.len() > 1 {
+            let (left, right) = split(leaves, depth);
+            let (path, sibling) = if bit(key, depth) { (right, left) } else { (left, right) };
+            siblings.push(subtrie_hash::<H>(sibling, depth + 1));
+            leaves = path;
+            depth += 1;
+        }
+        BinaryProof { siblings, leaf: leaves.first().copied() }
+    }
+}
+
+/// The binary trie commitment, with the values of the storage leaves encoded with
+/// [`FlaggedStorageValue::encode_value`](crate::root::FlaggedStorageValue::encode_value) and the
+/// accounts RLP encoded, as in the hexary trie.
+#[cfg(feature = "ethereum")]
+impl<H: TrieHasher> crate::commitment::StateCommitment for BinaryTrie<H> {
+    fn storage_root<T: crate::root::FlaggedStorageValue>(
+        storage: impl IntoIterator<Item = (B256, T)>,
+    ) -> B256 {
+        let mut trie = Self::default();
+        let mut value_buf = Vec::new();
+        for (hashed_slot, value) in storage {
+            value_buf.clear();
+            value.encode_value(&mut value_buf);
+            trie.insert(hashed_slot, &value_buf, value.is_private());
+        }
+        trie.root()
+    }
+
+    fn state_root<A: Into<crate::TrieAccount>>(state: impl IntoIterator<Item = (B256, A)>) -> B256 {
+        let mut trie = Self::default();
+        let mut account_rlp = Vec::new();
+        for (hashed_address, account) in state {
+            account_rlp.clear();
+            account.into().encode_into(&mut account_rlp);
+            // account leaves are always public
+            trie.insert(hashed_address, &account_rlp, false);
+        }
+        trie.root()
+    }
+}
+
+/// Returns the hash of the subtrie of the given sorted leaves sharing their first `depth` bits.
+fn subtrie_hash<H: TrieHasher>(leaves: &[BinaryLeaf], depth: usize) -> B256 {
+    match leaves {
+        [] => B256::ZERO,
+        [leaf] => leaf.hash::<H>(),
+        _ => {
+            let (left, right) = split(leaves, depth);
+            internal_hash::<H>(
+                &subtrie_hash::<H>(left, depth + 1),
+                &subtrie_hash::<H>(right, depth + 1),
+            )
+        }
+    }
+}
+
+fn internal_hash<H: TrieHasher>(left: &B256, right: &B256) -> B256 {
+    let mut preimage = [0u8; 65];
+    preimage[0] = INTERNAL;
+    preimage[1..33].copy_from_slice(left.as_slice());
+    preimage[33..].copy_from_slice(right.as_slice());
+    H::hash(&preimage)
+}
+
+/// Splits the sorted leaves by the bit at the given depth.
+fn split(leaves: &[BinaryLeaf], depth: usize) -> (&[BinaryLeaf], &[BinaryLeaf]) {
+    leaves.split_at(leaves.partition_point(|leaf| !bit(&leaf.key, depth)))
+}
+
+/// Returns the bit of the key at the given depth, from the most significant bit.
+#[inline]
+fn bit(key: &B256, depth: usize) -> bool {
+    key[depth / 8] & (0x80 >> (depth % 8)) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::keccak256;
+
+    fn leaf_hash(key: B256, value: &[u8], is_private: bool) -> B256 {
+        BinaryLeaf { key, value_hash: keccak256(value), is_private }.hash::<KeccakHasher>()
+    }
+
+    #[test]
+    fn binary_root() {
+        let mut trie = BinaryTrie::<KeccakHasher>::default();
+        assert_eq!(trie.root(), B256::ZERO);
+
+        let a = B256::with_last_byte(1);
+        trie.insert(a, b"a", false);
+        assert_eq!(trie.root(), leaf_hash(a, b"a", false));
+        trie.insert(a, b"a", true);
+        assert_eq!(trie.root(), leaf_hash(a, b"a", true));
+
+        // The keys differ in their first bit.
+        let mut b = B256::with_last_byte(2);
+        b[0] = 0x80;
+        trie.insert(b, b"b", false);
+        assert_eq!(
+            trie.root(),
+            internal_hash::<KeccakHasher>(&leaf_hash(a, b"a", true), &leaf_hash(b, b"b", false))
+        );
+
+        // The keys differ in their second bit, the first level has an empty child.
+        let mut c = b;
+        c[0] = 0xc0;
+        trie.remove(&a);
+        trie.insert(c, b"c", false);
+        let children =
+            internal_hash::<KeccakHasher>(&leaf_hash(b, b"b", false), &leaf_hash(c, b"c", false));
+        assert_eq!(trie.root(), internal_hash::<KeccakHasher>(&B256::ZERO, &children));
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn binary_state_commitment() {
+        use crate::{commitment::StateCommitment, MerklePatriciaTrie, TrieAccount};
+        use alloy_primitives::U256;
+
+        let storage = [(B256::with_last_byte(1), (U256::from(1), true))];
+        let mut value = Vec::new();
+        alloy_rlp::Encodable::encode(&U256::from(1), &mut value);
+        let storage_root = BinaryTrie::<KeccakHasher>::storage_root(storage);
+        assert_eq!(storage_root, leaf_hash(B256::with_last_byte(1), &value, true));
+
+        let account = TrieAccount { nonce: 1, ..Default::default() };
+        let state = [(B256::with_last_byte(2), account, storage)];
+        let root = BinaryTrie::<KeccakHasher>::state_root_with_storage(state);
+        let account = TrieAccount { storage_root, ..account };
+        let mut account_rlp = Vec::new();
+        account.encode_into(&mut account_rlp);
+        assert_eq!(root, leaf_hash(B256::with_last_byte(2), &account_rlp, false));
+        assert_ne!(root, MerklePatriciaTrie::state_root_with_storage(state));
+    }
+
+    #[test]
+    fn binary_proofs() {
+        let trie = BinaryTrie::<KeccakHasher>::from_leaves(
+            (0..100u64).map(|i| (keccak256(i.to_be_bytes()), i.to_be_bytes(), i % 2 == 0)),
+        );
+        assert_eq!(trie.len(), 100);
+        let root = trie.root();
+
+        for i in 0..100u64 {
+            let key = keccak256(i.to_be_bytes());
+            let proof = trie.proof(&key);
+            let leaf = proof.verify::<KeccakHasher>(root, key).unwrap().unwrap();
+            assert_eq!(leaf.value_hash, keccak256(i.to_be_bytes()));
+            assert_eq!(leaf.is_private, i % 2 == 0);
+
+            // The proof does not match another root or another leaf.
+            assert_eq!(proof.verify::<KeccakHasher>(B256::ZERO, key), None);
+            let mut tampered = proof.clone();
+            tampered.leaf.as_mut().unwrap().is_private = i % 2 != 0;
+            assert_eq!(tampered.verify::<KeccakHasher>(root, key), None);
+        }
+
+        for i in 100..200u64 {
+            let key = keccak256(i.to_be_bytes());
+            let proof = trie.proof(&key);
+            assert_eq!(proof.verify::<KeccakHasher>(root, key), Some(None));
+        }
+
+        // The proof of a leaf does not prove the absence of a key off its path.
+        let key = keccak256(0u64.to_be_bytes());
+        let mut off_path = key;
+        off_path[0] ^= 0x80;
+        assert_eq!(trie.proof(&key).verify::<KeccakHasher>(root, off_path), None);
+
+        assert_eq!(
+            BinaryTrie::<KeccakHasher>::default()
+                .proof(&B256::ZERO)
+                .verify::<KeccakHasher>(B256::ZERO, B256::ZERO),
+            Some(None)
+        );
+    }
+}
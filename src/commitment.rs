@@ -0,0 +1,94 @@
+//! Pluggable commitment schemes for the hashed accounts and storages, so that another scheme than
+//! the Merkle-Patricia trie can be slotted in without changing the callers.
+
+use crate::{
+    root::{self, FlaggedStorageValue},
+    TrieAccount,
+};
+use alloy_primitives::B256;
+
+/// A commitment scheme for the state, mapping the hashed accounts and storages to a root.
+pub trait StateCommitment {
+    /// Returns the commitment to the storage of an account, given its slots sorted by hashed slot.
+    ///
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    fn storage_root<T: FlaggedStorageValue>(storage: impl IntoIterator<Item = (B256, T)>) -> B256;
+
+    /// Returns the commitment to the state, given the accounts sorted by hashed address along
+    /// with their storage roots.
+    fn state_root<A: Into<TrieAccount>>(state: impl IntoIterator<Item = (B256, A)>) -> B256;
+
+    /// Returns the commitment to the state, given the accounts sorted by hashed address along
+    /// with their storage, replacing the storage roots of the accounts with
+    /// [StateCommitment::storage_root].
+    ///
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    fn state_root_with_storage<A, S, T>(state: impl IntoIterator<Item = (B256, A, S)>) -> B256
+    where
+        A: Into<TrieAccount>,
+        S: IntoIterator<Item = (B256, T)>,
+        T: FlaggedStorageValue,
+    {
+        Self::state_root(state.into_iter().map(|(hashed_address, account, storage)| {
+            let storage_root = Self::storage_root(storage);
+            (hashed_address, TrieAccount { storage_root, ..account.into() })
+        }))
+    }
+}
+
+/// The Merkle-Patricia trie commitment of Ethereum, computing the same roots as [root::state_root]
+/// and [root::storage_root].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MerklePatriciaTrie;
+
+impl StateCommitment for MerklePatriciaTrie {
+    fn storage_root<T: FlaggedStorageValue>(storage: impl IntoIterator<Item = (B256, T)>) -> B256 {
+        root::storage_root(storage)
+    }
+
+    fn state_root<A: Into<TrieAccount>>(state: impl IntoIterator<Item = (B256, A)>) -> B256 {
+        root::state_root(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{collections::BTreeMap, vec::Vec};
+    use alloy_primitives::{keccak256, U256};
+
+    #[test]
+    fn merkle_patricia_trie_commitment() {
+        let state = (0..20u64)
+            .map(|i| {
+                let storage = (0..i)
+                    .map(|slot| {
+                        (keccak256(slot.to_be_bytes()), (U256::from(slot + 1), slot % 2 == 0))
+                    })
+                    .collect::<BTreeMap<_, _>>();
+                (
+                    keccak256(i.to_be_bytes()),
+                    (TrieAccount { nonce: i, ..Default::default() }, storage),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let expected =
+            root::state_root(state.iter().map(|(hashed_address, (account, storage))| {
+                let storage_root = root::storage_root(storage.iter().map(|(k, v)| (*k, *v)));
+                (*hashed_address, TrieAccount { storage_root, ..*account })
+            }));
+        assert_eq!(
+            MerklePatriciaTrie::state_root_with_storage(state.iter().map(
+                |(hashed_address, (account, storage))| {
+                    (*hashed_address, *account, storage.iter().map(|(k, v)| (*k, *v)))
+                }
+            )),
+            expected
+        );
+        assert_eq!(
+            MerklePatriciaTrie::state_root(Vec::<(B256, TrieAccount)>::new()),
+            crate::EMPTY_ROOT_HASH
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Async variants of the cursor traits, for state held by remote providers such as an RPC node or
+//! an object storage.
+//!
+//! Every synchronous cursor and cursor factory is also an async one whose futures are ready
+//! immediately, so that in-memory and local stores can be mixed with remote ones.
+
+use super::{DatabaseError, HashedCursor, HashedStorageCursor, TrieCursor, TrieCursorFactory};
+use crate::{BranchNodeCompact, Nibbles};
+use alloy_primitives::B256;
+use core::future::{ready, Future};
+
+#[cfg(feature = "ethereum")]
+use super::HashedCursorFactory;
+#[cfg(feature = "ethereum")]
+use crate::TrieAccount;
+#[cfg(feature = "ethereum")]
+use alloy_primitives::U256;
+
+/// Async variant of [TrieCursor].
+pub trait AsyncTrieCursor: Send {
+    /// Moves the cursor to the exact key and returns the node stored there.
+    fn seek_exact(
+        &mut self,
+        key: Nibbles,
+    ) -> impl Future<Output = Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError>> + Send;
+
+    /// Moves the cursor to the first key that is greater than or equal to the given key.
+    fn seek(
+        &mut self,
+        key: Nibbles,
+    ) -> impl Future<Output = Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError>> + Send;
+
+    /// Moves the cursor to the next key.
+    fn next(
+        &mut self,
+    ) -> impl Future<Output = Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError>> + Send;
+
+    /// Returns the key the cursor is currently positioned at.
+    fn current(&mut self) -> impl Future<Output = Result<Option<Nibbles>, DatabaseError>> + Send;
+}
+
+/// Async variant of [HashedCursor].
+pub trait AsyncHashedCursor: Send {
+    /// The value of the entries.
+    type Value: Send;
+
+    /// Moves the cursor to the first key that is greater than or equal to the given key.
+    fn seek(
+        &mut self,
+        key: B256,
+    ) -> impl Future<Output = Result<Option<(B256, Self::Value)>, DatabaseError>> + Send;
+
+    /// Moves the cursor to the next entry.
+    fn next(
+        &mut self,
+    ) -> impl Future<Output = Result<Option<(B256, Self::Value)>, DatabaseError>> + Send;
+}
+
+/// Async variant of [HashedStorageCursor].
+pub trait AsyncHashedStorageCursor: AsyncHashedCursor {
+    /// Returns `true` if there are no storage entries for the account.
+    fn is_storage_empty(&mut self) -> impl Future<Output = Result<bool, DatabaseError>> + Send;
+}
+
+/// Async variant of [TrieCursorFactory].
+pub trait AsyncTrieCursorFactory: Send + Sync {
+    /// The account trie cursor type.
+    type AccountTrieCursor: AsyncTrieCursor;
+    /// The storage trie cursor type.
+    type StorageTrieCursor: AsyncTrieCursor;
+
+    /// Creates a cursor over the account trie nodes.
+    fn account_trie_cursor(
+        &self,
+    ) -> impl Future<Output = Result<Self::AccountTrieCursor, DatabaseError>> + Send;
+
+    /// Creates a cursor over the storage trie nodes of the given account.
+    fn storage_trie_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> impl Future<Output = Result<Self::StorageTrieCursor, DatabaseError>> + Send;
+}
+
+/// Async variant of [HashedCursorFactory].
+#[cfg(feature = "ethereum")]
+pub trait AsyncHashedCursorFactory: Send + Sync {
+    /// The hashed account cursor type.
+    type AccountCursor: AsyncHashedCursor<Value = TrieAccount>;
+    /// The hashed storage cursor type.
+    type StorageCursor: AsyncHashedStorageCursor<Value = (U256, bool)>;
+
+    /// Creates a cursor over the hashed accounts.
+    fn hashed_account_cursor(
+        &self,
+    ) -> impl Future<Output = Result<Self::AccountCursor, DatabaseError>> + Send;
+
+    /// Creates a cursor over the hashed storage of the given account.
+    fn hashed_storage_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> impl Future<Output = Result<Self::StorageCursor, DatabaseError>> + Send;
+}
+
+impl<C: TrieCursor + Send> AsyncTrieCursor for C {
+    fn seek_exact(
+        &mut self,
+        key: Nibbles,
+    ) -> impl Future<Output = Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError>> + Send
+    {
+        ready(TrieCursor::seek_exact(self, key))
+    }
+
+    fn seek(
+        &mut self,
+        key: Nibbles,
+    ) -> impl Future<Output = Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError>> + Send
+    {
+        ready(TrieCursor::seek(self, key))
+    }
+
+    fn next(
+        &mut self,
+    ) -> impl Future<Output = Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError>> + Send
+    {
+        ready(TrieCursor::next(self))
+    }
+
+    fn current(&mut self) -> impl Future<Output = Result<Option<Nibbles>, DatabaseError>> + Send {
+        ready(TrieCursor::current(self))
+    }
+}
+
+impl<C> AsyncHashedCursor for C
+where
+    C: HashedCursor + Send,
+    C::Value: Send,
+{
+    type Value = C::Value;
+
+    fn seek(
+        &mut self,
+        key: B256,
+    ) -> impl Future<Output = Result<Option<(B256, Self::Value)>, DatabaseError>> + Send {
+        ready(HashedCursor::seek(self, key))
+    }
+
+    fn next(
+        &mut self,
+    ) -> impl Future<Output = Result<Option<(B256, Self::Value)>, DatabaseError>> + Send {
+        ready(HashedCursor::next(self))
+    }
+}
+
+impl<C> AsyncHashedStorageCursor for C
+where
+    C: HashedStorageCursor + Send,
+    C::Value: Send,
+{
+    fn is_storage_empty(&mut self) -> impl Future<Output = Result<bool, DatabaseError>> + Send {
+        ready(HashedStorageCursor::is_storage_empty(self))
+    }
+}
+
+impl<T> AsyncTrieCursorFactory for T
+where
+    T: TrieCursorFactory + Send + Sync,
+    T::AccountTrieCursor: Send,
+    T::StorageTrieCursor: Send,
+{
+    type AccountTrieCursor = T::AccountTrieCursor;
+    type StorageTrieCursor = T::StorageTrieCursor;
+
+    fn account_trie_cursor(
+        &self,
+    ) -> impl Future<Output = Result<Self::AccountTrieCursor, DatabaseError>> + Send {
+        ready(TrieCursorFactory::account_trie_cursor(self))
+    }
+
+    fn storage_trie_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> impl Future<Output = Result<Self::StorageTrieCursor, DatabaseError>> + Send {
+        ready(TrieCursorFactory::storage_trie_cursor(self, hashed_address))
+    }
+}
+
+#[cfg(feature = "ethereum")]
+impl<H> AsyncHashedCursorFactory for H
+where
+    H: HashedCursorFactory + Send + Sync,
+    H::AccountCursor: Send,
+    H::StorageCursor: Send,
+{
+    type AccountCursor = H::AccountCursor;
+    type StorageCursor = H::StorageCursor;
+
+    fn hashed_account_cursor(
+        &self,
+    ) -> impl Future<Output = Result<Self::AccountCursor, DatabaseError>> + Send {
+        ready(HashedCursorFactory::hashed_account_cursor(self))
+    }
+
+    fn hashed_storage_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> impl Future<Output = Result<Self::StorageCursor, DatabaseError>> + Send {
+        ready(HashedCursorFactory::hashed_storage_cursor(self, hashed_address))
+    }
+}
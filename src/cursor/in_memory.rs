@@ -0,0 +1,222 @@
+//! In-memory reference implementations of the cursor traits.
+
+use super::{DatabaseError, HashedCursor, HashedStorageCursor, TrieCursor, TrieCursorFactory};
+use crate::{BranchNodeCompact, HashMap, Nibbles};
+use alloc::collections::BTreeMap;
+use alloy_primitives::B256;
+use core::ops::Bound;
+
+#[cfg(feature = "ethereum")]
+use super::HashedCursorFactory;
+#[cfg(feature = "ethereum")]
+use crate::TrieAccount;
+#[cfg(feature = "ethereum")]
+use alloy_primitives::U256;
+
+/// In-memory collection of persisted account and storage trie nodes.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InMemoryTrieNodes {
+    /// Account trie branch nodes keyed by path.
+    pub account_nodes: BTreeMap<Nibbles, BranchNodeCompact>,
+    /// Storage trie branch nodes keyed by hashed address and path.
+    pub storage_nodes: HashMap<B256, BTreeMap<Nibbles, BranchNodeCompact>>,
+}
+
+impl<'a> TrieCursorFactory for &'a InMemoryTrieNodes {
+    type AccountTrieCursor = InMemoryTrieCursor<'a>;
+    type StorageTrieCursor = InMemoryTrieCursor<'a>;
+
+    fn account_trie_cursor(&self) -> Result<Self::AccountTrieCursor, DatabaseError> {
+        let this = *self;
+        Ok(InMemoryTrieCursor::new(Some(&this.account_nodes)))
+    }
+
+    fn storage_trie_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> Result<Self::StorageTrieCursor, DatabaseError> {
+        let this = *self;
+        Ok(InMemoryTrieCursor::new(this.storage_nodes.get(&hashed_address)))
+    }
+}
+
+/// In-memory [TrieCursor] over sorted branch nodes.
+#[derive(Clone, Debug)]
+pub struct InMemoryTrieCursor<'a> {
+    nodes: Option<&'a BTreeMap<Nibbles, BranchNodeCompact>>,
+    current: Option<Nibbles>,
+}
+
+impl<'a> InMemoryTrieCursor<'a> {
+    /// Creates a new cursor over the given nodes. [None] represents an empty trie.
+    pub const fn new(nodes: Option<&'a BTreeMap<Nibbles, BranchNodeCompact>>) -> Self {
+        Self { nodes, current: None }
+    }
+
+    fn set_current(
+        &mut self,
+        entry: Option<(&Nibbles, &BranchNodeCompact)>,
+    ) -> Option<(Nibbles, BranchNodeCompact)> {
+        let entry = entry.map(|(key, node)| (key.clone(), node.clone()));
+        self.current = entry.as_ref().map(|(key, _)| key.clone());
+        entry
+    }
+}
+
+impl TrieCursor for InMemoryTrieCursor<'_> {
+    fn seek_exact(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        let entry = self.nodes.and_then(|nodes| nodes.get_key_value(&key));
+        Ok(self.set_current(entry))
+    }
+
+    fn seek(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        let entry = self.nodes.and_then(|nodes| nodes.range(key..).next());
+        Ok(self.set_current(entry))
+    }
+
+    fn next(&mut self) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        let lower = self.current.clone().map_or(Bound::Unbounded, Bound::Excluded);
+        let entry = self.nodes.and_then(|nodes| nodes.range((lower, Bound::Unbounded)).next());
+        Ok(self.set_current(entry))
+    }
+
+    fn current(&mut self) -> Result<Option<Nibbles>, DatabaseError> {
+        Ok(self.current.clone())
+    }
+}
+
+/// In-memory collection of hashed accounts and hashed storage slots.
+#[cfg(feature = "ethereum")]
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InMemoryHashedState {
+    /// Hashed accounts keyed by hashed address.
+    pub accounts: BTreeMap<B256, TrieAccount>,
+    /// Hashed storage slots along with their privacy flag keyed by hashed address and slot.
+    pub storages: HashMap<B256, BTreeMap<B256, (U256, bool)>>,
+}
+
+#[cfg(feature = "ethereum")]
+impl<'a> HashedCursorFactory for &'a InMemoryHashedState {
+    type AccountCursor = InMemoryHashedCursor<'a, TrieAccount>;
+    type StorageCursor = InMemoryHashedCursor<'a, (U256, bool)>;
+
+    fn hashed_account_cursor(&self) -> Result<Self::AccountCursor, DatabaseError> {
+        let this = *self;
+        Ok(InMemoryHashedCursor::new(Some(&this.accounts)))
+    }
+
+    fn hashed_storage_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> Result<Self::StorageCursor, DatabaseError> {
+        let this = *self;
+        Ok(InMemoryHashedCursor::new(this.storages.get(&hashed_address)))
+    }
+}
+
+/// In-memory [HashedCursor] over sorted hashed entries.
+#[derive(Clone, Debug)]
+pub struct InMemoryHashedCursor<'a, V> {
+    entries: Option<&'a BTreeMap<B256, V>>,
+    current: Option<B256>,
+}
+
+impl<'a, V: Clone> InMemoryHashedCursor<'a, V> {
+    /// Creates a new cursor over the given entries. [None] represents no entries.
+    pub const fn new(entries: Option<&'a BTreeMap<B256, V>>) -> Self {
+        Self { entries, current: None }
+    }
+
+    fn set_current(&mut self, entry: Option<(&B256, &V)>) -> Option<(B256, V)> {
+        let entry = entry.map(|(key, value)| (*key, value.clone()));
+        self.current = entry.as_ref().map(|(key, _)| *key);
+        entry
+    }
+}
+
+impl<V: Clone> HashedCursor for InMemoryHashedCursor<'_, V> {
+    type Value = V;
+
+    fn seek(&mut self, key: B256) -> Result<Option<(B256, V)>, DatabaseError> {
+        let entry = self.entries.and_then(|entries| entries.range(key..).next());
+        Ok(self.set_current(entry))
+    }
+
+    fn next(&mut self) -> Result<Option<(B256, V)>, DatabaseError> {
+        let lower = self.current.map_or(Bound::Unbounded, Bound::Excluded);
+        let entry =
+            self.entries.and_then(|entries| entries.range((lower, Bound::Unbounded)).next());
+        Ok(self.set_current(entry))
+    }
+}
+
+impl<V: Clone> HashedStorageCursor for InMemoryHashedCursor<'_, V> {
+    fn is_storage_empty(&mut self) -> Result<bool, DatabaseError> {
+        Ok(self.entries.map_or(true, BTreeMap::is_empty))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trie_cursor() {
+        let nodes = InMemoryTrieNodes {
+            account_nodes: BTreeMap::from_iter(
+                [&[0x1u8][..], &[0x1, 0x2][..], &[0x3][..]].map(|path| {
+                    (Nibbles::from_nibbles_unchecked(path), BranchNodeCompact::default())
+                }),
+            ),
+            ..Default::default()
+        };
+        let factory = &nodes;
+
+        let mut cursor = factory.account_trie_cursor().unwrap();
+        assert_eq!(cursor.current(), Ok(None));
+        let first = cursor.next().unwrap().map(|(key, _)| key);
+        assert_eq!(first, Some(Nibbles::from_nibbles_unchecked([0x1])));
+
+        let seeked = cursor.seek(Nibbles::from_nibbles_unchecked([0x2])).unwrap();
+        assert_eq!(seeked.map(|(key, _)| key), Some(Nibbles::from_nibbles_unchecked([0x3])));
+        assert_eq!(cursor.next(), Ok(None));
+        assert_eq!(cursor.current(), Ok(None));
+
+        assert!(cursor.seek_exact(Nibbles::from_nibbles_unchecked([0x2])).unwrap().is_none());
+        let exact = cursor.seek_exact(Nibbles::from_nibbles_unchecked([0x1, 0x2])).unwrap();
+        assert_eq!(exact.map(|(key, _)| key), Some(Nibbles::from_nibbles_unchecked([0x1, 0x2])));
+        let next = cursor.next().unwrap().map(|(key, _)| key);
+        assert_eq!(next, Some(Nibbles::from_nibbles_unchecked([0x3])));
+
+        let mut storage_cursor = factory.storage_trie_cursor(B256::ZERO).unwrap();
+        assert_eq!(storage_cursor.seek(Nibbles::default()), Ok(None));
+    }
+
+    #[test]
+    fn hashed_cursor() {
+        let entries =
+            BTreeMap::from_iter([(B256::with_last_byte(1), 1), (B256::with_last_byte(3), 3)]);
+        let mut cursor = InMemoryHashedCursor::new(Some(&entries));
+        assert!(!cursor.is_storage_empty().unwrap());
+        assert_eq!(cursor.seek(B256::with_last_byte(2)), Ok(Some((B256::with_last_byte(3), 3))));
+        assert_eq!(cursor.next(), Ok(None));
+        assert_eq!(cursor.seek_exact(B256::with_last_byte(2)), Ok(None));
+        assert_eq!(
+            cursor.seek_exact(B256::with_last_byte(1)),
+            Ok(Some((B256::with_last_byte(1), 1)))
+        );
+        assert_eq!(cursor.next(), Ok(Some((B256::with_last_byte(3), 3))));
+
+        let mut empty = InMemoryHashedCursor::<u8>::new(None);
+        assert!(empty.is_storage_empty().unwrap());
+        assert_eq!(empty.next(), Ok(None));
+    }
+}
@@ -0,0 +1,911 @@
+//! Read-only store of RLP encoded trie nodes keyed by their hashes in a memory-mapped file.
+//!
+//! The store is a reference implementation to run root computation and proof generation against
+//! large states without a database, through the cursor factories of [MmapNodeStore::state]. The
+//! file starts with a header made of the [MAGIC] bytes, the format [VERSION] and the number of
+//! nodes as a big endian `u64`, followed by the index of the nodes sorted by hash and then by the
+//! nodes themselves:
+//!
+//! | field  | size | description                                            |
+//! |--------|------|--------------------------------------------------------|
+//! | hash   | 32   | the hash of the node                                   |
+//! | offset | 8    | the offset of the node after the index, big endian     |
+//! | length | 4    | the length of the node, big endian                     |
+//! | flags  | 1    | `0x01` if the subtrie of the node holds private leaves |
+
+use super::{DatabaseError, HashedCursor, TrieCursor};
+use crate::{
+    nibbles::pack_b256,
+    nodes::{BranchNode, LeafValue, RlpNode, TrieNode},
+    BranchNodeCompact, Nibbles, TrieMask, EMPTY_ROOT_HASH,
+};
+use alloc::vec::Vec;
+use alloy_primitives::{keccak256, Bytes, B256};
+use alloy_rlp::Decodable;
+use memmap2::Mmap;
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+#[cfg(feature = "ethereum")]
+use super::{HashedCursorFactory, HashedStorageCursor, TrieCursorFactory};
+#[cfg(feature = "ethereum")]
+use crate::TrieAccount;
+#[cfg(feature = "ethereum")]
+use alloy_primitives::U256;
+
+/// The magic bytes every node store file starts with.
+pub const MAGIC: [u8; 4] = *b"STND";
+
+/// The version of the node store format written by [MmapNodeStore::write].
+pub const VERSION: u8 = 1;
+
+/// The length of the header.
+const HEADER_LEN: usize = 4 + 1 + 8;
+
+/// The length of an index entry.
+const ENTRY_LEN: usize = 32 + 8 + 4 + 1;
+
+/// The flag set on the index entries of the nodes whose subtries hold private leaves.
+const PRIVATE_FLAG: u8 = 0x01;
+
+/// Read-only store of RLP encoded trie nodes keyed by their hashes, backed by a memory-mapped file
+/// written with [MmapNodeStore::write].
+///
+/// Nodes are looked up by hash with [MmapNodeStore::get], the nodes themselves are iterated with
+/// [MmapNodeStore::cursor], and the leaves and the branch nodes of the trie with a given root with
+/// [MmapNodeStore::leaf_cursor] and [MmapNodeStore::trie_cursor].
+#[derive(Debug)]
+pub struct MmapNodeStore {
+    /// The mapped file.
+    mmap: Mmap,
+    /// The number of nodes.
+    len: usize,
+}
+
+impl MmapNodeStore {
+    /// Writes the nodes in the store format, keyed by their hashes. Duplicate nodes are written
+    /// once.
+    ///
+    /// The nodes must be the complete tries, whose nodes not encoded in-place in their parent are
+    /// all given, so that the subtries holding private leaves are flagged. Nodes missing from the
+    /// store are taken as holding public leaves only.
+    pub fn write<W: Write>(
+        writer: &mut W,
+        nodes: impl IntoIterator<Item = Bytes>,
+    ) -> io::Result<()> {
+        let mut nodes = Vec::from_iter(nodes.into_iter().map(|node| (keccak256(&node), node)));
+        nodes.sort_unstable_by_key(|(hash, _)| *hash);
+        nodes.dedup_by_key(|(hash, _)| *hash);
+        let mut private = vec![None; nodes.len()];
+        for index in 0..nodes.len() {
+            subtrie_is_private(&nodes, &mut private, index)?;
+        }
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&(nodes.len() as u64).to_be_bytes())?;
+        let mut offset = 0u64;
+        for ((hash, node), is_private) in nodes.iter().zip(private) {
+            let length = u32::try_from(node.len())
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "node too long"))?;
+            writer.write_all(hash.as_slice())?;
+            writer.write_all(&offset.to_be_bytes())?;
+            writer.write_all(&length.to_be_bytes())?;
+            writer.write_all(&[if is_private == Some(true) { PRIVATE_FLAG } else { 0 }])?;
+            offset += u64::from(length);
+        }
+        for (_, node) in &nodes {
+            writer.write_all(node)?;
+        }
+        writer.flush()
+    }
+
+    /// Maps the node store file at the given path, checking its header and index.
+    ///
+    /// The file must not be modified while it is mapped.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the store is read-only, and the file is required not to be modified while
+        // mapped.
+        let mmap = unsafe { Mmap::map(&file)? };
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        if mmap.len() < HEADER_LEN || mmap[..4] != MAGIC {
+            return Err(invalid("invalid node store magic"));
+        }
+        if mmap[4] != VERSION {
+            return Err(invalid("unsupported node store version"));
+        }
+        let len = u64::from_be_bytes(mmap[5..HEADER_LEN].try_into().unwrap());
+        let data_len = usize::try_from(len)
+            .ok()
+            .and_then(|len| len.checked_mul(ENTRY_LEN))
+            .and_then(|index_len| mmap.len().checked_sub(HEADER_LEN + index_len))
+            .ok_or_else(|| invalid("truncated node store index"))?;
+
+        let this = Self { len: len as usize, mmap };
+        let mut last_hash = None;
+        for index in 0..this.len {
+            let (hash, offset, length) = this.entry(index);
+            if last_hash.is_some_and(|last_hash| hash <= last_hash) {
+                return Err(invalid("unsorted node store index"));
+            }
+            if offset.checked_add(length).map_or(true, |end| end > data_len) {
+                return Err(invalid("node out of the node store bounds"));
+            }
+            if this.flags(index) & !PRIVATE_FLAG != 0 {
+                return Err(invalid("invalid node store flags"));
+            }
+            last_hash = Some(hash);
+        }
+        Ok(this)
+    }
+
+    /// Returns the number of nodes in the store.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the store has no nodes.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the RLP encoded node with the given hash.
+    pub fn get(&self, hash: &B256) -> Option<&[u8]> {
+        let index = self.search(hash);
+        (index < self.len && self.entry(index).0 == *hash).then(|| self.node(index))
+    }
+
+    /// Returns a cursor over the nodes of the store keyed by their hashes.
+    pub const fn cursor(&self) -> MmapNodeCursor<'_> {
+        MmapNodeCursor { store: self, current: None }
+    }
+
+    /// Returns a cursor over the leaves of the trie with the given root, e.g. to compute the root
+    /// of the trie incrementally or to generate proofs with a
+    /// [HashBuilder](crate::HashBuilder).
+    ///
+    /// The leaf keys must be 32 bytes long. Reading through a node missing from the store returns
+    /// a [DatabaseError].
+    pub const fn leaf_cursor(&self, root: B256) -> MmapLeafCursor<'_> {
+        MmapLeafCursor { store: self, root, stack: Vec::new() }
+    }
+
+    /// Returns a cursor over the branch nodes of the trie with the given root, as stored by the
+    /// [HashBuilder](crate::HashBuilder) with the updates retained, so that the unchanged subtries
+    /// are skipped when computing the root of the trie incrementally.
+    ///
+    /// Reading through a node missing from the store returns a [DatabaseError].
+    pub const fn trie_cursor(&self, root: B256) -> MmapTrieCursor<'_> {
+        MmapTrieCursor { store: self, root, stack: Vec::new(), current: None }
+    }
+
+    /// Returns the factories of the trie and hashed cursors over the state with the given root,
+    /// e.g. to compute the state root with [StateRoot](crate::StateRoot) or the proofs with
+    /// [account_proof](crate::proof::account_proof) against the stored state.
+    #[cfg(feature = "ethereum")]
+    pub const fn state(&self, root: B256) -> MmapState<'_> {
+        MmapState { store: self, root }
+    }
+
+    /// Returns the index of the first entry whose hash is not less than the given one.
+    fn search(&self, hash: &B256) -> usize {
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.entry(mid).0 < *hash {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    /// Returns the hash, the offset and the length of the node of the entry at the given index.
+    fn entry(&self, index: usize) -> (B256, usize, usize) {
+        let entry = &self.mmap[HEADER_LEN + index * ENTRY_LEN..][..ENTRY_LEN];
+        let hash = B256::from_slice(&entry[..32]);
+        let offset = u64::from_be_bytes(entry[32..40].try_into().unwrap());
+        let length = u32::from_be_bytes(entry[40..44].try_into().unwrap());
+        (hash, offset as usize, length as usize)
+    }
+
+    /// Returns the flags of the entry at the given index.
+    fn flags(&self, index: usize) -> u8 {
+        self.mmap[HEADER_LEN + index * ENTRY_LEN + ENTRY_LEN - 1]
+    }
+
+    /// Returns the node of the entry at the given index.
+    fn node(&self, index: usize) -> &[u8] {
+        let (_, offset, length) = self.entry(index);
+        &self.mmap[HEADER_LEN + self.len * ENTRY_LEN + offset..][..length]
+    }
+
+    /// Decodes the node referenced by its parent at the given path.
+    fn resolve(&self, path: &Nibbles, child: &RlpNode) -> Result<TrieNode, DatabaseError> {
+        let decode = |mut node: &[u8]| TrieNode::decode(&mut node).map_err(DatabaseError::new);
+        let Some(hash) = child.as_hash() else { return decode(child) };
+        if hash == EMPTY_ROOT_HASH {
+            return Ok(TrieNode::EmptyRoot);
+        }
+        let node = self.get(&hash).ok_or_else(|| {
+            DatabaseError::new(format_args!("missing trie node {hash} at path {path:?}"))
+        })?;
+        decode(node)
+    }
+
+    /// Resolves the child of an extension node at the given path, which must be a branch node.
+    fn resolve_branch(&self, path: &Nibbles, child: &RlpNode) -> Result<BranchNode, DatabaseError> {
+        match self.resolve(path, child)? {
+            TrieNode::Branch(branch) => Ok(branch),
+            _ => Err(DatabaseError::new(format_args!(
+                "extension node child at path {path:?} is not a branch node"
+            ))),
+        }
+    }
+
+    /// Returns `true` if the subtrie of the node referenced by its parent at the given path holds
+    /// private leaves.
+    fn is_private(&self, path: &Nibbles, child: &RlpNode) -> Result<bool, DatabaseError> {
+        if let Some(hash) = child.as_hash() {
+            let index = self.search(&hash);
+            if index < self.len && self.entry(index).0 == hash {
+                return Ok(self.flags(index) & PRIVATE_FLAG != 0);
+            }
+        }
+        Ok(match self.resolve(path, child)? {
+            TrieNode::EmptyRoot => false,
+            TrieNode::Leaf(leaf) => leaf.is_private,
+            TrieNode::Extension(extension) => {
+                self.is_private(&path.join(&extension.key), &extension.child)?
+            }
+            TrieNode::Branch(branch) => {
+                for (nibble, child) in branch.as_ref().children() {
+                    if let Some(child) = child {
+                        let mut child_path = path.clone();
+                        child_path.push(nibble);
+                        if self.is_private(&child_path, child)? {
+                            return Ok(true);
+                        }
+                    }
+                }
+                false
+            }
+        })
+    }
+
+    /// Returns `true` if the [HashBuilder](crate::HashBuilder) stores the branch node, i.e. one
+    /// of its children is a branch node or an extension node pointing to a stored branch node.
+    fn is_stored(&self, path: &Nibbles, branch: &BranchNode) -> Result<bool, DatabaseError> {
+        for (nibble, child) in branch.as_ref().children() {
+            let Some(child) = child else { continue };
+            let mut child_path = path.clone();
+            child_path.push(nibble);
+            match self.resolve(&child_path, child)? {
+                TrieNode::Branch(_) => return Ok(true),
+                TrieNode::Extension(extension) => {
+                    let child_path = child_path.join(&extension.key);
+                    let child = self.resolve_branch(&child_path, &extension.child)?;
+                    if self.is_stored(&child_path, &child)? {
+                        return Ok(true);
+                    }
+                }
+                TrieNode::EmptyRoot | TrieNode::Leaf(_) => {}
+            }
+        }
+        Ok(false)
+    }
+
+    /// Returns the branch node at the given path as stored by the [HashBuilder](crate::HashBuilder),
+    /// or [None] if it is not stored, and appends the branch nodes below it to `children` along
+    /// with their paths.
+    fn compact_branch(
+        &self,
+        root: B256,
+        path: &Nibbles,
+        branch: &BranchNode,
+        children: &mut Vec<(Nibbles, BranchNode)>,
+    ) -> Result<Option<BranchNodeCompact>, DatabaseError> {
+        let (mut tree_mask, mut hash_mask, mut private_mask) =
+            (TrieMask::default(), TrieMask::default(), TrieMask::default());
+        let mut hashes = Vec::new();
+        for (nibble, child) in branch.as_ref().children() {
+            let Some(child) = child else { continue };
+            let mut child_path = path.clone();
+            child_path.push(nibble);
+            if self.is_private(&child_path, child)? {
+                private_mask.set_bit(nibble);
+            }
+            match self.resolve(&child_path, child)? {
+                TrieNode::Branch(child_branch) => {
+                    let hash = child.as_hash().ok_or_else(|| {
+                        DatabaseError::new(format_args!(
+                            "branch node at path {child_path:?} is encoded in-place"
+                        ))
+                    })?;
+                    hash_mask.set_bit(nibble);
+                    hashes.push(hash);
+                    if self.is_stored(&child_path, &child_branch)? {
+                        tree_mask.set_bit(nibble);
+                    }
+                    children.push((child_path, child_branch));
+                }
+                TrieNode::Extension(extension) => {
+                    let child_path = child_path.join(&extension.key);
+                    let child_branch = self.resolve_branch(&child_path, &extension.child)?;
+                    if self.is_stored(&child_path, &child_branch)? {
+                        tree_mask.set_bit(nibble);
+                    }
+                    children.push((child_path, child_branch));
+                }
+                TrieNode::EmptyRoot | TrieNode::Leaf(_) => {}
+            }
+        }
+
+        let node = (!tree_mask.is_empty() || !hash_mask.is_empty()).then(|| {
+            BranchNodeCompact::new(
+                branch.state_mask,
+                tree_mask,
+                hash_mask,
+                hashes,
+                path.is_empty().then_some(root),
+            )
+            .with_private_mask(private_mask)
+        });
+        Ok(node)
+    }
+}
+
+/// Returns `true` if the subtrie of the node at the given index of the sorted nodes holds private
+/// leaves, memoizing the result of every visited node.
+fn subtrie_is_private(
+    nodes: &[(B256, Bytes)],
+    private: &mut [Option<bool>],
+    index: usize,
+) -> io::Result<bool> {
+    if let Some(is_private) = private[index] {
+        return Ok(is_private);
+    }
+    let is_private = node_is_private(nodes, private, &nodes[index].1)?;
+    private[index] = Some(is_private);
+    Ok(is_private)
+}
+
+/// Returns `true` if the subtrie of the RLP encoded node holds private leaves, see
+/// [subtrie_is_private].
+fn node_is_private(
+    nodes: &[(B256, Bytes)],
+    private: &mut [Option<bool>],
+    mut node: &[u8],
+) -> io::Result<bool> {
+    let node = TrieNode::decode(&mut node)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error.to_string()))?;
+    let children = match node {
+        TrieNode::EmptyRoot => return Ok(false),
+        TrieNode::Leaf(leaf) => return Ok(leaf.is_private),
+        TrieNode::Extension(extension) => Vec::from([extension.child]),
+        TrieNode::Branch(branch) => branch.stack,
+    };
+    for child in children {
+        let is_private = match child.as_hash() {
+            Some(hash) => match nodes.binary_search_by_key(&hash, |(hash, _)| *hash) {
+                Ok(index) => subtrie_is_private(nodes, private, index)?,
+                Err(_) => false,
+            },
+            None => node_is_private(nodes, private, &child)?,
+        };
+        if is_private {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// [HashedCursor] over the nodes of a [MmapNodeStore] keyed by their hashes.
+#[derive(Clone, Copy, Debug)]
+pub struct MmapNodeCursor<'a> {
+    store: &'a MmapNodeStore,
+    /// The index of the entry the cursor is positioned at.
+    current: Option<usize>,
+}
+
+impl MmapNodeCursor<'_> {
+    fn set_current(&mut self, index: usize) -> Option<(B256, Bytes)> {
+        if index >= self.store.len {
+            self.current = None;
+            return None;
+        }
+        self.current = Some(index);
+        Some((self.store.entry(index).0, Bytes::copy_from_slice(self.store.node(index))))
+    }
+}
+
+impl HashedCursor for MmapNodeCursor<'_> {
+    type Value = Bytes;
+
+    fn seek(&mut self, key: B256) -> Result<Option<(B256, Bytes)>, DatabaseError> {
+        Ok(self.set_current(self.store.search(&key)))
+    }
+
+    fn next(&mut self) -> Result<Option<(B256, Bytes)>, DatabaseError> {
+        let index = self.current.map_or(0, |index| index + 1);
+        Ok(self.set_current(index))
+    }
+}
+
+/// [HashedCursor] over the leaves of a trie stored in a [MmapNodeStore], see
+/// [MmapNodeStore::leaf_cursor].
+#[derive(Clone, Debug)]
+pub struct MmapLeafCursor<'a> {
+    store: &'a MmapNodeStore,
+    /// The root of the trie.
+    root: B256,
+    /// The paths and references of the nodes left to visit, the next one last.
+    stack: Vec<(Nibbles, RlpNode)>,
+}
+
+impl MmapLeafCursor<'_> {
+    /// Returns the next leaf whose key is not less than the lower bound, if any.
+    fn next_leaf(
+        &mut self,
+        lower_bound: Option<&Nibbles>,
+    ) -> Result<Option<(B256, LeafValue)>, DatabaseError> {
+        // Subtries whose keys are all less than the lower bound are not visited.
+        let in_bounds = |path: &Nibbles| {
+            lower_bound.map_or(true, |bound| path[..] >= bound[..path.len().min(bound.len())])
+        };
+        while let Some((path, child)) = self.stack.pop() {
+            match self.store.resolve(&path, &child)? {
+                TrieNode::EmptyRoot => {}
+                TrieNode::Leaf(leaf) => {
+                    let key = path.join(&leaf.key);
+                    if lower_bound.is_some_and(|bound| key < *bound) {
+                        continue;
+                    }
+                    if key.len() != 64 {
+                        return Err(DatabaseError::new(format_args!(
+                            "leaf key {key:?} is not 32 bytes long"
+                        )));
+                    }
+                    return Ok(Some((pack_b256(&key), leaf.leaf_value())));
+                }
+                TrieNode::Extension(extension) => {
+                    let child_path = path.join(&extension.key);
+                    if in_bounds(&child_path) {
+                        self.stack.push((child_path, extension.child));
+                    }
+                }
+                TrieNode::Branch(branch) => {
+                    let children =
+                        Vec::from_iter(branch.as_ref().children().filter_map(|(nibble, child)| {
+                            child.map(|child| (nibble, child.clone()))
+                        }));
+                    for (nibble, child) in children.into_iter().rev() {
+                        let mut child_path = path.clone();
+                        child_path.push(nibble);
+                        if in_bounds(&child_path) {
+                            self.stack.push((child_path, child));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl HashedCursor for MmapLeafCursor<'_> {
+    type Value = LeafValue;
+
+    fn seek(&mut self, key: B256) -> Result<Option<(B256, LeafValue)>, DatabaseError> {
+        self.stack = Vec::from([(Nibbles::default(), RlpNode::word_rlp(&self.root))]);
+        self.next_leaf(Some(&Nibbles::unpack(key)))
+    }
+
+    fn next(&mut self) -> Result<Option<(B256, LeafValue)>, DatabaseError> {
+        self.next_leaf(None)
+    }
+}
+
+/// [TrieCursor] over the branch nodes of a trie stored in a [MmapNodeStore], see
+/// [MmapNodeStore::trie_cursor].
+#[derive(Clone, Debug)]
+pub struct MmapTrieCursor<'a> {
+    store: &'a MmapNodeStore,
+    /// The root of the trie.
+    root: B256,
+    /// The paths of the branch nodes left to visit, the next one last.
+    stack: Vec<(Nibbles, BranchNode)>,
+    /// The path of the branch node the cursor is positioned at.
+    current: Option<Nibbles>,
+}
+
+impl MmapTrieCursor<'_> {
+    /// Moves the cursor back to the root of the trie.
+    fn reset(&mut self) -> Result<(), DatabaseError> {
+        self.stack.clear();
+        let root = Nibbles::default();
+        match self.store.resolve(&root, &RlpNode::word_rlp(&self.root))? {
+            TrieNode::Branch(branch) => self.stack.push((root, branch)),
+            TrieNode::Extension(extension) => {
+                let path = extension.key;
+                let branch = self.store.resolve_branch(&path, &extension.child)?;
+                self.stack.push((path, branch));
+            }
+            TrieNode::EmptyRoot | TrieNode::Leaf(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Returns the next stored branch node whose path is not less than the lower bound, if any.
+    fn next_node(
+        &mut self,
+        lower_bound: Option<&Nibbles>,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        // Subtries whose paths are all less than the lower bound are not visited.
+        let in_bounds = |path: &Nibbles| {
+            lower_bound.map_or(true, |bound| path[..] >= bound[..path.len().min(bound.len())])
+        };
+        while let Some((path, branch)) = self.stack.pop() {
+            let mut children = Vec::new();
+            let node = self.store.compact_branch(self.root, &path, &branch, &mut children)?;
+            self.stack.extend(children.into_iter().rev().filter(|(path, _)| in_bounds(path)));
+            if let Some(node) = node {
+                if lower_bound.map_or(true, |bound| path >= *bound) {
+                    self.current = Some(path.clone());
+                    return Ok(Some((path, node)));
+                }
+            }
+        }
+        self.current = None;
+        Ok(None)
+    }
+}
+
+impl TrieCursor for MmapTrieCursor<'_> {
+    fn seek_exact(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        let entry = self.seek(key.clone())?.filter(|(path, _)| *path == key);
+        if entry.is_none() {
+            self.current = None;
+        }
+        Ok(entry)
+    }
+
+    fn seek(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        self.reset()?;
+        self.next_node(Some(&key))
+    }
+
+    fn next(&mut self) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+        if self.current.is_none() {
+            self.reset()?;
+        }
+        self.next_node(None)
+    }
+
+    fn current(&mut self) -> Result<Option<Nibbles>, DatabaseError> {
+        Ok(self.current.clone())
+    }
+}
+
+/// Factory of the trie and hashed cursors over the state stored in a [MmapNodeStore], see
+/// [MmapNodeStore::state].
+///
+/// The storage tries are found through the storage roots of the accounts in the account trie.
+#[cfg(feature = "ethereum")]
+#[derive(Clone, Copy, Debug)]
+pub struct MmapState<'a> {
+    store: &'a MmapNodeStore,
+    /// The state root.
+    root: B256,
+}
+
+#[cfg(feature = "ethereum")]
+impl MmapState<'_> {
+    /// Returns the storage root of the account, or [EMPTY_ROOT_HASH] if it does not exist.
+    fn storage_root(&self, hashed_address: B256) -> Result<B256, DatabaseError> {
+        let mut accounts = MmapAccountCursor(self.store.leaf_cursor(self.root));
+        Ok(accounts
+            .seek_exact(hashed_address)?
+            .map_or(EMPTY_ROOT_HASH, |(_, account)| account.storage_root))
+    }
+}
+
+#[cfg(feature = "ethereum")]
+impl<'a> TrieCursorFactory for MmapState<'a> {
+    type AccountTrieCursor = MmapTrieCursor<'a>;
+    type StorageTrieCursor = MmapTrieCursor<'a>;
+
+    fn account_trie_cursor(&self) -> Result<Self::AccountTrieCursor, DatabaseError> {
+        Ok(self.store.trie_cursor(self.root))
+    }
+
+    fn storage_trie_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> Result<Self::StorageTrieCursor, DatabaseError> {
+        Ok(self.store.trie_cursor(self.storage_root(hashed_address)?))
+    }
+}
+
+#[cfg(feature = "ethereum")]
+impl<'a> HashedCursorFactory for MmapState<'a> {
+    type AccountCursor = MmapAccountCursor<'a>;
+    type StorageCursor = MmapStorageCursor<'a>;
+
+    fn hashed_account_cursor(&self) -> Result<Self::AccountCursor, DatabaseError> {
+        Ok(MmapAccountCursor(self.store.leaf_cursor(self.root)))
+    }
+
+    fn hashed_storage_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> Result<Self::StorageCursor, DatabaseError> {
+        Ok(MmapStorageCursor(self.store.leaf_cursor(self.storage_root(hashed_address)?)))
+    }
+}
+
+/// [HashedCursor] over the accounts of a state stored in a [MmapNodeStore], see [MmapState].
+#[cfg(feature = "ethereum")]
+#[derive(Clone, Debug)]
+pub struct MmapAccountCursor<'a>(MmapLeafCursor<'a>);
+
+#[cfg(feature = "ethereum")]
+impl MmapAccountCursor<'_> {
+    fn decode((key, value): (B256, LeafValue)) -> Result<(B256, TrieAccount), DatabaseError> {
+        let account = TrieAccount::decode(&mut &value.value()[..]).map_err(DatabaseError::new)?;
+        Ok((key, account))
+    }
+}
+
+#[cfg(feature = "ethereum")]
+impl HashedCursor for MmapAccountCursor<'_> {
+    type Value = TrieAccount;
+
+    fn seek(&mut self, key: B256) -> Result<Option<(B256, TrieAccount)>, DatabaseError> {
+        self.0.seek(key)?.map(Self::decode).transpose()
+    }
+
+    fn next(&mut self) -> Result<Option<(B256, TrieAccount)>, DatabaseError> {
+        self.0.next()?.map(Self::decode).transpose()
+    }
+}
+
+/// [HashedStorageCursor] over the storage slots of an account stored in a [MmapNodeStore], along
+/// with their privacy flag, see [MmapState].
+#[cfg(feature = "ethereum")]
+#[derive(Clone, Debug)]
+pub struct MmapStorageCursor<'a>(MmapLeafCursor<'a>);
+
+#[cfg(feature = "ethereum")]
+impl MmapStorageCursor<'_> {
+    fn decode((key, value): (B256, LeafValue)) -> Result<(B256, (U256, bool)), DatabaseError> {
+        let slot = U256::decode(&mut &value.value()[..]).map_err(DatabaseError::new)?;
+        Ok((key, (slot, value.is_private())))
+    }
+}
+
+#[cfg(feature = "ethereum")]
+impl HashedCursor for MmapStorageCursor<'_> {
+    type Value = (U256, bool);
+
+    fn seek(&mut self, key: B256) -> Result<Option<(B256, (U256, bool))>, DatabaseError> {
+        self.0.seek(key)?.map(Self::decode).transpose()
+    }
+
+    fn next(&mut self) -> Result<Option<(B256, (U256, bool))>, DatabaseError> {
+        self.0.next()?.map(Self::decode).transpose()
+    }
+}
+
+#[cfg(feature = "ethereum")]
+impl HashedStorageCursor for MmapStorageCursor<'_> {
+    fn is_storage_empty(&mut self) -> Result<bool, DatabaseError> {
+        Ok(self.0.root == EMPTY_ROOT_HASH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cursor::in_memory::InMemoryTrieNodes,
+        proof::{ProofNodes, ProofRetainer},
+        HashBuilder,
+    };
+    use alloc::collections::BTreeMap;
+
+    /// Writes the nodes to a temporary file and maps it.
+    fn write_store(name: &str, nodes: impl IntoIterator<Item = Bytes>) -> MmapNodeStore {
+        let path =
+            std::env::temp_dir().join(format!("alloy-trie-mmap-{name}-{}", std::process::id()));
+        let mut file = File::create(&path).unwrap();
+        MmapNodeStore::write(&mut file, nodes).unwrap();
+        let store = MmapNodeStore::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        store
+    }
+
+    #[test]
+    fn read_mapped_nodes() {
+        let leaves = (0..200u64)
+            .map(|i| (keccak256(i.to_be_bytes()), LeafValue::new(alloy_rlp::encode(i), i % 3 == 0)))
+            .collect::<BTreeMap<_, _>>();
+        let mut hb = HashBuilder::default()
+            .with_proof_retainer(ProofRetainer::from_iter(leaves.keys().map(Nibbles::unpack)));
+        for (key, value) in &leaves {
+            hb.add_leaf(Nibbles::unpack(key), value.value(), value.is_private());
+        }
+        let root = hb.root();
+        let nodes = hb
+            .take_proof_nodes()
+            .into_inner()
+            .into_iter()
+            .filter(|(path, node)| path.is_empty() || node.len() >= 32)
+            .map(|(_, node)| node)
+            .collect::<Vec<_>>();
+
+        let store = write_store("nodes", nodes.iter().chain(&nodes).cloned());
+
+        assert_eq!(store.len(), nodes.len());
+        for node in &nodes {
+            assert_eq!(store.get(&keccak256(node)), Some(&node[..]));
+        }
+        assert_eq!(store.get(&B256::ZERO), None);
+
+        // The nodes are iterated in hash order.
+        let mut cursor = store.cursor();
+        let mut hashes = Vec::new();
+        let mut entry = cursor.seek(B256::ZERO).unwrap();
+        while let Some((hash, node)) = entry {
+            assert_eq!(keccak256(&node), hash);
+            hashes.push(hash);
+            entry = cursor.next().unwrap();
+        }
+        assert_eq!(hashes.len(), nodes.len());
+        assert!(hashes.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // The leaves are iterated in key order from any key, and hash back to the root.
+        let mut cursor = store.leaf_cursor(root);
+        let mut hb = HashBuilder::default();
+        let mut entry = cursor.seek(B256::ZERO).unwrap();
+        let mut read = BTreeMap::new();
+        while let Some((key, value)) = entry {
+            hb.add_leaf(Nibbles::unpack(key), value.value(), value.is_private());
+            read.insert(key, value);
+            entry = cursor.next().unwrap();
+        }
+        assert_eq!(read, leaves);
+        assert_eq!(hb.root(), root);
+        for (key, value) in leaves.iter().step_by(17) {
+            assert_eq!(cursor.seek(*key), Ok(Some((*key, value.clone()))));
+            let after = leaves.range(*key..).nth(1).map(|(key, value)| (*key, value.clone()));
+            assert_eq!(cursor.next(), Ok(after));
+        }
+        assert_eq!(cursor.seek(B256::repeat_byte(0xff)), Ok(None));
+        assert_eq!(store.leaf_cursor(EMPTY_ROOT_HASH).seek(B256::ZERO), Ok(None));
+        assert!(store.leaf_cursor(B256::ZERO).seek(B256::ZERO).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn state_from_mapped_nodes() {
+        use crate::{
+            cursor::{in_memory::InMemoryHashedState, TrieCursorFactory},
+            proof::{account_proof, MultiProof, StorageMultiProof},
+            state_root::{state_root_with_overlay, StateRoot},
+            HashedPostState,
+        };
+        use alloy_primitives::{Address, U256};
+        use alloy_rlp::encode_fixed_size;
+
+        let mut state = InMemoryHashedState::default();
+        for i in 0..50u8 {
+            let hashed_address = keccak256(Address::repeat_byte(i));
+            state
+                .accounts
+                .insert(hashed_address, TrieAccount { nonce: i.into(), ..Default::default() });
+            if i % 5 != 0 {
+                state.storages.insert(
+                    hashed_address,
+                    BTreeMap::from_iter((1..=i % 7 * 10).map(|slot| {
+                        let value = U256::from(u32::from(i) * 1000 + u32::from(slot));
+                        (keccak256(B256::with_last_byte(slot)), (value, slot % 3 == 0))
+                    })),
+                );
+            }
+        }
+        let (root, updates) =
+            StateRoot::new(&InMemoryTrieNodes::default(), &state).root_with_updates().unwrap();
+
+        // Every node of the account and storage tries not encoded in-place.
+        let stored = |nodes: ProofNodes| {
+            nodes
+                .into_inner()
+                .into_iter()
+                .filter(|(path, node)| path.is_empty() || node.len() >= 32)
+                .map(|(_, node)| node)
+        };
+        let mut nodes = Vec::new();
+        let mut accounts = Vec::new();
+        for (hashed_address, account) in &state.accounts {
+            let storage = state.storages.get(hashed_address).cloned().unwrap_or_default();
+            let storage = StorageMultiProof::from_sorted_leaves(
+                storage.iter().map(|(slot, (value, is_private))| {
+                    (*slot, encode_fixed_size(value), *is_private)
+                }),
+                storage.keys().copied(),
+            );
+            accounts.push((
+                *hashed_address,
+                alloy_rlp::encode(TrieAccount { storage_root: storage.root, ..*account }),
+            ));
+            nodes.extend(stored(storage.subtree));
+        }
+        let (account_root, multiproof) =
+            MultiProof::from_sorted_accounts(accounts, state.accounts.keys().copied());
+        assert_eq!(account_root, root);
+        nodes.extend(stored(multiproof.account_subtree));
+        let store = write_store("state", nodes);
+        let mapped = store.state(root);
+
+        // The trie cursors return the branch nodes stored by the Hash Builder.
+        let read_nodes = |mut cursor: MmapTrieCursor<'_>| {
+            let mut nodes = BTreeMap::new();
+            let mut entry = cursor.seek(Nibbles::default()).unwrap();
+            while let Some((path, node)) = entry {
+                nodes.insert(path, node);
+                entry = cursor.next().unwrap();
+            }
+            nodes
+        };
+        let account_nodes = read_nodes(mapped.account_trie_cursor().unwrap());
+        assert!(!account_nodes.is_empty());
+        assert_eq!(account_nodes, BTreeMap::from_iter(updates.account_nodes.updated_nodes));
+        for (hashed_address, storage_updates) in updates.storage_tries {
+            let storage_nodes = read_nodes(mapped.storage_trie_cursor(hashed_address).unwrap());
+            assert_eq!(storage_nodes, BTreeMap::from_iter(storage_updates.updated_nodes));
+        }
+
+        assert_eq!(StateRoot::new(mapped, mapped).root(), Ok(root));
+
+        // The root is computed incrementally on top of the mapped state.
+        let changed = keccak256(Address::repeat_byte(3));
+        let private_slot = keccak256(B256::with_last_byte(0xff));
+        let post_state = HashedPostState::default()
+            .with_accounts([(changed, Some(TrieAccount { nonce: 1000, ..Default::default() }))])
+            .with_storages([(
+                changed,
+                crate::HashedStorage::from_iter([(private_slot, (U256::from(1), true))]),
+            )]);
+        let mut expected = state.clone();
+        expected.accounts.get_mut(&changed).unwrap().nonce = 1000;
+        expected.storages.get_mut(&changed).unwrap().insert(private_slot, (U256::from(1), true));
+        assert_eq!(
+            state_root_with_overlay(mapped, mapped, post_state),
+            StateRoot::new(&InMemoryTrieNodes::default(), &expected).root()
+        );
+
+        // The proofs are the same as the ones built from the hashed state.
+        let address = Address::repeat_byte(8);
+        let slots = [B256::with_last_byte(3), B256::with_last_byte(4), B256::with_last_byte(0xff)];
+        let proof = account_proof(mapped, mapped, address, &slots).unwrap();
+        assert_eq!(proof.verify(root), Ok(()));
+        assert_eq!(
+            proof,
+            account_proof(&InMemoryTrieNodes::default(), &state, address, &slots).unwrap()
+        );
+        assert!(proof.storage_proofs[0].is_private);
+    }
+}
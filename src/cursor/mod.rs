@@ -0,0 +1,132 @@
+//! Cursor abstractions over persisted trie nodes and hashed state entries.
+//!
+//! Implement these traits on top of a database to plug it into incremental root computation.
+//! In-memory reference implementations are provided in [`in_memory`], and in-memory state changes
+//! can be overlaid on top of any hashed cursors with [`post_state`].
+
+use crate::{BranchNodeCompact, Nibbles};
+use alloc::string::{String, ToString};
+use alloy_primitives::B256;
+use core::fmt;
+
+#[cfg(feature = "ethereum")]
+use crate::TrieAccount;
+#[cfg(feature = "ethereum")]
+use alloy_primitives::U256;
+
+pub mod in_memory;
+
+#[cfg(feature = "async")]
+pub mod asynchronous;
+
+#[cfg(feature = "mmap")]
+pub mod mmap;
+
+#[cfg(feature = "ethereum")]
+pub mod post_state;
+
+/// Error returned by the cursor implementations.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DatabaseError(String);
+
+impl DatabaseError {
+    /// Creates a new database error with the given message.
+    pub fn new(message: impl fmt::Display) -> Self {
+        Self(message.to_string())
+    }
+
+    /// Returns the error message.
+    pub fn message(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DatabaseError {}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "database error: {}", self.0)
+    }
+}
+
+/// A cursor for navigating the persisted branch nodes of a trie, keyed by their path.
+pub trait TrieCursor {
+    /// Moves the cursor to the exact key and returns the node stored there.
+    fn seek_exact(
+        &mut self,
+        key: Nibbles,
+    ) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError>;
+
+    /// Moves the cursor to the first key that is greater than or equal to the given key.
+    fn seek(&mut self, key: Nibbles)
+        -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError>;
+
+    /// Moves the cursor to the next key.
+    fn next(&mut self) -> Result<Option<(Nibbles, BranchNodeCompact)>, DatabaseError>;
+
+    /// Returns the key the cursor is currently positioned at.
+    fn current(&mut self) -> Result<Option<Nibbles>, DatabaseError>;
+}
+
+/// A cursor for navigating hashed state entries, such as hashed accounts or hashed storage slots.
+pub trait HashedCursor {
+    /// The value of the entries.
+    type Value;
+
+    /// Moves the cursor to the first key that is greater than or equal to the given key.
+    fn seek(&mut self, key: B256) -> Result<Option<(B256, Self::Value)>, DatabaseError>;
+
+    /// Moves the cursor to the next entry.
+    fn next(&mut self) -> Result<Option<(B256, Self::Value)>, DatabaseError>;
+
+    /// Moves the cursor to the exact key and returns the entry stored there.
+    fn seek_exact(&mut self, key: B256) -> Result<Option<(B256, Self::Value)>, DatabaseError> {
+        Ok(self.seek(key)?.filter(|(entry_key, _)| *entry_key == key))
+    }
+}
+
+/// A cursor for navigating the hashed storage entries of a single account.
+pub trait HashedStorageCursor: HashedCursor {
+    /// Returns `true` if there are no storage entries for the account.
+    fn is_storage_empty(&mut self) -> Result<bool, DatabaseError>;
+}
+
+/// Factory for creating cursors over persisted account and storage trie nodes.
+pub trait TrieCursorFactory {
+    /// The account trie cursor type.
+    type AccountTrieCursor: TrieCursor;
+    /// The storage trie cursor type.
+    type StorageTrieCursor: TrieCursor;
+
+    /// Creates a cursor over the account trie nodes.
+    fn account_trie_cursor(&self) -> Result<Self::AccountTrieCursor, DatabaseError>;
+
+    /// Creates a cursor over the storage trie nodes of the given account.
+    fn storage_trie_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> Result<Self::StorageTrieCursor, DatabaseError>;
+}
+
+/// Factory for creating cursors over hashed accounts and hashed storage slots.
+///
+/// Storage values are returned along with their privacy flag. The storage roots of the accounts
+/// returned by the account cursor are ignored by root computation and recomputed from the storage
+/// entries instead.
+#[cfg(feature = "ethereum")]
+pub trait HashedCursorFactory {
+    /// The hashed account cursor type.
+    type AccountCursor: HashedCursor<Value = TrieAccount>;
+    /// The hashed storage cursor type.
+    type StorageCursor: HashedStorageCursor<Value = (U256, bool)>;
+
+    /// Creates a cursor over the hashed accounts.
+    fn hashed_account_cursor(&self) -> Result<Self::AccountCursor, DatabaseError>;
+
+    /// Creates a cursor over the hashed storage of the given account.
+    fn hashed_storage_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> Result<Self::StorageCursor, DatabaseError>;
+}
@@ -0,0 +1,226 @@
+//! Hashed cursors overlaying in-memory state changes on top of other hashed cursors.
+
+use super::{DatabaseError, HashedCursor, HashedCursorFactory, HashedStorageCursor};
+use crate::hashed_state::HashedPostStateSorted;
+use alloy_primitives::{map::HashSet, B256};
+
+/// Factory of hashed cursors that overlay the changes of a [HashedPostStateSorted] on top of the
+/// cursors of another factory, such as the ones over the database.
+#[derive(Clone, Debug)]
+pub struct HashedPostStateCursorFactory<'a, F> {
+    /// The factory of the underlying cursors.
+    cursor_factory: F,
+    /// The state changes.
+    post_state: &'a HashedPostStateSorted,
+}
+
+impl<'a, F> HashedPostStateCursorFactory<'a, F> {
+    /// Creates a new factory overlaying the given state changes.
+    pub const fn new(cursor_factory: F, post_state: &'a HashedPostStateSorted) -> Self {
+        Self { cursor_factory, post_state }
+    }
+}
+
+impl<'a, F: HashedCursorFactory> HashedCursorFactory for HashedPostStateCursorFactory<'a, F> {
+    type AccountCursor = HashedPostStateCursor<'a, F::AccountCursor>;
+    type StorageCursor = HashedPostStateCursor<'a, F::StorageCursor>;
+
+    fn hashed_account_cursor(&self) -> Result<Self::AccountCursor, DatabaseError> {
+        Ok(HashedPostStateCursor::new(
+            self.cursor_factory.hashed_account_cursor()?,
+            &self.post_state.accounts.accounts,
+            Some(&self.post_state.accounts.destroyed_accounts),
+        ))
+    }
+
+    fn hashed_storage_cursor(
+        &self,
+        hashed_address: B256,
+    ) -> Result<Self::StorageCursor, DatabaseError> {
+        let cursor = self.cursor_factory.hashed_storage_cursor(hashed_address)?;
+        Ok(match self.post_state.storages.get(&hashed_address) {
+            Some(storage) => HashedPostStateCursor::new(
+                cursor,
+                &storage.non_zero_valued_slots,
+                Some(&storage.zero_valued_slots),
+            )
+            .with_wiped(storage.wiped),
+            None => HashedPostStateCursor::new(cursor, &[], None),
+        })
+    }
+}
+
+/// [HashedCursor] merging sorted in-memory entries with the entries of another cursor.
+///
+/// The in-memory entries take precedence over the entries of the underlying cursor, and the
+/// removed keys are skipped. All entries of the underlying cursor are skipped if it is wiped.
+#[derive(Debug)]
+pub struct HashedPostStateCursor<'a, C: HashedCursor> {
+    /// The underlying cursor.
+    cursor: C,
+    /// The changed entries sorted by key.
+    post_state: &'a [(B256, C::Value)],
+    /// The removed keys.
+    removed: Option<&'a HashSet<B256>>,
+    /// Whether the entries of the underlying cursor are all removed.
+    wiped: bool,
+    /// The key of the last returned entry.
+    last_key: Option<B256>,
+}
+
+impl<'a, C> HashedPostStateCursor<'a, C>
+where
+    C: HashedCursor,
+    C::Value: Clone,
+{
+    /// Creates a new cursor overlaying the given sorted entries and removed keys.
+    pub const fn new(
+        cursor: C,
+        post_state: &'a [(B256, C::Value)],
+        removed: Option<&'a HashSet<B256>>,
+    ) -> Self {
+        Self { cursor, post_state, removed, wiped: false, last_key: None }
+    }
+
+    /// Sets whether the entries of the underlying cursor are all removed, such as the slots of a
+    /// destroyed storage.
+    pub fn with_wiped(mut self, wiped: bool) -> Self {
+        self.wiped = wiped;
+        self
+    }
+
+    /// Returns the first entry at or after the given key, or strictly after it if `inclusive` is
+    /// `false`.
+    fn next_entry(
+        &mut self,
+        key: B256,
+        inclusive: bool,
+    ) -> Result<Option<(B256, C::Value)>, DatabaseError> {
+        let mut base = if self.wiped { None } else { self.cursor.seek(key)? };
+        while let Some((base_key, _)) = &base {
+            let skip = (!inclusive && *base_key == key)
+                || self.removed.is_some_and(|removed| removed.contains(base_key));
+            if !skip {
+                break;
+            }
+            base = self.cursor.next()?;
+        }
+
+        let index = self.post_state.partition_point(|(post_key, _)| {
+            if inclusive {
+                *post_key < key
+            } else {
+                *post_key <= key
+            }
+        });
+        let post = self.post_state.get(index).cloned();
+
+        let entry = match (base, post) {
+            (Some(base), Some(post)) => Some(if base.0 < post.0 { base } else { post }),
+            (base, post) => base.or(post),
+        };
+        self.last_key = entry.as_ref().map(|(key, _)| *key);
+        Ok(entry)
+    }
+}
+
+impl<C> HashedCursor for HashedPostStateCursor<'_, C>
+where
+    C: HashedCursor,
+    C::Value: Clone,
+{
+    type Value = C::Value;
+
+    fn seek(&mut self, key: B256) -> Result<Option<(B256, Self::Value)>, DatabaseError> {
+        self.next_entry(key, true)
+    }
+
+    fn next(&mut self) -> Result<Option<(B256, Self::Value)>, DatabaseError> {
+        match self.last_key {
+            Some(last_key) => self.next_entry(last_key, false),
+            None => self.next_entry(B256::ZERO, true),
+        }
+    }
+}
+
+impl<C> HashedStorageCursor for HashedPostStateCursor<'_, C>
+where
+    C: HashedStorageCursor,
+    C::Value: Clone,
+{
+    fn is_storage_empty(&mut self) -> Result<bool, DatabaseError> {
+        if !self.post_state.is_empty() {
+            return Ok(false);
+        }
+        if self.wiped || self.cursor.is_storage_empty()? {
+            return Ok(true);
+        }
+        Ok(self.seek(B256::ZERO)?.is_none())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cursor::in_memory::InMemoryHashedState,
+        hashed_state::{HashedPostState, HashedStorage},
+        TrieAccount,
+    };
+    use alloc::{collections::BTreeMap, vec::Vec};
+    use alloy_primitives::U256;
+
+    #[test]
+    fn overlay_cursors() {
+        let key = B256::with_last_byte;
+        let account = |nonce| TrieAccount { nonce, ..Default::default() };
+        let state = InMemoryHashedState {
+            accounts: BTreeMap::from_iter([1, 3, 5].map(|i| (key(i), account(i as u64)))),
+            storages: [
+                (key(1), BTreeMap::from_iter([(key(2), (U256::from(2), false))])),
+                (key(4), BTreeMap::from_iter([1, 2].map(|i| (key(i), (U256::from(i), false))))),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let post_state = HashedPostState::default()
+            .with_accounts([(key(3), None), (key(4), Some(account(4))), (key(5), Some(account(6)))])
+            .with_storages([
+                (key(1), HashedStorage::from_iter([(key(2), (U256::ZERO, false))])),
+                (key(3), HashedStorage::from_iter([(key(1), (U256::from(1), true))])),
+                (
+                    key(4),
+                    HashedStorage {
+                        wiped: true,
+                        ..HashedStorage::from_iter([(key(3), (U256::from(3), false))])
+                    },
+                ),
+            ])
+            .into_sorted();
+        let factory = HashedPostStateCursorFactory::new(&state, &post_state);
+
+        let mut cursor = factory.hashed_account_cursor().unwrap();
+        let mut accounts = Vec::new();
+        let mut entry = cursor.seek(B256::ZERO).unwrap();
+        while let Some((hashed_address, account)) = entry {
+            accounts.push((hashed_address, account.nonce));
+            entry = cursor.next().unwrap();
+        }
+        assert_eq!(accounts, [(key(1), 1), (key(4), 4), (key(5), 6)]);
+        assert_eq!(cursor.seek(key(2)).unwrap().map(|(key, _)| key), Some(key(4)));
+        assert_eq!(cursor.seek_exact(key(3)).unwrap(), None);
+
+        let mut wiped = factory.hashed_storage_cursor(key(1)).unwrap();
+        assert!(wiped.is_storage_empty().unwrap());
+        let mut created = factory.hashed_storage_cursor(key(3)).unwrap();
+        assert!(!created.is_storage_empty().unwrap());
+        assert_eq!(created.seek(B256::ZERO).unwrap(), Some((key(1), (U256::from(1), true))));
+        assert_eq!(created.next().unwrap(), None);
+        let mut destroyed = factory.hashed_storage_cursor(key(4)).unwrap();
+        assert!(!destroyed.is_storage_empty().unwrap());
+        assert_eq!(destroyed.seek(B256::ZERO).unwrap(), Some((key(3), (U256::from(3), false))));
+        assert_eq!(destroyed.next().unwrap(), None);
+        let mut untouched = factory.hashed_storage_cursor(key(5)).unwrap();
+        assert!(untouched.is_storage_empty().unwrap());
+    }
+}
@@ -0,0 +1,206 @@
+//! Deterministic dumps of the branch nodes persisted in a trie, e.g. to compare the tries built by
+//! different clients when debugging root mismatches.
+
+use crate::{
+    cursor::{DatabaseError, TrieCursor},
+    BranchNodeCompact, Nibbles,
+};
+use alloc::{string::String, vec::Vec};
+use core::fmt::{self, Write};
+
+/// Returns a dump of the branch nodes of the trie, sorted by path.
+///
+/// Every node is written on its own line with its masks and root hash, followed by one line per
+/// child with its hash and whether it is stored in the trie or holds private state.
+pub fn dump_trie<C: TrieCursor>(cursor: &mut C) -> Result<String, DatabaseError> {
+    let nodes = branch_nodes(cursor)?;
+    let mut out = String::new();
+    write_dump(&mut out, &nodes).expect("writing to a string does not fail");
+    Ok(out)
+}
+
+/// Returns the Graphviz DOT graph of the branch nodes of the trie.
+///
+/// Children stored in the trie link to the next stored branch node below them, the other children
+/// are drawn with their hash, if any. The edges to children holding private state are red.
+pub fn trie_to_dot<C: TrieCursor>(cursor: &mut C) -> Result<String, DatabaseError> {
+    let nodes = branch_nodes(cursor)?;
+    let mut out = String::new();
+    write_dot(&mut out, &nodes).expect("writing to a string does not fail");
+    Ok(out)
+}
+
+fn branch_nodes<C: TrieCursor>(
+    cursor: &mut C,
+) -> Result<Vec<(Nibbles, BranchNodeCompact)>, DatabaseError> {
+    let mut nodes = Vec::new();
+    let mut entry = cursor.seek(Nibbles::default())?;
+    while let Some(node) = entry {
+        nodes.push(node);
+        entry = cursor.next()?;
+    }
+    Ok(nodes)
+}
+
+fn write_dump(out: &mut String, nodes: &[(Nibbles, BranchNodeCompact)]) -> fmt::Result {
+    for (path, node) in nodes {
+        write!(
+            out,
+            "{} state={:?} tree={:?} hash={:?} private={:?}",
+            PathLabel(path),
+            node.state_mask,
+            node.tree_mask,
+            node.hash_mask,
+            node.private_mask
+        )?;
+        if let Some(root_hash) = node.root_hash {
+            write!(out, " root={root_hash}")?;
+        }
+        writeln!(out)?;
+
+        for nibble in (0..16).filter(|nibble| node.state_mask.is_bit_set(*nibble)) {
+            write!(out, "  {nibble:x}:")?;
+            if node.hash_mask.is_bit_set(nibble) {
+                write!(out, " {}", node.hash_for_nibble(nibble))?;
+            }
+            if node.tree_mask.is_bit_set(nibble) {
+                write!(out, " stored")?;
+            }
+            if node.private_mask.is_bit_set(nibble) {
+                write!(out, " private")?;
+            }
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_dot(out: &mut String, nodes: &[(Nibbles, BranchNodeCompact)]) -> fmt::Result {
+    writeln!(out, "digraph trie {{")?;
+    writeln!(out, "  node [shape=box, fontname=monospace];")?;
+    for (path, node) in nodes {
+        write!(out, "  \"b{}\" [label=\"{}", NodeId(path), PathLabel(path))?;
+        if let Some(root_hash) = node.root_hash {
+            write!(out, "\\n{root_hash}")?;
+        }
+        writeln!(out, "\"];")?;
+
+        for nibble in (0..16).filter(|nibble| node.state_mask.is_bit_set(*nibble)) {
+            let mut child_path = path.clone();
+            child_path.push(nibble);
+            let color = if node.private_mask.is_bit_set(nibble) { "red" } else { "black" };
+
+            // The stored branch node below the child, after its extension if any.
+            let stored = node
+                .tree_mask
+                .is_bit_set(nibble)
+                .then(|| nodes.iter().find(|(stored, _)| stored.starts_with(&child_path)))
+                .flatten();
+            if let Some((stored, _)) = stored {
+                writeln!(
+                    out,
+                    "  \"b{}\" -> \"b{}\" [label=\"{nibble:x}\", color={color}];",
+                    NodeId(path),
+                    NodeId(stored)
+                )?;
+                continue;
+            }
+
+            write!(
+                out,
+                "  \"c{}\" [shape=ellipse, label=\"{}",
+                NodeId(&child_path),
+                PathLabel(&child_path)
+            )?;
+            if node.hash_mask.is_bit_set(nibble) {
+                write!(out, "\\n{}", node.hash_for_nibble(nibble))?;
+            }
+            writeln!(out, "\"];")?;
+            writeln!(
+                out,
+                "  \"b{}\" -> \"c{}\" [label=\"{nibble:x}\", color={color}];",
+                NodeId(path),
+                NodeId(&child_path)
+            )?;
+        }
+    }
+    writeln!(out, "}}")
+}
+
+/// Formats a path as its hex nibbles.
+struct NodeId<'a>(&'a Nibbles);
+
+impl fmt::Display for NodeId<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|nibble| write!(f, "{nibble:x}"))
+    }
+}
+
+/// Formats a path as its hex nibbles, or `root` for the empty path.
+struct PathLabel<'a>(&'a Nibbles);
+
+impl fmt::Display for PathLabel<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            f.write_str("root")
+        } else {
+            fmt::Display::fmt(&NodeId(self.0), f)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor::in_memory::InMemoryTrieCursor;
+    use alloc::collections::BTreeMap;
+    use alloy_primitives::B256;
+
+    fn nodes() -> BTreeMap<Nibbles, BranchNodeCompact> {
+        BTreeMap::from([
+            (
+                Nibbles::default(),
+                BranchNodeCompact::new(
+                    0b110_u16,
+                    0b010_u16,
+                    0b100_u16,
+                    vec![B256::repeat_byte(2)],
+                    None,
+                )
+                .with_private_mask(0b100_u16),
+            ),
+            (
+                Nibbles::from_nibbles([0x1, 0xa]),
+                BranchNodeCompact::new(0b11_u16, 0_u16, 0b01_u16, vec![B256::repeat_byte(1)], None),
+            ),
+        ])
+    }
+
+    #[test]
+    fn dump() {
+        let nodes = nodes();
+        let dump = dump_trie(&mut InMemoryTrieCursor::new(Some(&nodes))).unwrap();
+        let lines = dump.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[0].starts_with("root state="));
+        assert_eq!(lines[1], "  1: stored");
+        assert_eq!(lines[2], format!("  2: {} private", B256::repeat_byte(2)));
+        assert!(lines[3].starts_with("1a state="));
+        assert_eq!(lines[4], format!("  0: {}", B256::repeat_byte(1)));
+        assert_eq!(lines[5], "  1:");
+
+        assert_eq!(dump_trie(&mut InMemoryTrieCursor::new(None)).unwrap(), "");
+    }
+
+    #[test]
+    fn dot() {
+        let nodes = nodes();
+        let dot = trie_to_dot(&mut InMemoryTrieCursor::new(Some(&nodes))).unwrap();
+        assert!(dot.starts_with("digraph trie {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("  \"b\" -> \"b1a\" [label=\"1\", color=black];\n"));
+        assert!(dot.contains("  \"b\" -> \"c2\" [label=\"2\", color=red];\n"));
+        assert!(dot.contains("  \"b1a\" -> \"c1a0\" [label=\"0\", color=black];\n"));
+        assert_eq!(dot, trie_to_dot(&mut InMemoryTrieCursor::new(Some(&nodes))).unwrap());
+    }
+}
@@ -0,0 +1,80 @@
+use core::fmt;
+use nybbles::Nibbles;
+
+/// The maximum length of a trie key in nibbles, the length of a hashed key.
+pub const MAX_KEY_LEN: usize = 64;
+
+/// Error computing a trie root from invalid leaves, see [`HashBuilder::try_add_leaf`].
+///
+/// [`HashBuilder::try_add_leaf`]: crate::HashBuilder::try_add_leaf
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum StateRootError {
+    /// The key does not come after the previous key.
+    UnsortedKey {
+        /// The added key.
+        key: Nibbles,
+        /// The previous key.
+        previous: Nibbles,
+    },
+    /// The key was already added with the same privacy.
+    DuplicateKey {
+        /// The added key.
+        key: Nibbles,
+    },
+    /// The key is longer than [MAX_KEY_LEN] nibbles.
+    KeyTooLong {
+        /// The added key.
+        key: Nibbles,
+    },
+    /// The key is below the previous leaf or branch, which can not have children.
+    NestedKey {
+        /// The added key.
+        key: Nibbles,
+        /// The previous key.
+        previous: Nibbles,
+    },
+    /// The key was already added with the other privacy, a slot can not be both public and
+    /// private.
+    InvalidPrivacyTransition {
+        /// The added key.
+        key: Nibbles,
+        /// Whether the key was previously added as private.
+        was_private: bool,
+    },
+    /// The branch has private leaves while the root of the public view is computed, which is
+    /// unknown for the branch.
+    PrivateBranchInPublicView {
+        /// The key of the branch.
+        key: Nibbles,
+    },
+}
+
+/// Enable Error trait implementation when core is stabilized.
+/// <https://github.com/rust-lang/rust/issues/103765>
+#[cfg(feature = "std")]
+impl std::error::Error for StateRootError {}
+
+impl fmt::Display for StateRootError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsortedKey { key, previous } => {
+                write!(f, "key {key:?} does not come after the previous key {previous:?}")
+            }
+            Self::DuplicateKey { key } => write!(f, "duplicate key {key:?}"),
+            Self::KeyTooLong { key } => {
+                write!(f, "key {key:?} is longer than {MAX_KEY_LEN} nibbles")
+            }
+            Self::NestedKey { key, previous } => {
+                write!(f, "key {key:?} is below the previous key {previous:?}")
+            }
+            Self::InvalidPrivacyTransition { key, was_private } => {
+                let (from, to) =
+                    if *was_private { ("private", "public") } else { ("public", "private") };
+                write!(f, "key {key:?} was added as {from} and then as {to}")
+            }
+            Self::PrivateBranchInPublicView { key } => {
+                write!(f, "branch {key:?} has private leaves, its public view is unknown")
+            }
+        }
+    }
+}
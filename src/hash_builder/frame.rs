@@ -0,0 +1,206 @@
+use super::{HashBuilder, TrieHasher};
+use crate::{nodes::TrieNode, proof::ProofRetainer, Nibbles};
+use alloc::vec::Vec;
+use alloy_primitives::B256;
+use alloy_rlp::Decodable;
+
+/// The top node of the subtrie of the keys starting with a prefix, computed on its own by
+/// [HashBuilder::finish_frame], e.g. on another thread or machine.
+///
+/// The frames of the subtries are merged into the whole trie by [HashBuilder::merge], so that its
+/// root can be computed without hashing the subtries again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PartialFrame {
+    /// The prefix of the keys of the subtrie.
+    pub prefix: Nibbles,
+    /// The top node of the subtrie, [None] if it is empty.
+    pub node: Option<FrameNode>,
+}
+
+/// The top node of a [PartialFrame].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrameNode {
+    /// The only leaf of the subtrie.
+    Leaf {
+        /// The full key of the leaf.
+        key: Nibbles,
+        /// The value of the leaf.
+        value: Vec<u8>,
+        /// Whether the leaf is private.
+        is_private: bool,
+    },
+    /// The branch node at the longest common prefix of the keys of the subtrie.
+    Branch {
+        /// The full path of the branch node.
+        key: Nibbles,
+        /// The hash of the branch node.
+        hash: B256,
+        /// Whether the branch node is stored in the database.
+        stored_in_database: bool,
+        /// Whether the subtrie contains private leaves.
+        is_private: bool,
+    },
+}
+
+impl<H: TrieHasher> HashBuilder<H> {
+    /// Finishes the subtrie of the added leaves and branches, whose keys all start with the given
+    /// prefix, into a [PartialFrame] to be merged with the frames of the other subtries.
+    ///
+    /// The updated branch nodes of the subtrie, including the top one, can be taken afterwards
+    /// with [HashBuilder::take_updates].
+    ///
+    /// # Panics
+    ///
+    /// If a key does not start with the prefix, or if the top branch node of the subtrie is
+    /// shorter than 32 bytes and encoded in place of its parent, which can not happen for hashed
+    /// keys.
+    pub fn finish_frame(&mut self, prefix: Nibbles) -> PartialFrame {
+        let is_private =
+            self.is_private == Some(true) || self.private_masks.iter().any(|mask| !mask.is_empty());
+        let (last_key, last_stored_in_database) = (self.key.clone(), self.stored_in_database);
+
+        // The retainer always retains the root node, which is the top node of the subtrie.
+        if self.proof_retainer.is_none() {
+            self.proof_retainer = Some(ProofRetainer::default());
+        }
+        let root = self.root();
+        let top = self
+            .proof_retainer
+            .as_ref()
+            .and_then(|retainer| retainer.proof_nodes().get(&Nibbles::default()))
+            .map(|rlp| TrieNode::decode(&mut &rlp[..]).expect("retained node is valid"));
+
+        let (key, hash) = match top {
+            Some(TrieNode::EmptyRoot) => return PartialFrame { prefix, node: None },
+            Some(TrieNode::Leaf(leaf)) => {
+                assert!(leaf.key.starts_with(&prefix), "leaf {:?} prefix {:?}", leaf.key, prefix);
+                let node = FrameNode::Leaf {
+                    key: leaf.key,
+                    value: leaf.value,
+                    is_private: leaf.is_private,
+                };
+                return PartialFrame { prefix, node: Some(node) };
+            }
+            Some(TrieNode::Extension(extension)) => {
+                let hash = extension.child.as_hash().expect("top branch node is hashed");
+                (extension.key, hash)
+            }
+            // The only branch added at the empty key is not retained.
+            Some(TrieNode::Branch(_)) | None => (Nibbles::default(), root),
+        };
+        assert!(key.starts_with(&prefix), "branch {:?} prefix {:?}", key, prefix);
+
+        let stored_in_database = if key == last_key {
+            last_stored_in_database
+        } else {
+            self.updated_branch_nodes.as_ref().is_some_and(|nodes| nodes.contains_key(&key))
+        };
+        let node = FrameNode::Branch { key, hash, stored_in_database, is_private };
+        PartialFrame { prefix, node: Some(node) }
+    }
+
+    /// Merges the frame of a subtrie into the trie, see [HashBuilder::finish_frame].
+    ///
+    /// The frames must be of disjoint prefixes, and merged in the order of their prefixes along
+    /// with the leaves and branches added around them.
+    ///
+    /// # Panics
+    ///
+    /// If the frame does not come after the current key, or if it is a branch with private leaves
+    /// while the root of the public view is computed.
+    pub fn merge(&mut self, frame: PartialFrame) {
+        match frame.node {
+            None => {}
+            Some(FrameNode::Leaf { key, value, is_private }) => {
+                self.add_leaf(key, &value, is_private);
+            }
+            Some(FrameNode::Branch { key, hash, stored_in_database, is_private }) => {
+                self.add_branch_with_privacy(key, hash, stored_in_database, is_private);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::updates::TrieUpdates;
+    use alloy_primitives::keccak256;
+
+    fn frames_root(leaves: &[(B256, Vec<u8>, bool)], prefix_len: usize) -> (B256, TrieUpdates) {
+        let prefix = |key: &B256| Nibbles::unpack(key).slice(..prefix_len);
+        let frames = leaves
+            .chunk_by(|(a, ..), (b, ..)| prefix(a) == prefix(b))
+            .map(|chunk| {
+                let mut hb = HashBuilder::default().with_updates(true);
+                for (key, value, is_private) in chunk {
+                    hb.add_leaf(Nibbles::unpack(key), value, *is_private);
+                }
+                let frame = hb.finish_frame(prefix(&chunk[0].0));
+                (frame, hb.take_updates())
+            })
+            .collect::<Vec<_>>();
+
+        let mut hb = HashBuilder::default().with_updates(true);
+        let mut updates = TrieUpdates::default();
+        for (frame, frame_updates) in frames {
+            hb.merge(frame);
+            updates.extend(frame_updates);
+        }
+        let root = hb.root();
+        updates.extend(hb.take_updates());
+        (root, updates)
+    }
+
+    fn assert_frames_root(leaves: &[(B256, Vec<u8>, bool)]) {
+        let mut hb = HashBuilder::default().with_updates(true);
+        for (key, value, is_private) in leaves {
+            hb.add_leaf(Nibbles::unpack(key), value, *is_private);
+        }
+        let (root, updates) = hb.root_with_updates();
+
+        for prefix_len in [0, 1, 2] {
+            let (frames_root, frames_updates) = frames_root(leaves, prefix_len);
+            assert_eq!(frames_root, root, "{prefix_len}");
+            assert_eq!(frames_updates.updated_nodes, updates.updated_nodes, "{prefix_len}");
+        }
+    }
+
+    #[test]
+    fn merge_frames() {
+        let mut leaves = (0..300u64)
+            .map(|i| (keccak256(i.to_be_bytes()), alloy_rlp::encode(i), i % 5 == 0))
+            .collect::<Vec<_>>();
+        leaves.sort_unstable_by_key(|(key, ..)| *key);
+        assert_frames_root(&leaves);
+
+        // A subtrie under a branch, a single leaf and a subtrie under an extension.
+        let key = |prefix: &[u8]| {
+            let mut key = B256::repeat_byte(0x5a);
+            key[..prefix.len()].copy_from_slice(prefix);
+            key
+        };
+        let leaves = [
+            (key(&[0x00]), b"first".to_vec(), false),
+            (key(&[0x01]), b"second".to_vec(), true),
+            (key(&[0x12, 0x34]), b"third".to_vec(), true),
+            (key(&[0x22, 0x22, 0x00]), b"fourth".to_vec(), false),
+            (key(&[0x22, 0x22, 0x01]), b"fifth".to_vec(), false),
+        ];
+        assert_frames_root(&leaves);
+        assert_frames_root(&leaves[2..3]);
+        assert_frames_root(&leaves[3..]);
+    }
+
+    #[test]
+    fn merge_empty_frame() {
+        let frame = HashBuilder::default().finish_frame(Nibbles::default());
+        assert_eq!(frame.node, None);
+
+        let mut hb = HashBuilder::default();
+        hb.merge(frame);
+        assert_eq!(hb.root(), crate::EMPTY_ROOT_HASH);
+    }
+}
@@ -0,0 +1,34 @@
+use crate::EMPTY_ROOT_HASH;
+use alloy_primitives::{keccak256, B256};
+use alloy_rlp::EMPTY_STRING_CODE;
+
+/// The 32-byte hash function used by the [`HashBuilder`](crate::HashBuilder) to hash trie nodes.
+///
+/// The node layout, including the privacy flag of the leaves, is the same for every hasher, only
+/// the node hashes and thus the root differ. Proof verification and the root helpers always use
+/// keccak-256.
+pub trait TrieHasher {
+    /// Hashes the given data.
+    fn hash(data: &[u8]) -> B256;
+
+    /// Returns the root of an empty trie, which is the hash of the RLP encoded empty string.
+    fn empty_root() -> B256 {
+        Self::hash(&[EMPTY_STRING_CODE])
+    }
+}
+
+/// The keccak-256 hasher used by Ethereum tries.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct KeccakHasher;
+
+impl TrieHasher for KeccakHasher {
+    #[inline]
+    fn hash(data: &[u8]) -> B256 {
+        keccak256(data)
+    }
+
+    #[inline]
+    fn empty_root() -> B256 {
+        EMPTY_ROOT_HASH
+    }
+}
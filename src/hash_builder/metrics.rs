@@ -0,0 +1,13 @@
+/// The work done by a [HashBuilder](super::HashBuilder) since its last root, reported by the
+/// `tracing` feature.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct RootMetrics {
+    /// The number of added leaves.
+    pub(crate) leaves: usize,
+    /// The number of added subtrie hashes.
+    pub(crate) branches: usize,
+    /// The number of branch nodes built from their children.
+    pub(crate) branch_nodes: usize,
+    /// The total length of the encoded nodes.
+    pub(crate) rlp_bytes: usize,
+}
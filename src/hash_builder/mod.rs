@@ -3,15 +3,44 @@
 use super::{
     nodes::{BranchNodeRef, ExtensionNodeRef, LeafNodeRef},
     proof::ProofRetainer,
-    BranchNodeCompact, Nibbles, TrieMask, EMPTY_ROOT_HASH,
+    BranchNodeCompact, Nibbles, TrieMask,
 };
-use crate::{nodes::RlpNode, proof::ProofNodes, HashMap};
-use alloc::vec::Vec;
-use alloy_primitives::{keccak256, B256};
-use alloy_rlp::EMPTY_STRING_CODE;
-use core::cmp;
+use crate::{nodes::RlpNode, proof::ProofNodes, updates::TrieUpdates, HashMap};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use alloy_primitives::B256;
+use alloy_rlp::{Encodable, EMPTY_STRING_CODE};
+use core::{cmp, marker::PhantomData};
 use tracing::trace;
 
+mod error;
+pub use error::{StateRootError, MAX_KEY_LEN};
+
+mod frame;
+pub use frame::{FrameNode, PartialFrame};
+
+mod hasher;
+pub use hasher::{KeccakHasher, TrieHasher};
+
+#[cfg(feature = "tracing")]
+mod metrics;
+#[cfg(feature = "tracing")]
+use metrics::RootMetrics;
+
+mod observer;
+pub use observer::TrieObserver;
+
+mod state;
+pub use state::HashBuilderState;
+
+mod stats;
+pub use stats::{TrieStats, TrieStatsObserver};
+
+mod pool;
+pub use pool::BufferPool;
+
+mod payload;
+pub use payload::{ChunkedEncoder, LeafPayload, PrivateLeafEncoder, SaltedKeccakEncoder};
+
 mod value;
 pub use value::{HashBuilderValue, HashBuilderValueRef};
 
@@ -39,9 +68,12 @@ pub use value::{HashBuilderValue, HashBuilderValueRef};
 /// up, combining the hashes of child nodes and ultimately generating the root hash. The root hash
 /// can then be used to verify the integrity and authenticity of the trie's data by constructing and
 /// verifying Merkle proofs.
-#[derive(Debug, Clone, Default)]
+///
+/// Nodes are hashed with keccak-256 by default, use [HashBuilder::new_with_hasher] to hash them
+/// with another [TrieHasher].
+#[derive(Debug, Clone)]
 #[allow(missing_docs)]
-pub struct HashBuilder {
+pub struct HashBuilder<H = KeccakHasher> {
     pub key: Nibbles,
     pub value: HashBuilderValue,
     pub is_private: Option<bool>,
@@ -50,6 +82,7 @@ pub struct HashBuilder {
     pub state_masks: Vec<TrieMask>,
     pub tree_masks: Vec<TrieMask>,
     pub hash_masks: Vec<TrieMask>,
+    pub private_masks: Vec<TrieMask>,
 
     pub stored_in_database: bool,
 
@@ -57,20 +90,164 @@ pub struct HashBuilder {
     pub proof_retainer: Option<ProofRetainer>,
 
     pub rlp_buf: Vec<u8>,
+
+    /// Pool of the reusable buffers, see [HashBuilder::with_allocator].
+    pub buffer_pool: BufferPool,
+
+    /// Builder of the public view of the trie, see [HashBuilder::with_public_root].
+    pub public_builder: Option<Box<Self>>,
+
+    /// Observer of the hashing work, see [HashBuilder::with_observer].
+    pub observer: Option<Arc<dyn TrieObserver>>,
+
+    /// Whether the nodes shorter than 32 bytes are hashed too, see
+    /// [HashBuilder::with_all_nodes_hashed].
+    pub hash_all_nodes: bool,
+
+    /// Whether the privacy flags are ignored, see [HashBuilder::with_privacy_ignored].
+    pub ignore_privacy: bool,
+
+    #[cfg(feature = "tracing")]
+    metrics: RootMetrics,
+
+    _hasher: PhantomData<H>,
 }
 
-impl HashBuilder {
+impl Default for HashBuilder {
+    fn default() -> Self {
+        Self::new_with_hasher()
+    }
+}
+
+impl<H: TrieHasher> HashBuilder<H> {
+    /// Creates a new Hash Builder hashing the trie nodes with the given [TrieHasher].
+    pub fn new_with_hasher() -> Self {
+        Self {
+            key: Nibbles::default(),
+            value: HashBuilderValue::default(),
+            is_private: None,
+            stack: Vec::new(),
+            state_masks: Vec::new(),
+            tree_masks: Vec::new(),
+            hash_masks: Vec::new(),
+            private_masks: Vec::new(),
+            stored_in_database: false,
+            updated_branch_nodes: None,
+            proof_retainer: None,
+            rlp_buf: Vec::new(),
+            buffer_pool: BufferPool::default(),
+            public_builder: None,
+            observer: None,
+            hash_all_nodes: false,
+            ignore_privacy: false,
+            #[cfg(feature = "tracing")]
+            metrics: RootMetrics::default(),
+            _hasher: PhantomData,
+        }
+    }
+
+    /// Reserves the internal stacks and buffers for keys of up to the given number of nibbles, so
+    /// that building the trie does not reallocate them.
+    pub fn with_capacity(mut self, max_key_len: usize) -> Self {
+        self.stack.reserve(max_key_len + 1);
+        self.state_masks.reserve(max_key_len);
+        self.tree_masks.reserve(max_key_len);
+        self.hash_masks.reserve(max_key_len);
+        self.private_masks.reserve(max_key_len);
+        // A branch node with all children hashed is the largest node.
+        self.rlp_buf.reserve(3 + 16 * (B256::len_bytes() + 1) + 1);
+        self
+    }
+
+    /// Sets the pool of the node stack and of the buffers used to hold the current value, to
+    /// encode the nodes and to commit to the private leaves, e.g. to preallocate them when building
+    /// tries with millions of leaves.
+    ///
+    /// The node stack, the value buffer and the encoding buffer are taken from the pool right
+    /// away, use [HashBuilder::into_allocator] to return them once the root is computed.
+    pub fn with_allocator(mut self, mut buffer_pool: BufferPool) -> Self {
+        self.stack = buffer_pool.take_stack();
+        self.value = HashBuilderValue::with_buffer(buffer_pool.take());
+        self.rlp_buf = buffer_pool.take();
+        self.buffer_pool = buffer_pool;
+        self
+    }
+
+    /// Returns the node stack and the buffers to the pool and returns it, so that the next trie can
+    /// be built with [HashBuilder::with_allocator] without allocating them again.
+    pub fn into_allocator(self) -> BufferPool {
+        let Self { stack, value, rlp_buf, mut buffer_pool, .. } = self;
+        buffer_pool.put_stack(stack);
+        buffer_pool.put(value.into_buffer());
+        buffer_pool.put(rlp_buf);
+        buffer_pool
+    }
+
     /// Enables the Hash Builder to store updated branch nodes.
     ///
-    /// Call [HashBuilder::split] to get the updates to branch nodes.
+    /// Call [HashBuilder::split] or [HashBuilder::take_updates] to get the updates to branch
+    /// nodes.
     pub fn with_updates(mut self, retain_updates: bool) -> Self {
         self.set_updates(retain_updates);
         self
     }
 
     /// Enable specified proof retainer.
-    pub fn with_proof_retainer(mut self, retainer: ProofRetainer) -> Self {
-        self.proof_retainer = Some(retainer);
+    ///
+    /// Accepts either a [ProofRetainer] or the target nibbles to retain proofs for.
+    /// Call [HashBuilder::take_proof_nodes] after computing the root to get the retained nodes.
+    pub fn with_proof_retainer(mut self, retainer: impl Into<ProofRetainer>) -> Self {
+        self.proof_retainer = Some(retainer.into());
+        self
+    }
+
+    /// Enables the Hash Builder to compute the root of the public view of the trie, which only
+    /// contains the public leaves, alongside the root of the full trie.
+    ///
+    /// Call [HashBuilder::public_root] to get the root of the public view.
+    pub fn with_public_root(mut self, public_root: bool) -> Self {
+        if public_root {
+            let public_builder = Self::new_with_hasher()
+                .with_all_nodes_hashed(self.hash_all_nodes)
+                .with_privacy_ignored(self.ignore_privacy);
+            self.public_builder = Some(Box::new(public_builder));
+        }
+        self
+    }
+
+    /// Sets the observer notified of the leaves and branches added and of the hashed nodes.
+    pub fn with_observer(mut self, observer: Arc<dyn TrieObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Hashes every node, including the nodes shorter than 32 bytes which are otherwise inlined in
+    /// their parent, so that all children are referenced by their hashes, e.g. for zk circuits
+    /// which cannot handle inlined nodes.
+    ///
+    /// NON-CONSENSUS: the roots of the tries with inlined nodes differ from their Merkle Patricia
+    /// Trie roots, and the retained proofs cannot be checked with
+    /// [verify_proof](crate::proof::verify_proof). Never use it for consensus roots, nor mix the
+    /// updates or stored branch nodes of both modes.
+    pub fn with_all_nodes_hashed(mut self, hash_all_nodes: bool) -> Self {
+        self.hash_all_nodes = hash_all_nodes;
+        if let Some(public_builder) = self.public_builder.as_mut() {
+            public_builder.hash_all_nodes = hash_all_nodes;
+        }
+        self
+    }
+
+    /// Ignores the privacy flags of the leaves and branches, which are all encoded as public ones,
+    /// so that the nodes and the roots are the same as the Ethereum ones, e.g. to check them
+    /// against upstream tries or mainnet data.
+    ///
+    /// NON-CONSENSUS: the roots of the tries with private leaves differ from their Seismic roots.
+    /// Never use it for consensus roots, nor mix the updates or stored branch nodes of both modes.
+    pub fn with_privacy_ignored(mut self, ignore_privacy: bool) -> Self {
+        self.ignore_privacy = ignore_privacy;
+        if let Some(public_builder) = self.public_builder.as_mut() {
+            public_builder.ignore_privacy = ignore_privacy;
+        }
         self
     }
 
@@ -89,6 +266,45 @@ impl HashBuilder {
         (self, updates.unwrap_or_default())
     }
 
+    /// Clears the state of the Hash Builder without deallocating its stacks and buffers, so that
+    /// it can be reused to compute the root of another trie.
+    ///
+    /// The retained updates are cleared while staying enabled, and the observer, the buffer pool and
+    /// the hashing of all nodes are kept. The proof retainer is removed since its targets are
+    /// specific to the previous trie.
+    pub fn reset(&mut self) {
+        self.key.clear();
+        self.value.clear();
+        self.is_private = None;
+        self.stack.clear();
+        self.state_masks.clear();
+        self.tree_masks.clear();
+        self.hash_masks.clear();
+        self.private_masks.clear();
+        self.stored_in_database = false;
+        if let Some(updated_branch_nodes) = self.updated_branch_nodes.as_mut() {
+            updated_branch_nodes.clear();
+        }
+        self.proof_retainer = None;
+        self.rlp_buf.clear();
+        #[cfg(feature = "tracing")]
+        {
+            self.metrics = RootMetrics::default();
+        }
+        if let Some(public_builder) = self.public_builder.as_mut() {
+            public_builder.reset();
+        }
+    }
+
+    /// Take and return the updated branch nodes collected so far as [TrieUpdates].
+    ///
+    /// Returns empty updates if [Self::with_updates] was not called.
+    pub fn take_updates(&mut self) -> TrieUpdates {
+        let updated_nodes =
+            self.updated_branch_nodes.as_mut().map(core::mem::take).unwrap_or_default();
+        TrieUpdates { updated_nodes, ..Default::default() }
+    }
+
     /// Take and return retained proof nodes.
     pub fn take_proof_nodes(&mut self) -> ProofNodes {
         self.proof_retainer.take().map(ProofRetainer::into_proof_nodes).unwrap_or_default()
@@ -120,37 +336,241 @@ impl HashBuilder {
         self.add_leaf_unchecked(key, value, is_private);
     }
 
+    /// Adds a new leaf element to the trie hash builder, whose value is encoded by the given
+    /// function directly into the buffer of the builder, which returns whether the leaf is
+    /// private.
+    ///
+    /// Unlike [HashBuilder::add_leaf], the value does not need to be encoded into an intermediate
+    /// buffer first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new key does not come after the current key.
+    pub fn add_leaf_with<F>(&mut self, key: Nibbles, encode: F)
+    where
+        F: FnOnce(&mut Vec<u8>) -> bool,
+    {
+        assert!(key > self.key, "add_leaf_with key {:?} self.key {:?}", key, self.key);
+        if let Some(observer) = &self.observer {
+            observer.on_leaf_added();
+        }
+        #[cfg(feature = "tracing")]
+        {
+            self.metrics.leaves += 1;
+        }
+        if !self.key.is_empty() {
+            self.update(&key);
+        }
+        self.log_key_value("old value");
+        self.key = key;
+        let is_private = self.value.set_bytes_with(encode) && !self.ignore_privacy;
+        self.is_private = Some(is_private);
+        self.log_key_value("new value");
+        if !is_private {
+            if let Some(public_builder) = self.public_builder.as_mut() {
+                public_builder.add_leaf_unchecked(self.key.clone(), self.value.as_slice(), false);
+            }
+        }
+    }
+
+    /// Adds a new leaf element and its value to the trie hash builder, returning an error instead of
+    /// panicking if the key is not valid.
+    pub fn try_add_leaf(
+        &mut self,
+        key: Nibbles,
+        value: &[u8],
+        is_private: bool,
+    ) -> Result<(), StateRootError> {
+        let is_private = is_private && !self.ignore_privacy;
+        if key.len() > MAX_KEY_LEN {
+            return Err(StateRootError::KeyTooLong { key });
+        }
+        let previous = &self.key;
+        if key <= *previous {
+            let is_leaf = matches!(self.value.as_ref(), HashBuilderValueRef::Bytes(_));
+            return Err(match self.is_private {
+                _ if key != *previous || previous.is_empty() => {
+                    StateRootError::UnsortedKey { key, previous: previous.clone() }
+                }
+                Some(was_private) if is_leaf && was_private != is_private => {
+                    StateRootError::InvalidPrivacyTransition { key, was_private }
+                }
+                _ => StateRootError::DuplicateKey { key },
+            });
+        }
+        if !previous.is_empty() && key.starts_with(previous) {
+            return Err(StateRootError::NestedKey { key, previous: previous.clone() });
+        }
+        self.add_leaf_unchecked(key, value, is_private);
+        Ok(())
+    }
+
+    /// Adds a new leaf element and its public or private payload to the trie hash builder.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new key does not come after the current key.
+    pub fn add_leaf_payload(&mut self, key: Nibbles, payload: LeafPayload<'_>) {
+        self.add_leaf(key, payload.value(), payload.is_private());
+    }
+
+    /// Adds a new private leaf element to the trie hash builder, storing the commitment to its
+    /// plaintext value produced by the given [PrivateLeafEncoder].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new key does not come after the current key.
+    pub fn add_private_leaf<E: PrivateLeafEncoder + ?Sized>(
+        &mut self,
+        key: Nibbles,
+        value: &[u8],
+        encoder: &E,
+    ) {
+        let mut commitment = self.buffer_pool.take();
+        encoder.commit(value, &mut commitment);
+        self.add_leaf_payload(key, LeafPayload::Private(&commitment));
+        self.buffer_pool.put(commitment);
+    }
+
     /// Adds a new leaf element and its value to the trie hash builder,
     /// without checking the order of the new key. This is only for
     /// performance-critical usage that guarantees keys are inserted
     /// in sorted order.
+    ///
+    /// The privacy flag is ignored if [HashBuilder::with_privacy_ignored] is enabled.
     pub fn add_leaf_unchecked(&mut self, key: Nibbles, value: &[u8], is_private: bool) {
         debug_assert!(key > self.key, "add_leaf_unchecked key {:?} self.key {:?}", key, self.key);
+        let is_private = is_private && !self.ignore_privacy;
+        if let Some(observer) = &self.observer {
+            observer.on_leaf_added();
+        }
+        #[cfg(feature = "tracing")]
+        {
+            self.metrics.leaves += 1;
+        }
+        if !is_private {
+            if let Some(public_builder) = self.public_builder.as_mut() {
+                public_builder.add_leaf_unchecked(key.clone(), value, false);
+            }
+        }
         if !self.key.is_empty() {
             self.update(&key);
         }
         self.set_key_value(key, HashBuilderValueRef::Bytes(value), Some(is_private));
     }
 
+    /// Adds the given leaf elements, sorted by key, and their values to the trie hash builder.
+    ///
+    /// The order of the keys is checked once for the whole batch, and the internal stacks are
+    /// reserved upfront for the longest key, instead of on every added leaf.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the keys are not sorted, or if the first key does not come after the current key.
+    pub fn extend_leaves(&mut self, leaves: &[(Nibbles, &[u8], bool)]) {
+        let Some((first_key, _, _)) = leaves.first() else { return };
+        assert!(*first_key > self.key, "extend_leaves key {:?} self.key {:?}", first_key, self.key);
+        assert!(
+            leaves.windows(2).all(|pair| pair[0].0 < pair[1].0),
+            "extend_leaves keys are not sorted"
+        );
+
+        let max_key_len = leaves.iter().map(|(key, _, _)| key.len()).max().unwrap_or_default();
+        self.stack.reserve((max_key_len + 1).saturating_sub(self.stack.len()));
+        for masks in [
+            &mut self.state_masks,
+            &mut self.tree_masks,
+            &mut self.hash_masks,
+            &mut self.private_masks,
+        ] {
+            masks.reserve(max_key_len.saturating_sub(masks.len()));
+        }
+
+        for (key, value, is_private) in leaves {
+            self.add_leaf_unchecked(key.clone(), value, *is_private);
+        }
+    }
+
     /// Adds a new branch element and its hash to the trie hash builder.
+    ///
+    /// The subtrie is considered to contain no private leaves, see
+    /// [HashBuilder::add_branch_with_privacy].
     pub fn add_branch(&mut self, key: Nibbles, value: B256, stored_in_database: bool) {
+        self.add_branch_with_privacy(key, value, stored_in_database, false);
+    }
+
+    /// Adds a new branch element and its hash to the trie hash builder, along with whether the
+    /// subtrie contains private leaves. The latter is recorded in the private masks of the
+    /// branch nodes.
+    ///
+    /// The privacy flag is ignored if [HashBuilder::with_privacy_ignored] is enabled.
+    ///
+    /// # Panics
+    ///
+    /// If the root of the public view is computed and the subtrie contains private leaves, see
+    /// [HashBuilder::try_add_branch_with_privacy].
+    pub fn add_branch_with_privacy(
+        &mut self,
+        key: Nibbles,
+        value: B256,
+        stored_in_database: bool,
+        is_private: bool,
+    ) {
+        if let Err(error) =
+            self.try_add_branch_with_privacy(key, value, stored_in_database, is_private)
+        {
+            panic!("{error}");
+        }
+    }
+
+    /// Adds a new branch element and its hash to the trie hash builder like
+    /// [HashBuilder::add_branch_with_privacy], returning an error if the root of the public view
+    /// is computed and the subtrie contains private leaves.
+    ///
+    /// Only the hash of the full subtrie is known for a branch, so the public view of a subtrie
+    /// with private leaves can not be computed and its leaves must be added instead.
+    pub fn try_add_branch_with_privacy(
+        &mut self,
+        key: Nibbles,
+        value: B256,
+        stored_in_database: bool,
+        is_private: bool,
+    ) -> Result<(), StateRootError> {
+        let is_private = is_private && !self.ignore_privacy;
+        if is_private && self.public_builder.is_some() {
+            return Err(StateRootError::PrivateBranchInPublicView { key });
+        }
         assert!(
             key > self.key || (self.key.is_empty() && key.is_empty()),
             "add_branch key {:?} self.key {:?}",
             key,
             self.key
         );
+        if let Some(observer) = &self.observer {
+            observer.on_branch_added();
+        }
+        #[cfg(feature = "tracing")]
+        {
+            self.metrics.branches += 1;
+        }
+        if let Some(public_builder) = self.public_builder.as_mut() {
+            public_builder.add_branch_with_privacy(key.clone(), value, false, false);
+        }
         if !self.key.is_empty() {
             self.update(&key);
         } else if key.is_empty() {
             self.stack.push(RlpNode::word_rlp(&value));
         }
-        self.set_key_value(key, HashBuilderValueRef::Hash(&value), None);
+        self.set_key_value(key, HashBuilderValueRef::Hash(&value), Some(is_private));
         self.stored_in_database = stored_in_database;
+        Ok(())
     }
 
     /// Returns the current root hash of the trie builder.
     pub fn root(&mut self) -> B256 {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(target: "trie::hash_builder", "root").entered();
+
         // Clears the internal state
         if !self.key.is_empty() {
             self.update(&Nibbles::default());
@@ -158,14 +578,43 @@ impl HashBuilder {
             self.value.clear();
         }
         let root = self.current_root();
-        if root == EMPTY_ROOT_HASH {
+        if root == H::empty_root() {
             if let Some(proof_retainer) = self.proof_retainer.as_mut() {
                 proof_retainer.retain(&Nibbles::default(), &[EMPTY_STRING_CODE])
             }
         }
+        #[cfg(feature = "tracing")]
+        {
+            let metrics = core::mem::take(&mut self.metrics);
+            tracing::debug!(
+                target: "trie::hash_builder",
+                ?root,
+                leaves = metrics.leaves,
+                branches = metrics.branches,
+                branch_nodes = metrics.branch_nodes,
+                rlp_bytes = metrics.rlp_bytes,
+                "computed root",
+            );
+        }
         root
     }
 
+    /// Returns the root hash of the trie along with the updated branch nodes, consuming the Hash
+    /// Builder so that the updates cannot be read before the root is finalized.
+    ///
+    /// Returns empty updates if [Self::with_updates] was not called.
+    pub fn root_with_updates(mut self) -> (B256, TrieUpdates) {
+        let root = self.root();
+        (root, self.take_updates())
+    }
+
+    /// Returns the root hash of the public view of the trie, which only contains the public leaves.
+    ///
+    /// Returns `None` if [HashBuilder::with_public_root] was not called.
+    pub fn public_root(&mut self) -> Option<B256> {
+        self.public_builder.as_mut().map(|public_builder| public_builder.root())
+    }
+
     #[inline]
     fn set_key_value(
         &mut self,
@@ -194,10 +643,13 @@ impl HashBuilder {
             if let Some(hash) = node_ref.as_hash() {
                 hash
             } else {
-                keccak256(node_ref)
+                if let Some(observer) = &self.observer {
+                    observer.on_bytes_hashed(node_ref.len());
+                }
+                H::hash(node_ref)
             }
         } else {
-            EMPTY_ROOT_HASH
+            H::empty_root()
         }
     }
 
@@ -207,6 +659,8 @@ impl HashBuilder {
     /// built so far.
     fn update(&mut self, succeeding: &Nibbles) {
         let mut build_extensions = false;
+        // whether the element being processed contains private leaves
+        let mut is_private = self.is_private.unwrap_or_default();
         // current / self.key is always the latest added element in the trie
         let mut current = self.key.clone();
         debug_assert!(!current.is_empty());
@@ -252,6 +706,11 @@ impl HashBuilder {
                 self.resize_masks(current.len());
             }
 
+            // Adjust the private masks for the element being a child of the branch node
+            if is_private {
+                self.private_masks[len] |= TrieMask::from_nibble(extra_digit);
+            }
+
             let mut len_from = len;
             if !succeeding.is_empty() || preceding_exists {
                 len_from += 1;
@@ -269,7 +728,15 @@ impl HashBuilder {
                         let is_private = self.is_private.unwrap();
                         let leaf_node = LeafNodeRef::new(&short_node_key, leaf_value, &is_private);
                         self.rlp_buf.clear();
-                        let rlp = leaf_node.rlp(&mut self.rlp_buf);
+                        leaf_node.encode(&mut self.rlp_buf);
+                        if let Some(observer) = &self.observer {
+                            observer.on_leaf_node_encoded(len_from, is_private, self.rlp_buf.len());
+                        }
+                        #[cfg(feature = "tracing")]
+                        {
+                            self.metrics.rlp_bytes += self.rlp_buf.len();
+                        }
+                        let rlp = self.rlp_node_from_buf();
                         trace!(
                             target: "trie::hash_builder",
                             ?leaf_node,
@@ -301,7 +768,15 @@ impl HashBuilder {
                 let extension_node = ExtensionNodeRef::new(&short_node_key, &stack_last);
 
                 self.rlp_buf.clear();
-                let rlp = extension_node.rlp(&mut self.rlp_buf);
+                extension_node.encode(&mut self.rlp_buf);
+                if let Some(observer) = &self.observer {
+                    observer.on_extension_node_encoded(len_from, self.rlp_buf.len());
+                }
+                #[cfg(feature = "tracing")]
+                {
+                    self.metrics.rlp_bytes += self.rlp_buf.len();
+                }
+                let rlp = self.rlp_node_from_buf();
                 trace!(
                     target: "trie::hash_builder",
                     ?extension_node,
@@ -324,6 +799,7 @@ impl HashBuilder {
                 let children = self.push_branch_node(&current, len);
                 // Need to store the branch node in an efficient format outside of the hash builder
                 self.store_branch_node(&current, len, children);
+                is_private = !self.private_masks[len].is_empty();
             }
 
             self.state_masks.resize(len, TrieMask::default());
@@ -348,6 +824,22 @@ impl HashBuilder {
         }
     }
 
+    /// Returns the node encoded in `rlp_buf` as an [RlpNode], hashing it if it is too large or if
+    /// all nodes are hashed.
+    fn rlp_node_from_buf(&self) -> RlpNode {
+        let rlp = if self.hash_all_nodes {
+            RlpNode::word_rlp(&H::hash(&self.rlp_buf))
+        } else {
+            RlpNode::from_rlp_with_hasher::<H>(&self.rlp_buf)
+        };
+        if rlp.is_hash() {
+            if let Some(observer) = &self.observer {
+                observer.on_bytes_hashed(self.rlp_buf.len());
+            }
+        }
+        rlp
+    }
+
     /// Given the size of the longest common prefix, it proceeds to create a branch node
     /// from the state mask and existing stack state, and store its RLP to the top of the stack,
     /// after popping all the relevant elements from the stack.
@@ -366,8 +858,24 @@ impl HashBuilder {
         };
 
         self.rlp_buf.clear();
-        let rlp = branch_node.rlp(&mut self.rlp_buf);
+        branch_node.encode(&mut self.rlp_buf);
+        let rlp = self.rlp_node_from_buf();
         self.retain_proof_from_buf(&current.slice(..len));
+        if let Some(observer) = &self.observer {
+            observer.on_branch_hashed();
+            observer.on_branch_node_encoded(len, self.rlp_buf.len());
+        }
+        #[cfg(feature = "tracing")]
+        {
+            self.metrics.branch_nodes += 1;
+            self.metrics.rlp_bytes += self.rlp_buf.len();
+            tracing::trace!(
+                target: "trie::hash_builder",
+                depth = len,
+                rlp_len = self.rlp_buf.len(),
+                "folded branch node",
+            );
+        }
 
         // Clears the stack from the branch node elements
         let first_child_idx = self.stack.len() - state_mask.count_ones() as usize;
@@ -409,9 +917,12 @@ impl HashBuilder {
                     self.hash_masks[len],
                     children,
                     (len == 0).then(|| self.current_root()),
-                );
+                )
+                .with_private_mask(self.private_masks[len]);
                 trace!(target: "trie::hash_builder", ?node, "intermediate node");
-                self.updated_branch_nodes.as_mut().unwrap().insert(common_prefix, node);
+                if let Some(updated_branch_nodes) = &mut self.updated_branch_nodes {
+                    updated_branch_nodes.insert(common_prefix, node);
+                }
             }
         }
     }
@@ -444,16 +955,19 @@ impl HashBuilder {
         );
         self.tree_masks.resize(new_len, TrieMask::default());
         self.hash_masks.resize(new_len, TrieMask::default());
+        self.private_masks.resize(new_len, TrieMask::default());
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{nodes::LeafNode, triehash_trie_root};
+    use crate::{
+        nodes::{BranchNode, LeafNode, LeafValue},
+        triehash_trie_root, EMPTY_ROOT_HASH,
+    };
     use alloc::collections::BTreeMap;
-    use alloy_primitives::{b256, hex, U256};
-    use alloy_rlp::Encodable;
+    use alloy_primitives::{b256, hex, keccak256, U256};
 
     fn assert_hashed_trie_root<'a, I, K>(iter: I)
     where
@@ -498,6 +1012,40 @@ mod tests {
         assert_eq!(HashBuilder::default().root(), EMPTY_ROOT_HASH);
     }
 
+    #[test]
+    fn try_add_leaf_errors() {
+        let key = |nibbles: &[u8]| Nibbles::from_nibbles_unchecked(nibbles);
+        let mut hb = HashBuilder::default();
+        assert_eq!(
+            hb.try_add_leaf(key(&[0; MAX_KEY_LEN + 1]), b"value", false),
+            Err(StateRootError::KeyTooLong { key: key(&[0; MAX_KEY_LEN + 1]) })
+        );
+        assert_eq!(hb.try_add_leaf(key(&[1, 2]), b"value", false), Ok(()));
+        assert_eq!(
+            hb.try_add_leaf(key(&[1, 1]), b"value", false),
+            Err(StateRootError::UnsortedKey { key: key(&[1, 1]), previous: key(&[1, 2]) })
+        );
+        assert_eq!(
+            hb.try_add_leaf(key(&[1, 2]), b"value", false),
+            Err(StateRootError::DuplicateKey { key: key(&[1, 2]) })
+        );
+        assert_eq!(
+            hb.try_add_leaf(key(&[1, 2]), b"value", true),
+            Err(StateRootError::InvalidPrivacyTransition { key: key(&[1, 2]), was_private: false })
+        );
+        assert_eq!(
+            hb.try_add_leaf(key(&[1, 2, 3]), b"value", false),
+            Err(StateRootError::NestedKey { key: key(&[1, 2, 3]), previous: key(&[1, 2]) })
+        );
+
+        // The rejected leaves are not added.
+        assert_eq!(hb.try_add_leaf(key(&[1, 3]), b"other", true), Ok(()));
+        let mut expected = HashBuilder::default();
+        expected.add_leaf(key(&[1, 2]), b"value", false);
+        expected.add_leaf(key(&[1, 3]), b"other", true);
+        assert_eq!(hb.root(), expected.root());
+    }
+
     #[test]
     #[cfg(feature = "arbitrary")]
     #[cfg_attr(miri, ignore = "no proptest")]
@@ -580,6 +1128,93 @@ mod tests {
         assert_eq!(_root, triehash_trie_root(data));
     }
 
+    #[test]
+    fn extend_leaves_matches_add_leaf() {
+        let leaves = (0..100u64)
+            .map(|i| (keccak256(i.to_be_bytes()), (alloy_rlp::encode(i), i % 4 == 0)))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut hb = HashBuilder::default().with_updates(true);
+        for (key, (value, is_private)) in &leaves {
+            hb.add_leaf(Nibbles::unpack(key), value, *is_private);
+        }
+
+        let batch = leaves
+            .iter()
+            .map(|(key, (value, is_private))| (Nibbles::unpack(key), &value[..], *is_private))
+            .collect::<Vec<_>>();
+        let mut batched = HashBuilder::default().with_updates(true);
+        batched.extend_leaves(&batch[..50]);
+        batched.extend_leaves(&[]);
+        batched.extend_leaves(&batch[50..]);
+
+        assert_eq!(batched.root(), hb.root());
+        assert_eq!(batched.split().1, hb.split().1);
+    }
+
+    #[test]
+    fn reset_reuses_builder() {
+        let tries = (0..3u64).map(|i| {
+            (0..10 * i)
+                .map(|j| (keccak256((i * 100 + j).to_be_bytes()), alloy_rlp::encode(j)))
+                .collect::<BTreeMap<_, _>>()
+        });
+
+        let mut hb = HashBuilder::default().with_capacity(64).with_updates(true);
+        for trie in tries {
+            let mut fresh = HashBuilder::default().with_updates(true);
+            for (key, value) in &trie {
+                hb.add_leaf(Nibbles::unpack(key), value, false);
+                fresh.add_leaf(Nibbles::unpack(key), value, false);
+            }
+            assert_eq!(hb.root(), fresh.root());
+            assert_eq!(hb.take_updates(), fresh.take_updates());
+
+            let stack_capacity = hb.stack.capacity();
+            hb.reset();
+            assert_eq!(hb.root(), EMPTY_ROOT_HASH);
+            assert_eq!(hb.updates_len(), 0);
+            assert!(hb.stack.is_empty());
+            assert_eq!(hb.stack.capacity(), stack_capacity);
+        }
+    }
+
+    #[test]
+    #[should_panic = "extend_leaves keys are not sorted"]
+    fn extend_leaves_unsorted() {
+        let mut hb = HashBuilder::default();
+        hb.extend_leaves(&[
+            (Nibbles::from_nibbles([0x2]), &[0x01][..], false),
+            (Nibbles::from_nibbles([0x1]), &[0x02][..], false),
+        ]);
+    }
+
+    #[test]
+    fn root_with_updates() {
+        let data = (0..20u64)
+            .map(|i| (keccak256(i.to_be_bytes()), alloy_rlp::encode(i)))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut hb = HashBuilder::default().with_updates(true);
+        let mut without_updates = HashBuilder::default();
+        for (key, value) in &data {
+            hb.add_leaf(Nibbles::unpack(key), value, false);
+            without_updates.add_leaf(Nibbles::unpack(key), value, false);
+        }
+        let mut finished = hb.clone();
+        let expected = finished.root();
+        let expected_updates = finished.split().1;
+
+        let (root, updates) = hb.root_with_updates();
+        assert_eq!(root, expected);
+        assert_eq!(updates.updated_nodes, expected_updates);
+        assert!(!updates.updated_nodes.is_empty());
+
+        let (root, updates) = without_updates.root_with_updates();
+        assert_eq!(root, expected);
+        assert!(updates.is_empty());
+    }
+
     #[test]
     fn test_root_raw_data() {
         let data = [
@@ -736,6 +1371,66 @@ mod tests {
         assert_ne!(hb_pub.root(), hb_priv.root());
     }
 
+    #[test]
+    fn privacy_ignored_matches_ethereum() {
+        let data = BTreeMap::from([
+            (hex!("646f").to_vec(), hex!("76657262").to_vec()),
+            (hex!("676f6f64").to_vec(), hex!("7075707079").to_vec()),
+            (hex!("676f6b32").to_vec(), hex!("7075707079").to_vec()),
+            (hex!("676f6b34").to_vec(), hex!("7075707079").to_vec()),
+        ]);
+
+        let mut hb = HashBuilder::default().with_public_root(true).with_privacy_ignored(true);
+        for (i, (key, val)) in data.iter().enumerate() {
+            hb.add_leaf(Nibbles::unpack(key), val, i % 2 == 0);
+        }
+        assert_eq!(hb.root(), triehash_trie_root(&data));
+        assert_eq!(hb.public_root(), Some(hb.root()));
+    }
+
+    #[test]
+    fn test_branch_node_private_mask() {
+        let data = [
+            (hex!("1000000000000000000000000000000000000000000000000000000000000000"), false),
+            (hex!("1100000000000000000000000000000000000000000000000000000000000000"), false),
+            (hex!("1110000000000000000000000000000000000000000000000000000000000000"), true),
+            (hex!("1200000000000000000000000000000000000000000000000000000000000000"), false),
+            (hex!("1220000000000000000000000000000000000000000000000000000000000000"), false),
+            (hex!("1320000000000000000000000000000000000000000000000000000000000000"), true),
+        ];
+
+        let mut hb = HashBuilder::default().with_updates(true);
+        for (key, is_private) in data {
+            hb.add_leaf(Nibbles::unpack(key), &[], is_private);
+        }
+        hb.root();
+        let (_, updates) = hb.split();
+
+        let update = updates.get(&Nibbles::from_nibbles_unchecked(hex!("01"))).unwrap();
+        assert_eq!(update.state_mask, TrieMask::new(0b1111));
+        // The branch at nibble `1` holds a private leaf, as does the leaf at nibble `3`
+        assert_eq!(update.private_mask, TrieMask::new(0b1010));
+
+        // Branch hashes carry the privacy of their subtries
+        let mut hb = HashBuilder::default().with_updates(true);
+        hb.add_branch_with_privacy(
+            Nibbles::from_nibbles_unchecked([1, 0]),
+            B256::ZERO,
+            false,
+            false,
+        );
+        hb.add_branch_with_privacy(
+            Nibbles::from_nibbles_unchecked([1, 1]),
+            B256::ZERO,
+            false,
+            true,
+        );
+        hb.root();
+        let (_, updates) = hb.split();
+        let update = updates.get(&Nibbles::from_nibbles_unchecked(hex!("01"))).unwrap();
+        assert_eq!(update.private_mask, TrieMask::new(0b0010));
+    }
+
     #[test]
     fn test_mixed_public_private_leaves() {
         let mut data = [
@@ -762,4 +1457,148 @@ mod tests {
         assert_ne!(root, hb_all_pub.root());
         assert_ne!(root, hb_all_priv.root());
     }
+
+    #[test]
+    fn test_custom_hasher() {
+        use alloy_rlp::Decodable;
+
+        /// Keccak-256 with a domain separation prefix.
+        #[derive(Clone, Debug)]
+        struct PrefixedKeccak;
+
+        impl TrieHasher for PrefixedKeccak {
+            fn hash(data: &[u8]) -> B256 {
+                keccak256([&[0x01u8][..], data].concat())
+            }
+        }
+
+        let mut empty = HashBuilder::<PrefixedKeccak>::new_with_hasher();
+        assert_eq!(empty.root(), PrefixedKeccak::hash(&[EMPTY_STRING_CODE]));
+
+        let leaves = (0..50u8).map(|i| (keccak256([i]), vec![i; 40])).collect::<BTreeMap<_, _>>();
+        let mut hb = HashBuilder::default();
+        let mut custom = HashBuilder::<PrefixedKeccak>::new_with_hasher()
+            .with_proof_retainer(vec![Nibbles::default()]);
+        for (key, value) in &leaves {
+            hb.add_leaf(Nibbles::unpack(key), value, true);
+            custom.add_leaf(Nibbles::unpack(key), value, true);
+        }
+        let root = custom.root();
+        assert_ne!(root, hb.root());
+
+        // The root is the custom hash of the root branch node.
+        let root_node = custom.take_proof_nodes().into_inner().remove(&Nibbles::default()).unwrap();
+        assert_eq!(PrefixedKeccak::hash(&root_node), root);
+        assert!(matches!(
+            crate::nodes::TrieNode::decode(&mut &root_node[..]).unwrap(),
+            crate::nodes::TrieNode::Branch(_)
+        ));
+    }
+
+    #[test]
+    fn test_public_root() {
+        let leaves = (0..50u8)
+            .map(|i| (keccak256([i]), (vec![i; 40], i % 3 == 0)))
+            .collect::<BTreeMap<_, _>>();
+        let mut hb = HashBuilder::default().with_public_root(true);
+        let mut public = HashBuilder::default();
+        for (key, (value, is_private)) in &leaves {
+            hb.add_leaf(Nibbles::unpack(key), value, *is_private);
+            if !is_private {
+                public.add_leaf(Nibbles::unpack(key), value, false);
+            }
+        }
+        assert_eq!(hb.public_root(), Some(public.root()));
+        assert_ne!(hb.public_root(), Some(hb.root()));
+        assert_eq!(HashBuilder::default().public_root(), None);
+
+        // A public branch is added to both views, a private one is rejected as its public view is
+        // unknown.
+        let mut hb = HashBuilder::default().with_public_root(true);
+        hb.add_branch(Nibbles::from_nibbles([0x1]), B256::repeat_byte(1), false);
+        assert!(hb.public_builder.as_ref().is_some_and(|public| public.key == hb.key));
+        let key = Nibbles::from_nibbles([0x2]);
+        assert_eq!(
+            hb.try_add_branch_with_privacy(key.clone(), B256::repeat_byte(2), false, true),
+            Err(StateRootError::PrivateBranchInPublicView { key })
+        );
+        assert_eq!(hb.public_root(), Some(hb.root()));
+    }
+
+    #[test]
+    #[should_panic = "public view"]
+    fn private_branch_with_public_root() {
+        let mut hb = HashBuilder::default().with_public_root(true);
+        hb.add_branch_with_privacy(Nibbles::from_nibbles([0x2]), B256::repeat_byte(2), false, true);
+    }
+
+    #[test]
+    fn test_add_leaf_payload() {
+        let leaves = (0..50u8)
+            .map(|i| (keccak256([i]), LeafValue::new(vec![i; 40], i % 3 == 0)))
+            .collect::<BTreeMap<_, _>>();
+        let mut hb = HashBuilder::default();
+        let mut flagged = HashBuilder::default();
+        for (key, value) in &leaves {
+            hb.add_leaf_payload(Nibbles::unpack(key), value.as_payload());
+            flagged.add_leaf(Nibbles::unpack(key), value.value(), value.is_private());
+        }
+        assert_eq!(hb.root(), flagged.root());
+    }
+
+    #[test]
+    fn test_add_leaf_with() {
+        let leaves = (0..50u8)
+            .map(|i| (keccak256([i]), (vec![i; 40], i % 3 == 0)))
+            .collect::<BTreeMap<_, _>>();
+        let mut hb = HashBuilder::default().with_updates(true).with_public_root(true);
+        let mut encoded = HashBuilder::default().with_updates(true).with_public_root(true);
+        for (key, (value, is_private)) in &leaves {
+            hb.add_leaf(Nibbles::unpack(key), value, *is_private);
+            encoded.add_leaf_with(Nibbles::unpack(key), |buf| {
+                buf.extend_from_slice(value);
+                *is_private
+            });
+        }
+        assert_eq!(hb.public_root(), encoded.public_root());
+        assert_eq!(hb.root_with_updates(), encoded.root_with_updates());
+    }
+
+    #[test]
+    fn test_all_nodes_hashed() {
+        // Two leaves short enough to be inlined in their branch node.
+        let leaves = [
+            (Nibbles::from_nibbles([0x1, 0x2]), vec![1]),
+            (Nibbles::from_nibbles([0x2, 0x3]), vec![2]),
+        ];
+        let mut hb = HashBuilder::default();
+        let mut hashed = HashBuilder::default()
+            .with_all_nodes_hashed(true)
+            .with_proof_retainer(vec![Nibbles::default()]);
+        let mut stack = Vec::new();
+        for (key, value) in &leaves {
+            hb.add_leaf(key.clone(), value, false);
+            hashed.add_leaf(key.clone(), value, false);
+            let rlp = alloy_rlp::encode(LeafNode::new(key.slice(1..), value.clone(), false));
+            assert!(rlp.len() < 32);
+            stack.push(RlpNode::word_rlp(&keccak256(&rlp)));
+        }
+        let branch = BranchNode::new(stack, TrieMask::new(0b0110));
+        let mut rlp = Vec::new();
+        branch.encode(&mut rlp);
+        assert_eq!(hashed.root(), keccak256(&rlp));
+        assert_ne!(hashed.root(), hb.root());
+        assert_eq!(hashed.take_proof_nodes().into_inner()[&Nibbles::default()][..], rlp[..]);
+
+        // Tries without inlined nodes have the same root in both modes.
+        let leaves = (0..50u8).map(|i| (keccak256([i]), vec![i; 40])).collect::<BTreeMap<_, _>>();
+        let mut hb = HashBuilder::default().with_public_root(true);
+        let mut hashed = HashBuilder::default().with_public_root(true).with_all_nodes_hashed(true);
+        for (key, value) in &leaves {
+            hb.add_leaf(Nibbles::unpack(key), value, false);
+            hashed.add_leaf(Nibbles::unpack(key), value, false);
+        }
+        assert_eq!(hashed.root(), hb.root());
+        assert_eq!(hashed.public_root(), hb.public_root());
+    }
 }
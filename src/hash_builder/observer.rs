@@ -0,0 +1,32 @@
+use core::fmt;
+
+/// Observer of the work done during root computation, e.g. to expose it as metrics.
+///
+/// Set with [`HashBuilder::with_observer`](crate::HashBuilder::with_observer), the incremental
+/// root computations forward their observer to all the hash builders they create. All methods
+/// default to no-ops.
+pub trait TrieObserver: fmt::Debug + Send + Sync {
+    /// Called when a leaf is added to the hash builder.
+    fn on_leaf_added(&self) {}
+
+    /// Called when the hash of a subtrie is added to the hash builder.
+    fn on_branch_added(&self) {}
+
+    /// Called when a branch node is built from its children.
+    fn on_branch_hashed(&self) {}
+
+    /// Called when the given number of bytes of encoded nodes are hashed.
+    fn on_bytes_hashed(&self, _len: usize) {}
+
+    /// Called when the storage root of an account is computed.
+    fn on_storage_root_computed(&self) {}
+
+    /// Called when a leaf node is encoded at the given depth, in nibbles.
+    fn on_leaf_node_encoded(&self, _depth: usize, _is_private: bool, _rlp_len: usize) {}
+
+    /// Called when an extension node is encoded at the given depth, in nibbles.
+    fn on_extension_node_encoded(&self, _depth: usize, _rlp_len: usize) {}
+
+    /// Called when a branch node is encoded at the given depth, in nibbles.
+    fn on_branch_node_encoded(&self, _depth: usize, _rlp_len: usize) {}
+}
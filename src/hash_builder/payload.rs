@@ -0,0 +1,206 @@
+use alloc::vec::Vec;
+use alloy_primitives::{keccak256, B256};
+
+/// The value of a leaf added to the [`HashBuilder`](crate::HashBuilder).
+///
+/// Private values are stored in the trie as commitments, see [`PrivateLeafEncoder`]. The owned
+/// counterpart is [`LeafValue`](crate::nodes::LeafValue).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LeafPayload<'a> {
+    /// Plaintext value of a public leaf.
+    Public(&'a [u8]),
+    /// Committed value of a private leaf.
+    Private(&'a [u8]),
+}
+
+impl<'a> LeafPayload<'a> {
+    /// Returns the value stored in the leaf.
+    pub const fn value(&self) -> &'a [u8] {
+        match *self {
+            Self::Public(value) | Self::Private(value) => value,
+        }
+    }
+
+    /// Returns `true` if the leaf is private.
+    pub const fn is_private(&self) -> bool {
+        matches!(self, Self::Private(_))
+    }
+}
+
+/// Commits to the plaintext values of private leaves before they are added to the trie.
+///
+/// Implemented for closures, e.g. to plug in a Pedersen commitment.
+pub trait PrivateLeafEncoder {
+    /// Appends the commitment to the given plaintext value to `out`.
+    fn commit(&self, value: &[u8], out: &mut Vec<u8>);
+}
+
+impl<F: Fn(&[u8], &mut Vec<u8>)> PrivateLeafEncoder for F {
+    fn commit(&self, value: &[u8], out: &mut Vec<u8>) {
+        self(value, out)
+    }
+}
+
+/// Commits to private values as `keccak256(salt || value)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SaltedKeccakEncoder {
+    /// The salt prepended to the values.
+    pub salt: B256,
+}
+
+impl SaltedKeccakEncoder {
+    /// Creates a new encoder with the given salt.
+    pub const fn new(salt: B256) -> Self {
+        Self { salt }
+    }
+}
+
+impl PrivateLeafEncoder for SaltedKeccakEncoder {
+    fn commit(&self, value: &[u8], out: &mut Vec<u8>) {
+        out.extend_from_slice(keccak256([self.salt.as_slice(), value].concat()).as_slice());
+    }
+}
+
+/// Commits to the private values larger than a threshold, such as ciphertexts, through the hashes
+/// of their chunks, so that the leaf nodes and the proofs stay small. Values up to the threshold
+/// are stored as-is.
+///
+/// The value of `len` bytes is split into chunks of `chunk_size` bytes, the last one possibly
+/// shorter, and committed to as `keccak256(be_u64(len) || keccak256(chunk_0) || ..)`. A verifier
+/// can check a single chunk against the leaf given the hashes of all chunks, see
+/// [ChunkedEncoder::verify_chunk].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkedEncoder {
+    /// The length of the largest value stored as-is.
+    pub threshold: usize,
+    /// The length of the chunks of the larger values.
+    pub chunk_size: usize,
+}
+
+impl ChunkedEncoder {
+    /// Creates a new encoder chunking the values above the threshold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the chunk size is zero.
+    pub const fn new(threshold: usize, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must not be zero");
+        Self { threshold, chunk_size }
+    }
+
+    /// Returns `true` if a value of the given length is committed through its chunks.
+    pub const fn is_chunked(&self, len: usize) -> bool {
+        len > self.threshold
+    }
+
+    /// Returns the hashes of the chunks of the value.
+    pub fn chunk_hashes(&self, value: &[u8]) -> Vec<B256> {
+        value.chunks(self.chunk_size).map(keccak256).collect()
+    }
+
+    /// Returns the commitment to a value of the given length from the hashes of its chunks.
+    pub fn commitment(len: usize, chunk_hashes: &[B256]) -> B256 {
+        let mut preimage = Vec::with_capacity(8 + chunk_hashes.len() * 32);
+        preimage.extend_from_slice(&(len as u64).to_be_bytes());
+        for hash in chunk_hashes {
+            preimage.extend_from_slice(hash.as_slice());
+        }
+        keccak256(preimage)
+    }
+
+    /// Returns `true` if the leaf value commits to the given value.
+    pub fn verify(&self, leaf_value: &[u8], value: &[u8]) -> bool {
+        let mut expected = Vec::new();
+        self.commit(value, &mut expected);
+        expected == leaf_value
+    }
+
+    /// Returns `true` if the chunk at the given index belongs to the chunked value of the given
+    /// length committed to by the leaf value, given the hashes of all chunks of the value.
+    pub fn verify_chunk(
+        &self,
+        leaf_value: &[u8],
+        len: usize,
+        chunk_hashes: &[B256],
+        index: usize,
+        chunk: &[u8],
+    ) -> bool {
+        let start = index.saturating_mul(self.chunk_size);
+        self.is_chunked(len)
+            && chunk_hashes.len() == len.div_ceil(self.chunk_size)
+            && start < len
+            && chunk.len() == self.chunk_size.min(len - start)
+            && chunk_hashes[index] == keccak256(chunk)
+            && Self::commitment(len, chunk_hashes) == leaf_value
+    }
+}
+
+impl PrivateLeafEncoder for ChunkedEncoder {
+    fn commit(&self, value: &[u8], out: &mut Vec<u8>) {
+        if self.is_chunked(value.len()) {
+            let commitment = Self::commitment(value.len(), &self.chunk_hashes(value));
+            out.extend_from_slice(commitment.as_slice());
+        } else {
+            out.extend_from_slice(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HashBuilder, Nibbles};
+
+    #[test]
+    fn private_leaf_commitments() {
+        let encoder = SaltedKeccakEncoder::new(B256::repeat_byte(0x42));
+        let (public, private) =
+            (Nibbles::unpack(B256::ZERO), Nibbles::unpack(B256::repeat_byte(1)));
+
+        let mut hb = HashBuilder::default();
+        hb.add_leaf_payload(public.clone(), LeafPayload::Public(b"public"));
+        hb.add_private_leaf(private.clone(), b"private", &encoder);
+
+        let mut expected = HashBuilder::default();
+        expected.add_leaf(public, b"public", false);
+        let commitment = keccak256([encoder.salt.as_slice(), &b"private"[..]].concat());
+        expected.add_leaf(private, commitment.as_slice(), true);
+        assert_eq!(hb.root(), expected.root());
+
+        let mut out = Vec::new();
+        let identity = |value: &[u8], out: &mut Vec<u8>| out.extend_from_slice(value);
+        identity.commit(b"private", &mut out);
+        assert_eq!(out, b"private");
+        assert_eq!(LeafPayload::Private(&out).value(), b"private");
+        assert!(!LeafPayload::Public(&out).is_private());
+    }
+
+    #[test]
+    fn chunked_private_values() {
+        let encoder = ChunkedEncoder::new(64, 100);
+        let small = [1u8; 64];
+        let large = Vec::from_iter((0..1000u32).map(|i| i as u8));
+
+        let mut hb = HashBuilder::default();
+        hb.add_private_leaf(Nibbles::unpack(B256::ZERO), &small, &encoder);
+        hb.add_private_leaf(Nibbles::unpack(B256::repeat_byte(1)), &large, &encoder);
+
+        let chunk_hashes = encoder.chunk_hashes(&large);
+        assert_eq!(chunk_hashes.len(), 10);
+        let commitment = ChunkedEncoder::commitment(large.len(), &chunk_hashes);
+        let mut expected = HashBuilder::default();
+        expected.add_leaf(Nibbles::unpack(B256::ZERO), &small, true);
+        expected.add_leaf(Nibbles::unpack(B256::repeat_byte(1)), commitment.as_slice(), true);
+        assert_eq!(hb.root(), expected.root());
+
+        assert!(encoder.verify(&small, &small));
+        assert!(encoder.verify(commitment.as_slice(), &large));
+        assert!(!encoder.verify(commitment.as_slice(), &large[1..]));
+
+        let chunk = &large[900..];
+        assert!(encoder.verify_chunk(commitment.as_slice(), 1000, &chunk_hashes, 9, chunk));
+        assert!(!encoder.verify_chunk(commitment.as_slice(), 1000, &chunk_hashes, 8, chunk));
+        assert!(!encoder.verify_chunk(commitment.as_slice(), 1000, &chunk_hashes, 10, chunk));
+        assert!(!encoder.verify_chunk(commitment.as_slice(), 999, &chunk_hashes, 9, chunk));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::nodes::RlpNode;
+use alloc::vec::Vec;
+
+/// The capacity of the node stacks, enough for keys of up to 64 nibbles.
+const STACK_CAPACITY: usize = 65;
+
+/// A pool of reusable buffers owned by a [`HashBuilder`](crate::HashBuilder), see
+/// [`HashBuilder::with_allocator`](crate::HashBuilder::with_allocator).
+///
+/// The node stack and the buffers used to hold the current value, to encode the nodes and to
+/// commit to the private leaves are taken from the pool and returned to it, so that they are
+/// allocated once instead of for every leaf or, with
+/// [`HashBuilder::into_allocator`](crate::HashBuilder::into_allocator), for every trie.
+#[derive(Clone, Debug, Default)]
+pub struct BufferPool {
+    buffers: Vec<Vec<u8>>,
+    buffer_capacity: usize,
+    stacks: Vec<Vec<RlpNode>>,
+}
+
+impl BufferPool {
+    /// Creates a pool of the given number of buffers, preallocated with the given capacity.
+    pub fn new(buffers: usize, buffer_capacity: usize) -> Self {
+        Self {
+            buffers: (0..buffers).map(|_| Vec::with_capacity(buffer_capacity)).collect(),
+            buffer_capacity,
+            stacks: Vec::new(),
+        }
+    }
+
+    /// Returns the number of buffers in the pool.
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Returns `true` if all the buffers are taken.
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+
+    /// Takes an empty buffer from the pool, allocating a new one if all the buffers are taken.
+    pub fn take(&mut self) -> Vec<u8> {
+        self.buffers.pop().unwrap_or_else(|| Vec::with_capacity(self.buffer_capacity))
+    }
+
+    /// Returns a buffer to the pool.
+    pub fn put(&mut self, mut buffer: Vec<u8>) {
+        buffer.clear();
+        self.buffers.push(buffer);
+    }
+
+    /// Takes an empty node stack from the pool, allocating a new one if all the stacks are taken.
+    pub fn take_stack(&mut self) -> Vec<RlpNode> {
+        self.stacks.pop().unwrap_or_else(|| Vec::with_capacity(STACK_CAPACITY))
+    }
+
+    /// Returns a node stack to the pool.
+    pub fn put_stack(&mut self, mut stack: Vec<RlpNode>) {
+        stack.clear();
+        self.stacks.push(stack);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hash_builder::SaltedKeccakEncoder, HashBuilder, Nibbles};
+    use alloy_primitives::{keccak256, B256};
+
+    #[test]
+    fn reuse_buffers() {
+        let mut pool = BufferPool::new(2, 64);
+        let buffer = pool.take();
+        assert_eq!(pool.len(), 1);
+        assert!(buffer.capacity() >= 64);
+        pool.put(buffer);
+        assert_eq!(pool.len(), 2);
+
+        let encoder = SaltedKeccakEncoder::new(B256::repeat_byte(0x42));
+        let keys = (0..100u64).map(|i| Nibbles::unpack(keccak256(i.to_be_bytes())));
+        let mut keys = keys.collect::<Vec<_>>();
+        keys.sort_unstable();
+
+        let mut expected = HashBuilder::default();
+        let mut hb = HashBuilder::default().with_allocator(BufferPool::new(3, 128));
+        for key in &keys {
+            expected.add_private_leaf(key.clone(), b"value", &encoder);
+            hb.add_private_leaf(key.clone(), b"value", &encoder);
+            // The commitment buffer is returned to the pool.
+            assert_eq!(hb.buffer_pool.len(), 1);
+        }
+        assert!(hb.rlp_buf.capacity() >= 128);
+        assert!(hb.stack.capacity() >= 65);
+        let root = expected.root();
+        assert_eq!(hb.root(), root);
+
+        // The buffers are handed over to the next builder.
+        let mut pool = hb.into_allocator();
+        assert_eq!(pool.len(), 3);
+        let stack = pool.take_stack();
+        let stack_ptr = stack.as_ptr();
+        pool.put_stack(stack);
+        let mut hb = HashBuilder::default().with_allocator(pool);
+        assert_eq!(hb.stack.as_ptr(), stack_ptr);
+        for key in keys {
+            hb.add_private_leaf(key, b"value", &encoder);
+        }
+        assert_eq!(hb.root(), root);
+    }
+}
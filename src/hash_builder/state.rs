@@ -0,0 +1,168 @@
+use super::{HashBuilder, HashBuilderValue, TrieHasher};
+use crate::{nodes::RlpNode, Nibbles, TrieMask};
+use alloc::{boxed::Box, vec::Vec};
+
+/// The state of a partially built trie, taken from a [HashBuilder] so that the trie can be
+/// persisted and completed later, e.g. across process restarts.
+///
+/// Only the trie being built and the modes it is hashed with are kept. The retained updates, the
+/// proof retainer, the observer and the buffer pool are dropped, they are enabled again on the
+/// restored [HashBuilder].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HashBuilderState {
+    /// The key of the current leaf or branch.
+    pub key: Nibbles,
+    /// The value of the current leaf or the hash of the current branch.
+    pub value: HashBuilderValue,
+    /// Whether the current leaf is private, [None] for branches.
+    pub is_private: Option<bool>,
+    /// The stack of the encoded nodes.
+    pub stack: Vec<RlpNode>,
+    /// The state masks of the branches being built.
+    pub state_masks: Vec<TrieMask>,
+    /// The tree masks of the branches being built.
+    pub tree_masks: Vec<TrieMask>,
+    /// The hash masks of the branches being built.
+    pub hash_masks: Vec<TrieMask>,
+    /// The private masks of the branches being built.
+    pub private_masks: Vec<TrieMask>,
+    /// Whether the current branch is stored in the database.
+    pub stored_in_database: bool,
+    /// The state of the builder of the public view of the trie, see
+    /// [HashBuilder::with_public_root].
+    pub public_state: Option<Box<Self>>,
+    /// Whether the nodes shorter than 32 bytes are hashed too, see
+    /// [HashBuilder::with_all_nodes_hashed].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hash_all_nodes: bool,
+    /// Whether the privacy flags are ignored, see [HashBuilder::with_privacy_ignored].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ignore_privacy: bool,
+}
+
+impl<H> From<HashBuilder<H>> for HashBuilderState {
+    fn from(hb: HashBuilder<H>) -> Self {
+        Self {
+            key: hb.key,
+            value: hb.value,
+            is_private: hb.is_private,
+            stack: hb.stack,
+            state_masks: hb.state_masks,
+            tree_masks: hb.tree_masks,
+            hash_masks: hb.hash_masks,
+            private_masks: hb.private_masks,
+            stored_in_database: hb.stored_in_database,
+            public_state: hb
+                .public_builder
+                .map(|public_builder| Box::new((*public_builder).into())),
+            hash_all_nodes: hb.hash_all_nodes,
+            ignore_privacy: hb.ignore_privacy,
+        }
+    }
+}
+
+impl<H: TrieHasher> From<HashBuilderState> for HashBuilder<H> {
+    fn from(state: HashBuilderState) -> Self {
+        Self {
+            key: state.key,
+            value: state.value,
+            is_private: state.is_private,
+            stack: state.stack,
+            state_masks: state.state_masks,
+            tree_masks: state.tree_masks,
+            hash_masks: state.hash_masks,
+            private_masks: state.private_masks,
+            stored_in_database: state.stored_in_database,
+            public_builder: state.public_state.map(|public_state| Box::new((*public_state).into())),
+            hash_all_nodes: state.hash_all_nodes,
+            ignore_privacy: state.ignore_privacy,
+            ..Self::new_with_hasher()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::keccak256;
+
+    #[test]
+    fn resume_from_state() {
+        let mut leaves = (0..100u64)
+            .map(|i| (Nibbles::unpack(keccak256(i.to_be_bytes())), i.to_be_bytes(), i % 3 == 0))
+            .collect::<Vec<_>>();
+        leaves.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        let build = |hb: &mut HashBuilder, leaves: &[(Nibbles, [u8; 8], bool)]| {
+            for (key, value, is_private) in leaves {
+                hb.add_leaf(key.clone(), value, *is_private);
+            }
+        };
+
+        let mut hb = HashBuilder::default().with_public_root(true);
+        build(&mut hb, &leaves);
+        let root = hb.root();
+        let public_root = hb.public_root();
+
+        let mut hb = HashBuilder::default().with_public_root(true);
+        build(&mut hb, &leaves[..40]);
+        let state = HashBuilderState::from(hb);
+        assert!(state.public_state.is_some());
+        #[cfg(feature = "serde")]
+        let state = {
+            let json = serde_json::to_string(&state).unwrap();
+            let decoded = serde_json::from_str::<HashBuilderState>(&json).unwrap();
+            assert_eq!(decoded, state);
+            decoded
+        };
+
+        let mut hb: HashBuilder = state.into();
+        build(&mut hb, &leaves[40..]);
+        assert_eq!(hb.root(), root);
+        assert_eq!(hb.public_root(), public_root);
+    }
+
+    #[test]
+    fn resume_keeps_hashing_modes() {
+        // Short keys and values, so that the leaves are encoded in-place unless all nodes are
+        // hashed.
+        let leaves = (0..32u8)
+            .map(|i| (Nibbles::unpack([i * 8, i]), [i; 4], i % 2 == 0))
+            .collect::<Vec<_>>();
+        let build = |hb: &mut HashBuilder, leaves: &[(Nibbles, [u8; 4], bool)]| {
+            for (key, value, is_private) in leaves {
+                hb.add_leaf(key.clone(), value, *is_private);
+            }
+        };
+        let new_builder = || {
+            HashBuilder::default()
+                .with_public_root(true)
+                .with_all_nodes_hashed(true)
+                .with_privacy_ignored(true)
+        };
+
+        let mut hb = HashBuilder::default();
+        build(&mut hb, &leaves);
+        let default_root = hb.root();
+
+        let mut hb = new_builder();
+        build(&mut hb, &leaves);
+        let root = hb.root();
+        let public_root = hb.public_root();
+        assert_ne!(root, default_root);
+
+        let mut hb = new_builder();
+        build(&mut hb, &leaves[..10]);
+        let state = HashBuilderState::from(hb);
+        assert!(state.hash_all_nodes && state.ignore_privacy);
+        #[cfg(feature = "serde")]
+        let state =
+            serde_json::from_str::<HashBuilderState>(&serde_json::to_string(&state).unwrap())
+                .unwrap();
+
+        let mut hb: HashBuilder = state.into();
+        build(&mut hb, &leaves[10..]);
+        assert_eq!(hb.root(), root);
+        assert_eq!(hb.public_root(), public_root);
+    }
+}
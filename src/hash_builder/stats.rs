@@ -0,0 +1,145 @@
+use super::TrieObserver;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Statistics of the nodes encoded by the [`HashBuilder`](crate::HashBuilder), gathered by a
+/// [TrieStatsObserver].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TrieStats {
+    /// The number of leaf nodes.
+    pub leaves: usize,
+    /// The number of private leaf nodes.
+    pub private_leaves: usize,
+    /// The number of branch nodes.
+    pub branches: usize,
+    /// The number of extension nodes.
+    pub extensions: usize,
+    /// The sum of the depths of the leaf nodes, in nibbles.
+    pub total_leaf_depth: usize,
+    /// The depth of the deepest leaf node, in nibbles.
+    pub max_depth: usize,
+    /// The total length of the encoded nodes, including the nodes encoded in place of their
+    /// parent.
+    pub rlp_bytes: usize,
+}
+
+impl TrieStats {
+    /// Returns the average depth of the leaf nodes, in nibbles.
+    pub fn average_depth(&self) -> f64 {
+        if self.leaves == 0 {
+            0.0
+        } else {
+            self.total_leaf_depth as f64 / self.leaves as f64
+        }
+    }
+}
+
+/// [TrieObserver] gathering the [TrieStats] of the nodes encoded by the hash builders it is set
+/// on, e.g. for capacity planning.
+///
+/// When set on an incremental root computation, only the nodes of the changed subtries are
+/// encoded and counted, the unchanged subtries are added by hash.
+#[derive(Debug, Default)]
+pub struct TrieStatsObserver {
+    leaves: AtomicUsize,
+    private_leaves: AtomicUsize,
+    branches: AtomicUsize,
+    extensions: AtomicUsize,
+    total_leaf_depth: AtomicUsize,
+    max_depth: AtomicUsize,
+    rlp_bytes: AtomicUsize,
+}
+
+impl TrieStatsObserver {
+    /// Returns the statistics gathered so far.
+    pub fn stats(&self) -> TrieStats {
+        TrieStats {
+            leaves: self.leaves.load(Ordering::Relaxed),
+            private_leaves: self.private_leaves.load(Ordering::Relaxed),
+            branches: self.branches.load(Ordering::Relaxed),
+            extensions: self.extensions.load(Ordering::Relaxed),
+            total_leaf_depth: self.total_leaf_depth.load(Ordering::Relaxed),
+            max_depth: self.max_depth.load(Ordering::Relaxed),
+            rlp_bytes: self.rlp_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl TrieObserver for TrieStatsObserver {
+    fn on_leaf_node_encoded(&self, depth: usize, is_private: bool, rlp_len: usize) {
+        self.leaves.fetch_add(1, Ordering::Relaxed);
+        if is_private {
+            self.private_leaves.fetch_add(1, Ordering::Relaxed);
+        }
+        self.total_leaf_depth.fetch_add(depth, Ordering::Relaxed);
+        self.max_depth.fetch_max(depth, Ordering::Relaxed);
+        self.rlp_bytes.fetch_add(rlp_len, Ordering::Relaxed);
+    }
+
+    fn on_extension_node_encoded(&self, _depth: usize, rlp_len: usize) {
+        self.extensions.fetch_add(1, Ordering::Relaxed);
+        self.rlp_bytes.fetch_add(rlp_len, Ordering::Relaxed);
+    }
+
+    fn on_branch_node_encoded(&self, _depth: usize, rlp_len: usize) {
+        self.branches.fetch_add(1, Ordering::Relaxed);
+        self.rlp_bytes.fetch_add(rlp_len, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HashBuilder, Nibbles};
+    use alloc::sync::Arc;
+    use alloy_primitives::{hex, B256};
+
+    fn stats(leaves: &[(B256, bool)]) -> TrieStats {
+        let observer = Arc::new(TrieStatsObserver::default());
+        let mut hb = HashBuilder::default().with_observer(observer.clone());
+        for (key, is_private) in leaves {
+            hb.add_leaf(Nibbles::unpack(key), &[0x01], *is_private);
+        }
+        hb.root();
+        observer.stats()
+    }
+
+    #[test]
+    fn trie_stats() {
+        assert_eq!(stats(&[]), TrieStats::default());
+        assert_eq!(stats(&[]).average_depth(), 0.0);
+
+        // branch(0: branch(0: leaf, 1: private leaf), 1: leaf)
+        let branches = stats(&[
+            (B256::ZERO, false),
+            (
+                B256::new(hex!("0100000000000000000000000000000000000000000000000000000000000000")),
+                true,
+            ),
+            (
+                B256::new(hex!("1000000000000000000000000000000000000000000000000000000000000000")),
+                false,
+            ),
+        ]);
+        assert_eq!(branches.leaves, 3);
+        assert_eq!(branches.private_leaves, 1);
+        assert_eq!(branches.branches, 2);
+        assert_eq!(branches.extensions, 0);
+        assert_eq!(branches.max_depth, 2);
+        assert_eq!(branches.average_depth(), 5.0 / 3.0);
+        assert!(branches.rlp_bytes > 0);
+
+        // ext(000) -> branch(0: leaf, 1: leaf)
+        let extension = stats(&[
+            (B256::ZERO, false),
+            (
+                B256::new(hex!("0001000000000000000000000000000000000000000000000000000000000000")),
+                false,
+            ),
+        ]);
+        assert_eq!(extension.leaves, 2);
+        assert_eq!(extension.branches, 1);
+        assert_eq!(extension.extensions, 1);
+        assert_eq!(extension.max_depth, 4);
+        assert_eq!(extension.average_depth(), 4.0);
+    }
+}
@@ -66,6 +66,18 @@ impl HashBuilderValue {
         Self::default()
     }
 
+    /// Creates a new empty value reusing the given buffer, see
+    /// [HashBuilder::with_allocator](crate::HashBuilder::with_allocator).
+    pub fn with_buffer(mut buf: Vec<u8>) -> Self {
+        buf.clear();
+        Self { buf, kind: HashBuilderValueKind::default() }
+    }
+
+    /// Consumes the value and returns its buffer.
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.buf
+    }
+
     /// Returns the value as a reference.
     #[inline]
     pub fn as_ref(&self) -> HashBuilderValueRef<'_> {
@@ -89,6 +101,14 @@ impl HashBuilderValue {
         self.kind = HashBuilderValueKind::Bytes;
     }
 
+    /// Sets the value to the bytes written by the given function, reusing the buffer.
+    #[inline]
+    pub fn set_bytes_with<R>(&mut self, f: impl FnOnce(&mut Vec<u8>) -> R) -> R {
+        self.buf.clear();
+        self.kind = HashBuilderValueKind::Bytes;
+        f(&mut self.buf)
+    }
+
     /// Sets the value from the given bytes.
     #[inline]
     pub fn set_from_ref(&mut self, value: HashBuilderValueRef<'_>) {
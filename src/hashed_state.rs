@@ -0,0 +1,464 @@
+//! Hashed state changes, such as the output of block execution, keyed by hashed addresses and
+//! hashed slots.
+
+use crate::{
+    nibbles::unpack_b256,
+    prefix_set::{PrefixSetMut, TriePrefixSetsMut},
+    root::{self, FlaggedStorageValue},
+    HashMap, TrieAccount,
+};
+use alloc::vec::Vec;
+use alloy_primitives::{map::HashSet, B256, U256};
+
+/// The changed hashed accounts and storages.
+///
+/// The storage roots of the accounts are ignored by root computation and recomputed from the
+/// storage entries instead.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HashedPostState {
+    /// The changed accounts indexed by hashed address. [None] marks a destroyed account.
+    pub accounts: HashMap<B256, Option<TrieAccount>>,
+    /// The changed storages indexed by hashed address.
+    pub storages: HashMap<B256, HashedStorage>,
+}
+
+impl HashedPostState {
+    /// Sets the changed accounts.
+    pub fn with_accounts(
+        mut self,
+        accounts: impl IntoIterator<Item = (B256, Option<TrieAccount>)>,
+    ) -> Self {
+        self.accounts = HashMap::from_iter(accounts);
+        self
+    }
+
+    /// Sets the changed storages.
+    pub fn with_storages(
+        mut self,
+        storages: impl IntoIterator<Item = (B256, HashedStorage)>,
+    ) -> Self {
+        self.storages = HashMap::from_iter(storages);
+        self
+    }
+
+    /// Returns `true` if there are no changes.
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.storages.is_empty()
+    }
+
+    /// Extends the state with the changes of a later state, the latter taking precedence.
+    pub fn extend(&mut self, other: Self) {
+        self.accounts.extend(other.accounts);
+        for (hashed_address, storage) in other.storages {
+            self.storages.entry(hashed_address).or_default().extend(storage);
+        }
+    }
+
+    /// Returns the prefix sets of the changed keys.
+    ///
+    /// The account prefix set contains the accounts with changed storage as well, since their
+    /// storage roots change.
+    pub fn construct_prefix_sets(&self) -> TriePrefixSetsMut {
+        let mut account_prefix_set = PrefixSetMut::with_capacity(self.accounts.len());
+        account_prefix_set.extend_keys(self.accounts.keys().map(unpack_b256));
+
+        let mut storage_prefix_sets = HashMap::default();
+        for (hashed_address, storage) in &self.storages {
+            account_prefix_set.insert(unpack_b256(hashed_address));
+            storage_prefix_sets.insert(*hashed_address, storage.construct_prefix_set());
+        }
+
+        TriePrefixSetsMut { account_prefix_set, storage_prefix_sets }
+    }
+
+    /// Converts the state into its sorted representation.
+    pub fn into_sorted(self) -> HashedPostStateSorted {
+        let mut accounts = Vec::new();
+        let mut destroyed_accounts = HashSet::default();
+        for (hashed_address, account) in self.accounts {
+            match account {
+                Some(account) => accounts.push((hashed_address, account)),
+                None => {
+                    destroyed_accounts.insert(hashed_address);
+                }
+            }
+        }
+        accounts.sort_unstable_by_key(|(hashed_address, _)| *hashed_address);
+
+        let storages = self
+            .storages
+            .into_iter()
+            .map(|(hashed_address, storage)| (hashed_address, storage.into_sorted()))
+            .collect();
+
+        HashedPostStateSorted {
+            accounts: HashedAccountsSorted { accounts, destroyed_accounts },
+            storages,
+        }
+    }
+}
+
+/// The changed hashed storage slots of an account.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HashedStorage {
+    /// Whether the whole storage was destroyed, e.g. by a self-destruct, before the changed slots
+    /// were set. The previous slots of a wiped storage are ignored.
+    pub wiped: bool,
+    /// The changed slot values along with their privacy flag indexed by hashed slot. Zero values
+    /// mark removed slots.
+    pub storage: HashMap<B256, (U256, bool)>,
+}
+
+impl FromIterator<(B256, (U256, bool))> for HashedStorage {
+    fn from_iter<T: IntoIterator<Item = (B256, (U256, bool))>>(iter: T) -> Self {
+        Self { wiped: false, storage: HashMap::from_iter(iter) }
+    }
+}
+
+impl HashedStorage {
+    /// Creates new empty storage changes, wiping the previous storage if `wiped` is set.
+    pub fn new(wiped: bool) -> Self {
+        Self { wiped, storage: HashMap::default() }
+    }
+
+    /// Returns `true` if there are no changes.
+    pub fn is_empty(&self) -> bool {
+        !self.wiped && self.storage.is_empty()
+    }
+
+    /// Extends the storage with the changes of a later storage, the latter taking precedence.
+    ///
+    /// If the later storage is wiped, the changes of this storage are discarded.
+    pub fn extend(&mut self, other: Self) {
+        if other.wiped {
+            self.wiped = true;
+            self.storage.clear();
+        }
+        self.storage.extend(other.storage);
+    }
+
+    /// Returns the prefix set of the changed slots.
+    ///
+    /// The prefix set of a wiped storage contains all keys, since none of the stored trie nodes
+    /// can be reused.
+    pub fn construct_prefix_set(&self) -> PrefixSetMut {
+        if self.wiped {
+            return PrefixSetMut::all();
+        }
+        PrefixSetMut::from_iter(self.storage.keys().map(unpack_b256))
+    }
+
+    /// Converts the storage into its sorted representation.
+    pub fn into_sorted(self) -> HashedStorageSorted {
+        let mut non_zero_valued_slots = Vec::new();
+        let mut zero_valued_slots = HashSet::default();
+        for (hashed_slot, (value, is_private)) in self.storage {
+            if value.is_zero() {
+                zero_valued_slots.insert(hashed_slot);
+            } else {
+                non_zero_valued_slots.push((hashed_slot, (value, is_private)));
+            }
+        }
+        non_zero_valued_slots.sort_unstable_by_key(|(hashed_slot, _)| *hashed_slot);
+
+        HashedStorageSorted { wiped: self.wiped, non_zero_valued_slots, zero_valued_slots }
+    }
+}
+
+/// The sorted representation of [HashedPostState].
+///
+/// The change sets of consecutive blocks can be merged with [HashedPostStateSorted::extend_ref]
+/// without going back to the unsorted representation.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HashedPostStateSorted {
+    /// The sorted changed accounts.
+    pub accounts: HashedAccountsSorted,
+    /// The sorted changed storages indexed by hashed address.
+    pub storages: HashMap<B256, HashedStorageSorted>,
+}
+
+impl HashedPostStateSorted {
+    /// Extends the state with the changes of a later state, the latter taking precedence.
+    pub fn extend_ref(&mut self, other: &Self) {
+        self.accounts.extend_ref(&other.accounts);
+        for (hashed_address, storage) in &other.storages {
+            self.storages.entry(*hashed_address).or_default().extend_ref(storage);
+        }
+    }
+}
+
+/// The sorted changed accounts of [HashedPostStateSorted].
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HashedAccountsSorted {
+    /// The changed accounts sorted by hashed address.
+    pub accounts: Vec<(B256, TrieAccount)>,
+    /// The hashed addresses of the destroyed accounts.
+    pub destroyed_accounts: HashSet<B256>,
+}
+
+impl HashedAccountsSorted {
+    /// Returns the change of the account, [None] if it did not change and `Some(None)` if it was
+    /// destroyed.
+    pub fn get(&self, hashed_address: &B256) -> Option<Option<TrieAccount>> {
+        if let Ok(index) = self.accounts.binary_search_by_key(hashed_address, |(key, _)| *key) {
+            return Some(Some(self.accounts[index].1));
+        }
+        self.destroyed_accounts.contains(hashed_address).then_some(None)
+    }
+
+    /// Extends the accounts with the changes of later accounts, the latter taking precedence.
+    pub fn extend_ref(&mut self, other: &Self) {
+        merge_sorted(&mut self.accounts, &other.accounts, &other.destroyed_accounts);
+        self.destroyed_accounts.retain(|hashed_address| {
+            other.accounts.binary_search_by_key(hashed_address, |(key, _)| *key).is_err()
+        });
+        self.destroyed_accounts.extend(&other.destroyed_accounts);
+    }
+
+    /// Calculates the root hash of the state made of the changed accounts alone, e.g. the genesis
+    /// accounts, see [`root::state_root`].
+    pub fn state_root(&self) -> B256 {
+        root::state_root(self.accounts.iter().copied())
+    }
+}
+
+/// The sorted representation of [HashedStorage].
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HashedStorageSorted {
+    /// Whether the previous storage was destroyed, see [HashedStorage::wiped].
+    pub wiped: bool,
+    /// The slots with non-zero values along with their privacy flag sorted by hashed slot.
+    pub non_zero_valued_slots: Vec<(B256, (U256, bool))>,
+    /// The hashed slots that were removed.
+    pub zero_valued_slots: HashSet<B256>,
+}
+
+impl HashedStorageSorted {
+    /// Returns the change of the slot, [None] if it did not change. A removed slot has a zero
+    /// public value.
+    pub fn get(&self, hashed_slot: &B256) -> Option<StorageTrieEntry> {
+        let entry =
+            match self.non_zero_valued_slots.binary_search_by_key(hashed_slot, |(key, _)| *key) {
+                Ok(index) => {
+                    let (value, is_private) = self.non_zero_valued_slots[index].1;
+                    StorageTrieEntry { key: *hashed_slot, value, is_private }
+                }
+                Err(_) if self.zero_valued_slots.contains(hashed_slot) => {
+                    StorageTrieEntry { key: *hashed_slot, value: U256::ZERO, is_private: false }
+                }
+                Err(_) => return None,
+            };
+        Some(entry)
+    }
+
+    /// Returns the slots with non-zero values sorted by hashed slot.
+    pub fn entries(&self) -> impl Iterator<Item = StorageTrieEntry> + '_ {
+        self.non_zero_valued_slots.iter().map(|(key, (value, is_private))| StorageTrieEntry {
+            key: *key,
+            value: *value,
+            is_private: *is_private,
+        })
+    }
+
+    /// Extends the storage with the changes of a later storage, the latter taking precedence.
+    ///
+    /// If the later storage is wiped, the changes of this storage are discarded.
+    pub fn extend_ref(&mut self, other: &Self) {
+        if other.wiped {
+            self.clone_from(other);
+            return;
+        }
+        merge_sorted(
+            &mut self.non_zero_valued_slots,
+            &other.non_zero_valued_slots,
+            &other.zero_valued_slots,
+        );
+        self.zero_valued_slots.retain(|hashed_slot| {
+            other.non_zero_valued_slots.binary_search_by_key(hashed_slot, |(key, _)| *key).is_err()
+        });
+        self.zero_valued_slots.extend(&other.zero_valued_slots);
+    }
+
+    /// Calculates the root hash of the storage made of the slots with non-zero values alone, e.g.
+    /// of a wiped storage, see [`root::storage_root`].
+    ///
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn storage_root(&self) -> B256 {
+        root::storage_root(self.entries().map(|entry| (entry.key, entry)))
+    }
+}
+
+/// A storage slot along with its value and privacy flag, keyed by hashed slot.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StorageTrieEntry {
+    /// The hashed slot.
+    pub key: B256,
+    /// The value of the slot.
+    pub value: U256,
+    /// Whether the slot is private.
+    pub is_private: bool,
+}
+
+impl FlaggedStorageValue for StorageTrieEntry {
+    fn is_private(&self) -> bool {
+        self.is_private
+    }
+
+    fn value(&self) -> &U256 {
+        &self.value
+    }
+}
+
+/// Merges the later sorted entries into the sorted entries, the later ones taking precedence, and
+/// drops the removed keys.
+fn merge_sorted<V: Clone>(
+    entries: &mut Vec<(B256, V)>,
+    later: &[(B256, V)],
+    removed: &HashSet<B256>,
+) {
+    let earlier = core::mem::take(entries);
+    entries.reserve(earlier.len() + later.len());
+    let mut later = later.iter().peekable();
+    for (key, value) in earlier {
+        while let Some(entry) = later.next_if(|(later_key, _)| *later_key < key) {
+            entries.push(entry.clone());
+        }
+        if removed.contains(&key) || later.peek().is_some_and(|(later_key, _)| *later_key == key) {
+            continue;
+        }
+        entries.push((key, value));
+    }
+    entries.extend(later.cloned());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Nibbles;
+
+    #[test]
+    fn extend_and_sort() {
+        let (first, second) = (B256::with_last_byte(1), B256::with_last_byte(2));
+        let account = TrieAccount { nonce: 1, ..Default::default() };
+
+        let mut state = HashedPostState::default()
+            .with_accounts([(second, Some(account)), (first, Some(account))])
+            .with_storages([(
+                first,
+                HashedStorage::from_iter([
+                    (second, (U256::from(1), true)),
+                    (first, (U256::from(1), false)),
+                ]),
+            )]);
+        state.extend(
+            HashedPostState::default()
+                .with_accounts([(second, None)])
+                .with_storages([(first, HashedStorage::from_iter([(first, (U256::ZERO, false))]))]),
+        );
+
+        let sorted = state.into_sorted();
+        assert_eq!(sorted.accounts.accounts, vec![(first, account)]);
+        assert_eq!(sorted.accounts.destroyed_accounts, HashSet::from_iter([second]));
+        let storage = &sorted.storages[&first];
+        assert_eq!(storage.non_zero_valued_slots, vec![(second, (U256::from(1), true))]);
+        assert_eq!(storage.zero_valued_slots, HashSet::from_iter([first]));
+        assert!(!storage.wiped);
+    }
+
+    #[test]
+    fn extend_sorted_like_unsorted() {
+        let key = B256::with_last_byte;
+        let account = |nonce| Some(TrieAccount { nonce, ..Default::default() });
+        let blocks = [
+            HashedPostState::default()
+                .with_accounts([(key(1), account(1)), (key(2), account(1)), (key(3), None)])
+                .with_storages([(
+                    key(1),
+                    HashedStorage::from_iter([
+                        (key(1), (U256::from(1), false)),
+                        (key(2), (U256::from(2), true)),
+                        (key(3), (U256::ZERO, false)),
+                    ]),
+                )]),
+            HashedPostState::default()
+                .with_accounts([(key(2), None), (key(3), account(2)), (key(4), account(2))])
+                .with_storages([
+                    (
+                        key(1),
+                        HashedStorage::from_iter([
+                            (key(2), (U256::ZERO, false)),
+                            (key(3), (U256::from(3), true)),
+                            (key(4), (U256::from(4), false)),
+                        ]),
+                    ),
+                    (key(2), HashedStorage::new(true)),
+                ]),
+            HashedPostState::default().with_storages([(key(1), {
+                let mut storage = HashedStorage::new(true);
+                storage.storage.insert(key(5), (U256::from(5), true));
+                storage
+            })]),
+        ];
+
+        let mut unsorted = HashedPostState::default();
+        let mut sorted = HashedPostStateSorted::default();
+        for (i, block) in blocks.into_iter().enumerate() {
+            sorted.extend_ref(&block.clone().into_sorted());
+            unsorted.extend(block);
+            assert_eq!(sorted, unsorted.clone().into_sorted(), "{i}");
+        }
+
+        assert_eq!(sorted.accounts.get(&key(1)), Some(account(1)));
+        assert_eq!(sorted.accounts.get(&key(2)), Some(None));
+        assert_eq!(sorted.accounts.get(&key(5)), None);
+        let storage = &sorted.storages[&key(1)];
+        assert_eq!(
+            storage.get(&key(5)),
+            Some(StorageTrieEntry { key: key(5), value: U256::from(5), is_private: true })
+        );
+        assert_eq!(storage.get(&key(1)), None);
+        assert_eq!(storage.storage_root(), root::storage_root([(key(5), (U256::from(5), true))]));
+    }
+
+    #[test]
+    fn extend_wiped_storage() {
+        let (first, second) = (B256::with_last_byte(1), B256::with_last_byte(2));
+        let mut storage = HashedStorage::from_iter([(first, (U256::from(1), false))]);
+        assert!(!HashedStorage::new(true).is_empty());
+
+        let mut wiped = HashedStorage::new(true);
+        wiped.storage.insert(second, (U256::from(2), true));
+        storage.extend(wiped.clone());
+        assert_eq!(storage, wiped);
+
+        // Later changes are applied on top of the wiped storage.
+        storage.extend(HashedStorage::from_iter([(first, (U256::from(3), false))]));
+        assert!(storage.wiped);
+        assert_eq!(storage.storage.len(), 2);
+
+        assert!(storage.construct_prefix_set().freeze().all());
+        assert!(storage.into_sorted().wiped);
+    }
+
+    #[test]
+    fn prefix_sets() {
+        let (first, second) = (B256::with_last_byte(1), B256::with_last_byte(2));
+        let state = HashedPostState::default()
+            .with_accounts([(first, None)])
+            .with_storages([(second, HashedStorage::from_iter([(first, (U256::from(1), true))]))]);
+
+        let mut prefix_sets = state.construct_prefix_sets().freeze();
+        assert_eq!(prefix_sets.account_prefix_set.len(), 2);
+        assert!(prefix_sets.account_prefix_set.contains(&Nibbles::unpack(first)));
+        assert!(prefix_sets.account_prefix_set.contains(&Nibbles::unpack(second)));
+        let storage_prefix_set = prefix_sets.storage_prefix_sets.get_mut(&second).unwrap();
+        assert!(storage_prefix_set.contains(&Nibbles::unpack(first)));
+        assert!(!prefix_sets.storage_prefix_sets.contains_key(&first));
+    }
+}
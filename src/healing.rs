@@ -0,0 +1,107 @@
+//! Trie healing, finding the nodes missing from a partially synced trie.
+
+use crate::{
+    nodes::{RlpNode, TrieNode},
+    HashMap, Nibbles, EMPTY_ROOT_HASH,
+};
+use alloc::vec::Vec;
+use alloy_primitives::{Bytes, B256};
+use alloy_rlp::Decodable;
+
+/// Walks the trie with the given root down from the known nodes, keyed by their hashes like a
+/// [TrieWitness](crate::TrieWitness), and returns the paths and hashes of the nodes that are
+/// referenced but not known, sorted by path.
+///
+/// Fetching the returned nodes and walking the trie again until no nodes are missing heals the
+/// trie, e.g. after snap sync. Storage tries are referenced by the account leaves and must be
+/// walked separately from their storage roots.
+pub fn missing_nodes(
+    root: B256,
+    nodes: &HashMap<B256, Bytes>,
+) -> Result<Vec<(Nibbles, B256)>, alloy_rlp::Error> {
+    let mut missing = Vec::new();
+    if root == EMPTY_ROOT_HASH {
+        return Ok(missing);
+    }
+
+    let mut stack = Vec::from([(Nibbles::default(), RlpNode::word_rlp(&root))]);
+    while let Some((path, node_ref)) = stack.pop() {
+        let mut encoded = match node_ref.as_hash() {
+            Some(hash) => match nodes.get(&hash) {
+                Some(node) => &node[..],
+                None => {
+                    missing.push((path, hash));
+                    continue;
+                }
+            },
+            // Nodes shorter than a hash are encoded in place of their reference.
+            None => &node_ref[..],
+        };
+
+        match TrieNode::decode(&mut encoded)? {
+            TrieNode::Branch(branch) => {
+                // Children are pushed in reverse to pop them in path order.
+                let branch = branch.as_ref();
+                let children = branch.children().collect::<Vec<_>>();
+                for (nibble, child) in children.into_iter().rev() {
+                    if let Some(child) = child {
+                        let mut child_path = path.clone();
+                        child_path.push(nibble);
+                        stack.push((child_path, child.clone()));
+                    }
+                }
+            }
+            TrieNode::Extension(extension) => {
+                stack.push((path.join(&extension.key), extension.child));
+            }
+            TrieNode::Leaf(_) | TrieNode::EmptyRoot => {}
+        }
+    }
+    Ok(missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{proof::ProofRetainer, HashBuilder};
+    use alloc::collections::BTreeMap;
+    use alloy_primitives::keccak256;
+
+    #[test]
+    fn find_missing_nodes() {
+        let leaves = (0..200u64)
+            .map(|i| (Nibbles::unpack(keccak256(i.to_be_bytes())), alloy_rlp::encode(i)))
+            .collect::<BTreeMap<_, _>>();
+        let mut hb = HashBuilder::default()
+            .with_proof_retainer(ProofRetainer::from_iter(leaves.keys().cloned()));
+        for (key, value) in &leaves {
+            hb.add_leaf(key.clone(), value, false);
+        }
+        let root = hb.root();
+        let proof_nodes = hb.take_proof_nodes();
+
+        let mut nodes = proof_nodes
+            .iter_sorted()
+            .map(|(_, node)| (keccak256(node), node.clone()))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(missing_nodes(root, &nodes), Ok(Vec::new()));
+        assert_eq!(missing_nodes(EMPTY_ROOT_HASH, &HashMap::default()), Ok(Vec::new()));
+        assert_eq!(
+            missing_nodes(root, &HashMap::default()),
+            Ok(Vec::from([(Nibbles::default(), root)]))
+        );
+
+        // Remove a few nodes below the root.
+        let mut removed = proof_nodes
+            .iter_sorted()
+            .filter(|(path, _)| path.len() == 2 && path[1] == 0)
+            .map(|(path, node)| (path.clone(), keccak256(node)))
+            .collect::<Vec<_>>();
+        assert!(!removed.is_empty());
+        for (_, hash) in &removed {
+            nodes.remove(hash);
+        }
+        removed.sort();
+        assert_eq!(missing_nodes(root, &nodes), Ok(removed));
+    }
+}
@@ -0,0 +1,150 @@
+//! Hashing of the addresses and storage slots used as trie keys.
+
+use crate::HashMap;
+use alloy_primitives::{keccak256, Address, B256};
+
+/// Hashes addresses and storage slots into the keys of the state and storage tries, see
+/// [`state_root_unhashed_with_key_hasher`](crate::root::state_root_unhashed_with_key_hasher).
+pub trait KeyHasher {
+    /// Returns the hashed address.
+    fn hash_address(&mut self, address: &Address) -> B256;
+
+    /// Returns the hashed storage slot.
+    fn hash_slot(&mut self, slot: &B256) -> B256;
+}
+
+/// [KeyHasher] hashing every key with keccak-256.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeccakKeyHasher;
+
+impl KeyHasher for KeccakKeyHasher {
+    #[inline]
+    fn hash_address(&mut self, address: &Address) -> B256 {
+        keccak256(address)
+    }
+
+    #[inline]
+    fn hash_slot(&mut self, slot: &B256) -> B256 {
+        keccak256(slot)
+    }
+}
+
+/// [KeyHasher] memoizing the hashed addresses and storage slots, since the same accounts and
+/// slots recur from block to block.
+///
+/// Once the cache holds `capacity` keys of a kind, the keys of that kind are evicted all at once.
+#[derive(Clone, Debug)]
+pub struct HashedKeyCache {
+    addresses: HashMap<Address, B256>,
+    slots: HashMap<B256, B256>,
+    capacity: usize,
+    hits: usize,
+    misses: usize,
+}
+
+impl HashedKeyCache {
+    /// Creates a cache holding up to `capacity` addresses and `capacity` slots.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            addresses: HashMap::default(),
+            slots: HashMap::default(),
+            capacity,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns the number of cached addresses and slots.
+    pub fn len(&self) -> usize {
+        self.addresses.len() + self.slots.len()
+    }
+
+    /// Returns `true` if no key is cached.
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty() && self.slots.is_empty()
+    }
+
+    /// Returns the number of keys found in the cache.
+    pub const fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Returns the number of keys hashed because they were not in the cache.
+    pub const fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Evicts all the cached keys.
+    pub fn clear(&mut self) {
+        self.addresses.clear();
+        self.slots.clear();
+    }
+
+    fn hash_cached<K: Copy + Eq + core::hash::Hash + AsRef<[u8]>>(
+        cache: &mut HashMap<K, B256>,
+        capacity: usize,
+        (hits, misses): (&mut usize, &mut usize),
+        key: &K,
+    ) -> B256 {
+        if let Some(hashed) = cache.get(key) {
+            *hits += 1;
+            return *hashed;
+        }
+        *misses += 1;
+        if cache.len() >= capacity {
+            cache.clear();
+        }
+        let hashed = keccak256(key);
+        if capacity > 0 {
+            cache.insert(*key, hashed);
+        }
+        hashed
+    }
+}
+
+impl KeyHasher for HashedKeyCache {
+    fn hash_address(&mut self, address: &Address) -> B256 {
+        Self::hash_cached(
+            &mut self.addresses,
+            self.capacity,
+            (&mut self.hits, &mut self.misses),
+            address,
+        )
+    }
+
+    fn hash_slot(&mut self, slot: &B256) -> B256 {
+        Self::hash_cached(&mut self.slots, self.capacity, (&mut self.hits, &mut self.misses), slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashed_key_cache() {
+        let mut cache = HashedKeyCache::new(2);
+        let (first, second, third) =
+            (Address::with_last_byte(1), Address::with_last_byte(2), Address::with_last_byte(3));
+
+        assert_eq!(cache.hash_address(&first), keccak256(first));
+        assert_eq!(cache.hash_address(&first), keccak256(first));
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+        assert_eq!(cache.hash_slot(&B256::ZERO), keccak256(B256::ZERO));
+        assert_eq!(cache.len(), 2);
+
+        // The addresses are evicted once the capacity is reached, the slots are kept.
+        cache.hash_address(&second);
+        cache.hash_address(&third);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.hash_slot(&B256::ZERO), keccak256(B256::ZERO));
+        assert_eq!((cache.hits(), cache.misses()), (2, 4));
+
+        cache.clear();
+        assert!(cache.is_empty());
+
+        let mut disabled = HashedKeyCache::new(0);
+        assert_eq!(disabled.hash_address(&first), KeccakKeyHasher.hash_address(&first));
+        assert!(disabled.is_empty());
+    }
+}
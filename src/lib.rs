@@ -23,17 +23,96 @@ pub mod nodes;
 pub use nodes::BranchNodeCompact;
 
 pub mod hash_builder;
-pub use hash_builder::HashBuilder;
+pub use hash_builder::{HashBuilder, StateRootError};
 
 pub mod proof;
 
+pub mod cursor;
+
+pub mod sparse;
+pub use sparse::{DecodedTrie, SparseTrie};
+
+pub mod updates;
+pub use updates::{StateTrieUpdates, TrieUpdates};
+
+pub mod prefix_set;
+pub use prefix_set::{PrefixSet, PrefixSetMut};
+
+pub mod nibbles_map;
+pub use nibbles_map::NibblesMap;
+
+pub mod walker;
+pub use walker::{CursorSubNode, TrieWalker};
+
+pub mod node_iter;
+pub use node_iter::{TrieElement, TrieNodeIter};
+
+pub mod healing;
+pub use healing::missing_nodes;
+
+pub mod pruning;
+pub use pruning::removable_nodes;
+
+#[cfg(feature = "std")]
+pub mod snapshot;
+
+#[cfg(feature = "debug")]
+pub mod debug;
+
+#[cfg(feature = "binary-trie")]
+pub mod binary;
+
+#[cfg(feature = "ethereum")]
+pub mod state_root;
+#[cfg(feature = "ethereum")]
+pub use state_root::{
+    reverted_storage_root, state_root_with_overlay, StateRoot, StateRootCheckpoint,
+    StateRootProgress, StorageRoot,
+};
+
+#[cfg(all(feature = "async", feature = "ethereum"))]
+pub mod async_state_root;
+#[cfg(all(feature = "async", feature = "ethereum"))]
+pub use async_state_root::{async_account_proof, AsyncStateRoot, AsyncStorageRoot};
+
+#[cfg(feature = "ethereum")]
+pub mod commitment;
+#[cfg(feature = "ethereum")]
+pub use commitment::{MerklePatriciaTrie, StateCommitment};
+
+#[cfg(feature = "ethereum")]
+pub mod key_hasher;
+
+#[cfg(feature = "ethereum")]
+pub mod hashed_state;
+#[cfg(feature = "ethereum")]
+pub use hashed_state::{HashedPostState, HashedStorage};
+
+#[cfg(feature = "ethereum")]
+pub mod trie_input;
+#[cfg(feature = "ethereum")]
+pub use trie_input::TrieInput;
+
+#[cfg(feature = "ethereum")]
+pub mod witness;
+#[cfg(feature = "ethereum")]
+pub use witness::TrieWitness;
+
 #[cfg(feature = "ethereum")]
 mod account;
+#[cfg(feature = "account-extensions")]
+pub use account::AccountExtension;
 #[cfg(feature = "ethereum")]
-pub use account::TrieAccount;
+pub use account::{AccountInfo, GenesisAccount, TrieAccount};
 
 mod mask;
-pub use mask::TrieMask;
+pub use mask::{PrivacyMask, TrieMask, TrieMaskIter};
+
+#[cfg(feature = "arbitrary")]
+pub mod strategies;
+
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 
 #[allow(missing_docs)]
 pub mod root;
@@ -42,7 +121,17 @@ pub mod root;
 pub use alloy_primitives::map::HashMap;
 
 #[doc(no_inline)]
-pub use nybbles::{self, Nibbles};
+pub use nybbles;
+
+/// The nibble path type used for trie keys.
+///
+/// Provides the path arithmetic needed to navigate the trie, such as [`Nibbles::increment`],
+/// [`Nibbles::common_prefix_length`], [`Nibbles::starts_with`], [`Nibbles::join`] and
+/// [`Nibbles::last`].
+#[doc(no_inline)]
+pub use nybbles::Nibbles;
+
+pub mod nibbles;
 
 use alloy_primitives::{b256, B256};
 
@@ -54,6 +143,23 @@ pub const EMPTY_ROOT_HASH: B256 =
 pub const KECCAK_EMPTY: B256 =
     b256!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470");
 
+/// RLP encoding of the empty string, which encodes the empty root node and the missing children
+/// of branch nodes.
+pub const EMPTY_STRING_CODE: u8 = alloy_rlp::EMPTY_STRING_CODE;
+
+/// Returns `true` if the root is the root of an empty trie, such as the storage root of an account
+/// without storage.
+#[inline]
+pub fn is_empty_root(root: B256) -> bool {
+    root == EMPTY_ROOT_HASH
+}
+
+/// Returns `true` if the code hash is the hash of empty code, i.e. the account has no code.
+#[inline]
+pub fn is_empty_code_hash(code_hash: B256) -> bool {
+    code_hash == KECCAK_EMPTY
+}
+
 #[cfg(test)]
 pub(crate) fn triehash_trie_root<I, K, V>(iter: I) -> B256
 where
@@ -1,4 +1,4 @@
-use core::fmt;
+use core::{fmt, iter::FusedIterator};
 use derive_more::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Deref, From, Not};
 
 /// A struct representing a mask of 16 bits, used for Ethereum trie operations.
@@ -76,6 +76,30 @@ impl TrieMask {
         self.0.count_ones() as u8
     }
 
+    /// Returns the mask of the bits set in either mask.
+    #[inline]
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Returns the mask of the bits set in both masks.
+    #[inline]
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// Returns the mask of the bits set in this mask but not in `other`.
+    #[inline]
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// Returns an iterator over the indexes of the bits set in the mask, in ascending order.
+    #[inline]
+    pub const fn iter(self) -> TrieMaskIter {
+        TrieMaskIter(self.0)
+    }
+
     /// Returns the index of the first bit set in the mask, or `None` if the mask is empty.
     #[inline]
     pub const fn first_set_bit_index(self) -> Option<u8> {
@@ -98,3 +122,146 @@ impl TrieMask {
         self.0 &= !(1u16 << index);
     }
 }
+
+impl IntoIterator for TrieMask {
+    type Item = u8;
+    type IntoIter = TrieMaskIter;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the indexes of the bits set in a [TrieMask], see [TrieMask::iter].
+#[derive(Clone, Debug)]
+pub struct TrieMaskIter(u16);
+
+impl Iterator for TrieMaskIter {
+    type Item = u8;
+
+    #[inline]
+    fn next(&mut self) -> Option<u8> {
+        if self.0 == 0 {
+            return None;
+        }
+        let index = self.0.trailing_zeros() as u8;
+        self.0 &= self.0 - 1;
+        Some(index)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for TrieMaskIter {
+    #[inline]
+    fn next_back(&mut self) -> Option<u8> {
+        if self.0 == 0 {
+            return None;
+        }
+        let index = 15 - self.0.leading_zeros() as u8;
+        self.0 &= !(1u16 << index);
+        Some(index)
+    }
+}
+
+impl ExactSizeIterator for TrieMaskIter {}
+
+impl FusedIterator for TrieMaskIter {}
+
+/// The mask of the children of a branch node whose subtries contain private leaves, see
+/// [`BranchNodeCompact::private_mask`](crate::BranchNodeCompact::private_mask).
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deref, From)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(derive_arbitrary::Arbitrary, proptest_derive::Arbitrary))]
+pub struct PrivacyMask(TrieMask);
+
+impl fmt::Debug for PrivacyMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PrivacyMask({:016b})", self.0.get())
+    }
+}
+
+impl From<PrivacyMask> for TrieMask {
+    #[inline]
+    fn from(mask: PrivacyMask) -> Self {
+        mask.0
+    }
+}
+
+impl PrivacyMask {
+    /// Creates a new `PrivacyMask` from the given mask.
+    #[inline]
+    pub const fn new(mask: TrieMask) -> Self {
+        Self(mask)
+    }
+
+    /// Returns the underlying [TrieMask].
+    #[inline]
+    pub const fn mask(self) -> TrieMask {
+        self.0
+    }
+
+    /// Returns `true` if the subtrie of the given child contains private leaves.
+    #[inline]
+    pub const fn is_private(self, nibble: u8) -> bool {
+        self.0.is_bit_set(nibble)
+    }
+
+    /// Returns `true` if any child subtrie contains private leaves.
+    #[inline]
+    pub const fn has_private(self) -> bool {
+        !self.0.is_empty()
+    }
+
+    /// Marks the subtrie of the given child as containing private leaves.
+    #[inline]
+    pub fn set_private(&mut self, nibble: u8) {
+        self.0.set_bit(nibble)
+    }
+
+    /// Returns the children of the given state mask whose subtries only contain public leaves.
+    #[inline]
+    pub const fn public_children(self, state_mask: TrieMask) -> TrieMask {
+        state_mask.difference(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn set_operations() {
+        let a = TrieMask::new(0b1010_0000_0000_0110);
+        let b = TrieMask::new(0b0010_0000_0000_0011);
+        assert_eq!(a.union(b), a | b);
+        assert_eq!(a.intersection(b), a & b);
+        assert_eq!(a.difference(b), TrieMask::new(0b1000_0000_0000_0100));
+        assert_eq!(a.count_bits(), 4);
+        assert_eq!(a.first_set_bit_index(), Some(1));
+
+        assert_eq!(a.iter().collect::<Vec<_>>(), [1, 2, 13, 15]);
+        assert_eq!(a.iter().rev().collect::<Vec<_>>(), [15, 13, 2, 1]);
+        assert_eq!(a.iter().len(), 4);
+        assert_eq!(TrieMask::default().into_iter().next(), None);
+        assert_eq!(TrieMask::new(u16::MAX).iter().collect::<Vec<_>>(), Vec::from_iter(0..16));
+    }
+
+    #[test]
+    fn privacy_mask() {
+        let state_mask = TrieMask::new(0b0111);
+        let mut privacy = PrivacyMask::default();
+        assert!(!privacy.has_private());
+        privacy.set_private(1);
+        assert!(privacy.is_private(1));
+        assert!(!privacy.is_private(0));
+        assert_eq!(privacy.public_children(state_mask), TrieMask::new(0b0101));
+        assert_eq!(TrieMask::from(privacy), TrieMask::from_nibble(1));
+    }
+}
@@ -0,0 +1,87 @@
+//! Fast conversions between 32-byte keys and their nibbles.
+//!
+//! The hashed keys of the state and storage tries are unpacked once per leaf, so the conversions
+//! process 8 bytes at a time in 128-bit registers instead of one byte at a time as
+//! [`Nibbles::unpack`] and [`Nibbles::pack`] do.
+
+use crate::Nibbles;
+use alloy_primitives::B256;
+
+/// Keeps the low byte of every 16-bit lane.
+const LOW_BYTES: u128 = 0x00ff_00ff_00ff_00ff_00ff_00ff_00ff_00ff;
+/// Keeps the low nibble of every byte.
+const LOW_NIBBLES: u128 = 0x0f0f_0f0f_0f0f_0f0f_0f0f_0f0f_0f0f_0f0f;
+
+/// Unpacks the key into its 64 nibbles, like [`Nibbles::unpack`].
+#[inline]
+pub fn unpack_b256(key: &B256) -> Nibbles {
+    let mut nibbles = [0u8; 64];
+    for (chunk, out) in key.chunks_exact(8).zip(nibbles.chunks_exact_mut(16)) {
+        let bytes = u64::from_be_bytes(chunk.try_into().unwrap());
+        out.copy_from_slice(&unpack_u64(bytes).to_be_bytes());
+    }
+    Nibbles::from_nibbles_unchecked(nibbles)
+}
+
+/// Packs up to 64 nibbles into a key, padding it with zeros, like [`Nibbles::pack`].
+///
+/// # Panics
+///
+/// If there are more than 64 nibbles.
+#[inline]
+pub fn pack_b256(nibbles: &[u8]) -> B256 {
+    assert!(nibbles.len() <= 64, "too many nibbles for a 32-byte key: {}", nibbles.len());
+    let mut padded = [0u8; 64];
+    padded[..nibbles.len()].copy_from_slice(nibbles);
+
+    let mut key = B256::ZERO;
+    for (chunk, out) in padded.chunks_exact(16).zip(key.chunks_exact_mut(8)) {
+        let lanes = u128::from_be_bytes(chunk.try_into().unwrap());
+        out.copy_from_slice(&pack_u128(lanes).to_be_bytes());
+    }
+    key
+}
+
+/// Spreads the 8 bytes into 16-bit lanes and splits every byte into its high and low nibble.
+#[inline]
+const fn unpack_u64(bytes: u64) -> u128 {
+    let mut v = bytes as u128;
+    v = (v | (v << 32)) & 0x0000_0000_ffff_ffff_0000_0000_ffff_ffff;
+    v = (v | (v << 16)) & 0x0000_ffff_0000_ffff_0000_ffff_0000_ffff;
+    v = (v | (v << 8)) & LOW_BYTES;
+    ((v << 4) | v) & LOW_NIBBLES
+}
+
+/// Merges the nibble pairs of the 16-bit lanes into bytes and gathers them, the inverse of
+/// [unpack_u64].
+#[inline]
+const fn pack_u128(nibbles: u128) -> u64 {
+    let mut v = nibbles & LOW_NIBBLES;
+    v = (v | (v >> 4)) & LOW_BYTES;
+    v = (v | (v >> 8)) & 0x0000_ffff_0000_ffff_0000_ffff_0000_ffff;
+    v = (v | (v >> 16)) & 0x0000_0000_ffff_ffff_0000_0000_ffff_ffff;
+    (v | (v >> 32)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::keccak256;
+
+    #[test]
+    fn unpack_and_pack() {
+        for i in 0..100u64 {
+            let key = keccak256(i.to_be_bytes());
+            let nibbles = unpack_b256(&key);
+            assert_eq!(nibbles, Nibbles::unpack(key));
+            assert_eq!(pack_b256(&nibbles), key);
+
+            for len in [0, 1, 15, 16, 17, 63] {
+                let mut expected = nibbles.slice(..len).pack();
+                expected.resize(32, 0);
+                assert_eq!(pack_b256(&nibbles[..len]), B256::from_slice(&expected));
+            }
+        }
+        assert_eq!(unpack_b256(&B256::repeat_byte(0xff)), Nibbles::unpack(B256::repeat_byte(0xff)));
+    }
+}
@@ -0,0 +1,300 @@
+//! Radix map keyed by nibble paths.
+//!
+//! Trie nodes, prefix sets and updates are all keyed by nibble paths that share long prefixes, so
+//! [`NibblesMap`] stores the shared prefixes once in a compressed radix tree and answers prefix
+//! queries by descending to the subtree of the prefix instead of scanning the keys.
+
+use crate::Nibbles;
+use alloc::{vec, vec::Vec};
+use core::{fmt, mem};
+
+/// Map keyed by nibble paths, iterated in lexicographic order of the keys.
+#[derive(Clone)]
+pub struct NibblesMap<V> {
+    root: Node<V>,
+    len: usize,
+}
+
+/// Node of the radix tree, holding the edge from its parent and its children sorted by the first
+/// nibble of their edges.
+#[derive(Clone, Debug)]
+struct Node<V> {
+    path: Nibbles,
+    value: Option<V>,
+    children: Vec<Self>,
+}
+
+impl<V> Node<V> {
+    const fn empty(path: Nibbles) -> Self {
+        Self { path, value: None, children: Vec::new() }
+    }
+
+    fn position(&self, nibble: u8) -> Result<usize, usize> {
+        self.children.binary_search_by_key(&nibble, |child| child.path[0])
+    }
+
+    fn child(&self, nibble: u8) -> Option<&Self> {
+        self.position(nibble).ok().map(|i| &self.children[i])
+    }
+
+    /// Splits the edge of the node after `at` nibbles, moving its value and children to a new
+    /// child holding the rest of the edge.
+    fn split(&mut self, at: usize) {
+        let rest = Nibbles::from_nibbles_unchecked(&self.path[at..]);
+        self.path.truncate(at);
+        let child =
+            Self { path: rest, value: self.value.take(), children: mem::take(&mut self.children) };
+        self.children = vec![child];
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Option<V> {
+        if key.is_empty() {
+            return self.value.take();
+        }
+        let i = self.position(key[0]).ok()?;
+        let child = &mut self.children[i];
+        let rest = key.strip_prefix(&child.path[..])?;
+        let removed = child.remove(rest)?;
+        if child.value.is_none() {
+            match child.children.len() {
+                0 => {
+                    self.children.remove(i);
+                }
+                1 => {
+                    let grandchild = child.children.pop().unwrap();
+                    child.path = child.path.join(&grandchild.path);
+                    child.value = grandchild.value;
+                    child.children = grandchild.children;
+                }
+                _ => {}
+            }
+        }
+        Some(removed)
+    }
+}
+
+impl<V> Default for NibblesMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> NibblesMap<V> {
+    /// Creates an empty map.
+    pub const fn new() -> Self {
+        Self { root: Node::empty(Nibbles::new()), len: 0 }
+    }
+
+    /// Returns the number of entries in the map.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the map has no entries.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all entries from the map.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Inserts the value at the key, returning the previous value at the key.
+    pub fn insert(&mut self, key: Nibbles, value: V) -> Option<V> {
+        let mut node = &mut self.root;
+        let mut key = &key[..];
+        loop {
+            if key.is_empty() {
+                let previous = node.value.replace(value);
+                if previous.is_none() {
+                    self.len += 1;
+                }
+                return previous;
+            }
+            match node.position(key[0]) {
+                Ok(i) => {
+                    let child = &mut node.children[i];
+                    let common = child.path.common_prefix_length(key);
+                    if common < child.path.len() {
+                        child.split(common);
+                    }
+                    key = &key[common..];
+                    node = child;
+                }
+                Err(i) => {
+                    let mut child = Node::empty(Nibbles::from_nibbles_unchecked(key));
+                    child.value = Some(value);
+                    node.children.insert(i, child);
+                    self.len += 1;
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Returns the value at the key.
+    pub fn get(&self, key: &Nibbles) -> Option<&V> {
+        let mut node = &self.root;
+        let mut key = &key[..];
+        while let Some(&nibble) = key.first() {
+            let child = node.child(nibble)?;
+            key = key.strip_prefix(&child.path[..])?;
+            node = child;
+        }
+        node.value.as_ref()
+    }
+
+    /// Returns a mutable reference to the value at the key.
+    pub fn get_mut(&mut self, key: &Nibbles) -> Option<&mut V> {
+        let mut node = &mut self.root;
+        let mut key = &key[..];
+        while let Some(&nibble) = key.first() {
+            let i = node.position(nibble).ok()?;
+            let child = &mut node.children[i];
+            key = key.strip_prefix(&child.path[..])?;
+            node = child;
+        }
+        node.value.as_mut()
+    }
+
+    /// Returns `true` if the map has a value at the key.
+    pub fn contains_key(&self, key: &Nibbles) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Removes the value at the key, returning it.
+    pub fn remove(&mut self, key: &Nibbles) -> Option<V> {
+        let removed = self.root.remove(key)?;
+        self.len -= 1;
+        Some(removed)
+    }
+
+    /// Returns an iterator over the entries of the map in lexicographic order of the keys.
+    pub fn iter(&self) -> Iter<'_, V> {
+        Iter { stack: vec![(Nibbles::new(), &self.root)] }
+    }
+
+    /// Returns an iterator over the entries whose keys start with the prefix, in lexicographic
+    /// order of the keys.
+    pub fn iter_prefix(&self, prefix: &Nibbles) -> Iter<'_, V> {
+        let mut node = &self.root;
+        let mut path = Nibbles::new();
+        let mut key = &prefix[..];
+        while let Some(&nibble) = key.first() {
+            let Some(child) = node.child(nibble) else { return Iter { stack: Vec::new() } };
+            let common = child.path.common_prefix_length(key);
+            if common < key.len() && common < child.path.len() {
+                return Iter { stack: Vec::new() };
+            }
+            key = &key[common..];
+            path.extend_from_slice(&child.path);
+            node = child;
+        }
+        Iter { stack: vec![(path, node)] }
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for NibblesMap<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<V> FromIterator<(Nibbles, V)> for NibblesMap<V> {
+    fn from_iter<I: IntoIterator<Item = (Nibbles, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<V> Extend<(Nibbles, V)> for NibblesMap<V> {
+    fn extend<I: IntoIterator<Item = (Nibbles, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<'a, V> IntoIterator for &'a NibblesMap<V> {
+    type Item = (Nibbles, &'a V);
+    type IntoIter = Iter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the entries of a [`NibblesMap`] in lexicographic order of the keys.
+#[derive(Debug)]
+pub struct Iter<'a, V> {
+    stack: Vec<(Nibbles, &'a Node<V>)>,
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (Nibbles, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((path, node)) = self.stack.pop() {
+            self.stack
+                .extend(node.children.iter().rev().map(|child| (path.join(&child.path), child)));
+            if let Some(value) = &node.value {
+                return Some((path, value));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::BTreeMap;
+
+    #[test]
+    fn matches_btree_map() {
+        let keys = (0..512u32)
+            .map(|i| {
+                let len = (i * 7 % 9) as usize;
+                Nibbles::from_nibbles_unchecked(
+                    (0..len).map(|j| ((i >> (j % 4)) as u8 ^ j as u8) & 0x3).collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut map = NibblesMap::new();
+        let mut expected = BTreeMap::new();
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(map.insert(key.clone(), i), expected.insert(key.clone(), i));
+            if i % 3 == 0 {
+                let removed = &keys[i / 2];
+                assert_eq!(map.remove(removed), expected.remove(removed));
+            }
+        }
+        let updated = expected.keys().nth(expected.len() / 2).unwrap().clone();
+        *map.get_mut(&updated).unwrap() += 1000;
+        *expected.get_mut(&updated).unwrap() += 1000;
+
+        assert_eq!(map.len(), expected.len());
+        assert!(map.iter().map(|(key, value)| (key, *value)).eq(expected.clone()));
+        for key in &keys {
+            assert_eq!(map.get(key), expected.get(key));
+            for len in 0..=key.len() {
+                let prefix = key.slice(..len);
+                let in_range = expected
+                    .range(prefix.clone()..)
+                    .take_while(|(key, _)| key.has_prefix(&prefix))
+                    .map(|(key, value)| (key.clone(), *value));
+                assert!(map.iter_prefix(&prefix).map(|(key, value)| (key, *value)).eq(in_range));
+            }
+        }
+        assert_eq!(map.iter_prefix(&Nibbles::from_nibbles_unchecked([0xf])).count(), 0);
+
+        for key in &keys {
+            assert_eq!(map.remove(key), expected.remove(key));
+        }
+        assert!(map.is_empty());
+        assert!(map.root.children.is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+//! Iterator merging the walked branch nodes with the hashed entries they do not cover.
+
+use crate::{
+    cursor::{DatabaseError, HashedCursor, TrieCursor},
+    nibbles::unpack_b256,
+    walker::TrieWalker,
+    Nibbles,
+};
+use alloy_primitives::B256;
+
+/// An element yielded by the [TrieNodeIter].
+#[derive(Debug)]
+pub enum TrieElement<V> {
+    /// A skipped subtrie represented by its stored hash.
+    Branch {
+        /// The path of the subtrie.
+        key: Nibbles,
+        /// The hash of the subtrie.
+        hash: B256,
+        /// Whether the children of the subtrie are stored in the trie.
+        children_are_in_trie: bool,
+        /// Whether the subtrie contains private leaves.
+        is_private: bool,
+    },
+    /// A hashed entry that has to be hashed into the trie.
+    Leaf(B256, V),
+}
+
+/// Iterates over the elements of a trie in key order, yielding the stored hashes of the
+/// unchanged subtries and the hashed entries of the changed ones.
+#[derive(Debug)]
+pub struct TrieNodeIter<C, H: HashedCursor> {
+    /// The walker over the stored branch nodes.
+    pub walker: TrieWalker<C>,
+    /// The cursor over the hashed entries.
+    hashed_cursor: H,
+    /// The position of the iteration over the hashed entries.
+    state: NodeIterState<H::Value>,
+}
+
+impl<C: TrieCursor, H: HashedCursor> TrieNodeIter<C, H> {
+    /// Creates a new iterator from the walker and the hashed cursor.
+    pub fn new(walker: TrieWalker<C>, hashed_cursor: H) -> Self {
+        Self { walker, hashed_cursor, state: NodeIterState::default() }
+    }
+
+    /// Resumes the iteration after the given hashed key, the last one yielded by a previous
+    /// iterator whose walker was resumed with
+    /// [`TrieWalker::from_stack`](crate::walker::TrieWalker::from_stack).
+    pub fn with_last_hashed_key(mut self, last_hashed_key: Option<B256>) -> Self {
+        self.state = NodeIterState::resumed(last_hashed_key);
+        self
+    }
+
+    /// Returns the next element of the trie.
+    pub fn try_next(&mut self) -> Result<Option<TrieElement<H::Value>>, DatabaseError> {
+        loop {
+            match self.state.step(&self.walker) {
+                NodeIterStep::Branch(element) => return Ok(Some(element)),
+                NodeIterStep::Leaf(hashed_key, value) => {
+                    self.state.current_hashed_entry = self.hashed_cursor.next()?;
+                    return Ok(Some(TrieElement::Leaf(hashed_key, value)));
+                }
+                NodeIterStep::Resume(last_hashed_key) => {
+                    self.hashed_cursor.seek(last_hashed_key)?;
+                    self.state.current_hashed_entry = self.hashed_cursor.next()?;
+                }
+                NodeIterStep::Seek(seek_key) => {
+                    self.state.current_hashed_entry = self.hashed_cursor.seek(seek_key)?;
+                    self.walker.advance()?;
+                }
+                NodeIterStep::Done => return Ok(None),
+            }
+        }
+    }
+}
+
+/// The cursor operation that moves a [TrieNodeIter] forward, see [NodeIterState::step].
+#[derive(Debug)]
+pub(crate) enum NodeIterStep<V> {
+    /// Yield the skipped subtrie.
+    Branch(TrieElement<V>),
+    /// Yield the hashed entry, once the hashed cursor is moved to the next entry.
+    Leaf(B256, V),
+    /// Move the hashed cursor to the entry after the given key, the last one yielded before
+    /// resuming.
+    Resume(B256),
+    /// Seek the hashed cursor to the given key and advance the walker.
+    Seek(B256),
+    /// The trie is exhausted.
+    Done,
+}
+
+/// The position of a [TrieNodeIter] over the hashed entries, apart from the cursors so that the
+/// same steps drive both the synchronous and the async cursors.
+#[derive(Debug)]
+pub(crate) struct NodeIterState<V> {
+    /// The hashed entry the cursor is positioned at.
+    pub(crate) current_hashed_entry: Option<(B256, V)>,
+    /// Whether the current walker position was already checked for skipping.
+    current_walker_key_checked: bool,
+    /// The last hashed key yielded by a previous iteration to resume from.
+    last_hashed_key: Option<B256>,
+}
+
+impl<V> Default for NodeIterState<V> {
+    fn default() -> Self {
+        Self::resumed(None)
+    }
+}
+
+impl<V> NodeIterState<V> {
+    /// Creates the state resuming after the given hashed key, see
+    /// [TrieNodeIter::with_last_hashed_key].
+    const fn resumed(last_hashed_key: Option<B256>) -> Self {
+        Self {
+            current_hashed_entry: None,
+            // The walker position was checked before the previous iterator yielded its last key.
+            current_walker_key_checked: last_hashed_key.is_some(),
+            last_hashed_key,
+        }
+    }
+
+    /// Returns the next cursor operation given the position of the walker.
+    pub(crate) fn step<C>(&mut self, walker: &TrieWalker<C>) -> NodeIterStep<V> {
+        loop {
+            if !self.current_walker_key_checked {
+                if let Some(key) = walker.key() {
+                    self.current_walker_key_checked = true;
+                    if walker.can_skip_current_node() {
+                        return NodeIterStep::Branch(TrieElement::Branch {
+                            key: key.clone(),
+                            hash: walker.hash().expect("skipped node has a hash"),
+                            children_are_in_trie: walker.children_are_in_trie(),
+                            is_private: walker.is_private(),
+                        });
+                    }
+                }
+            }
+
+            if let Some((hashed_key, value)) = self.current_hashed_entry.take() {
+                // The entry lies beyond the current walker position, which has to be handled
+                // first. The entry is found again by the next seek.
+                if walker.key().is_some_and(|key| key < &unpack_b256(&hashed_key)) {
+                    self.current_walker_key_checked = false;
+                    continue;
+                }
+
+                return NodeIterStep::Leaf(hashed_key, value);
+            }
+
+            if let Some(last_hashed_key) = self.last_hashed_key.take() {
+                return NodeIterStep::Resume(last_hashed_key);
+            }
+
+            return match walker.next_unprocessed_key() {
+                Some(seek_key) => NodeIterStep::Seek(seek_key),
+                None => NodeIterStep::Done,
+            };
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use super::{super::TrieMask, RlpNode, CHILD_INDEX_RANGE};
+use super::{
+    super::{PrivacyMask, TrieMask},
+    RlpNode, CHILD_INDEX_RANGE,
+};
 use alloy_primitives::{hex, B256};
 use alloy_rlp::{length_of_length, Buf, BufMut, Decodable, Encodable, Header, EMPTY_STRING_CODE};
 use core::{fmt, ops::Range, slice::Iter};
@@ -21,6 +24,33 @@ pub struct BranchNode {
     pub state_mask: TrieMask,
 }
 
+#[cfg(feature = "arbitrary")]
+impl<'u> arbitrary::Arbitrary<'u> for BranchNode {
+    fn arbitrary(g: &mut arbitrary::Unstructured<'u>) -> arbitrary::Result<Self> {
+        let state_mask: TrieMask = g.arbitrary()?;
+        let stack = (0..state_mask.count_ones())
+            .map(|_| g.arbitrary())
+            .collect::<arbitrary::Result<Vec<RlpNode>>>()?;
+        Ok(Self::new(stack, state_mask))
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl proptest::arbitrary::Arbitrary for BranchNode {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with((): Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        any::<TrieMask>()
+            .prop_flat_map(|state_mask| {
+                proptest::collection::vec(any::<RlpNode>(), state_mask.count_ones() as usize)
+                    .prop_map(move |stack| Self::new(stack, state_mask))
+            })
+            .boxed()
+    }
+}
+
 impl fmt::Debug for BranchNode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("BranchNode")
@@ -61,8 +91,12 @@ impl Decodable for BranchNode {
             }
 
             // Decode without advancing
-            let Header { payload_length, .. } = Header::decode(&mut &bytes[..])?;
-            let len = payload_length + length_of_length(payload_length);
+            let mut child = bytes;
+            let Header { payload_length, .. } = Header::decode(&mut child)?;
+            let len = bytes.len() - child.len() + payload_length;
+            if len > bytes.len() {
+                return Err(alloy_rlp::Error::InputTooShort);
+            }
             stack.push(RlpNode::from_raw_rlp(&bytes[..len])?);
             bytes.advance(len);
             state_mask.set_bit(index);
@@ -282,6 +316,49 @@ pub struct BranchNodeCompact {
     pub hashes: Arc<Vec<B256>>,
     /// An optional root hash of the subtree rooted at this branch node.
     pub root_hash: Option<B256>,
+    /// The bitmask representing the children at the respective nibble positions in the trie
+    /// whose subtries contain private leaves. If the bit at position `i` (counting from the right)
+    /// is set (1), the subtrie of the child at the nibble value `i` holds private state.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub private_mask: TrieMask,
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'u> arbitrary::Arbitrary<'u> for BranchNodeCompact {
+    fn arbitrary(g: &mut arbitrary::Unstructured<'u>) -> arbitrary::Result<Self> {
+        let state_mask: TrieMask = g.arbitrary()?;
+        let tree_mask = TrieMask::new(g.arbitrary::<u16>()? & state_mask.get());
+        let hash_mask = TrieMask::new(g.arbitrary::<u16>()? & state_mask.get());
+        let private_mask = TrieMask::new(g.arbitrary::<u16>()? & state_mask.get());
+        let hashes = (0..hash_mask.count_ones())
+            .map(|_| g.arbitrary())
+            .collect::<arbitrary::Result<Vec<B256>>>()?;
+        Ok(Self::new(state_mask, tree_mask, hash_mask, hashes, g.arbitrary()?)
+            .with_private_mask(private_mask))
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl proptest::arbitrary::Arbitrary for BranchNodeCompact {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with((): Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        (any::<u16>(), any::<u16>(), any::<u16>(), any::<u16>())
+            .prop_flat_map(|(state_mask, tree_mask, hash_mask, private_mask)| {
+                let hash_mask = hash_mask & state_mask;
+                (
+                    proptest::collection::vec(any::<B256>(), hash_mask.count_ones() as usize),
+                    any::<Option<B256>>(),
+                )
+                    .prop_map(move |(hashes, root_hash)| {
+                        Self::new(state_mask, tree_mask & state_mask, hash_mask, hashes, root_hash)
+                            .with_private_mask(private_mask & state_mask)
+                    })
+            })
+            .boxed()
+    }
 }
 
 impl BranchNodeCompact {
@@ -304,7 +381,31 @@ impl BranchNodeCompact {
             "state_mask {state_mask:?} hash_mask: {hash_mask:?}"
         );
         assert_eq!(hash_mask.count_ones() as usize, hashes.len());
-        Self { state_mask, tree_mask, hash_mask, hashes: hashes.into(), root_hash }
+        Self {
+            state_mask,
+            tree_mask,
+            hash_mask,
+            hashes: hashes.into(),
+            root_hash,
+            private_mask: TrieMask::default(),
+        }
+    }
+
+    /// Sets the mask of the children whose subtries contain private leaves.
+    pub fn with_private_mask(mut self, private_mask: impl Into<TrieMask>) -> Self {
+        let private_mask = private_mask.into();
+        assert!(
+            private_mask.is_subset_of(self.state_mask),
+            "state_mask {:?} private_mask: {private_mask:?}",
+            self.state_mask
+        );
+        self.private_mask = private_mask;
+        self
+    }
+
+    /// Returns the [PrivacyMask] of the children, see [BranchNodeCompact::private_mask].
+    pub const fn privacy_mask(&self) -> PrivacyMask {
+        PrivacyMask::new(self.private_mask)
     }
 
     /// Returns the hash associated with the given nibble.
@@ -313,6 +414,97 @@ impl BranchNodeCompact {
         let index = (*self.hash_mask & mask).count_ones();
         self.hashes[index as usize]
     }
+
+    /// Returns the length of the [marshalled](Self::marshal) node.
+    pub fn marshalled_len(&self) -> usize {
+        let private_mask_len = if self.private_mask.is_empty() { 0 } else { 2 };
+        Self::MASKS_LEN
+            + (self.root_hash.is_some() as usize + self.hashes.len()) * B256::len_bytes()
+            + private_mask_len
+    }
+
+    /// Encodes the node into the compact database representation used by reth: the big-endian
+    /// state, tree and hash masks, followed by the optional root hash and the child hashes.
+    ///
+    /// A non-empty private mask is appended as a trailing big-endian mask, so nodes without
+    /// private children are encoded exactly as in reth.
+    pub fn marshal(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.marshalled_len());
+        self.marshal_into(&mut out);
+        out
+    }
+
+    /// Encodes the node into the given buffer. See [Self::marshal] for the format.
+    pub fn marshal_into(&self, out: &mut dyn BufMut) {
+        out.put_u16(self.state_mask.get());
+        out.put_u16(self.tree_mask.get());
+        out.put_u16(self.hash_mask.get());
+        if let Some(root_hash) = &self.root_hash {
+            out.put_slice(root_hash.as_slice());
+        }
+        for hash in self.hashes.iter() {
+            out.put_slice(hash.as_slice());
+        }
+        if !self.private_mask.is_empty() {
+            out.put_u16(self.private_mask.get());
+        }
+    }
+
+    /// Decodes the node from its compact database representation. See [Self::marshal] for the
+    /// format.
+    ///
+    /// The root hash is present if there is one more hash than set bits in the hash mask.
+    /// Returns [None] if the bytes are not a valid node, including an explicit empty private mask
+    /// that [Self::marshal] would have omitted.
+    pub fn unmarshal(mut bytes: &[u8]) -> Option<Self> {
+        let hash_len = B256::len_bytes();
+        if bytes.len() < Self::MASKS_LEN {
+            return None;
+        }
+        let private_mask = match (bytes.len() - Self::MASKS_LEN) % hash_len {
+            0 => TrieMask::default(),
+            2 => {
+                let (rest, private_mask) = bytes.split_at(bytes.len() - 2);
+                bytes = rest;
+                // An empty private mask is omitted, so that every node has a single encoding.
+                match u16::from_be_bytes([private_mask[0], private_mask[1]]) {
+                    0 => return None,
+                    private_mask => TrieMask::new(private_mask),
+                }
+            }
+            _ => return None,
+        };
+
+        let state_mask = TrieMask::new(bytes.get_u16());
+        let tree_mask = TrieMask::new(bytes.get_u16());
+        let hash_mask = TrieMask::new(bytes.get_u16());
+        if !tree_mask.is_subset_of(state_mask)
+            || !hash_mask.is_subset_of(state_mask)
+            || !private_mask.is_subset_of(state_mask)
+        {
+            return None;
+        }
+
+        let num_hashes = bytes.len() / hash_len;
+        let root_hash = if num_hashes == hash_mask.count_ones() as usize + 1 {
+            let root_hash = B256::from_slice(&bytes[..hash_len]);
+            bytes.advance(hash_len);
+            Some(root_hash)
+        } else if num_hashes == hash_mask.count_ones() as usize {
+            None
+        } else {
+            return None;
+        };
+
+        let hashes = bytes.chunks_exact(hash_len).map(B256::from_slice).collect();
+        Some(
+            Self::new(state_mask, tree_mask, hash_mask, hashes, root_hash)
+                .with_private_mask(private_mask),
+        )
+    }
+
+    /// The length of the masks in the compact representation.
+    const MASKS_LEN: usize = 6;
 }
 
 #[cfg(test)]
@@ -321,6 +513,14 @@ mod tests {
     use crate::nodes::{ExtensionNode, LeafNode};
     use nybbles::Nibbles;
 
+    #[test]
+    fn decode_truncated_branch_child() {
+        // The first child claims 32 bytes, but only 16 are left in the list.
+        let mut encoded = vec![0xd1, 0xa0];
+        encoded.extend([EMPTY_STRING_CODE; 16]);
+        assert_eq!(BranchNode::decode(&mut &encoded[..]), Err(alloy_rlp::Error::InputTooShort));
+    }
+
     #[test]
     fn rlp_branch_node_roundtrip() {
         let empty = BranchNode::default();
@@ -341,7 +541,7 @@ mod tests {
             LeafNode::new(Nibbles::from_nibbles(hex!("0203")), hex!("1234").to_vec(), false);
         let mut buf = vec![];
         let leaf_rlp = leaf_child.as_ref().rlp(&mut buf);
-        let branch_with_leaf = BranchNode::new(vec![leaf_rlp.clone()], TrieMask::new(0b0010));
+        let branch_with_leaf = BranchNode::new(vec![leaf_rlp], TrieMask::new(0b0010));
         let encoded = alloy_rlp::encode(&branch_with_leaf);
         assert_eq!(BranchNode::decode(&mut &encoded[..]).unwrap(), branch_with_leaf);
 
@@ -367,4 +567,49 @@ mod tests {
         let encoded = alloy_rlp::encode(&full);
         assert_eq!(BranchNode::decode(&mut &encoded[..]).unwrap(), full);
     }
+
+    #[test]
+    fn branch_node_compact_marshal_roundtrip() {
+        let hashes = vec![
+            B256::from(hex!("90d53cd810cc5d4243766cd4451e7b9d14b736a1148b26b3baac7617f617d321")),
+            B256::from(hex!("cc35c964dda53ba6c0b87798073a9628dbc9cd26b5cce88eb69655a9c609caf1")),
+        ];
+        let root_hash = B256::repeat_byte(0xaa);
+
+        let node = BranchNodeCompact::new(
+            0xf607u16,
+            0x0005u16,
+            0x4004u16,
+            hashes.clone(),
+            Some(root_hash),
+        );
+        let encoded = node.marshal();
+        assert_eq!(encoded.len(), node.marshalled_len());
+        assert_eq!(encoded[..6], hex!("f60700054004"));
+        assert_eq!(encoded[6..38], root_hash[..]);
+        assert_eq!(BranchNodeCompact::unmarshal(&encoded), Some(node));
+
+        let node = BranchNodeCompact::new(0xf607u16, 0x0005u16, 0x4004u16, hashes, None);
+        let encoded = node.marshal();
+        assert_eq!(encoded.len(), 6 + 64);
+        assert_eq!(BranchNodeCompact::unmarshal(&encoded), Some(node.clone()));
+
+        // truncated hash, missing hashes and hash mask outside of the state mask
+        assert_eq!(BranchNodeCompact::unmarshal(&encoded[..encoded.len() - 1]), None);
+        assert_eq!(BranchNodeCompact::unmarshal(&encoded[..38]), None);
+        assert_eq!(BranchNodeCompact::unmarshal(&hex!("000100000002")), None);
+
+        // private mask is appended
+        let node = node.with_private_mask(0x0005u16);
+        let encoded = node.marshal();
+        assert_eq!(encoded.len(), node.marshalled_len());
+        assert_eq!(encoded[encoded.len() - 2..], hex!("0005"));
+        assert_eq!(BranchNodeCompact::unmarshal(&encoded), Some(node));
+        assert_eq!(BranchNodeCompact::unmarshal(&hex!("0001000000000002")), None);
+
+        // an explicit empty private mask is not the canonical encoding
+        let node = BranchNodeCompact::unmarshal(&hex!("0001000000000001")).unwrap();
+        assert_eq!(node.marshal(), hex!("0001000000000001"));
+        assert_eq!(BranchNodeCompact::unmarshal(&hex!("0001000000000000")), None);
+    }
 }
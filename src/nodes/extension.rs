@@ -17,6 +17,7 @@ use alloc::vec::Vec;
 /// complexity when performing operations on the trie.
 #[derive(PartialEq, Eq, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(derive_arbitrary::Arbitrary, proptest_derive::Arbitrary))]
 pub struct ExtensionNode {
     /// The key for this extension node.
     pub key: Nibbles,
@@ -1,4 +1,5 @@
 use super::{super::Nibbles, encode_path_leaf, unpack_path_to_nibbles, RlpNode};
+use crate::hash_builder::LeafPayload;
 use alloy_primitives::{hex, Bytes};
 use alloy_rlp::{length_of_length, BufMut, Decodable, Encodable, Header};
 use core::fmt;
@@ -15,10 +16,12 @@ use alloc::vec::Vec;
 /// node means that the search has successfully found the value associated with that key.
 #[derive(PartialEq, Eq, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(derive_arbitrary::Arbitrary, proptest_derive::Arbitrary))]
 pub struct LeafNode {
     /// The key for this leaf node.
     pub key: Nibbles,
     /// The node value.
+    #[cfg_attr(feature = "serde", serde(with = "hex"))]
     pub value: Vec<u8>,
     /// Whether the node holds private state
     pub is_private: bool,
@@ -92,12 +95,82 @@ impl LeafNode {
         Self { key, value, is_private }
     }
 
+    /// Creates a new leaf node with the given key and [LeafValue].
+    pub fn from_value(key: Nibbles, value: LeafValue) -> Self {
+        let is_private = value.is_private();
+        Self { key, value: value.into_value().into(), is_private }
+    }
+
+    /// Returns the value of the leaf along with its privacy.
+    pub fn leaf_value(&self) -> LeafValue {
+        LeafValue::new(self.value.clone(), self.is_private)
+    }
+
     /// Return leaf node as [LeafNodeRef].
     pub fn as_ref(&self) -> LeafNodeRef<'_> {
         LeafNodeRef { key: &self.key, value: &self.value, is_private: &self.is_private }
     }
 }
 
+/// The value stored in a leaf along with its privacy, the owned counterpart of [LeafPayload].
+///
+/// The privacy is marked by the flag of the leaf key in the encoded node, see
+/// [LeafNode::PRIV_EVEN_FLAG].
+#[derive(PartialEq, Eq, Clone, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LeafValue {
+    /// Value of a public leaf.
+    Public(Bytes),
+    /// Value of a private leaf.
+    Private(Bytes),
+}
+
+impl LeafValue {
+    /// Creates a new leaf value with the given privacy.
+    pub fn new(value: impl Into<Bytes>, is_private: bool) -> Self {
+        let value = value.into();
+        if is_private {
+            Self::Private(value)
+        } else {
+            Self::Public(value)
+        }
+    }
+
+    /// Returns the value stored in the leaf.
+    pub const fn value(&self) -> &Bytes {
+        match self {
+            Self::Public(value) | Self::Private(value) => value,
+        }
+    }
+
+    /// Consumes the leaf value and returns the value stored in the leaf.
+    pub fn into_value(self) -> Bytes {
+        match self {
+            Self::Public(value) | Self::Private(value) => value,
+        }
+    }
+
+    /// Returns `true` if the leaf is private.
+    pub const fn is_private(&self) -> bool {
+        matches!(self, Self::Private(_))
+    }
+
+    /// Returns the value as a [LeafPayload], e.g. to add it to a
+    /// [HashBuilder](crate::HashBuilder).
+    pub fn as_payload(&self) -> LeafPayload<'_> {
+        match self {
+            Self::Public(value) => LeafPayload::Public(value),
+            Self::Private(value) => LeafPayload::Private(value),
+        }
+    }
+}
+
+impl From<LeafPayload<'_>> for LeafValue {
+    fn from(payload: LeafPayload<'_>) -> Self {
+        Self::new(payload.value().to_vec(), payload.is_private())
+    }
+}
+
 /// Reference to the leaf node. See [LeafNode] from more information.
 pub struct LeafNodeRef<'a> {
     /// The key for this leaf node.
@@ -113,6 +186,7 @@ impl fmt::Debug for LeafNodeRef<'_> {
         f.debug_struct("LeafNodeRef")
             .field("key", &self.key)
             .field("value", &hex::encode(self.value))
+            .field("is_private", self.is_private)
             .finish()
     }
 }
@@ -216,4 +290,19 @@ mod tests {
 
         assert_ne!(leaf.as_ref().rlp(&mut vec![]), priv_leaf.as_ref().rlp(&mut vec![]));
     }
+
+    #[test]
+    fn leaf_value_roundtrip() {
+        for is_private in [false, true] {
+            let value = LeafValue::new(hex!("76657262").to_vec(), is_private);
+            assert_eq!(value.as_payload().value(), hex!("76657262"));
+            assert_eq!(value.as_payload().is_private(), is_private);
+            assert_eq!(LeafValue::from(value.as_payload()), value);
+
+            let nibbles = Nibbles::from_nibbles_unchecked(hex!("0604060f"));
+            let leaf = LeafNode::from_value(nibbles, value.clone());
+            assert_eq!(leaf.is_private, value.is_private());
+            assert_eq!(leaf.leaf_value(), value);
+        }
+    }
 }
@@ -1,6 +1,6 @@
 //! Various branch nodes produced by the hash builder.
 
-use alloy_primitives::{Bytes, B256};
+use alloy_primitives::{keccak256, Bytes, B256};
 use alloy_rlp::{Decodable, Encodable, Header, EMPTY_STRING_CODE};
 use core::ops::Range;
 use nybbles::Nibbles;
@@ -16,7 +16,7 @@ mod extension;
 pub use extension::{ExtensionNode, ExtensionNodeRef};
 
 mod leaf;
-pub use leaf::{LeafNode, LeafNodeRef};
+pub use leaf::{LeafNode, LeafNodeRef, LeafValue};
 
 mod rlp;
 pub use rlp::RlpNode;
@@ -25,8 +25,14 @@ pub use rlp::RlpNode;
 pub const CHILD_INDEX_RANGE: Range<u8> = 0..16;
 
 /// Enum representing an MPT trie node.
+///
+/// This is the canonical decoded representation of the nodes, shared by proof verification, the
+/// sparse trie and witness consumers. A node is referenced by its parent through
+/// [TrieNode::rlp], which embeds nodes shorter than 32 bytes in place of their hash, and
+/// [TrieNode::decode_embedded] reverses it.
 #[derive(PartialEq, Eq, Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(derive_arbitrary::Arbitrary, proptest_derive::Arbitrary))]
 pub enum TrieNode {
     /// Variant representing empty root node.
     EmptyRoot,
@@ -98,30 +104,10 @@ impl Decodable for TrieNode {
                 let mut key = items.remove(0);
 
                 let encoded_key = Header::decode_bytes(&mut key, false)?;
-                if encoded_key.is_empty() {
-                    return Err(alloy_rlp::Error::Custom("trie node key empty"));
-                }
-
-                // extract the high order part of the nibble to then pick the odd nibble out
-                let key_flag = encoded_key[0] & 0xf0;
-                // Retrieve first byte. If it's [Some], then the nibbles are odd.
-                let (first, is_private_opt) = match key_flag {
-                    LeafNode::PUB_EVEN_FLAG => (None, Some(false)),
-                    LeafNode::PRIV_EVEN_FLAG => (None, Some(true)),
-                    LeafNode::PUB_ODD_FLAG => (Some(encoded_key[0] & 0x0f), Some(false)),
-                    LeafNode::PRIV_ODD_FLAG => (Some(encoded_key[0] & 0x0f), Some(true)),
-                    ExtensionNode::ODD_FLAG => (Some(encoded_key[0] & 0x0f), None),
-                    ExtensionNode::EVEN_FLAG => (None, None),
-                    _ => {
-                        return Err(alloy_rlp::Error::Custom("node is not leaf or extension node"))
-                    }
-                };
-
-                let key = unpack_path_to_nibbles(first, &encoded_key[1..]);
-                let is_leaf = is_private_opt.is_some();
+                let (key, is_leaf, is_private) = decode_path(encoded_key)?;
                 let node = if is_leaf {
                     let value = Bytes::decode(&mut items.remove(0))?.into();
-                    Self::Leaf(LeafNode::new(key, value, is_private_opt.unwrap()))
+                    Self::Leaf(LeafNode::new(key, value, is_private))
                 } else {
                     // We don't decode value because it is expected to be RLP encoded.
                     Self::Extension(ExtensionNode::new(
@@ -143,6 +129,23 @@ impl TrieNode {
         self.encode(rlp);
         RlpNode::from_rlp(rlp)
     }
+
+    /// Returns the keccak-256 hash of the RLP encoded node, regardless of its length, as used to
+    /// reference a root node or to key the node in a database.
+    #[inline]
+    pub fn hash(&self) -> B256 {
+        keccak256(alloy_rlp::encode(self))
+    }
+
+    /// Decodes the node referenced by the given child pointer if it is encoded in place, or
+    /// returns `None` if the child is referenced by its hash.
+    #[inline]
+    pub fn decode_embedded(child: &RlpNode) -> alloy_rlp::Result<Option<Self>> {
+        if child.is_hash() {
+            return Ok(None);
+        }
+        Self::decode(&mut &child[..]).map(Some)
+    }
 }
 
 /// Given an RLP-encoded node, returns it either as `rlp(node)` or `rlp(keccak(rlp(node)))`.
@@ -192,13 +195,17 @@ pub(crate) fn unpack_path_to_nibbles(first: Option<u8>, rest: &[u8]) -> Nibbles
 /// The method takes the following input:
 /// `is_leaf`: A boolean value indicating whether the current node is a leaf node or not.
 ///
-/// The first byte of the encoded vector is set based on the `is_leaf` flag and the parity of
-/// the hex data length (even or odd number of nibbles).
+/// The first byte of the encoded vector is set based on the `is_leaf` and `is_private` flags and
+/// the parity of the hex data length (even or odd number of nibbles).
 ///  - If the node is an extension with even length, the header byte is `0x00`.
 ///  - If the node is an extension with odd length, the header byte is `0x10 + <first nibble>`.
 ///  - If the node is a leaf with even length, the header byte is `0x20`.
 ///  - If the node is a leaf with odd length, the header byte is `0x30 + <first nibble>`.
 ///
+/// Private leaves additionally set the `0x40` bit of the header byte, which makes their header
+/// byte `0x60` for even lengths and `0x70 + <first nibble>` for odd lengths. Extension nodes are
+/// always public.
+///
 /// If there is an odd number of nibbles, store the first nibble in the lower 4 bits of the
 /// first byte of encoded.
 ///
@@ -232,7 +239,15 @@ pub(crate) fn unpack_path_to_nibbles(first: Option<u8>, rest: &[u8]) -> Nibbles
 /// // Leaf node with an odd path length:
 /// let nibbles = Nibbles::from_nibbles(&[0x0A, 0x0B, 0x0C]);
 /// assert_eq!(encode_path_leaf(&nibbles, true, false)[..], [0x3A, 0xBC]);
+///
+/// // Private leaf node with an odd path length:
+/// let nibbles = Nibbles::from_nibbles(&[0x0A, 0x0B, 0x0C]);
+/// assert_eq!(encode_path_leaf(&nibbles, true, true)[..], [0x7A, 0xBC]);
 /// ```
+///
+/// # Panics
+///
+/// Panics if `is_private` is set for an extension node.
 #[inline]
 pub fn encode_path_leaf(nibbles: &Nibbles, is_leaf: bool, is_private: bool) -> SmallVec<[u8; 36]> {
     let mut nibbles = nibbles.as_slice();
@@ -259,12 +274,88 @@ pub fn encode_path_leaf(nibbles: &Nibbles, is_leaf: bool, is_private: bool) -> S
     }
 }
 
+/// Decodes a compact array of bytes produced by [encode_path_leaf] back into the path.
+///
+/// Returns the nibbles of the path, whether it is the path of a leaf node, and whether that leaf
+/// node is private, following the header byte flags documented in [encode_path_leaf].
+///
+/// # Examples
+///
+/// ```
+/// use alloy_trie::nodes::{decode_path, encode_path_leaf};
+/// use nybbles::Nibbles;
+///
+/// let nibbles = Nibbles::from_nibbles(&[0x0A, 0x0B, 0x0C]);
+/// assert_eq!(decode_path(&[0x7A, 0xBC]).unwrap(), (nibbles.clone(), true, true));
+/// assert_eq!(
+///     decode_path(&encode_path_leaf(&nibbles, false, false)).unwrap(),
+///     (nibbles, false, false)
+/// );
+/// ```
+pub fn decode_path(encoded: &[u8]) -> alloy_rlp::Result<(Nibbles, bool, bool)> {
+    let Some(&header) = encoded.first() else {
+        return Err(alloy_rlp::Error::Custom("trie node key empty"));
+    };
+
+    // The flags are encoded in the high order nibble, the low order one is the first nibble of an
+    // odd length path.
+    let odd_nibble = header & 0x0f;
+    let (first, is_leaf, is_private) = match header & 0xf0 {
+        LeafNode::PUB_EVEN_FLAG => (None, true, false),
+        LeafNode::PRIV_EVEN_FLAG => (None, true, true),
+        LeafNode::PUB_ODD_FLAG => (Some(odd_nibble), true, false),
+        LeafNode::PRIV_ODD_FLAG => (Some(odd_nibble), true, true),
+        ExtensionNode::ODD_FLAG => (Some(odd_nibble), false, false),
+        ExtensionNode::EVEN_FLAG => (None, false, false),
+        _ => return Err(alloy_rlp::Error::Custom("node is not leaf or extension node")),
+    };
+
+    Ok((unpack_path_to_nibbles(first, &encoded[1..]), is_leaf, is_private))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::TrieMask;
     use alloy_primitives::hex;
 
+    #[test]
+    fn path_encoding_roundtrip() {
+        for len in 0..6 {
+            let nibbles =
+                Nibbles::from_nibbles_unchecked((0..len).map(|i| i as u8 + 1).collect::<Vec<_>>());
+            for (is_leaf, is_private) in [(false, false), (true, false), (true, true)] {
+                let encoded = encode_path_leaf(&nibbles, is_leaf, is_private);
+                assert_eq!(decode_path(&encoded).unwrap(), (nibbles.clone(), is_leaf, is_private));
+            }
+        }
+
+        assert!(decode_path(&[]).is_err());
+        assert!(decode_path(&[0x40]).is_err());
+    }
+
+    #[test]
+    fn hash_and_embedded_nodes() {
+        let leaf = TrieNode::Leaf(LeafNode::new(
+            Nibbles::from_nibbles_unchecked(hex!("0604060f")),
+            hex!("76657262").to_vec(),
+            true,
+        ));
+        let mut rlp = vec![];
+        let child = leaf.rlp(&mut rlp);
+        assert!(!child.is_hash());
+        assert_eq!(leaf.hash(), keccak256(&rlp));
+        assert_eq!(TrieNode::decode_embedded(&child).unwrap(), Some(leaf.clone()));
+
+        let branch = TrieNode::Branch(BranchNode::new(
+            core::iter::repeat(child).take(16).collect(),
+            TrieMask::new(u16::MAX),
+        ));
+        let reference = branch.rlp(&mut vec![]);
+        assert_eq!(reference.as_hash(), Some(branch.hash()));
+        assert_eq!(TrieNode::decode_embedded(&reference).unwrap(), None);
+    }
+
     #[test]
     fn rlp_empty_root_node() {
         let empty_root = TrieNode::EmptyRoot;
@@ -333,6 +424,21 @@ mod tests {
         assert_eq!(TrieNode::decode(&mut &rlp[..]).unwrap(), branch);
     }
 
+    #[test]
+    fn rlp_typed_node_decoding_checks_flags() {
+        let key = Nibbles::from_nibbles_unchecked(hex!("0604060f"));
+        let priv_leaf = LeafNode::new(key.clone(), hex!("76657262").to_vec(), true);
+        let rlp = alloy_rlp::encode(&priv_leaf);
+        assert_eq!(LeafNode::decode(&mut &rlp[..]).unwrap(), priv_leaf);
+        assert!(ExtensionNode::decode(&mut &rlp[..]).is_err());
+        assert!(BranchNode::decode(&mut &rlp[..]).is_err());
+
+        let extension = ExtensionNode::new(key, RlpNode::word_rlp(&B256::repeat_byte(23)));
+        let rlp = alloy_rlp::encode(&extension);
+        assert_eq!(ExtensionNode::decode(&mut &rlp[..]).unwrap(), extension);
+        assert!(LeafNode::decode(&mut &rlp[..]).is_err());
+    }
+
     #[test]
     fn hashed_encode_path_regression() {
         let nibbles = Nibbles::from_nibbles(hex!("05010406040a040203030f010805020b050c04070003070e0909070f010b0a0805020301070c0a0902040b0f000f0006040a04050f020b090701000a0a040b"));
@@ -371,4 +477,28 @@ mod tests {
             }
         });
     }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_trie_node_roundtrip() {
+        let leaf = LeafNode::new(Nibbles::from_nibbles_unchecked([0x1, 0x2]), vec![0xab; 3], true);
+        let json = serde_json::to_value(&leaf).unwrap();
+        assert_eq!(json["value"].as_str().unwrap().trim_start_matches("0x"), "ababab");
+        assert_eq!(json["is_private"], true);
+
+        let child = RlpNode::word_rlp(&B256::repeat_byte(23));
+        let nodes = [
+            TrieNode::EmptyRoot,
+            TrieNode::Leaf(leaf),
+            TrieNode::Extension(ExtensionNode::new(
+                Nibbles::from_nibbles_unchecked([0x3]),
+                child.clone(),
+            )),
+            TrieNode::Branch(BranchNode::new(vec![child.clone(), child], TrieMask::new(0b11))),
+        ];
+        for node in nodes {
+            let json = serde_json::to_string(&node).unwrap();
+            assert_eq!(serde_json::from_str::<TrieNode>(&json).unwrap(), node);
+        }
+    }
 }
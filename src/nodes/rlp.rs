@@ -1,4 +1,5 @@
-use alloy_primitives::{hex, keccak256, B256};
+use crate::hash_builder::{KeccakHasher, TrieHasher};
+use alloy_primitives::{hex, B256};
 use alloy_rlp::EMPTY_STRING_CODE;
 use arrayvec::ArrayVec;
 use core::fmt;
@@ -7,9 +8,23 @@ const MAX: usize = 33;
 
 /// An RLP-encoded node.
 #[derive(Clone, Default, PartialEq, Eq)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RlpNode(ArrayVec<u8, MAX>);
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for RlpNode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        hex::serialize(&self.0, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for RlpNode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes: alloc::vec::Vec<u8> = hex::deserialize(deserializer)?;
+        Self::from_raw(&bytes).ok_or_else(|| serde::de::Error::custom("RLP node too large"))
+    }
+}
+
 impl alloy_rlp::Decodable for RlpNode {
     fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
         let bytes = alloy_rlp::Header::decode_bytes(buf, false)?;
@@ -67,11 +82,18 @@ impl RlpNode {
     #[doc(alias = "rlp_node")]
     #[inline]
     pub fn from_rlp(rlp: &[u8]) -> Self {
+        Self::from_rlp_with_hasher::<KeccakHasher>(rlp)
+    }
+
+    /// Given an RLP-encoded node, returns it either as `rlp(node)` or `rlp(hash(rlp(node)))` using
+    /// the given hasher.
+    #[inline]
+    pub fn from_rlp_with_hasher<H: TrieHasher>(rlp: &[u8]) -> Self {
         if rlp.len() < 32 {
             // SAFETY: `rlp` is less than max capacity (33).
             unsafe { Self::from_raw(rlp).unwrap_unchecked() }
         } else {
-            Self::word_rlp(&keccak256(rlp))
+            Self::word_rlp(&H::hash(rlp))
         }
     }
 
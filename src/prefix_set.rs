@@ -0,0 +1,220 @@
+//! Prefix sets of changed trie keys used to drive incremental root computation.
+
+use crate::{HashMap, Nibbles};
+use alloc::{sync::Arc, vec::Vec};
+use alloy_primitives::B256;
+
+/// Collection of mutable prefix sets for the account trie and the storage tries.
+///
+/// The account prefix set must contain the keys of all accounts whose storage changed, otherwise
+/// their storage roots are not recomputed.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TriePrefixSetsMut {
+    /// Prefix set of the changed hashed account keys.
+    pub account_prefix_set: PrefixSetMut,
+    /// Prefix sets of the changed hashed storage keys indexed by hashed address.
+    pub storage_prefix_sets: HashMap<B256, PrefixSetMut>,
+}
+
+impl TriePrefixSetsMut {
+    /// Extends the prefix sets with the keys of another collection of prefix sets.
+    pub fn extend(&mut self, other: Self) {
+        self.account_prefix_set.extend(other.account_prefix_set);
+        for (hashed_address, prefix_set) in other.storage_prefix_sets {
+            self.storage_prefix_sets.entry(hashed_address).or_default().extend(prefix_set);
+        }
+    }
+
+    /// Freezes the prefix sets for lookups.
+    pub fn freeze(self) -> TriePrefixSets {
+        TriePrefixSets {
+            account_prefix_set: self.account_prefix_set.freeze(),
+            storage_prefix_sets: self
+                .storage_prefix_sets
+                .into_iter()
+                .map(|(hashed_address, prefix_set)| (hashed_address, prefix_set.freeze()))
+                .collect(),
+        }
+    }
+}
+
+/// Collection of frozen prefix sets for the account trie and the storage tries.
+#[derive(Clone, Default, Debug)]
+pub struct TriePrefixSets {
+    /// Prefix set of the changed hashed account keys.
+    pub account_prefix_set: PrefixSet,
+    /// Prefix sets of the changed hashed storage keys indexed by hashed address.
+    pub storage_prefix_sets: HashMap<B256, PrefixSet>,
+}
+
+/// A mutable set of changed trie keys.
+///
+/// Call [PrefixSetMut::freeze] to sort the keys and get a [PrefixSet] for lookups.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrefixSetMut {
+    /// Whether every key should be considered changed.
+    all: bool,
+    /// The changed keys in insertion order.
+    keys: Vec<Nibbles>,
+}
+
+impl FromIterator<Nibbles> for PrefixSetMut {
+    fn from_iter<T: IntoIterator<Item = Nibbles>>(iter: T) -> Self {
+        Self { all: false, keys: Vec::from_iter(iter) }
+    }
+}
+
+impl PrefixSetMut {
+    /// Creates an empty prefix set with the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { all: false, keys: Vec::with_capacity(capacity) }
+    }
+
+    /// Creates a prefix set that considers every key changed.
+    pub fn all() -> Self {
+        Self { all: true, keys: Vec::new() }
+    }
+
+    /// Inserts the changed key.
+    pub fn insert(&mut self, key: Nibbles) {
+        self.keys.push(key);
+    }
+
+    /// Extends the prefix set with the keys of another prefix set.
+    pub fn extend(&mut self, other: Self) {
+        self.all |= other.all;
+        self.keys.extend(other.keys);
+    }
+
+    /// Extends the prefix set with the given changed keys.
+    pub fn extend_keys(&mut self, keys: impl IntoIterator<Item = Nibbles>) {
+        self.keys.extend(keys);
+    }
+
+    /// Returns the number of inserted keys, including duplicates.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns `true` if no keys were inserted and the set does not consider every key changed.
+    pub fn is_empty(&self) -> bool {
+        !self.all && self.keys.is_empty()
+    }
+
+    /// Sorts and deduplicates the keys and returns the frozen [PrefixSet].
+    pub fn freeze(mut self) -> PrefixSet {
+        if self.all {
+            return PrefixSet { all: true, index: 0, keys: Arc::default() };
+        }
+        self.keys.sort_unstable();
+        self.keys.dedup();
+        self.keys.shrink_to_fit();
+        PrefixSet { all: false, index: 0, keys: Arc::new(self.keys) }
+    }
+}
+
+/// A frozen, sorted set of changed trie keys.
+///
+/// Lookups are optimized for prefixes queried in ascending order, as done by the trie walker.
+#[derive(Clone, Default, Debug)]
+pub struct PrefixSet {
+    /// Whether every key should be considered changed.
+    all: bool,
+    /// The position of the last lookup.
+    index: usize,
+    /// The sorted, deduplicated changed keys.
+    keys: Arc<Vec<Nibbles>>,
+}
+
+impl PrefixSet {
+    /// Returns `true` if any of the keys in the set starts with the given prefix.
+    pub fn contains(&mut self, prefix: &[u8]) -> bool {
+        if self.all {
+            return true;
+        }
+
+        while self.index > 0 && &self.keys[self.index][..] > prefix {
+            self.index -= 1;
+        }
+
+        for (idx, key) in self.keys[self.index..].iter().enumerate() {
+            if key.starts_with(prefix) {
+                self.index += idx;
+                return true;
+            }
+            if &key[..] > prefix {
+                self.index += idx;
+                return false;
+            }
+        }
+
+        false
+    }
+
+    /// Returns `true` if the set considers every key changed.
+    pub const fn all(&self) -> bool {
+        self.all
+    }
+
+    /// Returns an iterator over the keys in sorted order.
+    pub fn iter(&self) -> core::slice::Iter<'_, Nibbles> {
+        self.keys.iter()
+    }
+
+    /// Returns the number of keys.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns `true` if the set has no keys and does not consider every key changed.
+    pub fn is_empty(&self) -> bool {
+        !self.all && self.keys.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a PrefixSet {
+    type Item = &'a Nibbles;
+    type IntoIter = core::slice::Iter<'a, Nibbles>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contains_with_multiple_lookups() {
+        let mut prefix_set = PrefixSetMut::default();
+        prefix_set.insert(Nibbles::from_nibbles_unchecked([1, 2, 3]));
+        prefix_set.insert(Nibbles::from_nibbles_unchecked([1, 2, 4]));
+        prefix_set.insert(Nibbles::from_nibbles_unchecked([4, 5, 6]));
+        prefix_set.insert(Nibbles::from_nibbles_unchecked([1, 2, 3]));
+        let mut prefix_set = prefix_set.freeze();
+        assert_eq!(prefix_set.len(), 3);
+
+        assert!(prefix_set.contains(&[]));
+        assert!(prefix_set.contains(&[1, 2]));
+        assert!(prefix_set.contains(&[1, 2, 4]));
+        assert!(!prefix_set.contains(&[4, 6]));
+        assert!(prefix_set.contains(&[4, 5]));
+        assert!(!prefix_set.contains(&[1, 3]));
+        assert!(prefix_set.contains(&[1, 2, 3]));
+        assert!(!prefix_set.contains(&[7]));
+    }
+
+    #[test]
+    fn all_and_empty() {
+        let mut all = PrefixSetMut::all().freeze();
+        assert!(all.all());
+        assert!(all.contains(&[0xf, 0xf]));
+
+        let mut empty = PrefixSetMut::default().freeze();
+        assert!(empty.is_empty());
+        assert!(!empty.contains(&[]));
+    }
+}
@@ -0,0 +1,694 @@
+use crate::{
+    cursor::{DatabaseError, HashedCursorFactory, TrieCursorFactory},
+    is_empty_root,
+    node_iter::{TrieElement, TrieNodeIter},
+    nodes::{LeafNode, TrieNode},
+    proof::{
+        verify_proof, MultiProof, ProofVerificationError, ProofVerificationOutcome,
+        StorageMultiProof,
+    },
+    state_root::StorageRoot,
+    walker::TrieWalker,
+    witness::expand_paths_to,
+    HashBuilder, HashMap, Nibbles, TrieAccount, EMPTY_ROOT_HASH, KECCAK_EMPTY,
+};
+use alloy_primitives::{keccak256, map::HashSet, Address, Bytes, B256, U256};
+use alloy_rlp::{encode_fixed_size, Decodable};
+
+#[cfg(feature = "account-extensions")]
+use crate::AccountExtension;
+use alloc::vec::Vec;
+
+/// The merkle proof of an account and some of its storage slots, as returned by
+/// [EIP-1186](https://eips.ethereum.org/EIPS/eip-1186) `eth_getProof`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct AccountProof {
+    /// The address of the account.
+    pub address: Address,
+    /// The balance of the account.
+    pub balance: U256,
+    /// The nonce of the account.
+    #[cfg_attr(feature = "serde", serde(with = "crate::account::quantity"))]
+    pub nonce: u64,
+    /// The code hash of the account.
+    pub code_hash: B256,
+    /// The storage root of the account.
+    #[cfg_attr(feature = "serde", serde(rename = "storageHash"))]
+    pub storage_root: B256,
+    /// The extra fields of the account, see [TrieAccount::extension].
+    #[cfg(feature = "account-extensions")]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "AccountExtension::is_none")
+    )]
+    pub extension: AccountExtension,
+    /// The account proof nodes ordered from the state root.
+    #[cfg_attr(feature = "serde", serde(rename = "accountProof"))]
+    pub proof: Vec<Bytes>,
+    /// The proofs of the requested storage slots.
+    #[cfg_attr(feature = "serde", serde(rename = "storageProof"))]
+    pub storage_proofs: Vec<StorageProof>,
+}
+
+impl AccountProof {
+    /// Creates a proof of a nonexistent account.
+    pub const fn new(address: Address) -> Self {
+        Self {
+            address,
+            balance: U256::ZERO,
+            nonce: 0,
+            code_hash: KECCAK_EMPTY,
+            storage_root: EMPTY_ROOT_HASH,
+            #[cfg(feature = "account-extensions")]
+            extension: AccountExtension::None,
+            proof: Vec::new(),
+            storage_proofs: Vec::new(),
+        }
+    }
+
+    /// Returns the account as stored in the account trie, or [None] if the account is empty.
+    pub fn account(&self) -> Option<TrieAccount> {
+        let account = TrieAccount {
+            nonce: self.nonce,
+            balance: self.balance,
+            storage_root: self.storage_root,
+            code_hash: self.code_hash,
+            #[cfg(feature = "account-extensions")]
+            extension: self.extension,
+        };
+        (!account.is_empty()).then_some(account)
+    }
+
+    /// Verifies the account proof against the state root and the storage proofs against the
+    /// storage root of the account.
+    pub fn verify(&self, root: B256) -> Result<(), ProofVerificationError> {
+        for storage_proof in &self.storage_proofs {
+            storage_proof.verify(self.storage_root)?;
+        }
+
+        verify_proof(
+            root,
+            Nibbles::unpack(keccak256(self.address)),
+            self.account().map(alloy_rlp::encode),
+            false,
+            &self.proof,
+        )?;
+        Ok(())
+    }
+}
+
+/// The merkle proof of a storage slot, including whether the slot is private.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct StorageProof {
+    /// The unhashed storage slot.
+    pub key: B256,
+    /// The value of the slot.
+    pub value: U256,
+    /// Whether the slot is private.
+    pub is_private: bool,
+    /// The storage proof nodes ordered from the storage root.
+    pub proof: Vec<Bytes>,
+}
+
+impl StorageProof {
+    /// Creates a proof of an empty storage slot.
+    pub const fn new(key: B256) -> Self {
+        Self { key, value: U256::ZERO, is_private: false, proof: Vec::new() }
+    }
+
+    /// Verifies the storage proof against the storage root.
+    ///
+    /// The outcome tells a private slot apart from an absent one.
+    pub fn verify(&self, root: B256) -> Result<ProofVerificationOutcome, ProofVerificationError> {
+        let expected_value =
+            (!self.value.is_zero()).then(|| encode_fixed_size(&self.value).to_vec());
+        verify_proof(
+            root,
+            Nibbles::unpack(keccak256(self.key)),
+            expected_value,
+            self.is_private,
+            &self.proof,
+        )
+    }
+}
+
+/// The merkle proof that an account exists and its storage trie is empty, i.e. its storage root
+/// is [EMPTY_ROOT_HASH].
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct EmptyStorageProof {
+    /// The address of the account.
+    pub address: Address,
+    /// The balance of the account.
+    pub balance: U256,
+    /// The nonce of the account.
+    #[cfg_attr(feature = "serde", serde(with = "crate::account::quantity"))]
+    pub nonce: u64,
+    /// The code hash of the account.
+    pub code_hash: B256,
+    /// The extra fields of the account, see [TrieAccount::extension].
+    #[cfg(feature = "account-extensions")]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "AccountExtension::is_none")
+    )]
+    pub extension: AccountExtension,
+    /// The account proof nodes ordered from the state root.
+    #[cfg_attr(feature = "serde", serde(rename = "accountProof"))]
+    pub proof: Vec<Bytes>,
+}
+
+impl EmptyStorageProof {
+    /// Returns the account as stored in the account trie.
+    pub fn account(&self) -> TrieAccount {
+        TrieAccount {
+            nonce: self.nonce,
+            balance: self.balance,
+            storage_root: EMPTY_ROOT_HASH,
+            code_hash: self.code_hash,
+            #[cfg(feature = "account-extensions")]
+            extension: self.extension,
+        }
+    }
+
+    /// Verifies that the account with the empty storage root is included in the state root.
+    pub fn verify(&self, root: B256) -> Result<(), ProofVerificationError> {
+        verify_proof(
+            root,
+            Nibbles::unpack(keccak256(self.address)),
+            Some(alloy_rlp::encode(self.account())),
+            false,
+            &self.proof,
+        )?;
+        Ok(())
+    }
+}
+
+impl AccountProof {
+    /// Converts the proof into an [EmptyStorageProof], or returns [None] if the account does not
+    /// exist or its storage is not empty.
+    pub fn into_empty_storage_proof(self) -> Option<EmptyStorageProof> {
+        self.account()?;
+        is_empty_root(self.storage_root).then_some(EmptyStorageProof {
+            address: self.address,
+            balance: self.balance,
+            nonce: self.nonce,
+            code_hash: self.code_hash,
+            #[cfg(feature = "account-extensions")]
+            extension: self.extension,
+            proof: self.proof,
+        })
+    }
+}
+
+impl MultiProof {
+    /// Builds the [AccountProof] of the given address and storage slots, decoding the account and
+    /// the slot values from the leaves of the multiproof.
+    ///
+    /// The multiproof must target the hashed address and the hashed slots.
+    pub fn account_proof(
+        &self,
+        address: Address,
+        slots: &[B256],
+    ) -> Result<AccountProof, alloy_rlp::Error> {
+        let hashed_address = keccak256(address);
+        let nodes = self.account_proof_nodes(hashed_address);
+
+        let mut account_proof = AccountProof::new(address);
+        if let Some(leaf) = target_leaf(&nodes, &Nibbles::unpack(hashed_address))? {
+            let account = TrieAccount::decode(&mut &leaf.value[..])?;
+            account_proof.balance = account.balance;
+            account_proof.nonce = account.nonce;
+            account_proof.code_hash = account.code_hash;
+            account_proof.storage_root = account.storage_root;
+            #[cfg(feature = "account-extensions")]
+            {
+                account_proof.extension = account.extension;
+            }
+        }
+        account_proof.proof = nodes.into_iter().map(|(_, node)| node).collect();
+
+        let empty = StorageMultiProof::empty();
+        let storage = self.storages.get(&hashed_address).unwrap_or(&empty);
+        for slot in slots {
+            let hashed_slot = keccak256(slot);
+            let nodes = storage.storage_proof_nodes(hashed_slot);
+
+            let mut storage_proof = StorageProof::new(*slot);
+            if let Some(leaf) = target_leaf(&nodes, &Nibbles::unpack(hashed_slot))? {
+                storage_proof.value = U256::decode(&mut &leaf.value[..])?;
+                storage_proof.is_private = leaf.is_private;
+            }
+            storage_proof.proof = nodes.into_iter().map(|(_, node)| node).collect();
+            account_proof.storage_proofs.push(storage_proof);
+        }
+
+        Ok(account_proof)
+    }
+}
+
+/// Builds the [AccountProof] of the given address and storage slots from the stored trie nodes
+/// and the hashed state, as served by `eth_getProof`.
+///
+/// Only the stored nodes on the paths to the account and the slots are rebuilt, the other
+/// subtries are added by their stored hashes. The stored trie nodes must be consistent with the
+/// hashed state.
+pub fn account_proof<T, H>(
+    trie_cursor_factory: T,
+    hashed_cursor_factory: H,
+    address: Address,
+    slots: &[B256],
+) -> Result<AccountProof, DatabaseError>
+where
+    T: TrieCursorFactory + Clone,
+    H: HashedCursorFactory + Clone,
+{
+    let hashed_address = keccak256(address);
+    let mut storage_collector = StorageProofCollector::new(slots);
+    let walker = TrieWalker::new(
+        trie_cursor_factory.storage_trie_cursor(hashed_address)?,
+        expand_paths_to(&storage_collector.targets),
+    )?;
+    let mut storage_node_iter =
+        TrieNodeIter::new(walker, hashed_cursor_factory.hashed_storage_cursor(hashed_address)?);
+    while let Some(element) = storage_node_iter.try_next()? {
+        storage_collector.add(element);
+    }
+
+    let mut collector = AccountProofCollector::new(address, storage_collector.finish(slots));
+    let walker = TrieWalker::new(
+        trie_cursor_factory.account_trie_cursor()?,
+        expand_paths_to(&collector.targets),
+    )?;
+    let mut account_node_iter =
+        TrieNodeIter::new(walker, hashed_cursor_factory.hashed_account_cursor()?);
+    while let Some(mut element) = account_node_iter.try_next()? {
+        if let TrieElement::Leaf(leaf_address, account) = &mut element {
+            if *leaf_address != hashed_address {
+                account.storage_root = StorageRoot::new(
+                    trie_cursor_factory.clone(),
+                    hashed_cursor_factory.clone(),
+                    *leaf_address,
+                )
+                .root()?;
+            }
+        }
+        collector.add(element);
+    }
+
+    Ok(collector.finish())
+}
+
+/// Builds the [EmptyStorageProof] of the given address from the stored trie nodes and the hashed
+/// state, or returns [None] if the account does not exist or its storage is not empty.
+pub fn empty_storage_proof<T, H>(
+    trie_cursor_factory: T,
+    hashed_cursor_factory: H,
+    address: Address,
+) -> Result<Option<EmptyStorageProof>, DatabaseError>
+where
+    T: TrieCursorFactory + Clone,
+    H: HashedCursorFactory + Clone,
+{
+    Ok(account_proof(trie_cursor_factory, hashed_cursor_factory, address, &[])?
+        .into_empty_storage_proof())
+}
+
+/// Collects the [AccountProof] from the elements of the account trie, once the storage proofs
+/// are collected by a [StorageProofCollector].
+///
+/// The elements are read by the caller, so that both the synchronous and the async cursors can
+/// be used.
+#[derive(Debug)]
+pub(crate) struct AccountProofCollector {
+    /// The hashed address of the account.
+    hashed_address: B256,
+    /// The path of the account leaf, as the only proof target.
+    pub(crate) targets: Vec<Nibbles>,
+    /// The hash builder retaining the proof nodes.
+    hash_builder: HashBuilder,
+    /// The storage root of the account.
+    storage_root: B256,
+    /// The proof being collected.
+    account_proof: AccountProof,
+    /// The scratch buffer of the encoded accounts.
+    account_rlp: Vec<u8>,
+}
+
+impl AccountProofCollector {
+    /// Creates a new collector given the storage root of the account and the storage proofs.
+    pub(crate) fn new(address: Address, (storage_root, storage_proofs): StorageProofs) -> Self {
+        let hashed_address = keccak256(address);
+        let targets = Vec::from([Nibbles::unpack(hashed_address)]);
+        let mut account_proof = AccountProof::new(address);
+        // The slots of a nonexistent account are proven against the empty storage root.
+        account_proof.storage_proofs = storage_proofs;
+        Self {
+            hashed_address,
+            storage_root,
+            hash_builder: HashBuilder::default().with_proof_retainer(targets.clone()),
+            targets,
+            account_proof,
+            account_rlp: Vec::new(),
+        }
+    }
+
+    /// Adds the next element of the account trie.
+    ///
+    /// The storage roots of the accounts other than the proven one must already be set.
+    pub(crate) fn add(&mut self, element: TrieElement<TrieAccount>) {
+        match element {
+            TrieElement::Branch { key, hash, children_are_in_trie, is_private } => {
+                self.hash_builder.add_branch_with_privacy(
+                    key,
+                    hash,
+                    children_are_in_trie,
+                    is_private,
+                );
+            }
+            TrieElement::Leaf(leaf_address, mut account) => {
+                if leaf_address == self.hashed_address {
+                    account.storage_root = self.storage_root;
+                    self.account_proof.balance = account.balance;
+                    self.account_proof.nonce = account.nonce;
+                    self.account_proof.code_hash = account.code_hash;
+                    self.account_proof.storage_root = self.storage_root;
+                    #[cfg(feature = "account-extensions")]
+                    {
+                        self.account_proof.extension = account.extension;
+                    }
+                }
+
+                self.account_rlp.clear();
+                account.encode_into(&mut self.account_rlp);
+                // account nodes are always public
+                self.hash_builder.add_leaf(Nibbles::unpack(leaf_address), &self.account_rlp, false);
+            }
+        }
+    }
+
+    /// Returns the collected proof.
+    pub(crate) fn finish(mut self) -> AccountProof {
+        self.hash_builder.root();
+        self.account_proof.proof =
+            self.hash_builder.take_proof_nodes().matching_nodes_for(&self.targets[0]);
+        self.account_proof
+    }
+}
+
+/// The storage root of an account along with the proofs of some of its slots.
+pub(crate) type StorageProofs = (B256, Vec<StorageProof>);
+
+/// Collects the storage root of an account and the proofs of the given slots from the elements
+/// of its storage trie, see [AccountProofCollector].
+#[derive(Debug)]
+pub(crate) struct StorageProofCollector {
+    /// The hashed slots to prove.
+    hashed_slots: HashSet<B256>,
+    /// The paths of the hashed slots.
+    pub(crate) targets: Vec<Nibbles>,
+    /// The hash builder retaining the proof nodes.
+    hash_builder: HashBuilder,
+    /// The values of the proven slots along with their privacy.
+    values: HashMap<B256, (U256, bool)>,
+}
+
+impl StorageProofCollector {
+    /// Creates a new collector of the proofs of the given slots.
+    pub(crate) fn new(slots: &[B256]) -> Self {
+        let hashed_slots = HashSet::<B256>::from_iter(slots.iter().map(keccak256));
+        let targets = Vec::from_iter(hashed_slots.iter().map(Nibbles::unpack));
+        Self {
+            hashed_slots,
+            hash_builder: HashBuilder::default().with_proof_retainer(targets.clone()),
+            targets,
+            values: HashMap::default(),
+        }
+    }
+
+    /// Adds the next element of the storage trie.
+    pub(crate) fn add(&mut self, element: TrieElement<(U256, bool)>) {
+        match element {
+            TrieElement::Branch { key, hash, children_are_in_trie, is_private } => {
+                self.hash_builder.add_branch_with_privacy(
+                    key,
+                    hash,
+                    children_are_in_trie,
+                    is_private,
+                );
+            }
+            TrieElement::Leaf(hashed_slot, (value, is_private)) => {
+                self.hash_builder.add_leaf(
+                    Nibbles::unpack(hashed_slot),
+                    encode_fixed_size(&value).as_ref(),
+                    is_private,
+                );
+                if self.hashed_slots.contains(&hashed_slot) {
+                    self.values.insert(hashed_slot, (value, is_private));
+                }
+            }
+        }
+    }
+
+    /// Returns the storage root and the proofs of the given slots, in order.
+    pub(crate) fn finish(mut self, slots: &[B256]) -> StorageProofs {
+        let root = self.hash_builder.root();
+        let proof_nodes = self.hash_builder.take_proof_nodes();
+        let storage_proofs = slots
+            .iter()
+            .map(|slot| {
+                let hashed_slot = keccak256(slot);
+                let mut storage_proof = StorageProof::new(*slot);
+                if let Some(&(value, is_private)) = self.values.get(&hashed_slot) {
+                    storage_proof.value = value;
+                    storage_proof.is_private = is_private;
+                }
+                storage_proof.proof = proof_nodes.matching_nodes_for(&Nibbles::unpack(hashed_slot));
+                storage_proof
+            })
+            .collect();
+        (root, storage_proofs)
+    }
+}
+
+/// Returns the leaf of the target if it is the last of the sorted proof nodes.
+fn target_leaf(
+    nodes: &[(Nibbles, Bytes)],
+    target: &Nibbles,
+) -> Result<Option<LeafNode>, alloy_rlp::Error> {
+    let Some((path, node)) = nodes.last() else { return Ok(None) };
+    match TrieNode::decode(&mut &node[..])? {
+        TrieNode::Leaf(leaf) if path.join(&leaf.key) == *target => Ok(Some(leaf)),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cursor::in_memory::{InMemoryHashedState, InMemoryTrieNodes},
+        root::storage_root,
+        state_root::StateRoot,
+    };
+    use alloc::collections::BTreeMap;
+    use alloy_primitives::address;
+
+    #[test]
+    fn account_proof_roundtrip() {
+        let address = address!("0x000000000000000000000000000000000000dead");
+        let slots = (1..=16u8).map(B256::with_last_byte).collect::<Vec<_>>();
+        let mut storage = slots
+            .iter()
+            .map(|slot| {
+                let value = U256::from(slot[31]);
+                (keccak256(slot), encode_fixed_size(&value).to_vec(), slot[31] % 2 == 0)
+            })
+            .collect::<Vec<_>>();
+        storage.sort_unstable_by_key(|(hashed_slot, ..)| *hashed_slot);
+        let missing_slot = B256::with_last_byte(0xff);
+        let storage = StorageMultiProof::from_sorted_leaves(
+            storage,
+            [keccak256(slots[1]), keccak256(slots[2]), keccak256(missing_slot)],
+        );
+
+        let account = TrieAccount { nonce: 3, storage_root: storage.root, ..Default::default() };
+        let mut accounts = (1..=32u8)
+            .map(|i| {
+                let other = TrieAccount { nonce: u64::from(i), ..Default::default() };
+                (keccak256(Address::repeat_byte(i)), alloy_rlp::encode(other))
+            })
+            .chain([(keccak256(address), alloy_rlp::encode(account))])
+            .collect::<Vec<_>>();
+        accounts.sort_unstable_by_key(|(hashed_address, _)| *hashed_address);
+        let missing_address = Address::repeat_byte(0xff);
+        let (root, mut multiproof) = MultiProof::from_sorted_accounts(
+            accounts,
+            [keccak256(address), keccak256(missing_address)],
+        );
+        multiproof.storages.insert(keccak256(address), storage);
+
+        let account_proof =
+            multiproof.account_proof(address, &[slots[1], slots[2], missing_slot]).unwrap();
+        assert_eq!(account_proof.account(), Some(account));
+        assert_eq!(account_proof.storage_proofs[0].value, U256::from(2));
+        assert!(account_proof.storage_proofs[0].is_private);
+        assert_eq!(account_proof.storage_proofs[1].value, U256::from(3));
+        assert!(!account_proof.storage_proofs[1].is_private);
+        assert_eq!(account_proof.storage_proofs[2].value, U256::ZERO);
+        assert_eq!(account_proof.verify(root), Ok(()));
+        let storage_root = account_proof.storage_root;
+        assert_eq!(
+            account_proof.storage_proofs[0].verify(storage_root),
+            Ok(ProofVerificationOutcome::IncludedPrivate)
+        );
+        assert_eq!(
+            account_proof.storage_proofs[1].verify(storage_root),
+            Ok(ProofVerificationOutcome::Included)
+        );
+        assert_eq!(
+            account_proof.storage_proofs[2].verify(storage_root),
+            Ok(ProofVerificationOutcome::Excluded)
+        );
+
+        // Flipping the privacy flag of a slot fails the verification.
+        let mut tampered = account_proof;
+        tampered.storage_proofs[0].is_private = false;
+        assert!(tampered.verify(root).is_err());
+
+        let missing = multiproof.account_proof(missing_address, &[]).unwrap();
+        assert_eq!(missing.account(), None);
+        assert_eq!(missing.verify(root), Ok(()));
+    }
+
+    #[test]
+    fn account_proof_from_cursors() {
+        let mut state = InMemoryHashedState::default();
+        for i in 0..50u8 {
+            let hashed_address = keccak256(Address::repeat_byte(i));
+            state
+                .accounts
+                .insert(hashed_address, TrieAccount { nonce: i.into(), ..Default::default() });
+            state.storages.insert(
+                hashed_address,
+                BTreeMap::from_iter((1..=20u8).map(|slot| {
+                    (keccak256(B256::with_last_byte(slot)), (U256::from(i + slot), slot % 2 == 0))
+                })),
+            );
+        }
+
+        let mut nodes = InMemoryTrieNodes::default();
+        let (root, updates) = StateRoot::new(&nodes, &state).root_with_updates().unwrap();
+        nodes.account_nodes.extend(updates.account_nodes.updated_nodes);
+        for (hashed_address, storage_updates) in updates.storage_tries {
+            nodes
+                .storage_nodes
+                .entry(hashed_address)
+                .or_default()
+                .extend(storage_updates.updated_nodes);
+        }
+
+        let address = Address::repeat_byte(7);
+        let slots = [B256::with_last_byte(2), B256::with_last_byte(3), B256::with_last_byte(0xff)];
+        let proof = account_proof(&nodes, &state, address, &slots).unwrap();
+        assert_eq!(proof.verify(root), Ok(()));
+
+        // Same as the proof built from the leaves of the whole state.
+        let accounts = Vec::from_iter(state.accounts.iter().map(|(hashed_address, account)| {
+            let storage_root = storage_root(state.storages[hashed_address].clone());
+            (*hashed_address, alloy_rlp::encode(TrieAccount { storage_root, ..*account }))
+        }));
+        let (_, mut multiproof) = MultiProof::from_sorted_accounts(accounts, [keccak256(address)]);
+        multiproof.storages.insert(
+            keccak256(address),
+            StorageMultiProof::from_sorted_leaves(
+                state.storages[&keccak256(address)].iter().map(
+                    |(hashed_slot, (value, is_private))| {
+                        (*hashed_slot, encode_fixed_size(value), *is_private)
+                    },
+                ),
+                slots.iter().map(keccak256),
+            ),
+        );
+        assert_eq!(proof, multiproof.account_proof(address, &slots).unwrap());
+        assert!(proof.storage_proofs[0].is_private);
+        assert_eq!(proof.storage_proofs[1].value, U256::from(10));
+
+        let missing = account_proof(&nodes, &state, Address::repeat_byte(0xff), &slots).unwrap();
+        assert_eq!(missing.account(), None);
+        assert_eq!(missing.verify(root), Ok(()));
+    }
+
+    #[test]
+    fn empty_storage_proof_from_cursors() {
+        let mut state = InMemoryHashedState::default();
+        for i in 0..20u8 {
+            let hashed_address = keccak256(Address::repeat_byte(i));
+            state
+                .accounts
+                .insert(hashed_address, TrieAccount { nonce: i.into(), ..Default::default() });
+            if i % 2 == 0 {
+                state.storages.insert(
+                    hashed_address,
+                    BTreeMap::from([(keccak256(B256::with_last_byte(1)), (U256::from(i), true))]),
+                );
+            }
+        }
+        let nodes = InMemoryTrieNodes::default();
+        let root = StateRoot::new(&nodes, &state).root().unwrap();
+
+        let proof = empty_storage_proof(&nodes, &state, Address::repeat_byte(3)).unwrap().unwrap();
+        assert_eq!(proof.nonce, 3);
+        assert_eq!(proof.verify(root), Ok(()));
+
+        // An account with storage, or a missing one, has no empty storage proof.
+        assert_eq!(empty_storage_proof(&nodes, &state, Address::repeat_byte(2)).unwrap(), None);
+        assert_eq!(empty_storage_proof(&nodes, &state, Address::repeat_byte(0xff)).unwrap(), None);
+
+        // The proof does not verify for a different account state.
+        let mut tampered = proof;
+        tampered.nonce += 1;
+        assert!(tampered.verify(root).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "account-extensions")]
+    fn extended_account_proof() {
+        let mut state = InMemoryHashedState::default();
+        for i in 0..20u8 {
+            let account = TrieAccount { nonce: i.into(), ..Default::default() }
+                .with_extension(AccountExtension::CodeSize(u64::from(i) * 100));
+            state.accounts.insert(keccak256(Address::repeat_byte(i)), account);
+        }
+        let nodes = InMemoryTrieNodes::default();
+        let root = StateRoot::new(&nodes, &state).root().unwrap();
+
+        let address = Address::repeat_byte(5);
+        let proof = account_proof(&nodes, &state, address, &[]).unwrap();
+        assert_eq!(proof.extension, AccountExtension::CodeSize(500));
+        assert_eq!(proof.account(), Some(state.accounts[&keccak256(address)]));
+        assert_eq!(proof.verify(root), Ok(()));
+
+        let accounts = Vec::from_iter(
+            state
+                .accounts
+                .iter()
+                .map(|(hashed_address, account)| (*hashed_address, alloy_rlp::encode(account))),
+        );
+        let (_, multiproof) = MultiProof::from_sorted_accounts(accounts, [keccak256(address)]);
+        assert_eq!(multiproof.account_proof(address, &[]).unwrap(), proof);
+
+        let empty_storage_proof = proof.into_empty_storage_proof().unwrap();
+        assert_eq!(empty_storage_proof.verify(root), Ok(()));
+
+        // The proof does not verify for a different extension.
+        let mut tampered = empty_storage_proof;
+        tampered.extension = AccountExtension::None;
+        assert!(tampered.verify(root).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::sparse::SparseTrieError;
 use alloy_primitives::{Bytes, B256};
 use core::fmt;
 use nybbles::Nibbles;
@@ -27,6 +28,46 @@ pub enum ProofVerificationError {
     },
     /// Encountered unexpected empty root node.
     UnexpectedEmptyRoot,
+    /// The range proof is malformed.
+    InvalidRangeProof(&'static str),
+    /// A proof node is malformed or padded.
+    MalformedNode(&'static str),
+    /// The proof has more nodes than allowed by the limits.
+    TooManyNodes {
+        /// Number of nodes in the proof, counted up to the first node over the limit.
+        got: usize,
+        /// Maximum number of nodes.
+        max: usize,
+    },
+    /// The proven path is longer than allowed by the limits.
+    PathTooDeep {
+        /// Length of the path in nibbles.
+        got: usize,
+        /// Maximum length of the path in nibbles.
+        max: usize,
+    },
+    /// The expected value or a proof node is larger than allowed by the limits.
+    ValueTooLarge {
+        /// Length of the value in bytes.
+        got: usize,
+        /// Maximum length of the value in bytes.
+        max: usize,
+    },
+    /// A node needed to apply an update is not in the proof, e.g. the sibling a branch node
+    /// collapses into when a leaf is removed.
+    MissingNode {
+        /// Path of the missing node.
+        path: Nibbles,
+        /// Hash of the missing node.
+        hash: B256,
+    },
+    /// A storage slot is verified without the account, which commits to the storage root.
+    MissingAccount {
+        /// Hashed address of the account.
+        hashed_address: B256,
+    },
+    /// The proof nodes do not form a trie.
+    InvalidTrie(SparseTrieError),
     /// Error during RLP decoding of trie node.
     Rlp(alloy_rlp::Error),
 }
@@ -58,11 +99,45 @@ impl fmt::Display for ProofVerificationError {
             Self::UnexpectedEmptyRoot => {
                 write!(f, "unexpected empty root node")
             }
+            Self::InvalidRangeProof(reason) => {
+                write!(f, "invalid range proof: {reason}")
+            }
+            Self::MalformedNode(reason) => {
+                write!(f, "malformed proof node: {reason}")
+            }
+            Self::TooManyNodes { got, max } => {
+                write!(f, "proof has at least {got} nodes, at most {max} allowed")
+            }
+            Self::PathTooDeep { got, max } => {
+                write!(f, "proven path has {got} nibbles, at most {max} allowed")
+            }
+            Self::ValueTooLarge { got, max } => {
+                write!(f, "proof value has {got} bytes, at most {max} allowed")
+            }
+            Self::MissingNode { path, hash } => {
+                write!(f, "missing proof node at path {path:?} with hash {hash}")
+            }
+            Self::MissingAccount { hashed_address } => {
+                write!(f, "storage of account {hashed_address} verified without the account")
+            }
+            Self::InvalidTrie(error) => {
+                write!(f, "invalid proof trie: {error}")
+            }
             Self::Rlp(error) => fmt::Display::fmt(error, f),
         }
     }
 }
 
+impl From<SparseTrieError> for ProofVerificationError {
+    fn from(source: SparseTrieError) -> Self {
+        match source {
+            SparseTrieError::BlindedNode { path, hash } => Self::MissingNode { path, hash },
+            SparseTrieError::Rlp(error) => Self::Rlp(error),
+            error => Self::InvalidTrie(error),
+        }
+    }
+}
+
 impl From<alloy_rlp::Error> for ProofVerificationError {
     fn from(source: alloy_rlp::Error) -> Self {
         Self::Rlp(source)
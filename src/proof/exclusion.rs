@@ -0,0 +1,83 @@
+use crate::{
+    proof::{verify_proof, ProofRetainer, ProofVerificationError},
+    HashBuilder, Nibbles,
+};
+use alloc::vec::Vec;
+use alloy_primitives::{Bytes, B256};
+
+/// Computes the root from sorted leaves and returns it along with the proof that the given key is
+/// not in the trie, e.g. that an account is not in the state.
+///
+/// The proof is made of the nodes on the path to the key, from the root down to the node where
+/// the trie diverges from the key. Returns [None] if the key is one of the leaves.
+///
+/// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+///
+/// # Panics
+///
+/// If the leaves are not in sorted order.
+pub fn exclusion_proof<I, V>(leaves: I, key: B256) -> Option<(B256, Vec<Bytes>)>
+where
+    I: IntoIterator<Item = (B256, V, bool)>,
+    V: AsRef<[u8]>,
+{
+    let target = Nibbles::unpack(key);
+    let mut hb =
+        HashBuilder::default().with_proof_retainer(ProofRetainer::from_iter([target.clone()]));
+    for (leaf_key, value, is_private) in leaves {
+        if leaf_key == key {
+            return None;
+        }
+        hb.add_leaf(Nibbles::unpack(leaf_key), value.as_ref(), is_private);
+    }
+
+    let root = hb.root();
+    let proof = hb.take_proof_nodes().matching_nodes_for(&target);
+    Some((root, proof))
+}
+
+/// Verifies the proof that the given key is not in the trie with the given root, see
+/// [exclusion_proof].
+pub fn verify_exclusion_proof<'a, I>(
+    root: B256,
+    key: B256,
+    proof: I,
+) -> Result<(), ProofVerificationError>
+where
+    I: IntoIterator<Item = &'a Bytes>,
+{
+    verify_proof(root, Nibbles::unpack(key), None, false, proof).map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EMPTY_ROOT_HASH;
+    use alloy_primitives::{keccak256, U256};
+
+    #[test]
+    fn exclusion_proofs() {
+        let mut leaves = (0..100u64)
+            .map(|i| (keccak256(i.to_be_bytes()), alloy_rlp::encode(U256::from(i)), i % 3 == 0))
+            .collect::<Vec<_>>();
+        leaves.sort_unstable_by_key(|(key, ..)| *key);
+
+        for i in 100..120u64 {
+            let key = keccak256(i.to_be_bytes());
+            let (root, proof) = exclusion_proof(leaves.iter().cloned(), key).unwrap();
+            assert_eq!(verify_exclusion_proof(root, key, &proof), Ok(()));
+        }
+
+        // A leaf of the trie can not be proven absent, private or not.
+        for (key, ..) in leaves.iter().take(3) {
+            assert_eq!(exclusion_proof(leaves.iter().cloned(), *key), None);
+            let (root, proof) = crate::proof::range_proof(leaves.iter().cloned(), *key, *key);
+            assert!(verify_exclusion_proof(root, *key, &proof.left_proof).is_err());
+        }
+
+        let (root, proof) =
+            exclusion_proof(Vec::<(B256, Vec<u8>, bool)>::new(), B256::ZERO).unwrap();
+        assert_eq!(root, EMPTY_ROOT_HASH);
+        assert_eq!(verify_exclusion_proof(root, B256::ZERO, &proof), Ok(()));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    proof::{verify_proof, ProofVerificationError, ProofVerificationOutcome},
+    Nibbles,
+};
+use alloc::vec::Vec;
+use alloy_primitives::{Bytes, B256};
+
+/// Limits on the size of the proofs received from untrusted sources, bounding the work done to
+/// verify them.
+///
+/// The default limits fit the proofs of the 32-byte hashed keys of the state and storage tries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofLimits {
+    /// Maximum number of proof nodes.
+    pub max_nodes: usize,
+    /// Maximum length in nibbles of the proven path.
+    pub max_depth: usize,
+    /// Maximum length in bytes of the expected value and of every proof node.
+    pub max_value_size: usize,
+}
+
+impl Default for ProofLimits {
+    fn default() -> Self {
+        Self { max_nodes: 65, max_depth: 64, max_value_size: 1024 }
+    }
+}
+
+impl ProofLimits {
+    /// Checks the key, the expected value and the proof nodes against the limits.
+    pub fn check<'a, I>(
+        &self,
+        key: &Nibbles,
+        expected_value: Option<&[u8]>,
+        proof: I,
+    ) -> Result<(), ProofVerificationError>
+    where
+        I: IntoIterator<Item = &'a Bytes>,
+    {
+        if key.len() > self.max_depth {
+            return Err(ProofVerificationError::PathTooDeep {
+                got: key.len(),
+                max: self.max_depth,
+            });
+        }
+        self.check_size(expected_value.map_or(0, <[u8]>::len))?;
+        for (index, node) in proof.into_iter().enumerate() {
+            // Stop at the first node over the limit instead of walking the whole proof.
+            if index == self.max_nodes {
+                return Err(ProofVerificationError::TooManyNodes {
+                    got: index + 1,
+                    max: self.max_nodes,
+                });
+            }
+            self.check_size(node.len())?;
+        }
+        Ok(())
+    }
+
+    fn check_size(&self, len: usize) -> Result<(), ProofVerificationError> {
+        if len > self.max_value_size {
+            return Err(ProofVerificationError::ValueTooLarge {
+                got: len,
+                max: self.max_value_size,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Verifies the proof like [verify_proof], after checking it against the limits so that proofs
+/// received from untrusted sources are rejected before being decoded.
+pub fn verify_proof_with_limits<'a, I>(
+    root: B256,
+    key: Nibbles,
+    expected_value: Option<Vec<u8>>,
+    expected_is_private: bool,
+    proof: I,
+    limits: &ProofLimits,
+) -> Result<ProofVerificationOutcome, ProofVerificationError>
+where
+    I: IntoIterator<Item = &'a Bytes>,
+    I::IntoIter: Clone,
+{
+    let proof = proof.into_iter();
+    limits.check(&key, expected_value.as_deref(), proof.clone())?;
+    verify_proof(root, key, expected_value, expected_is_private, proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{proof::ProofRetainer, HashBuilder};
+    use alloy_primitives::{hex, keccak256};
+    use alloy_rlp::EMPTY_STRING_CODE;
+
+    #[test]
+    fn reject_proofs_over_limits() {
+        let mut leaves = (0..64u8).map(|i| (keccak256([i]), [i; 32])).collect::<Vec<_>>();
+        leaves.sort_unstable_by_key(|(key, _)| *key);
+        let target = Nibbles::unpack(leaves[3].0);
+        let mut hb =
+            HashBuilder::default().with_proof_retainer(ProofRetainer::from_iter([target.clone()]));
+        for (key, value) in &leaves {
+            hb.add_leaf(Nibbles::unpack(key), value, false);
+        }
+        let root = hb.root();
+        let proof = hb.take_proof_nodes().matching_nodes_for(&target);
+        let value = Some(leaves[3].1.to_vec());
+
+        let limits = ProofLimits::default();
+        assert_eq!(
+            verify_proof_with_limits(root, target.clone(), value.clone(), false, &proof, &limits),
+            Ok(ProofVerificationOutcome::Included)
+        );
+
+        let padded = Vec::from_iter(proof.iter().cycle().take(limits.max_nodes + 1).cloned());
+        assert_eq!(
+            verify_proof_with_limits(root, target.clone(), value.clone(), false, &padded, &limits),
+            Err(ProofVerificationError::TooManyNodes { got: 66, max: 65 })
+        );
+        // The nodes past the limit are not read.
+        assert_eq!(
+            limits.check(&target, None, core::iter::repeat(&proof[0])),
+            Err(ProofVerificationError::TooManyNodes { got: 66, max: 65 })
+        );
+
+        let deep = target.join(&target);
+        assert_eq!(
+            verify_proof_with_limits(root, deep, value.clone(), false, &proof, &limits),
+            Err(ProofVerificationError::PathTooDeep { got: 128, max: 64 })
+        );
+
+        let small = ProofLimits { max_value_size: 16, ..limits };
+        assert_eq!(
+            verify_proof_with_limits(root, target.clone(), value, false, &proof, &small),
+            Err(ProofVerificationError::ValueTooLarge { got: 32, max: 16 })
+        );
+        let large_node = Bytes::from(vec![0; 2048]);
+        assert_eq!(
+            verify_proof_with_limits(root, target, None, false, [&large_node], &limits),
+            Err(ProofVerificationError::ValueTooLarge { got: 2048, max: 1024 })
+        );
+    }
+
+    #[test]
+    fn malformed_proof_within_limits() {
+        // A branch node with an in-place extension node pointing to an in-place leaf node is
+        // within the limits, and rejected instead of panicking once decoded.
+        let mut branch = vec![0xd5, EMPTY_STRING_CODE];
+        branch.extend(hex!("c41ac22001"));
+        branch.extend([EMPTY_STRING_CODE; 15]);
+        let root = keccak256(&branch);
+        let proof = [Bytes::from(branch)];
+
+        let key = Nibbles::from_nibbles([0x1, 0xa]);
+        assert!(matches!(
+            verify_proof_with_limits(root, key, None, false, &proof, &ProofLimits::default()),
+            Err(ProofVerificationError::MalformedNode(_))
+        ));
+    }
+}
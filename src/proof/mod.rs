@@ -4,7 +4,10 @@
 use alloc::vec::Vec;
 
 mod verify;
-pub use verify::verify_proof;
+pub use verify::{verify_proof, ProofVerificationOutcome};
+
+mod verify_no_alloc;
+pub use verify_no_alloc::verify_proof_no_alloc;
 
 mod error;
 pub use error::ProofVerificationError;
@@ -15,6 +18,39 @@ pub use decoded_proof_nodes::DecodedProofNodes;
 mod decoded_retainer;
 pub use decoded_retainer::DecodedProofRetainer;
 
+mod multiproof;
+pub use multiproof::{verify_multiproof, MultiProof, StorageMultiProof};
+
+#[cfg(feature = "ethereum")]
+mod account_proof;
+#[cfg(feature = "ethereum")]
+pub use account_proof::{
+    account_proof, empty_storage_proof, AccountProof, EmptyStorageProof, StorageProof,
+};
+#[cfg(all(feature = "async", feature = "ethereum"))]
+pub(crate) use account_proof::{AccountProofCollector, StorageProofCollector};
+
+mod exclusion;
+pub use exclusion::{exclusion_proof, verify_exclusion_proof};
+
+mod limits;
+pub use limits::{verify_proof_with_limits, ProofLimits};
+
+mod normalize;
+pub use normalize::{check_proof_nodes, normalize_proof};
+
+mod ordered;
+pub use ordered::{ordered_trie_proof, ordered_trie_proof_with_encoder, verify_ordered_trie_proof};
+
+mod range;
+pub use range::{range_proof, verify_range_proof, RangeProof};
+
+mod subtree;
+pub use subtree::subtree_root_from_proof;
+
+mod update;
+pub use update::{compute_root_after_update, compute_root_after_updates};
+
 mod proof_nodes;
 pub use proof_nodes::ProofNodes;
 
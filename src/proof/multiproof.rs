@@ -0,0 +1,490 @@
+use crate::{
+    proof::{
+        compute_root_after_updates, verify_proof, ProofNodes, ProofRetainer,
+        ProofVerificationError, ProofVerificationOutcome,
+    },
+    HashBuilder, HashMap, Nibbles, EMPTY_ROOT_HASH,
+};
+use alloy_primitives::{Bytes, B256, U256};
+use alloy_rlp::{Decodable, Header, EMPTY_STRING_CODE};
+
+use alloc::vec::Vec;
+
+/// The state multiproof of target accounts and multiproofs of their storage tries.
+///
+/// Proof nodes are keyed by their path in the trie, so interior nodes shared between several
+/// targets are only stored once.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiProof {
+    /// State trie multiproof for requested accounts.
+    pub account_subtree: ProofNodes,
+    /// Storage trie multiproofs keyed by hashed account address.
+    pub storages: HashMap<B256, StorageMultiProof>,
+}
+
+impl MultiProof {
+    /// Creates a new multiproof from the account subtree and storage multiproofs.
+    pub const fn new(
+        account_subtree: ProofNodes,
+        storages: HashMap<B256, StorageMultiProof>,
+    ) -> Self {
+        Self { account_subtree, storages }
+    }
+
+    /// Computes the state root from sorted account leaves and retains the proof nodes for the
+    /// given hashed addresses. Storage multiproofs should be inserted into [MultiProof::storages]
+    /// separately.
+    ///
+    /// Returns the state root along with the multiproof.
+    ///
+    /// # Panics
+    ///
+    /// If the accounts are not in sorted order.
+    pub fn from_sorted_accounts<I, V>(
+        accounts: I,
+        targets: impl IntoIterator<Item = B256>,
+    ) -> (B256, Self)
+    where
+        I: IntoIterator<Item = (B256, V)>,
+        V: AsRef<[u8]>,
+    {
+        let (root, account_subtree) = build_with_proofs(
+            accounts.into_iter().map(|(hashed_address, account)| (hashed_address, account, false)),
+            targets,
+        );
+        (root, Self { account_subtree, storages: HashMap::default() })
+    }
+
+    /// Returns `true` if the multiproof does not contain any account or storage proof nodes.
+    pub fn is_empty(&self) -> bool {
+        self.account_subtree.is_empty() && self.storages.is_empty()
+    }
+
+    /// Returns the sorted account proof nodes on the path to the given hashed address.
+    pub fn account_proof_nodes(&self, hashed_address: B256) -> Vec<(Nibbles, Bytes)> {
+        self.account_subtree.matching_nodes_sorted(&Nibbles::unpack(hashed_address))
+    }
+
+    /// Computes the state root after applying the account updates, given as
+    /// `(hashed_address, new_account_rlp)` pairs with [None] removing the account, to the state
+    /// trie with the given root. See [compute_root_after_updates] for more info.
+    pub fn state_root_after_updates(
+        &self,
+        root: B256,
+        accounts: impl IntoIterator<Item = (B256, Option<Vec<u8>>)>,
+    ) -> Result<B256, ProofVerificationError> {
+        compute_root_after_updates(
+            root,
+            &self.account_subtree,
+            accounts.into_iter().map(|(hashed_address, account)| {
+                // account nodes are always public
+                (Nibbles::unpack(hashed_address), account.map(|account| (account, false)))
+            }),
+        )
+    }
+
+    /// Extends this multiproof with another one, merging the storage multiproofs.
+    ///
+    /// Returns [ProofVerificationError::RootMismatch] and leaves this multiproof untouched if the
+    /// storage multiproofs of an account have different roots.
+    pub fn extend(&mut self, other: Self) -> Result<(), ProofVerificationError> {
+        for (hashed_address, storage) in &other.storages {
+            if let Some(existing) = self.storages.get(hashed_address) {
+                if existing.root != storage.root {
+                    return Err(ProofVerificationError::RootMismatch {
+                        got: storage.root,
+                        expected: existing.root,
+                    });
+                }
+            }
+        }
+
+        self.account_subtree.extend_from(other.account_subtree);
+        for (hashed_address, storage) in other.storages {
+            match self.storages.get_mut(&hashed_address) {
+                Some(existing) => {
+                    existing.subtree.extend_from(storage.subtree);
+                }
+                None => {
+                    self.storages.insert(hashed_address, storage);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The merkle multiproof of a storage trie.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StorageMultiProof {
+    /// Storage trie root.
+    pub root: B256,
+    /// Storage multiproof for requested slots.
+    pub subtree: ProofNodes,
+}
+
+impl Default for StorageMultiProof {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl StorageMultiProof {
+    /// Creates a new storage multiproof from the storage root and the proof nodes.
+    pub const fn new(root: B256, subtree: ProofNodes) -> Self {
+        Self { root, subtree }
+    }
+
+    /// Creates a multiproof of an empty storage trie.
+    pub fn empty() -> Self {
+        Self {
+            root: EMPTY_ROOT_HASH,
+            subtree: ProofNodes::from_iter([(
+                Nibbles::default(),
+                Bytes::from_static(&[EMPTY_STRING_CODE]),
+            )]),
+        }
+    }
+
+    /// Computes the storage root from sorted storage leaves and retains the proof nodes for the
+    /// given hashed slots.
+    ///
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    ///
+    /// # Panics
+    ///
+    /// If the leaves are not in sorted order.
+    pub fn from_sorted_leaves<I, V>(leaves: I, targets: impl IntoIterator<Item = B256>) -> Self
+    where
+        I: IntoIterator<Item = (B256, V, bool)>,
+        V: AsRef<[u8]>,
+    {
+        let (root, subtree) = build_with_proofs(leaves, targets);
+        Self { root, subtree }
+    }
+
+    /// Returns the sorted storage proof nodes on the path to the given hashed slot.
+    pub fn storage_proof_nodes(&self, hashed_slot: B256) -> Vec<(Nibbles, Bytes)> {
+        self.subtree.matching_nodes_sorted(&Nibbles::unpack(hashed_slot))
+    }
+
+    /// Computes the storage root after applying the updates, given as
+    /// `(hashed_slot, new_value)` pairs with [None] removing the slot.
+    /// See [compute_root_after_updates] for more info.
+    ///
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn root_after_updates(
+        &self,
+        updates: impl IntoIterator<Item = (B256, Option<(Vec<u8>, bool)>)>,
+    ) -> Result<B256, ProofVerificationError> {
+        compute_root_after_updates(
+            self.root,
+            &self.subtree,
+            updates.into_iter().map(|(hashed_slot, value)| (Nibbles::unpack(hashed_slot), value)),
+        )
+    }
+
+    /// Verify the storage proof of the given hashed slot against the storage root.
+    pub fn verify(
+        &self,
+        hashed_slot: B256,
+        expected_value: Option<Vec<u8>>,
+        expected_is_private: bool,
+    ) -> Result<ProofVerificationOutcome, ProofVerificationError> {
+        let proof = self.storage_proof_nodes(hashed_slot);
+        verify_proof(
+            self.root,
+            Nibbles::unpack(hashed_slot),
+            expected_value,
+            expected_is_private,
+            proof.iter().map(|(_, node)| node),
+        )
+    }
+}
+
+/// Verify all account and storage targets covered by the multiproof.
+///
+/// Accounts are given as `(hashed_address, expected_account_rlp)` pairs and verified against the
+/// state `root`. Storage slots are given as
+/// `(hashed_address, hashed_slot, expected_value_rlp, expected_is_private)` and verified against
+/// the root of the corresponding storage multiproof, which must be the storage root of the
+/// verified account. The account of every storage slot must thus be verified along, and a missing
+/// storage multiproof is only accepted for an account with an empty storage trie.
+pub fn verify_multiproof<A, S>(
+    root: B256,
+    multiproof: &MultiProof,
+    accounts: A,
+    storages: S,
+) -> Result<(), ProofVerificationError>
+where
+    A: IntoIterator<Item = (B256, Option<Vec<u8>>)>,
+    S: IntoIterator<Item = (B256, B256, Option<Vec<u8>>, bool)>,
+{
+    let mut verified_accounts = HashMap::<_, _>::default();
+    for (hashed_address, expected_account) in accounts {
+        let proof = multiproof.account_proof_nodes(hashed_address);
+        verify_proof(
+            root,
+            Nibbles::unpack(hashed_address),
+            expected_account.clone(),
+            false,
+            proof.iter().map(|(_, node)| node),
+        )?;
+        verified_accounts.insert(hashed_address, expected_account);
+    }
+
+    let empty = StorageMultiProof::empty();
+    for (hashed_address, hashed_slot, expected_value, expected_is_private) in storages {
+        // The storage multiproof is authenticated by the storage root of the verified account.
+        let storage_root = match verified_accounts.get(&hashed_address) {
+            Some(Some(account)) => decode_storage_root(account)?,
+            Some(None) => EMPTY_ROOT_HASH,
+            None => return Err(ProofVerificationError::MissingAccount { hashed_address }),
+        };
+        let storage = match multiproof.storages.get(&hashed_address) {
+            Some(storage) => storage,
+            None if storage_root == EMPTY_ROOT_HASH => &empty,
+            None => {
+                return Err(ProofVerificationError::MissingNode {
+                    path: Nibbles::default(),
+                    hash: storage_root,
+                })
+            }
+        };
+        if storage.root != storage_root {
+            return Err(ProofVerificationError::RootMismatch {
+                got: storage.root,
+                expected: storage_root,
+            });
+        }
+        storage.verify(hashed_slot, expected_value, expected_is_private)?;
+    }
+
+    Ok(())
+}
+
+/// Decodes the storage root of the RLP encoded account, which comes after the nonce and the
+/// balance.
+fn decode_storage_root(mut account: &[u8]) -> alloy_rlp::Result<B256> {
+    let mut fields = Header::decode_bytes(&mut account, true)?;
+    u64::decode(&mut fields)?;
+    U256::decode(&mut fields)?;
+    B256::decode(&mut fields)
+}
+
+/// Computes the root of the trie built from sorted leaves while retaining the proof nodes for
+/// the given targets.
+fn build_with_proofs<I, V>(leaves: I, targets: impl IntoIterator<Item = B256>) -> (B256, ProofNodes)
+where
+    I: IntoIterator<Item = (B256, V, bool)>,
+    V: AsRef<[u8]>,
+{
+    let retainer = ProofRetainer::from_iter(targets.into_iter().map(Nibbles::unpack));
+    let mut hb = HashBuilder::default().with_proof_retainer(retainer);
+    for (hashed_key, value, is_private) in leaves {
+        hb.add_leaf(Nibbles::unpack(hashed_key), value.as_ref(), is_private);
+    }
+    let root = hb.root();
+    (root, hb.take_proof_nodes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account_rlp(nonce: u64, storage_root: B256) -> Vec<u8> {
+        let fields = [
+            alloy_rlp::encode(nonce),
+            alloy_rlp::encode(U256::ZERO),
+            alloy_rlp::encode(storage_root),
+            alloy_rlp::encode(B256::ZERO),
+        ]
+        .concat();
+        let mut account = Vec::new();
+        Header { list: true, payload_length: fields.len() }.encode(&mut account);
+        account.extend(fields);
+        account
+    }
+
+    fn storage_leaves() -> Vec<(B256, Vec<u8>, bool)> {
+        (1..=32u8)
+            .map(|i| {
+                let value = alloy_rlp::encode(U256::from(i));
+                (B256::repeat_byte(i), value, i % 2 == 0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty_storage_multiproof() {
+        let storage = StorageMultiProof::empty();
+        assert_eq!(
+            storage.verify(B256::repeat_byte(1), None, false),
+            Ok(ProofVerificationOutcome::Excluded)
+        );
+        assert!(storage.verify(B256::repeat_byte(1), Some(vec![1]), false).is_err());
+    }
+
+    #[test]
+    fn storage_multiproof_roundtrip() {
+        let leaves = storage_leaves();
+        let targets = [B256::repeat_byte(3), B256::repeat_byte(4), B256::repeat_byte(0xff)];
+        let storage = StorageMultiProof::from_sorted_leaves(leaves.clone(), targets);
+
+        for (slot, value, is_private) in leaves.iter().filter(|(slot, ..)| targets.contains(slot)) {
+            let outcome = storage.verify(*slot, Some(value.clone()), *is_private).unwrap();
+            assert!(outcome.is_included());
+            assert_eq!(outcome.is_private(), *is_private);
+            assert!(storage.verify(*slot, Some(value.clone()), !is_private).is_err());
+        }
+        assert_eq!(
+            storage.verify(B256::repeat_byte(0xff), None, false),
+            Ok(ProofVerificationOutcome::Excluded)
+        );
+    }
+
+    #[test]
+    fn account_multiproof_roundtrip() {
+        let leaves = storage_leaves();
+        let storage = StorageMultiProof::from_sorted_leaves(leaves.clone(), [leaves[1].0]);
+        let storage_root = storage.root;
+        let accounts = (1..=64u8)
+            .map(|i| {
+                let account_storage_root = if i == 7 { storage_root } else { EMPTY_ROOT_HASH };
+                (B256::repeat_byte(i), account_rlp(i.into(), account_storage_root))
+            })
+            .collect::<Vec<_>>();
+        let targets = [B256::repeat_byte(7), B256::repeat_byte(9)];
+        let (root, mut multiproof) =
+            MultiProof::from_sorted_accounts(accounts.iter().cloned(), targets);
+        multiproof.storages.insert(targets[0], storage);
+
+        let expected_accounts =
+            targets.map(|target| (target, Some(accounts[target[0] as usize - 1].1.clone())));
+        let slot = (targets[0], leaves[1].0, Some(leaves[1].1.clone()), leaves[1].2);
+        assert_eq!(
+            verify_multiproof(
+                root,
+                &multiproof,
+                expected_accounts.clone(),
+                [slot.clone(), (targets[1], B256::repeat_byte(1), None, false)],
+            ),
+            Ok(())
+        );
+        assert!(verify_multiproof(
+            root,
+            &multiproof,
+            [(targets[0], Some(vec![0; 40]))],
+            core::iter::empty(),
+        )
+        .is_err());
+
+        // The storage multiproofs are authenticated by the storage roots of the verified accounts.
+        assert_eq!(
+            verify_multiproof(root, &multiproof, core::iter::empty(), [slot.clone()]),
+            Err(ProofVerificationError::MissingAccount { hashed_address: targets[0] })
+        );
+        let mut forged = multiproof.clone();
+        let forged_storage =
+            StorageMultiProof::from_sorted_leaves([leaves[1].clone()], [leaves[1].0]);
+        let forged_root = forged_storage.root;
+        forged.storages.insert(targets[0], forged_storage);
+        assert_eq!(
+            verify_multiproof(root, &forged, expected_accounts.clone(), [slot]),
+            Err(ProofVerificationError::RootMismatch { got: forged_root, expected: storage_root })
+        );
+        let mut missing = multiproof;
+        missing.storages.remove(&targets[0]);
+        assert_eq!(
+            verify_multiproof(
+                root,
+                &missing,
+                expected_accounts,
+                [(targets[0], B256::repeat_byte(0xff), None, false)],
+            ),
+            Err(ProofVerificationError::MissingNode {
+                path: Nibbles::default(),
+                hash: storage_root
+            })
+        );
+    }
+
+    #[test]
+    fn multiproof_roots_after_updates() {
+        let accounts = (1..=64u8).map(|i| (B256::repeat_byte(i), vec![i; 40])).collect::<Vec<_>>();
+        let targets = [B256::repeat_byte(7), B256::repeat_byte(0xff)];
+        let (root, multiproof) =
+            MultiProof::from_sorted_accounts(accounts.iter().cloned(), targets);
+        let mut updated = accounts;
+        updated[6].1 = vec![0; 40];
+        updated.push((B256::repeat_byte(0xff), vec![0xff; 40]));
+        assert_eq!(
+            multiproof.state_root_after_updates(
+                root,
+                [(targets[0], Some(vec![0; 40])), (targets[1], Some(vec![0xff; 40]))]
+            ),
+            Ok(MultiProof::from_sorted_accounts(updated, []).0)
+        );
+
+        let leaves = storage_leaves();
+        let storage = StorageMultiProof::from_sorted_leaves(leaves.clone(), [leaves[1].0]);
+        let mut updated = leaves.clone();
+        updated[1] = (leaves[1].0, vec![0x42], true);
+        assert_eq!(
+            storage.root_after_updates([(leaves[1].0, Some((vec![0x42], true)))]),
+            Ok(StorageMultiProof::from_sorted_leaves(updated, []).root)
+        );
+        assert_eq!(
+            StorageMultiProof::empty().root_after_updates([(leaves[1].0, None)]),
+            Ok(EMPTY_ROOT_HASH)
+        );
+    }
+
+    #[test]
+    fn multiproof_extend_deduplicates_shared_nodes() {
+        let accounts = (1..=64u8).map(|i| (B256::repeat_byte(i), vec![i; 40])).collect::<Vec<_>>();
+        let (root, mut first) =
+            MultiProof::from_sorted_accounts(accounts.iter().cloned(), [B256::repeat_byte(7)]);
+        let (_, second) =
+            MultiProof::from_sorted_accounts(accounts.iter().cloned(), [B256::repeat_byte(9)]);
+        let (_, combined) = MultiProof::from_sorted_accounts(
+            accounts.iter().cloned(),
+            [B256::repeat_byte(7), B256::repeat_byte(9)],
+        );
+
+        first.extend(second).unwrap();
+        assert_eq!(first, combined);
+        assert_eq!(
+            verify_multiproof(
+                root,
+                &first,
+                [(B256::repeat_byte(9), Some(vec![9; 40]))],
+                core::iter::empty()
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn multiproof_extend_rejects_different_storage_roots() {
+        let hashed_address = B256::repeat_byte(7);
+        let mut multiproof = MultiProof::default();
+        multiproof.storages.insert(hashed_address, StorageMultiProof::empty());
+        let mut other = MultiProof::default();
+        let storage =
+            StorageMultiProof::from_sorted_leaves(storage_leaves(), [B256::repeat_byte(1)]);
+        let storage_root = storage.root;
+        other.storages.insert(hashed_address, storage);
+
+        assert_eq!(
+            multiproof.extend(other),
+            Err(ProofVerificationError::RootMismatch {
+                got: storage_root,
+                expected: EMPTY_ROOT_HASH
+            })
+        );
+        assert_eq!(multiproof.storages[&hashed_address], StorageMultiProof::empty());
+    }
+}
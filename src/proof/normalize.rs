@@ -0,0 +1,132 @@
+use crate::{
+    nodes::{RlpNode, TrieNode},
+    proof::{ProofNodes, ProofVerificationError},
+    HashMap, Nibbles,
+};
+use alloc::vec::Vec;
+use alloy_primitives::{keccak256, Bytes, B256};
+use alloy_rlp::Decodable;
+
+/// Normalizes the nodes of a proof received from an untrusted source into the nodes on the paths
+/// from the root, keyed by their path.
+///
+/// Duplicate nodes are kept once, and nodes that are malformed or not reachable from the root,
+/// e.g. padding, are stripped. The nodes embedded in their parents are added at their paths, as
+/// retained by the [HashBuilder](crate::HashBuilder).
+pub fn normalize_proof(root: B256, proof: Vec<Bytes>) -> ProofNodes {
+    let nodes =
+        HashMap::<B256, Bytes>::from_iter(proof.into_iter().map(|node| (keccak256(&node), node)));
+
+    let mut normalized = ProofNodes::default();
+    let mut stack = Vec::from([(Nibbles::default(), RlpNode::word_rlp(&root))]);
+    while let Some((path, child)) = stack.pop() {
+        let node = match child.as_hash() {
+            Some(hash) => match nodes.get(&hash) {
+                Some(node) => node.clone(),
+                None => continue,
+            },
+            None => Bytes::copy_from_slice(&child),
+        };
+        let Ok(trie_node) = decode_checked(&node) else { continue };
+        match trie_node {
+            TrieNode::EmptyRoot | TrieNode::Leaf(_) => {}
+            TrieNode::Extension(extension) => {
+                stack.push((path.join(&extension.key), extension.child));
+            }
+            TrieNode::Branch(branch) => {
+                for (nibble, child) in branch.as_ref().children() {
+                    let Some(child) = child else { continue };
+                    let mut child_path = path.clone();
+                    child_path.push(nibble);
+                    stack.push((child_path, child.clone()));
+                }
+            }
+        }
+        normalized.insert(path, node);
+    }
+    normalized
+}
+
+/// Checks that every node of a proof is a single, canonically encoded trie node, so that
+/// malformed or padded nodes are rejected before verifying the proof.
+pub fn check_proof_nodes<'a, I>(proof: I) -> Result<(), ProofVerificationError>
+where
+    I: IntoIterator<Item = &'a Bytes>,
+{
+    proof.into_iter().try_for_each(|node| decode_checked(node).map(drop))
+}
+
+/// Decodes the trie node, rejecting trailing bytes and non-canonical encodings.
+fn decode_checked(node: &[u8]) -> Result<TrieNode, ProofVerificationError> {
+    let mut buf = node;
+    let trie_node = TrieNode::decode(&mut buf)?;
+    if !buf.is_empty() {
+        return Err(ProofVerificationError::MalformedNode("trailing bytes after the node"));
+    }
+    if alloy_rlp::encode(&trie_node) != node {
+        return Err(ProofVerificationError::MalformedNode("non-canonical node encoding"));
+    }
+    Ok(trie_node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        proof::{verify_proof, ProofRetainer},
+        HashBuilder, EMPTY_ROOT_HASH,
+    };
+
+    #[test]
+    fn normalize_untrusted_proof() {
+        let mut leaves =
+            (0..64u8).map(|i| (keccak256([i]), [i; 32], i % 2 == 0)).collect::<Vec<_>>();
+        leaves.sort_unstable_by_key(|(key, ..)| *key);
+        let targets = [Nibbles::unpack(leaves[5].0), Nibbles::unpack(leaves[40].0)];
+        let mut hb = HashBuilder::default().with_proof_retainer(ProofRetainer::from_iter(targets));
+        for (key, value, is_private) in &leaves {
+            hb.add_leaf(Nibbles::unpack(key), value, *is_private);
+        }
+        let root = hb.root();
+        let proof_nodes = hb.take_proof_nodes();
+        let target = Nibbles::unpack(leaves[5].0);
+        let proof = proof_nodes.matching_nodes_for(&target);
+        assert_eq!(check_proof_nodes(&proof), Ok(()));
+
+        // Shuffled with duplicates, the nodes of another path, a padded node and garbage.
+        let mut padded = proof.last().unwrap().to_vec();
+        padded.push(0);
+        let padded = Bytes::from(padded);
+        let mut received = proof.clone();
+        received.reverse();
+        received.extend(proof.iter().cloned());
+        received.extend(proof_nodes.matching_nodes_for(&Nibbles::unpack(leaves[40].0)));
+        received.push(padded.clone());
+        received.push(Bytes::from_static(&[0xc1]));
+        assert!(check_proof_nodes(&received).is_err());
+        assert_eq!(
+            check_proof_nodes([&padded]),
+            Err(ProofVerificationError::MalformedNode("trailing bytes after the node"))
+        );
+
+        let normalized = normalize_proof(root, received);
+        assert_eq!(
+            normalized.matching_nodes_sorted(&target),
+            proof_nodes.matching_nodes_sorted(&target)
+        );
+        assert_eq!(
+            verify_proof(
+                root,
+                target.clone(),
+                Some(leaves[5].1.to_vec()),
+                leaves[5].2,
+                &normalized.matching_nodes_for(&target),
+            )
+            .map(|outcome| outcome.is_private()),
+            Ok(leaves[5].2)
+        );
+
+        // Nodes not reachable from the root are stripped.
+        assert!(normalize_proof(EMPTY_ROOT_HASH, proof).is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{
+    proof::{verify_proof, ProofRetainer, ProofVerificationError},
+    root::{adjust_index_for_rlp, rlp_index_nibbles},
+    HashBuilder, Nibbles,
+};
+use alloc::vec::Vec;
+use alloy_primitives::{Bytes, B256};
+use alloy_rlp::Encodable;
+
+/// Computes the root of the ordered trie of the rlp encodable items, like
+/// [`ordered_trie_root`](crate::root::ordered_trie_root), and returns it along with the proof of
+/// the item at the given index, e.g. a receipt or a transaction of a block.
+///
+/// The proof is an exclusion proof if the index is out of bounds.
+pub fn ordered_trie_proof<T: Encodable>(items: &[T], index: usize) -> (B256, Vec<Bytes>) {
+    ordered_trie_proof_with_encoder(items, index, |item, buf| item.encode(buf))
+}
+
+/// Computes the root of the ordered trie of the items with a custom encoder, like
+/// [`ordered_trie_root_with_encoder`](crate::root::ordered_trie_root_with_encoder), and returns it
+/// along with the proof of the item at the given index.
+///
+/// The proof is an exclusion proof if the index is out of bounds.
+pub fn ordered_trie_proof_with_encoder<T, F>(
+    items: &[T],
+    index: usize,
+    mut encode: F,
+) -> (B256, Vec<Bytes>)
+where
+    F: FnMut(&T, &mut Vec<u8>),
+{
+    let target = ordered_trie_key(index);
+    let mut hb =
+        HashBuilder::default().with_proof_retainer(ProofRetainer::from_iter([target.clone()]));
+    let mut value_buf = Vec::new();
+    for i in 0..items.len() {
+        value_buf.clear();
+        encode(&items[adjust_index_for_rlp(i, items.len())], &mut value_buf);
+        hb.add_leaf(rlp_index_nibbles(i, items.len()), &value_buf, false);
+    }
+
+    let root = hb.root();
+    let mut proof = hb.take_proof_nodes().matching_nodes_for(&target);
+    // The nodes shorter than a word are encoded in place of their parent, unlike the root.
+    let mut is_root = true;
+    proof.retain(|node| core::mem::take(&mut is_root) || node.len() >= 32);
+    (root, proof)
+}
+
+/// Verifies the proof of the item at the given index of an ordered trie against its root.
+///
+/// The expected value is the encoded item, or [None] to verify that the index is out of bounds.
+pub fn verify_ordered_trie_proof<'a, I>(
+    root: B256,
+    index: usize,
+    expected_value: Option<Vec<u8>>,
+    proof: I,
+) -> Result<(), ProofVerificationError>
+where
+    I: IntoIterator<Item = &'a Bytes>,
+{
+    // ordered tries only hold public leaves
+    verify_proof(root, ordered_trie_key(index), expected_value, false, proof).map(drop)
+}
+
+/// Returns the key of the item at the given index, the nibbles of the RLP encoded index.
+fn ordered_trie_key(index: usize) -> Nibbles {
+    Nibbles::unpack(alloy_rlp::encode_fixed_size(&index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::root::ordered_trie_root;
+
+    #[test]
+    fn ordered_trie_proofs() {
+        for len in [1usize, 2, 0x7f, 0x80, 0x81, 300] {
+            let items = Vec::from_iter((0..len as u64).map(|i| vec![i as u8; (i % 40) as usize]));
+            let root = ordered_trie_root(&items);
+
+            for index in [0, 1, len / 2, 0x7f, 0x80, len - 1] {
+                if index >= len {
+                    continue;
+                }
+                let (proof_root, proof) = ordered_trie_proof(&items, index);
+                assert_eq!(proof_root, root);
+                let value = alloy_rlp::encode(&items[index]);
+                assert_eq!(
+                    verify_ordered_trie_proof(root, index, Some(value.clone()), &proof),
+                    Ok(())
+                );
+
+                // The proof does not hold for another index or value.
+                let other = (index + 1) % len;
+                let other_value = alloy_rlp::encode(&items[other]);
+                if other_value != value {
+                    assert!(verify_ordered_trie_proof(root, other, Some(value), &proof).is_err());
+                    assert!(
+                        verify_ordered_trie_proof(root, index, Some(other_value), &proof).is_err()
+                    );
+                }
+            }
+
+            let (_, proof) = ordered_trie_proof(&items, len);
+            assert_eq!(verify_ordered_trie_proof(root, len, None, &proof), Ok(()));
+        }
+
+        let (root, proof) = ordered_trie_proof::<u64>(&[], 0);
+        assert_eq!(root, crate::EMPTY_ROOT_HASH);
+        assert_eq!(verify_ordered_trie_proof(root, 0, None, &proof), Ok(()));
+    }
+
+    #[test]
+    fn small_receipt_sets() {
+        // The whole trie of a few small receipts is shorter than a word, so its nodes are encoded in
+        // place of their parent and the proof is made of the root alone.
+        for len in 1..=4u64 {
+            let items = Vec::from_iter(1..=len);
+            let root = ordered_trie_root(&items);
+            for index in 0..=len as usize {
+                let (proof_root, proof) = ordered_trie_proof(&items, index);
+                assert_eq!(proof_root, root);
+                assert!(proof.len() <= 1);
+                let value = items.get(index).map(alloy_rlp::encode);
+                assert_eq!(verify_ordered_trie_proof(root, index, value, &proof), Ok(()));
+            }
+        }
+    }
+}
@@ -50,6 +50,19 @@ impl ProofNodes {
         nodes
     }
 
+    /// Return the RLP encoded proof nodes on the path to the target, ordered from the root, as
+    /// expected by `eth_getProof`.
+    pub fn matching_nodes_for(&self, target: &Nibbles) -> Vec<Bytes> {
+        self.matching_nodes_sorted(target).into_iter().map(|(_, node)| node).collect()
+    }
+
+    /// Return iterator over all proof nodes sorted by path.
+    pub fn iter_sorted(&self) -> impl Iterator<Item = (&Nibbles, &Bytes)> {
+        let mut nodes = Vec::from_iter(self.0.iter());
+        nodes.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        nodes.into_iter()
+    }
+
     /// Insert the RLP encoded trie node at key.
     pub fn insert(&mut self, key: Nibbles, node: Bytes) -> Option<Bytes> {
         self.0.insert(key, node)
@@ -57,9 +70,7 @@ impl ProofNodes {
 
     /// Return the sorted vec of all proof nodes.
     pub fn nodes_sorted(&self) -> Vec<(Nibbles, Bytes)> {
-        let mut nodes = Vec::from_iter(self.0.iter().map(|(k, v)| (k.clone(), v.clone())));
-        nodes.sort_unstable_by(|a, b| a.0.cmp(&b.0));
-        nodes
+        self.iter_sorted().map(|(k, v)| (k.clone(), v.clone())).collect()
     }
 
     /// Convert into sorted vec of all proof nodes.
@@ -69,6 +80,11 @@ impl ProofNodes {
         nodes
     }
 
+    /// Convert into the flat vec of all RLP encoded proof nodes sorted by path.
+    pub fn into_proof_bytes(self) -> Vec<Bytes> {
+        self.into_nodes_sorted().into_iter().map(|(_, node)| node).collect()
+    }
+
     /// Convert wrapper struct into inner map.
     pub fn into_inner(self) -> HashMap<Nibbles, Bytes> {
         self.0
@@ -79,3 +95,36 @@ impl ProofNodes {
         self.extend(other.0);
     }
 }
+
+impl From<ProofNodes> for Vec<Bytes> {
+    fn from(nodes: ProofNodes) -> Self {
+        nodes.into_proof_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorted_proof_bytes() {
+        let path = |nibbles: &[u8]| Nibbles::from_nibbles_unchecked(nibbles);
+        let nodes = ProofNodes::from_iter([
+            (path(&[0x1, 0x2]), Bytes::from_static(&[0x12])),
+            (path(&[]), Bytes::from_static(&[0x00])),
+            (path(&[0x2]), Bytes::from_static(&[0x02])),
+            (path(&[0x1]), Bytes::from_static(&[0x01])),
+        ]);
+
+        let keys = nodes.iter_sorted().map(|(key, _)| key.clone()).collect::<Vec<_>>();
+        assert_eq!(keys, [path(&[]), path(&[0x1]), path(&[0x1, 0x2]), path(&[0x2])]);
+        assert_eq!(
+            nodes.matching_nodes_for(&path(&[0x1, 0x2, 0x3])),
+            [Bytes::from_static(&[0x00]), Bytes::from_static(&[0x01]), Bytes::from_static(&[0x12])]
+        );
+        assert_eq!(
+            Vec::from(nodes),
+            [0x00, 0x01, 0x12, 0x02].map(|byte| Bytes::copy_from_slice(&[byte]))
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use crate::{
+    nodes::{RlpNode, TrieNode},
+    proof::{ProofRetainer, ProofVerificationError},
+    updates::TrieUpdates,
+    HashBuilder, Nibbles, EMPTY_ROOT_HASH,
+};
+use alloc::vec::Vec;
+use alloy_primitives::{Bytes, B256};
+use alloy_rlp::{Decodable, EMPTY_STRING_CODE};
+use core::cmp::Ordering;
+
+/// The proof that the given leaves are all the leaves of a trie between a start and an end key,
+/// both inclusive, as served by snap sync.
+///
+/// The start and the end keys are proven by their proofs, regardless of whether they exist in the
+/// trie.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RangeProof {
+    /// The first key of the range.
+    pub start_key: B256,
+    /// The last key of the range.
+    pub end_key: B256,
+    /// The leaves within the range along with their privacy flag, sorted by key.
+    pub leaves: Vec<(B256, Bytes, bool)>,
+    /// The proof nodes on the path to the start key, ordered from the root.
+    pub left_proof: Vec<Bytes>,
+    /// The proof nodes on the path to the end key, ordered from the root.
+    pub right_proof: Vec<Bytes>,
+}
+
+impl RangeProof {
+    /// Verifies the range proof against the given root, see [verify_range_proof].
+    pub fn verify(&self, root: B256) -> Result<TrieUpdates, ProofVerificationError> {
+        verify_range_proof(
+            root,
+            self.start_key,
+            self.end_key,
+            &self.leaves,
+            &self.left_proof,
+            &self.right_proof,
+        )
+    }
+}
+
+/// Computes the root from sorted leaves and returns it along with the proof of the leaves between
+/// the start and the end key, both inclusive.
+///
+/// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+///
+/// # Panics
+///
+/// If the leaves are not in sorted order.
+pub fn range_proof<I, V>(leaves: I, start_key: B256, end_key: B256) -> (B256, RangeProof)
+where
+    I: IntoIterator<Item = (B256, V, bool)>,
+    V: AsRef<[u8]>,
+{
+    let (start, end) = (Nibbles::unpack(start_key), Nibbles::unpack(end_key));
+    let mut hb = HashBuilder::default()
+        .with_proof_retainer(ProofRetainer::from_iter([start.clone(), end.clone()]));
+    let mut range = Vec::new();
+    for (key, value, is_private) in leaves {
+        let value = value.as_ref();
+        hb.add_leaf(Nibbles::unpack(key), value, is_private);
+        if (start_key..=end_key).contains(&key) {
+            range.push((key, Bytes::copy_from_slice(value), is_private));
+        }
+    }
+
+    let root = hb.root();
+    let proof_nodes = hb.take_proof_nodes();
+    let proof = RangeProof {
+        start_key,
+        end_key,
+        leaves: range,
+        left_proof: proof_nodes.matching_nodes_for(&start),
+        right_proof: proof_nodes.matching_nodes_for(&end),
+    };
+    (root, proof)
+}
+
+/// Verifies that the given leaves, sorted by key, are all the leaves of the trie with the given
+/// root between the start and the end key, both inclusive.
+///
+/// The subtries left of the start key and right of the end key are reconstructed from the proofs
+/// of these keys, and the root is computed from them and the given leaves. Returns the branch
+/// nodes of the reconstructed trie, so that the range can be stored without recomputing them.
+///
+/// Subtries outside of the range must be referenced by hash, nodes encoded in place of their
+/// parent are not supported.
+pub fn verify_range_proof<V: AsRef<[u8]>>(
+    root: B256,
+    start_key: B256,
+    end_key: B256,
+    leaves: &[(B256, V, bool)],
+    left_proof: &[Bytes],
+    right_proof: &[Bytes],
+) -> Result<TrieUpdates, ProofVerificationError> {
+    if start_key > end_key {
+        return Err(ProofVerificationError::InvalidRangeProof("start key is after the end key"));
+    }
+    if !leaves.iter().all(|(key, _, _)| (start_key..=end_key).contains(key))
+        || !leaves.windows(2).all(|pair| pair[0].0 < pair[1].0)
+    {
+        return Err(ProofVerificationError::InvalidRangeProof(
+            "leaves are not sorted within the range",
+        ));
+    }
+
+    let left = boundary_items(root, &Nibbles::unpack(start_key), Ordering::Less, left_proof)?;
+    let right = boundary_items(root, &Nibbles::unpack(end_key), Ordering::Greater, right_proof)?;
+
+    // The subtries left of the start key come before the leaves, and the subtries right of the
+    // end key come after them.
+    let mut hb = HashBuilder::default().with_updates(true);
+    for (path, item) in left {
+        item.add_to(&mut hb, path);
+    }
+    for (key, value, is_private) in leaves {
+        hb.add_leaf(Nibbles::unpack(key), value.as_ref(), *is_private);
+    }
+    for (path, item) in right {
+        item.add_to(&mut hb, path);
+    }
+
+    let (got, updates) = hb.root_with_updates();
+    if got != root {
+        return Err(ProofVerificationError::RootMismatch { got, expected: root });
+    }
+    Ok(updates)
+}
+
+/// Subtrie outside of the range, reconstructed from a boundary proof.
+enum BoundaryItem {
+    /// Subtrie referenced by the hash of its root node.
+    Hash(B256),
+    /// Single leaf with its value and privacy flag.
+    Leaf(Vec<u8>, bool),
+}
+
+impl BoundaryItem {
+    fn add_to(self, hb: &mut HashBuilder, path: Nibbles) {
+        match self {
+            Self::Hash(hash) => hb.add_branch(path, hash, false),
+            Self::Leaf(value, is_private) => hb.add_leaf(path, &value, is_private),
+        }
+    }
+}
+
+/// Walks the proof of the given key and returns the subtries on the given side of the key,
+/// sorted by path.
+fn boundary_items(
+    root: B256,
+    key: &Nibbles,
+    side: Ordering,
+    proof: &[Bytes],
+) -> Result<Vec<(Nibbles, BoundaryItem)>, ProofVerificationError> {
+    let mut items = Vec::new();
+    let mut path = Nibbles::default();
+    let mut expected = Some(RlpNode::word_rlp(&root));
+    for (index, node) in proof.iter().enumerate() {
+        let is_empty_root =
+            index == 0 && root == EMPTY_ROOT_HASH && node[..] == [EMPTY_STRING_CODE];
+        if !is_empty_root && expected.as_ref() != Some(&RlpNode::from_rlp(node)) {
+            return Err(ProofVerificationError::ValueMismatch {
+                path,
+                got: Some(node.clone()),
+                expected: expected.map(|expected| Bytes::copy_from_slice(&expected)),
+                got_private: false,
+                expected_private: false,
+            });
+        }
+        expected = None;
+
+        match TrieNode::decode(&mut &node[..])? {
+            TrieNode::EmptyRoot => {}
+            TrieNode::Branch(branch) => {
+                let nibble = *key.get(path.len()).ok_or(
+                    ProofVerificationError::InvalidRangeProof("branch node below the key"),
+                )?;
+                for (child_nibble, child) in branch.as_ref().children() {
+                    let Some(child) = child else { continue };
+                    if child_nibble == nibble {
+                        expected = Some(child.clone());
+                    } else if child_nibble.cmp(&nibble) == side {
+                        let mut child_path = path.clone();
+                        child_path.push(child_nibble);
+                        items.push((child_path, BoundaryItem::Hash(child_hash(child)?)));
+                    }
+                }
+                path.push(nibble);
+            }
+            TrieNode::Extension(extension) => {
+                let extension_path = path.join(&extension.key);
+                if key.starts_with(&extension_path) {
+                    path = extension_path;
+                    expected = Some(extension.child);
+                } else if extension_path.cmp(key) == side {
+                    items.push((extension_path, BoundaryItem::Hash(child_hash(&extension.child)?)));
+                }
+            }
+            TrieNode::Leaf(leaf) => {
+                let leaf_path = path.join(&leaf.key);
+                if leaf_path.cmp(key) == side {
+                    items.push((leaf_path, BoundaryItem::Leaf(leaf.value, leaf.is_private)));
+                }
+            }
+        }
+    }
+
+    items.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(items)
+}
+
+/// Returns the hash referencing the child node, failing if it is encoded in place.
+fn child_hash(child: &RlpNode) -> Result<B256, ProofVerificationError> {
+    child.as_hash().ok_or(ProofVerificationError::InvalidRangeProof(
+        "subtries encoded in place are not supported",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::BTreeMap;
+    use alloy_primitives::keccak256;
+
+    fn leaves() -> Vec<(B256, Vec<u8>, bool)> {
+        (0..100u64)
+            .map(|i| (keccak256(i.to_be_bytes()), (alloy_rlp::encode(i), i % 3 == 0)))
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .map(|(key, (value, is_private))| (key, value, is_private))
+            .collect()
+    }
+
+    #[test]
+    fn verify_ranges() {
+        let leaves = leaves();
+        let keys = leaves.iter().map(|(key, _, _)| *key).collect::<Vec<_>>();
+        // Keys right after the given ones, which are not in the trie.
+        let between = |mut key: B256| {
+            key[31] = key[31].wrapping_add(1);
+            key
+        };
+
+        for (start_key, end_key) in [
+            (B256::ZERO, B256::repeat_byte(0xff)),
+            (keys[10], keys[20]),
+            (between(keys[10]), between(keys[20])),
+            (keys[0], keys[0]),
+            (between(keys[50]), between(keys[50])),
+            (B256::ZERO, between(keys[5])),
+            (between(keys[90]), B256::repeat_byte(0xff)),
+        ] {
+            let (root, proof) = range_proof(leaves.iter().cloned(), start_key, end_key);
+            let updates = proof.verify(root).unwrap();
+            if proof.leaves.len() > 1 {
+                assert!(!updates.updated_nodes.is_empty());
+            }
+
+            // Missing, extra and changed leaves are detected.
+            if let Some(last) = proof.leaves.len().checked_sub(1) {
+                let mut missing = proof.clone();
+                missing.leaves.remove(last);
+                assert!(missing.verify(root).is_err());
+
+                let mut changed = proof.clone();
+                changed.leaves[0].2 = !changed.leaves[0].2;
+                assert!(changed.verify(root).is_err());
+            }
+            if proof.leaves.last().map(|(key, _, _)| *key) != Some(end_key) {
+                let mut extra = proof.clone();
+                extra.leaves.push((end_key, Bytes::from_static(b"extra"), false));
+                assert!(extra.verify(root).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn verify_empty_trie_range() {
+        let (root, proof) = range_proof(
+            core::iter::empty::<(B256, Vec<u8>, bool)>(),
+            B256::ZERO,
+            B256::repeat_byte(0xff),
+        );
+        assert_eq!(root, EMPTY_ROOT_HASH);
+        assert_eq!(proof.verify(root), Ok(TrieUpdates::default()));
+        assert!(proof.verify(B256::repeat_byte(1)).is_err());
+    }
+
+    #[test]
+    fn invalid_ranges() {
+        let (root, proof) = range_proof(leaves(), B256::ZERO, B256::repeat_byte(0xff));
+
+        let mut unsorted = proof.clone();
+        unsorted.leaves.swap(0, 1);
+        assert_eq!(
+            unsorted.verify(root),
+            Err(ProofVerificationError::InvalidRangeProof(
+                "leaves are not sorted within the range"
+            ))
+        );
+
+        let mut reversed = proof;
+        core::mem::swap(&mut reversed.start_key, &mut reversed.end_key);
+        assert_eq!(
+            reversed.verify(root),
+            Err(ProofVerificationError::InvalidRangeProof("start key is after the end key"))
+        );
+    }
+}
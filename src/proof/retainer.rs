@@ -19,6 +19,12 @@ impl FromIterator<Nibbles> for ProofRetainer {
     }
 }
 
+impl From<Vec<Nibbles>> for ProofRetainer {
+    fn from(targets: Vec<Nibbles>) -> Self {
+        Self::new(targets)
+    }
+}
+
 impl ProofRetainer {
     /// Create new retainer with target nibbles.
     pub fn new(targets: Vec<Nibbles>) -> Self {
@@ -30,6 +36,11 @@ impl ProofRetainer {
         self.targets.iter().any(|target| target.starts_with(prefix))
     }
 
+    /// Returns the proofs collected so far.
+    pub(crate) const fn proof_nodes(&self) -> &ProofNodes {
+        &self.proof_nodes
+    }
+
     /// Returns all collected proofs.
     pub fn into_proof_nodes(self) -> ProofNodes {
         self.proof_nodes
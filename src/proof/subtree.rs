@@ -0,0 +1,110 @@
+use crate::{
+    nodes::{RlpNode, TrieNode},
+    Nibbles,
+};
+use alloy_primitives::{keccak256, Bytes, B256};
+use alloy_rlp::Decodable;
+
+/// Returns the hash of the node at the given path, walking the proof nodes ordered from the root,
+/// e.g. to verify a subtrie separately from the rest of the trie.
+///
+/// Nodes encoded in place of their parent are hashed. Returns [None] if the proof does not reach a
+/// node at the given path, e.g. if the path ends within the key of an extension or a leaf.
+///
+/// The proof is expected to be valid, see [verify_proof](crate::proof::verify_proof).
+pub fn subtree_root_from_proof<'a, I>(
+    proof: I,
+    prefix: &Nibbles,
+) -> Result<Option<B256>, alloy_rlp::Error>
+where
+    I: IntoIterator<Item = &'a Bytes>,
+{
+    let mut proof = proof.into_iter();
+    let Some(root) = proof.next() else { return Ok(None) };
+    if prefix.is_empty() {
+        return Ok(Some(keccak256(root)));
+    }
+
+    let mut path = Nibbles::default();
+    let mut node = TrieNode::decode(&mut &root[..])?;
+    loop {
+        let child = match node {
+            TrieNode::Branch(branch) => {
+                let Some(&nibble) = prefix.get(path.len()) else { return Ok(None) };
+                let Some(child) = branch
+                    .as_ref()
+                    .children()
+                    .find(|(child_nibble, _)| *child_nibble == nibble)
+                    .and_then(|(_, child)| child.cloned())
+                else {
+                    return Ok(None);
+                };
+                path.push(nibble);
+                child
+            }
+            TrieNode::Extension(extension) => {
+                path.extend_from_slice(&extension.key);
+                extension.child
+            }
+            TrieNode::Leaf(_) | TrieNode::EmptyRoot => return Ok(None),
+        };
+
+        if path == *prefix {
+            return Ok(Some(child.as_hash().unwrap_or_else(|| keccak256(&child))));
+        }
+        if !prefix.starts_with(&path) {
+            return Ok(None);
+        }
+
+        // Nodes referenced by hash are the next nodes of the proof, unless the path leaves it.
+        node = match TrieNode::decode_embedded(&child)? {
+            Some(embedded) => embedded,
+            None => match proof.next() {
+                Some(next) if RlpNode::from_rlp(next) == child => TrieNode::decode(&mut &next[..])?,
+                _ => return Ok(None),
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{proof::ProofRetainer, HashBuilder};
+    use alloc::collections::BTreeMap;
+
+    #[test]
+    fn subtree_roots() {
+        let leaves = (0..200u64)
+            .map(|i| {
+                (Nibbles::unpack(keccak256(i.to_be_bytes())), (alloy_rlp::encode(i), i % 2 == 0))
+            })
+            .collect::<BTreeMap<_, _>>();
+        let target = leaves.keys().nth(100).unwrap().clone();
+        let mut hb =
+            HashBuilder::default().with_proof_retainer(ProofRetainer::from_iter([target.clone()]));
+        for (key, (value, is_private)) in &leaves {
+            hb.add_leaf(key.clone(), value, *is_private);
+        }
+        let root = hb.root();
+        let proof_nodes = hb.take_proof_nodes();
+        let proof = proof_nodes.matching_nodes_for(&target);
+
+        assert_eq!(subtree_root_from_proof(&proof, &Nibbles::default()), Ok(Some(root)));
+        for (path, node) in proof_nodes.matching_nodes_sorted(&target) {
+            assert_eq!(subtree_root_from_proof(&proof, &path), Ok(Some(keccak256(node))));
+        }
+
+        // Paths within the key of the leaf or off the proof are not reached.
+        assert_eq!(subtree_root_from_proof(&proof, &target), Ok(None));
+        assert_eq!(subtree_root_from_proof(core::iter::empty(), &target), Ok(None));
+        let mut off_path = target.slice(..2);
+        off_path.push(0);
+        for nibble in 0..16 {
+            off_path.set_at(1, nibble);
+            if nibble != target[1] {
+                assert_eq!(subtree_root_from_proof(&proof, &off_path), Ok(None));
+            }
+        }
+    }
+}
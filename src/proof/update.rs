@@ -0,0 +1,262 @@
+use crate::{
+    nodes::TrieNode,
+    proof::{verify_proof, ProofNodes, ProofVerificationError},
+    sparse::{SparseTrie, SparseTrieError},
+    Nibbles,
+};
+use alloc::vec::Vec;
+use alloy_primitives::{Bytes, B256};
+use alloy_rlp::Decodable;
+
+/// Verifies the proof of the current value of the key against the root, like [verify_proof], and
+/// computes the root of the trie after the leaf of the key is set to the new value, or removed if
+/// [None], using only the proof nodes.
+///
+/// This is the state transition of a light client or a fraud proof: the prover provides the proof
+/// of the value before the update, and the verifier derives the root after it.
+///
+/// Removing a leaf whose branch collapses into a single sibling needs the sibling node, which is
+/// not part of the proof of the key. [ProofVerificationError::MissingNode] is returned in that
+/// case, see [compute_root_after_updates] to provide it.
+///
+/// SEISMIC WARNING: Ensure that the new leaf is flagged correctly when calling
+pub fn compute_root_after_update<'a, I>(
+    root: B256,
+    key: Nibbles,
+    expected_value: Option<Vec<u8>>,
+    expected_is_private: bool,
+    proof: I,
+    new_value: Option<(Vec<u8>, bool)>,
+) -> Result<B256, ProofVerificationError>
+where
+    I: IntoIterator<Item = &'a Bytes>,
+{
+    let proof = Vec::from_iter(proof);
+    verify_proof(root, key.clone(), expected_value, expected_is_private, proof.iter().copied())?;
+
+    // Reveal the proof nodes along the path of the key, in order from the root.
+    let mut trie = SparseTrie::blind(root);
+    let mut proof = proof.into_iter();
+    loop {
+        match trie.get(&key) {
+            Ok(_) => break,
+            Err(SparseTrieError::BlindedNode { path, hash }) => {
+                let Some(node) = proof.next() else {
+                    return Err(ProofVerificationError::MissingNode { path, hash });
+                };
+                trie.reveal_node(path, TrieNode::decode(&mut &node[..])?)?;
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    match new_value {
+        Some((value, is_private)) => trie.insert_leaf(key, value, is_private)?,
+        None => {
+            trie.remove_leaf(&key)?;
+        }
+    }
+    Ok(trie.root())
+}
+
+/// Computes the root of the trie after applying a batch of updates, setting the leaf of every key
+/// to its new value, or removing it if [None], using only the proof nodes, e.g. the account subtree
+/// of a [`MultiProof`](crate::proof::MultiProof) witnessing a block.
+///
+/// The proof nodes are checked against the root as they are revealed, and the updates are applied
+/// in order. Removing a leaf whose branch collapses into a single sibling needs the sibling node,
+/// which must be part of the proof nodes, e.g. by also targeting a key under it. Otherwise
+/// [ProofVerificationError::MissingNode] is returned with the path of the sibling, as for any other
+/// node missing on the path of an updated key.
+///
+/// SEISMIC WARNING: Ensure that the new leaves are flagged correctly when calling
+pub fn compute_root_after_updates<I>(
+    root: B256,
+    proof_nodes: &ProofNodes,
+    updates: I,
+) -> Result<B256, ProofVerificationError>
+where
+    I: IntoIterator<Item = (Nibbles, Option<(Vec<u8>, bool)>)>,
+{
+    let mut trie = SparseTrie::from_proof_nodes(root, proof_nodes)?;
+    for (key, new_value) in updates {
+        match new_value {
+            Some((value, is_private)) => trie.insert_leaf(key, value, is_private)?,
+            None => {
+                trie.remove_leaf(&key)?;
+            }
+        }
+    }
+    Ok(trie.root())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{proof::ProofRetainer, HashBuilder, EMPTY_ROOT_HASH};
+    use alloc::collections::BTreeMap;
+    use alloy_primitives::keccak256;
+
+    fn root_and_proof(
+        leaves: &BTreeMap<Nibbles, (Vec<u8>, bool)>,
+        target: &Nibbles,
+    ) -> (B256, Vec<Bytes>) {
+        let mut hb =
+            HashBuilder::default().with_proof_retainer(ProofRetainer::from_iter([target.clone()]));
+        for (key, (value, is_private)) in leaves {
+            hb.add_leaf(key.clone(), value, *is_private);
+        }
+        let root = hb.root();
+        (root, hb.take_proof_nodes().matching_nodes_for(target))
+    }
+
+    #[test]
+    fn root_after_update() {
+        let leaves = (0..100u64)
+            .map(|i| {
+                (Nibbles::unpack(keccak256(i.to_be_bytes())), (alloy_rlp::encode(i), i % 3 == 0))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let (mut removed, mut missing) = (0, 0);
+        for (key, (value, is_private)) in leaves.iter().take(20) {
+            let (root, proof) = root_and_proof(&leaves, key);
+
+            // update in place and flip the privacy of the leaf
+            let new_value = (b"updated".to_vec(), !is_private);
+            let mut expected = leaves.clone();
+            expected.insert(key.clone(), new_value.clone());
+            assert_eq!(
+                compute_root_after_update(
+                    root,
+                    key.clone(),
+                    Some(value.clone()),
+                    *is_private,
+                    &proof,
+                    Some(new_value)
+                ),
+                Ok(root_and_proof(&expected, key).0)
+            );
+
+            // remove, unless the branch collapses into a sibling out of the proof
+            let mut expected = leaves.clone();
+            expected.remove(key);
+            match compute_root_after_update(
+                root,
+                key.clone(),
+                Some(value.clone()),
+                *is_private,
+                &proof,
+                None,
+            ) {
+                Ok(new_root) => {
+                    assert_eq!(new_root, root_and_proof(&expected, key).0);
+                    removed += 1;
+                }
+                Err(ProofVerificationError::MissingNode { .. }) => missing += 1,
+                Err(error) => panic!("unexpected error: {error}"),
+            }
+
+            // the old value must be proven
+            assert!(compute_root_after_update(
+                root,
+                key.clone(),
+                Some(b"wrong".to_vec()),
+                *is_private,
+                &proof,
+                None
+            )
+            .is_err());
+        }
+        assert!(removed > 0 && missing > 0);
+
+        // insert a new key from its exclusion proof
+        let key = Nibbles::unpack(keccak256(1000u64.to_be_bytes()));
+        let (root, proof) = root_and_proof(&leaves, &key);
+        let mut expected = leaves;
+        expected.insert(key.clone(), (b"new".to_vec(), true));
+        assert_eq!(
+            compute_root_after_update(
+                root,
+                key.clone(),
+                None,
+                false,
+                &proof,
+                Some((b"new".to_vec(), true))
+            ),
+            Ok(root_and_proof(&expected, &key).0)
+        );
+
+        // insert into and remove from an empty trie
+        assert_eq!(
+            compute_root_after_update(
+                EMPTY_ROOT_HASH,
+                key.clone(),
+                None,
+                false,
+                [],
+                Some((b"new".to_vec(), true))
+            ),
+            Ok(root_and_proof(&BTreeMap::from([(key.clone(), (b"new".to_vec(), true))]), &key).0)
+        );
+        assert_eq!(
+            compute_root_after_update(EMPTY_ROOT_HASH, key, None, false, [], None),
+            Ok(EMPTY_ROOT_HASH)
+        );
+    }
+
+    #[test]
+    fn root_after_batch_updates() {
+        let leaves = (0..200u64)
+            .map(|i| {
+                (Nibbles::unpack(keccak256(i.to_be_bytes())), (alloy_rlp::encode(i), i % 3 == 0))
+            })
+            .collect::<BTreeMap<_, _>>();
+        let keys = Vec::from_iter(leaves.keys().cloned());
+
+        // update, insert and remove, with removals collapsing branches
+        let mut updates = Vec::new();
+        for (i, key) in keys.iter().enumerate().step_by(3) {
+            updates.push((key.clone(), (i % 2 == 0).then(|| (b"updated".to_vec(), i % 4 == 0))));
+        }
+        for i in 1000..1010u64 {
+            updates.push((Nibbles::unpack(keccak256(i.to_be_bytes())), Some((vec![1; 40], false))));
+        }
+        let mut expected = leaves.clone();
+        for (key, new_value) in &updates {
+            match new_value {
+                Some(new_value) => expected.insert(key.clone(), new_value.clone()),
+                None => expected.remove(key),
+            };
+        }
+        let expected_root = root_and_proof(&expected, &Nibbles::default()).0;
+
+        // Targeting the updated keys is not enough for the removals collapsing branches, the
+        // missing siblings are added to the targets until the witness is complete.
+        let mut targets = Vec::from_iter(updates.iter().map(|(key, _)| key.clone()));
+        let mut missing = 0;
+        let new_root = loop {
+            let mut hb = HashBuilder::default()
+                .with_proof_retainer(ProofRetainer::from_iter(targets.iter().cloned()));
+            for (key, (value, is_private)) in &leaves {
+                hb.add_leaf(key.clone(), value, *is_private);
+            }
+            let root = hb.root();
+            match compute_root_after_updates(root, &hb.take_proof_nodes(), updates.clone()) {
+                Err(ProofVerificationError::MissingNode { path, .. }) => {
+                    targets.push(keys.iter().find(|key| key.starts_with(&path)).unwrap().clone());
+                    missing += 1;
+                }
+                result => break result,
+            }
+        };
+        assert_eq!(new_root, Ok(expected_root));
+        assert!(missing > 0);
+
+        // The nodes must match the root.
+        let (root, _) = root_and_proof(&leaves, &Nibbles::default());
+        let (_, other_proof) = root_and_proof(&expected, &keys[0]);
+        let other_nodes = ProofNodes::from_iter([(Nibbles::default(), other_proof[0].clone())]);
+        assert!(compute_root_after_updates(root, &other_nodes, updates).is_err());
+    }
+}
@@ -6,22 +6,46 @@ use crate::{
     EMPTY_ROOT_HASH,
 };
 use alloc::vec::Vec;
-use alloy_primitives::{Bytes, B256};
+use alloy_primitives::{keccak256, Bytes, B256};
 use alloy_rlp::{Decodable, EMPTY_STRING_CODE};
 use core::ops::Deref;
 use nybbles::Nibbles;
 
+/// The outcome of a successful proof verification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ProofVerificationOutcome {
+    /// The key is in the trie and its leaf is public.
+    Included,
+    /// The key is in the trie and its leaf is flagged private.
+    IncludedPrivate,
+    /// The key is not in the trie.
+    Excluded,
+}
+
+impl ProofVerificationOutcome {
+    /// Returns `true` if the key is in the trie.
+    pub const fn is_included(&self) -> bool {
+        matches!(self, Self::Included | Self::IncludedPrivate)
+    }
+
+    /// Returns `true` if the key is in the trie and its leaf is flagged private.
+    pub const fn is_private(&self) -> bool {
+        matches!(self, Self::IncludedPrivate)
+    }
+}
+
 /// Verify the proof for given key value pair against the provided state root.
 ///
 /// The expected node value can be either [Some] if it's expected to be present
-/// in the tree or [None] if this is an exclusion proof.
+/// in the tree or [None] if this is an exclusion proof. On success, returns whether the key was
+/// proven to be included, with a public or private leaf, or excluded.
 pub fn verify_proof<'a, I>(
     root: B256,
     key: Nibbles,
     expected_value: Option<Vec<u8>>,
     expected_is_private: bool,
     proof: I,
-) -> Result<(), ProofVerificationError>
+) -> Result<ProofVerificationOutcome, ProofVerificationError>
 where
     I: IntoIterator<Item = &'a Bytes>,
 {
@@ -31,7 +55,7 @@ where
     if proof.peek().map_or(true, |node| node.as_ref() == [EMPTY_STRING_CODE]) {
         return if root == EMPTY_ROOT_HASH {
             if expected_value.is_none() {
-                Ok(())
+                Ok(ProofVerificationOutcome::Excluded)
             } else {
                 Err(ProofVerificationError::ValueMismatch {
                     path: key,
@@ -49,10 +73,12 @@ where
     let mut walked_path = Nibbles::with_capacity(key.len());
     let mut last_decoded_node = Some(NodeDecodingResult::Node(RlpNode::word_rlp(&root)));
     let mut last_decoded_node_is_private = false;
-    for node in proof {
+    for (index, node) in proof.enumerate() {
         // Check if the node that we just decoded (or root node, if we just started) matches
-        // the expected node from the proof.
-        if Some(RlpNode::from_rlp(node).as_slice()) != last_decoded_node.as_deref() {
+        // the expected node from the proof. The root node is hashed even if shorter than a word.
+        let node_rlp =
+            if index == 0 { RlpNode::word_rlp(&keccak256(node)) } else { RlpNode::from_rlp(node) };
+        if Some(node_rlp.as_slice()) != last_decoded_node.as_deref() {
             let got = Some(Bytes::copy_from_slice(node));
             let expected = last_decoded_node.as_deref().map(Bytes::copy_from_slice);
             return Err(ProofVerificationError::ValueMismatch {
@@ -75,10 +101,16 @@ where
 
     // Last decoded node should have the key that we are looking for.
     last_decoded_node = last_decoded_node.filter(|_| walked_path == key);
+    // A diverging leaf proves the exclusion of the key, whatever its own privacy.
+    last_decoded_node_is_private &= last_decoded_node.is_some();
     if last_decoded_node.as_deref() == expected_value.as_deref()
         && last_decoded_node_is_private == expected_is_private
     {
-        Ok(())
+        Ok(match (last_decoded_node, last_decoded_node_is_private) {
+            (None, _) => ProofVerificationOutcome::Excluded,
+            (Some(_), false) => ProofVerificationOutcome::Included,
+            (Some(_), true) => ProofVerificationOutcome::IncludedPrivate,
+        })
     } else {
         Err(ProofVerificationError::ValueMismatch {
             path: key,
@@ -122,18 +154,21 @@ fn process_trie_node(
     last_decoded_node_is_private: &mut bool,
 ) -> Result<Option<NodeDecodingResult>, ProofVerificationError> {
     let node = match node {
-        TrieNode::Branch(branch) => process_branch(branch, walked_path, key)?,
+        TrieNode::Branch(branch) => {
+            process_branch(branch, walked_path, key, last_decoded_node_is_private)?
+        }
         TrieNode::Extension(extension) => {
             walked_path.extend_from_slice(&extension.key);
-            if extension.child.is_hash() {
-                Some(NodeDecodingResult::Node(extension.child))
-            } else {
-                process_trie_node(
-                    TrieNode::decode(&mut &extension.child[..])?,
-                    walked_path,
-                    key,
-                    last_decoded_node_is_private,
-                )?
+            match TrieNode::decode_embedded(&extension.child)? {
+                Some(child @ (TrieNode::Branch(_) | TrieNode::EmptyRoot)) => {
+                    process_trie_node(child, walked_path, key, last_decoded_node_is_private)?
+                }
+                Some(TrieNode::Extension(_) | TrieNode::Leaf(_)) => {
+                    return Err(ProofVerificationError::MalformedNode(
+                        "extension node child is not a branch node",
+                    ))
+                }
+                None => Some(NodeDecodingResult::Node(extension.child)),
             }
         }
         TrieNode::Leaf(leaf) => {
@@ -151,6 +186,7 @@ fn process_branch(
     mut branch: BranchNode,
     walked_path: &mut Nibbles,
     key: &Nibbles,
+    last_decoded_node_is_private: &mut bool,
 ) -> Result<Option<NodeDecodingResult>, ProofVerificationError> {
     if let Some(next) = key.get(walked_path.len()) {
         let mut stack_ptr = branch.as_ref().first_child_index();
@@ -160,17 +196,21 @@ fn process_branch(
                     walked_path.push(*next);
 
                     let child = branch.stack.remove(stack_ptr);
-                    if child.len() == B256::len_bytes() + 1 {
-                        return Ok(Some(NodeDecodingResult::Node(child)));
-                    } else {
+                    match TrieNode::decode_embedded(&child)? {
+                        None => return Ok(Some(NodeDecodingResult::Node(child))),
                         // This node is encoded in-place.
-                        match TrieNode::decode(&mut &child[..])? {
+                        Some(child) => match child {
                             TrieNode::Branch(child_branch) => {
                                 // An in-place branch node can only have direct, also in-place
                                 // encoded, leaf children, as anything else overflows this branch
                                 // node, making it impossible to be encoded in-place in the first
                                 // place.
-                                return process_branch(child_branch, walked_path, key);
+                                return process_branch(
+                                    child_branch,
+                                    walked_path,
+                                    key,
+                                    last_decoded_node_is_private,
+                                );
                             }
                             TrieNode::Extension(child_extension) => {
                                 walked_path.extend_from_slice(&child_extension.key);
@@ -188,23 +228,28 @@ fn process_branch(
                                             extension_child_branch,
                                             walked_path,
                                             key,
+                                            last_decoded_node_is_private,
                                         );
                                     }
-                                    node @ (TrieNode::EmptyRoot
-                                    | TrieNode::Extension(_)
-                                    | TrieNode::Leaf(_)) => {
-                                        unreachable!("unexpected extension node child: {node:?}")
+                                    TrieNode::EmptyRoot => {
+                                        return Err(ProofVerificationError::UnexpectedEmptyRoot)
+                                    }
+                                    TrieNode::Extension(_) | TrieNode::Leaf(_) => {
+                                        return Err(ProofVerificationError::MalformedNode(
+                                            "extension node child is not a branch node",
+                                        ))
                                     }
                                 }
                             }
                             TrieNode::Leaf(child_leaf) => {
                                 walked_path.extend_from_slice(&child_leaf.key);
+                                *last_decoded_node_is_private = child_leaf.is_private;
                                 return Ok(Some(NodeDecodingResult::Value(child_leaf.value)));
                             }
                             TrieNode::EmptyRoot => {
                                 return Err(ProofVerificationError::UnexpectedEmptyRoot)
                             }
-                        }
+                        },
                     };
                 }
                 stack_ptr += 1;
@@ -223,7 +268,7 @@ mod tests {
         proof::{ProofNodes, ProofRetainer},
         triehash_trie_root, HashBuilder, TrieMask,
     };
-    use alloy_primitives::hex;
+    use alloy_primitives::{hex, keccak256};
     use alloy_rlp::{Encodable, EMPTY_STRING_CODE};
     use core::str::FromStr;
 
@@ -246,7 +291,7 @@ mod tests {
                 empty_is_private,
                 proof.into_nodes_sorted().iter().map(|(_, node)| node),
             ),
-            Ok(())
+            Ok(ProofVerificationOutcome::Excluded)
         );
 
         let mut dummy_proof = vec![];
@@ -285,7 +330,7 @@ mod tests {
 
         assert_eq!(
             verify_proof(root, first_key.clone(), Some(first_value.clone()), false, &proof),
-            Ok(())
+            Ok(ProofVerificationOutcome::Included)
         );
         assert_eq!(
             verify_proof(root, first_key.clone(), None, false, &proof),
@@ -300,7 +345,7 @@ mod tests {
 
         assert_eq!(
             verify_proof(root, second_key.clone(), Some(second_value.clone()), false, &proof),
-            Ok(())
+            Ok(ProofVerificationOutcome::Included)
         );
         assert_eq!(
             verify_proof(root, second_key.clone(), None, false, &proof),
@@ -337,7 +382,7 @@ mod tests {
                 is_private,
                 proof.iter().map(|(_, node)| node)
             ),
-            Ok(())
+            Ok(ProofVerificationOutcome::Included)
         );
     }
 
@@ -371,12 +416,12 @@ mod tests {
                 false,
                 first_proof.iter().map(|(_, node)| node)
             ),
-            Ok(())
+            Ok(ProofVerificationOutcome::Included)
         );
         // Verify private version does not exist
         assert!(verify_proof(
             root,
-            first_key.clone(),
+            first_key,
             Some(first_value.to_vec()),
             true,
             first_proof.iter().map(|(_, node)| node)
@@ -395,12 +440,12 @@ mod tests {
                 true,
                 second_proof.iter().map(|(_, node)| node)
             ),
-            Ok(())
+            Ok(ProofVerificationOutcome::IncludedPrivate)
         );
         // verify public version does not exist
         assert!(verify_proof(
             root,
-            second_key.clone(),
+            second_key,
             Some(second_value.to_vec()),
             false,
             second_proof.iter().map(|(_, node)| node)
@@ -408,6 +453,30 @@ mod tests {
         .is_err());
     }
 
+    #[test]
+    fn proof_retainer_from_targets() {
+        let target = Nibbles::unpack(B256::with_last_byte(0x2));
+        let value = B256::with_last_byte(0x2);
+
+        let mut hash_builder = HashBuilder::default().with_proof_retainer(vec![target.clone()]);
+        hash_builder.add_leaf(Nibbles::unpack(B256::with_last_byte(0x1)), &value[..], false);
+        hash_builder.add_leaf(target.clone(), &value[..], false);
+        let root = hash_builder.root();
+
+        let proof = hash_builder.take_proof_nodes().matching_nodes_sorted(&target);
+        assert!(!proof.is_empty());
+        assert_eq!(
+            verify_proof(
+                root,
+                target,
+                Some(value.to_vec()),
+                false,
+                proof.iter().map(|(_, node)| node)
+            ),
+            Ok(ProofVerificationOutcome::Included)
+        );
+    }
+
     #[test]
     fn non_existent_proof_verification() {
         let is_priavte = false;
@@ -429,7 +498,7 @@ mod tests {
         let proof = hash_builder.take_proof_nodes().into_nodes_sorted();
         assert_eq!(
             verify_proof(root, target, None, is_priavte, proof.iter().map(|(_, node)| node)),
-            Ok(())
+            Ok(ProofVerificationOutcome::Excluded)
         );
     }
 
@@ -472,7 +541,7 @@ mod tests {
                 is_private,
                 proof.into_nodes_sorted().iter().map(|(_, node)| node)
             ),
-            Ok(())
+            Ok(ProofVerificationOutcome::Excluded)
         );
 
         let retainer = ProofRetainer::from_iter([target.clone()]);
@@ -507,7 +576,7 @@ mod tests {
                 is_private,
                 proof.into_nodes_sorted().iter().map(|(_, node)| node)
             ),
-            Ok(())
+            Ok(ProofVerificationOutcome::Included)
         );
     }
 
@@ -539,7 +608,7 @@ mod tests {
                 is_private,
                 proof.iter().map(|(_, node)| node)
             ),
-            Ok(())
+            Ok(ProofVerificationOutcome::Included)
         );
     }
 
@@ -574,7 +643,7 @@ mod tests {
                 is_private,
                 proof.matching_nodes_sorted(&target1).iter().map(|(_, node)| node)
             ),
-            Ok(())
+            Ok(ProofVerificationOutcome::Included)
         );
 
         assert_eq!(
@@ -585,7 +654,7 @@ mod tests {
                 is_private,
                 proof.matching_nodes_sorted(&target2).iter().map(|(_, node)| node)
             ),
-            Ok(())
+            Ok(ProofVerificationOutcome::Included)
         );
     }
 
@@ -815,8 +884,86 @@ mod tests {
             let proofs = hash_builder.take_proof_nodes();
             for (key, value) in hashed {
                 let nibbles = Nibbles::unpack(key);
-                assert_eq!(verify_proof(root, nibbles.clone(), Some(value), false, proofs.matching_nodes_sorted(&nibbles).iter().map(|(_, node)| node)), Ok(()));
+                assert_eq!(verify_proof(root, nibbles.clone(), Some(value), false, proofs.matching_nodes_sorted(&nibbles).iter().map(|(_, node)| node)), Ok(ProofVerificationOutcome::Included));
             }
         });
     }
+
+    #[test]
+    fn proof_verification_outcome_of_leaves_encoded_in_place() {
+        // ext(1) -> branch(2: public leaf, 3: private leaf), with the branch and the leaves encoded
+        // in place.
+        let mut buffer = vec![];
+        let branch = TrieNode::Branch(BranchNode::new(
+            vec![
+                {
+                    buffer.clear();
+                    TrieNode::Leaf(LeafNode::new(Nibbles::default(), vec![0x01], false))
+                        .rlp(&mut buffer)
+                },
+                {
+                    buffer.clear();
+                    TrieNode::Leaf(LeafNode::new(Nibbles::default(), vec![0x02], true))
+                        .rlp(&mut buffer)
+                },
+            ],
+            TrieMask::new(0b0000000000001100_u16),
+        ));
+        buffer.clear();
+        let branch = branch.rlp(&mut buffer);
+        assert!(branch.as_hash().is_none());
+        let extension =
+            TrieNode::Extension(ExtensionNode::new(Nibbles::from_nibbles([0x1]), branch));
+        buffer.clear();
+        extension.encode(&mut buffer);
+        let root = keccak256(&buffer);
+        let proof = [Bytes::from(buffer)];
+
+        let public_key = Nibbles::from_nibbles([0x1, 0x2]);
+        let private_key = Nibbles::from_nibbles([0x1, 0x3]);
+        assert_eq!(
+            verify_proof(root, public_key, Some(vec![0x01]), false, &proof),
+            Ok(ProofVerificationOutcome::Included)
+        );
+        assert_eq!(
+            verify_proof(root, private_key.clone(), Some(vec![0x02]), true, &proof),
+            Ok(ProofVerificationOutcome::IncludedPrivate)
+        );
+        assert!(verify_proof(root, private_key, Some(vec![0x02]), false, &proof).is_err());
+        assert_eq!(
+            verify_proof(root, Nibbles::from_nibbles([0x1, 0x4]), None, false, &proof),
+            Ok(ProofVerificationOutcome::Excluded)
+        );
+    }
+
+    #[test]
+    fn diverging_private_leaf_proves_exclusion() {
+        let leaf =
+            alloy_rlp::encode(LeafNode::new(Nibbles::from_nibbles([0x1, 0x2]), vec![0x01], true));
+        let root = keccak256(&leaf);
+        let proof = [Bytes::from(leaf)];
+
+        let key = Nibbles::from_nibbles([0x1, 0x3]);
+        assert_eq!(
+            verify_proof(root, key.clone(), None, false, &proof),
+            Ok(ProofVerificationOutcome::Excluded)
+        );
+        assert!(verify_proof(root, key, None, true, &proof).is_err());
+    }
+
+    #[test]
+    fn in_place_extension_with_leaf_child() {
+        // A branch node whose child 1 is an extension node encoded in-place, itself pointing to a
+        // leaf node encoded in-place, which no Hash Builder produces.
+        let mut branch = vec![0xd5, EMPTY_STRING_CODE];
+        branch.extend(hex!("c41ac22001"));
+        branch.extend([EMPTY_STRING_CODE; 15]);
+        let root = keccak256(&branch);
+        let proof = [Bytes::from(branch)];
+
+        assert_eq!(
+            verify_proof(root, Nibbles::from_nibbles([0x1, 0xa]), None, false, &proof),
+            Err(ProofVerificationError::MalformedNode("extension node child is not a branch node"))
+        );
+    }
 }
@@ -0,0 +1,346 @@
+//! Proof verification without decoding the proof nodes.
+
+use crate::{
+    nodes::{ExtensionNode, LeafNode, RlpNode},
+    proof::{ProofVerificationError, ProofVerificationOutcome},
+    EMPTY_ROOT_HASH,
+};
+use alloy_primitives::{keccak256, Bytes, B256};
+use alloy_rlp::{Header, EMPTY_STRING_CODE};
+use arrayvec::ArrayVec;
+use nybbles::Nibbles;
+
+/// Verify the proof for given key value pair against the provided state root, like
+/// [`verify_proof`](crate::proof::verify_proof), without allocating unless the verification fails.
+///
+/// The RLP encoded proof nodes are walked in place instead of being decoded, which makes the
+/// verification usable in constrained environments, such as light clients and on-chain verifiers.
+/// Keys of up to 32 bytes are tracked on the stack.
+pub fn verify_proof_no_alloc<'a, I>(
+    root: B256,
+    key: &Nibbles,
+    expected_value: Option<&[u8]>,
+    expected_is_private: bool,
+    proof: I,
+) -> Result<ProofVerificationOutcome, ProofVerificationError>
+where
+    I: IntoIterator<Item = &'a [u8]>,
+{
+    let mut proof = proof.into_iter().peekable();
+
+    // If the proof is empty or contains only an empty node, the expected value must be None.
+    if proof.peek().map_or(true, |node| *node == [EMPTY_STRING_CODE]) {
+        return if root != EMPTY_ROOT_HASH {
+            Err(ProofVerificationError::RootMismatch { got: EMPTY_ROOT_HASH, expected: root })
+        } else if let Some(expected) = expected_value {
+            Err(ProofVerificationError::ValueMismatch {
+                path: key.clone(),
+                got: None,
+                expected: Some(Bytes::copy_from_slice(expected)),
+                got_private: false,
+                expected_private: true,
+            })
+        } else {
+            Ok(ProofVerificationOutcome::Excluded)
+        };
+    }
+
+    let mut walked_path = Nibbles::with_capacity(key.len());
+    let mut last_decoded_node = Some(Walked::Node(RlpNode::word_rlp(&root)));
+    let mut last_decoded_node_is_private = false;
+    for (index, node) in proof.enumerate() {
+        // Check if the node matches the one referenced by the previous node (or the root, which
+        // is hashed even if shorter than a word).
+        let node_rlp =
+            if index == 0 { RlpNode::word_rlp(&keccak256(node)) } else { RlpNode::from_rlp(node) };
+        let matches = matches!(
+            &last_decoded_node,
+            Some(Walked::Node(expected)) if *expected == node_rlp
+        );
+        if !matches {
+            return Err(ProofVerificationError::ValueMismatch {
+                path: walked_path,
+                got: Some(Bytes::copy_from_slice(node)),
+                expected: last_decoded_node
+                    .as_ref()
+                    .map(|node| Bytes::copy_from_slice(node.bytes())),
+                got_private: last_decoded_node_is_private,
+                expected_private: false,
+            });
+        }
+
+        last_decoded_node =
+            walk_node(node, &mut walked_path, key, &mut last_decoded_node_is_private, false)?;
+    }
+
+    // Last walked node should have the key that we are looking for.
+    let got = last_decoded_node.as_ref().filter(|_| walked_path == *key).map(Walked::bytes);
+    last_decoded_node_is_private &= got.is_some();
+    if got == expected_value && last_decoded_node_is_private == expected_is_private {
+        Ok(match (got, last_decoded_node_is_private) {
+            (None, _) => ProofVerificationOutcome::Excluded,
+            (Some(_), false) => ProofVerificationOutcome::Included,
+            (Some(_), true) => ProofVerificationOutcome::IncludedPrivate,
+        })
+    } else {
+        Err(ProofVerificationError::ValueMismatch {
+            path: key.clone(),
+            got: got.map(Bytes::copy_from_slice),
+            expected: expected_value.map(Bytes::copy_from_slice),
+            got_private: last_decoded_node_is_private,
+            expected_private: expected_is_private,
+        })
+    }
+}
+
+/// The result of walking a node of the proof.
+enum Walked<'a> {
+    /// Reference to the next node of the proof.
+    Node(RlpNode),
+    /// Value of the reached leaf.
+    Value(&'a [u8]),
+}
+
+impl Walked<'_> {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Self::Node(node) => node.as_slice(),
+            Self::Value(value) => value,
+        }
+    }
+}
+
+/// Walks the given RLP encoded node along the key, descending into the nodes encoded in place.
+///
+/// The node must be a branch node if `branch_only` is set, as the child of an extension node.
+fn walk_node<'a>(
+    node: &'a [u8],
+    walked_path: &mut Nibbles,
+    key: &Nibbles,
+    is_private: &mut bool,
+    branch_only: bool,
+) -> Result<Option<Walked<'a>>, ProofVerificationError> {
+    let mut payload = node;
+    let header = Header::decode(&mut payload)?;
+    if !header.list {
+        return Err(if header.payload_length == 0 {
+            ProofVerificationError::UnexpectedEmptyRoot
+        } else {
+            alloy_rlp::Error::UnexpectedString.into()
+        });
+    }
+    let mut payload =
+        payload.get(..header.payload_length).ok_or(alloy_rlp::Error::InputTooShort)?;
+
+    let mut items = ArrayVec::<&[u8], 17>::new();
+    while !payload.is_empty() {
+        items
+            .try_push(next_item(&mut payload)?)
+            .map_err(|_| alloy_rlp::Error::Custom("invalid number of items in the list"))?;
+    }
+    if branch_only && items.len() != 17 {
+        return Err(ProofVerificationError::MalformedNode(
+            "extension node child is not a branch node",
+        ));
+    }
+
+    match items.len() {
+        17 => {
+            if items[16] != [EMPTY_STRING_CODE] {
+                return Err(alloy_rlp::Error::Custom("branch node values are not supported").into());
+            }
+            let Some(&nibble) = key.get(walked_path.len()) else { return Ok(None) };
+            let child = items[nibble as usize];
+            if child == [EMPTY_STRING_CODE] {
+                return Ok(None);
+            }
+            walked_path.push(nibble);
+            walk_child(child, walked_path, key, is_private, false)
+        }
+        2 => {
+            let encoded_key = Header::decode_bytes(&mut items[0], false)?;
+            let Some(&first) = encoded_key.first() else {
+                return Err(alloy_rlp::Error::Custom("trie node key empty").into());
+            };
+            let (odd, leaf_is_private) = match first & 0xf0 {
+                LeafNode::PUB_EVEN_FLAG => (false, Some(false)),
+                LeafNode::PRIV_EVEN_FLAG => (false, Some(true)),
+                LeafNode::PUB_ODD_FLAG => (true, Some(false)),
+                LeafNode::PRIV_ODD_FLAG => (true, Some(true)),
+                ExtensionNode::ODD_FLAG => (true, None),
+                ExtensionNode::EVEN_FLAG => (false, None),
+                _ => {
+                    return Err(
+                        alloy_rlp::Error::Custom("node is not leaf or extension node").into()
+                    )
+                }
+            };
+            if odd {
+                walked_path.push(first & 0x0f);
+            }
+            for byte in &encoded_key[1..] {
+                walked_path.push(byte >> 4);
+                walked_path.push(byte & 0x0f);
+            }
+
+            match leaf_is_private {
+                Some(leaf_is_private) => {
+                    *is_private = leaf_is_private;
+                    Ok(Some(Walked::Value(Header::decode_bytes(&mut items[1], false)?)))
+                }
+                None => walk_child(items[1], walked_path, key, is_private, true),
+            }
+        }
+        _ => Err(alloy_rlp::Error::Custom("invalid number of items in the list").into()),
+    }
+}
+
+/// Returns the reference to the child if it is hashed, or walks it if it is encoded in place, see
+/// [walk_node].
+fn walk_child<'a>(
+    child: &'a [u8],
+    walked_path: &mut Nibbles,
+    key: &Nibbles,
+    is_private: &mut bool,
+    branch_only: bool,
+) -> Result<Option<Walked<'a>>, ProofVerificationError> {
+    if child.len() == B256::len_bytes() + 1 {
+        Ok(Some(Walked::Node(RlpNode::from_raw_rlp(child)?)))
+    } else {
+        walk_node(child, walked_path, key, is_private, branch_only)
+    }
+}
+
+/// Splits the next RLP item, including its header, off the given list payload.
+fn next_item<'a>(payload: &mut &'a [u8]) -> alloy_rlp::Result<&'a [u8]> {
+    let item = *payload;
+    let header = Header::decode(payload)?;
+    let rest = payload.get(header.payload_length..).ok_or(alloy_rlp::Error::InputTooShort)?;
+    *payload = rest;
+    Ok(&item[..item.len() - rest.len()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        proof::{verify_proof, ProofRetainer},
+        HashBuilder,
+    };
+    use alloc::{collections::BTreeMap, vec::Vec};
+    use alloy_primitives::{hex, keccak256};
+
+    #[test]
+    fn matches_verify_proof() {
+        let leaves = (0..100u64)
+            .map(|i| (keccak256(i.to_be_bytes()), (vec![i as u8; 1 + i as usize % 40], i % 3 == 0)))
+            .collect::<BTreeMap<_, _>>();
+        let targets = leaves
+            .keys()
+            .take(10)
+            .copied()
+            .chain([B256::ZERO, B256::repeat_byte(0xff)])
+            .map(Nibbles::unpack)
+            .collect::<Vec<_>>();
+
+        let mut hb =
+            HashBuilder::default().with_proof_retainer(ProofRetainer::from_iter(targets.clone()));
+        for (key, (value, is_private)) in &leaves {
+            hb.add_leaf(Nibbles::unpack(key), value, *is_private);
+        }
+        let root = hb.root();
+        let proof_nodes = hb.take_proof_nodes();
+
+        for target in targets {
+            let proof = proof_nodes.matching_nodes_for(&target);
+            let leaf = leaves.get(&B256::from_slice(&target.pack()));
+            let expected_value = leaf.map(|(value, _)| value.clone());
+            let is_private = leaf.is_some_and(|(_, is_private)| *is_private);
+
+            let result = verify_proof_no_alloc(
+                root,
+                &target,
+                expected_value.as_deref(),
+                is_private,
+                proof.iter().map(|node| &node[..]),
+            );
+            let outcome = match leaf {
+                None => ProofVerificationOutcome::Excluded,
+                Some((_, false)) => ProofVerificationOutcome::Included,
+                Some((_, true)) => ProofVerificationOutcome::IncludedPrivate,
+            };
+            assert_eq!(result, Ok(outcome));
+            assert_eq!(
+                result,
+                verify_proof(root, target.clone(), expected_value, is_private, &proof)
+            );
+
+            // A wrong value fails the same way as with decoded nodes.
+            assert_eq!(
+                verify_proof_no_alloc(
+                    root,
+                    &target,
+                    Some(&b"wrong"[..]),
+                    is_private,
+                    proof.iter().map(|node| &node[..]),
+                ),
+                verify_proof(root, target, Some(b"wrong".to_vec()), is_private, &proof)
+            );
+        }
+
+        assert_eq!(
+            verify_proof_no_alloc(EMPTY_ROOT_HASH, &Nibbles::default(), None, false, []),
+            Ok(ProofVerificationOutcome::Excluded)
+        );
+    }
+
+    #[test]
+    fn short_root_and_diverging_private_leaf() {
+        let leaf =
+            alloy_rlp::encode(LeafNode::new(Nibbles::from_nibbles([0x1, 0x2]), vec![0x01], true));
+        assert!(leaf.len() < 32);
+        let root = keccak256(&leaf);
+        let proof = [Bytes::from(leaf)];
+
+        for (key, value, is_private, outcome) in [
+            ([0x1, 0x2], Some(vec![0x01]), true, ProofVerificationOutcome::IncludedPrivate),
+            ([0x1, 0x3], None, false, ProofVerificationOutcome::Excluded),
+        ] {
+            let key = Nibbles::from_nibbles(key);
+            let result = verify_proof_no_alloc(
+                root,
+                &key,
+                value.as_deref(),
+                is_private,
+                proof.iter().map(|node| &node[..]),
+            );
+            assert_eq!(result, Ok(outcome));
+            assert_eq!(result, verify_proof(root, key, value, is_private, &proof));
+        }
+    }
+
+    #[test]
+    fn extension_with_in_place_leaf_child() {
+        // A branch node with an in-place extension node pointing to an in-place leaf node, and
+        // a root extension node pointing to an in-place leaf node.
+        let mut branch = vec![0xd5, EMPTY_STRING_CODE];
+        branch.extend(hex!("c41ac22001"));
+        branch.extend([EMPTY_STRING_CODE; 15]);
+        let extension = hex!("c411c22001").to_vec();
+
+        for (node, key) in [(branch, [0x1, 0xa]), (extension, [0x1, 0x0])] {
+            let root = keccak256(&node);
+            let proof = [Bytes::from(node)];
+            let key = Nibbles::from_nibbles(key);
+            let result =
+                verify_proof_no_alloc(root, &key, None, false, proof.iter().map(|node| &node[..]));
+            assert_eq!(
+                result,
+                Err(ProofVerificationError::MalformedNode(
+                    "extension node child is not a branch node"
+                ))
+            );
+            assert_eq!(result, verify_proof(root, key, None, false, &proof));
+        }
+    }
+}
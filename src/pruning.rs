@@ -0,0 +1,195 @@
+//! Trie pruning, finding the nodes no longer referenced after a state transition.
+
+use crate::{nodes::TrieNode, HashMap, EMPTY_ROOT_HASH};
+use alloc::vec::Vec;
+use alloy_primitives::{map::HashSet, Bytes, B256};
+use alloy_rlp::Decodable;
+
+/// Returns the hashes of the nodes of the trie with the old root that are not part of the trie
+/// with the new root, sorted, i.e. the garbage left in a store of trie nodes keyed by their hashes,
+/// like a [TrieWitness](crate::TrieWitness), after the state transition from the old root to the
+/// new one.
+///
+/// Both tries are walked down from their roots level by level, and the walks stop at the subtries
+/// reached by both, found by their hashes even if they moved to another path, so that only the
+/// nodes around the changed paths are visited. Nodes missing from the store are not descended
+/// into, see [missing_nodes](crate::missing_nodes). Storage tries are referenced by the account
+/// leaves and must be pruned separately from their storage roots.
+pub fn removable_nodes(
+    old_root: B256,
+    new_root: B256,
+    nodes: &HashMap<B256, Bytes>,
+) -> Result<Vec<B256>, alloy_rlp::Error> {
+    let mut old = Walk::new(old_root);
+    let mut new = Walk::new(new_root);
+    let mut shared = HashSet::<B256>::default();
+    while !old.level.is_empty() || !new.level.is_empty() {
+        old.visit_level();
+        new.visit_level();
+
+        // Subtries reached by both walks are shared, whatever their depth in either trie.
+        for (hash, _) in &old.level {
+            if new.parents.contains_key(hash) {
+                shared.insert(*hash);
+            }
+        }
+        for (hash, _) in &new.level {
+            if old.parents.contains_key(hash) {
+                shared.insert(*hash);
+            }
+        }
+
+        old.expand_level(&shared, nodes)?;
+        new.expand_level(&shared, nodes)?;
+    }
+
+    let mut removable = Vec::from_iter(
+        old.parents
+            .keys()
+            .filter(|hash| !new.parents.contains_key(*hash) && !old.is_shared(**hash, &shared))
+            .copied(),
+    );
+    removable.sort_unstable();
+    Ok(removable)
+}
+
+/// Breadth-first walk of a trie, keeping the parent of every visited node.
+#[derive(Debug)]
+struct Walk {
+    /// The parents of the visited nodes, [None] for the root.
+    parents: HashMap<B256, Option<B256>>,
+    /// The nodes of the current level along with their parents.
+    level: Vec<(B256, Option<B256>)>,
+}
+
+impl Walk {
+    fn new(root: B256) -> Self {
+        let level = if root == EMPTY_ROOT_HASH { Vec::new() } else { Vec::from([(root, None)]) };
+        Self { parents: HashMap::default(), level }
+    }
+
+    /// Marks the nodes of the current level as visited.
+    fn visit_level(&mut self) {
+        for (hash, parent) in &self.level {
+            self.parents.entry(*hash).or_insert(*parent);
+        }
+    }
+
+    /// Replaces the current level with the children of its nodes, skipping the shared subtries.
+    fn expand_level(
+        &mut self,
+        shared: &HashSet<B256>,
+        nodes: &HashMap<B256, Bytes>,
+    ) -> Result<(), alloy_rlp::Error> {
+        let mut next = Vec::new();
+        for (hash, _) in core::mem::take(&mut self.level) {
+            if self.is_shared(hash, shared) {
+                continue;
+            }
+            let Some(node) = nodes.get(&hash) else { continue };
+            let mut children = Vec::new();
+            hashed_children(TrieNode::decode(&mut &node[..])?, &mut children)?;
+            next.extend(
+                children
+                    .into_iter()
+                    .filter(|child| !self.parents.contains_key(child))
+                    .map(|child| (child, Some(hash))),
+            );
+        }
+        self.level = next;
+        Ok(())
+    }
+
+    /// Returns `true` if the visited node or one of its ancestors is shared.
+    fn is_shared(&self, hash: B256, shared: &HashSet<B256>) -> bool {
+        let mut current = Some(hash);
+        while let Some(hash) = current {
+            if shared.contains(&hash) {
+                return true;
+            }
+            current = self.parents.get(&hash).copied().flatten();
+        }
+        false
+    }
+}
+
+/// Appends the hashes of the children of the node, looking through the children encoded in place.
+fn hashed_children(node: TrieNode, children: &mut Vec<B256>) -> Result<(), alloy_rlp::Error> {
+    let child_refs = match node {
+        TrieNode::Branch(branch) => branch.stack,
+        TrieNode::Extension(extension) => Vec::from([extension.child]),
+        TrieNode::Leaf(_) | TrieNode::EmptyRoot => Vec::new(),
+    };
+    for child in child_refs {
+        match child.as_hash() {
+            Some(hash) => children.push(hash),
+            None => {
+                if let Some(embedded) = TrieNode::decode_embedded(&child)? {
+                    hashed_children(embedded, children)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{proof::ProofRetainer, HashBuilder, Nibbles};
+    use alloc::collections::BTreeMap;
+    use alloy_primitives::keccak256;
+
+    /// Returns the root and the nodes referenced by hash of the trie of the leaves.
+    fn trie_nodes(leaves: &BTreeMap<Nibbles, Vec<u8>>) -> (B256, HashMap<B256, Bytes>) {
+        let mut hb = HashBuilder::default()
+            .with_proof_retainer(ProofRetainer::from_iter(leaves.keys().cloned()));
+        for (key, value) in leaves {
+            hb.add_leaf(key.clone(), value, false);
+        }
+        let root = hb.root();
+        let nodes = hb
+            .take_proof_nodes()
+            .iter_sorted()
+            .filter(|(path, node)| path.is_empty() || node.len() >= 32)
+            .map(|(_, node)| (keccak256(node), node.clone()))
+            .collect();
+        (root, nodes)
+    }
+
+    #[test]
+    fn removable_nodes_after_transition() {
+        let old_leaves = (0..200u64)
+            .map(|i| (Nibbles::unpack(keccak256(i.to_be_bytes())), alloy_rlp::encode(i)))
+            .collect::<BTreeMap<_, _>>();
+        let (old_root, old_nodes) = trie_nodes(&old_leaves);
+
+        // Update, remove and insert leaves, collapsing and splitting branches.
+        let mut new_leaves = old_leaves.clone();
+        for (i, key) in old_leaves.keys().enumerate().step_by(7) {
+            if i % 2 == 0 {
+                new_leaves.insert(key.clone(), b"updated".to_vec());
+            } else {
+                new_leaves.remove(key);
+            }
+        }
+        for i in 1000..1020u64 {
+            new_leaves.insert(Nibbles::unpack(keccak256(i.to_be_bytes())), alloy_rlp::encode(i));
+        }
+        let (new_root, new_nodes) = trie_nodes(&new_leaves);
+
+        let mut nodes = old_nodes.clone();
+        nodes.extend(new_nodes.clone());
+        let mut expected =
+            Vec::from_iter(old_nodes.keys().filter(|hash| !new_nodes.contains_key(*hash)).copied());
+        expected.sort_unstable();
+        assert!(!expected.is_empty());
+        assert_eq!(removable_nodes(old_root, new_root, &nodes), Ok(expected));
+
+        assert_eq!(removable_nodes(old_root, old_root, &nodes), Ok(Vec::new()));
+        assert_eq!(removable_nodes(EMPTY_ROOT_HASH, new_root, &nodes), Ok(Vec::new()));
+        let mut all = Vec::from_iter(old_nodes.into_keys());
+        all.sort_unstable();
+        assert_eq!(removable_nodes(old_root, EMPTY_ROOT_HASH, &nodes), Ok(all));
+    }
+}
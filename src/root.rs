@@ -1,10 +1,21 @@
-use crate::{HashBuilder, EMPTY_ROOT_HASH};
+use crate::{
+    nodes::{BranchNodeRef, ExtensionNodeRef, LeafNodeRef, RlpNode},
+    HashBuilder, TrieMask, EMPTY_ROOT_HASH,
+};
 use alloc::vec::Vec;
-use alloy_primitives::B256;
+use alloy_primitives::{keccak256, B256};
 use alloy_rlp::Encodable;
+use arrayvec::ArrayVec;
+use core::ops::Range;
 use nybbles::Nibbles;
 
 /// Adjust the index of an item for rlp encoding.
+///
+/// The keys of an ordered trie are the RLP encoded indexes of the items, which do not sort like the
+/// indexes: `0` is encoded as `0x80`, after the single byte encodings of `1..=0x7f`, and before the
+/// length prefixed encodings from `0x80` on. Returns the index of the item whose leaf is the `i`-th
+/// in sorted order in a trie of `len` items, so that the leaves can be added to the
+/// [`HashBuilder`] in order. See [rlp_index_nibbles] for the key of the leaf.
 pub const fn adjust_index_for_rlp(i: usize, len: usize) -> usize {
     if i > 0x7f {
         i
@@ -15,6 +26,44 @@ pub const fn adjust_index_for_rlp(i: usize, len: usize) -> usize {
     }
 }
 
+/// Adjust the index of an item for rlp encoding, returning [None] if it is out of bounds.
+/// See [`adjust_index_for_rlp`] for more info.
+pub const fn checked_adjust_index_for_rlp(i: usize, len: usize) -> Option<usize> {
+    if i < len {
+        Some(adjust_index_for_rlp(i, len))
+    } else {
+        None
+    }
+}
+
+/// Returns the key of the `i`-th leaf in sorted order of an ordered trie of `len` items, the
+/// nibbles of the RLP encoded index [`adjust_index_for_rlp(i, len)`](adjust_index_for_rlp).
+///
+/// This is the path of the leaf in the trie, which proof tooling can use to retain or verify the
+/// proofs of the items of an ordered trie, such as the receipts of a block, in leaf order. The
+/// position of the leaf of the item at a given index is the position of the index in
+/// [ordered_trie_root_indices].
+pub fn rlp_index_nibbles(i: usize, len: usize) -> Nibbles {
+    Nibbles::unpack(alloy_rlp::encode_fixed_size(&adjust_index_for_rlp(i, len)))
+}
+
+/// Returns the indexes of the items of an ordered trie in the order of their leaves, i.e. the
+/// sorted order of the RLP encoded indexes, e.g. to align the leaves or the proofs of the trie
+/// with the positions of the items.
+///
+/// The items `1..=0x7f` come first since their keys are single bytes below `0x80`, followed by the
+/// item `0` encoded as `0x80` and the items from `0x80` on in order. The `i`-th element equals
+/// [`adjust_index_for_rlp(i, len)`](adjust_index_for_rlp).
+pub fn ordered_trie_root_indices(len: usize) -> Vec<usize> {
+    let mut indices = Vec::with_capacity(len);
+    if len > 0 {
+        indices.extend(1..len.min(0x80));
+        indices.push(0);
+        indices.extend(0x80..len);
+    }
+    indices
+}
+
 /// Compute a trie root of the collection of rlp encodable items.
 /// This function does not support private nodes.
 /// and is used for things like receipt roots rather than state roots.
@@ -29,26 +78,248 @@ pub fn ordered_trie_root_with_encoder<T, F>(items: &[T], mut encode: F) -> B256
 where
     F: FnMut(&T, &mut Vec<u8>),
 {
-    if items.is_empty() {
+    ordered_trie_root_with_privacy_encoder(items, |item, buf| {
+        encode(item, buf);
+        false
+    })
+}
+
+/// Compute a trie root of the collection of rlp encodable items, marking each leaf as private
+/// according to the given predicate.
+/// See [`ordered_trie_root_with_privacy_encoder`] for more info.
+pub fn ordered_trie_root_with_privacy<T, P>(items: &[T], mut is_private: P) -> B256
+where
+    T: Encodable,
+    P: FnMut(&T) -> bool,
+{
+    ordered_trie_root_with_privacy_encoder(items, |item, buf| {
+        item.encode(buf);
+        is_private(item)
+    })
+}
+
+/// Compute a trie root of the collection of items with a custom encoder that also returns
+/// whether the encoded item should be stored in a private leaf.
+/// SEISMIC WARNING: Ensure that shielded items are flagged correctly when calling
+pub fn ordered_trie_root_with_privacy_encoder<T, F>(items: &[T], mut encode: F) -> B256
+where
+    F: FnMut(&T, &mut Vec<u8>) -> bool,
+{
+    ordered_trie_root_from_iter_with_privacy_encoder(items, |item, buf| encode(*item, buf))
+}
+
+/// Compute a trie root of the rlp encodable items yielded by the iterator, without requiring
+/// them to be collected into a slice first.
+/// See [`ordered_trie_root`] for more info.
+pub fn ordered_trie_root_from_iter<T, I>(items: I) -> B256
+where
+    T: Encodable,
+    I: IntoIterator<Item = T>,
+    I::IntoIter: ExactSizeIterator,
+{
+    ordered_trie_root_from_iter_with_privacy_encoder(items, |item, buf| {
+        item.encode(buf);
+        false
+    })
+}
+
+/// Compute a trie root of the items yielded by the iterator with a custom encoder that also
+/// returns whether the encoded item should be stored in a private leaf.
+///
+/// Only the first item is buffered, since it is inserted after the items with the indexes up
+/// to `0x7f`, all other items are inserted in the order they are yielded.
+/// SEISMIC WARNING: Ensure that shielded items are flagged correctly when calling
+pub fn ordered_trie_root_from_iter_with_privacy_encoder<T, I, F>(items: I, mut encode: F) -> B256
+where
+    I: IntoIterator<Item = T>,
+    I::IntoIter: ExactSizeIterator,
+    F: FnMut(&T, &mut Vec<u8>) -> bool,
+{
+    let mut items = items.into_iter();
+    let items_len = items.len();
+    let Some(first_item) = items.next() else {
         return EMPTY_ROOT_HASH;
-    }
+    };
 
-    let mut value_buffer = Vec::new();
+    let mut first_value_buffer = Vec::new();
+    let first_is_private = encode(&first_item, &mut first_value_buffer);
 
     let mut hb = HashBuilder::default();
-    let items_len = items.len();
     for i in 0..items_len {
-        let index = adjust_index_for_rlp(i, items_len);
+        let key = rlp_index_nibbles(i, items_len);
+        if adjust_index_for_rlp(i, items_len) == 0 {
+            hb.add_leaf(key, &first_value_buffer, first_is_private);
+        } else {
+            let item = items.next().expect("iterator yielded fewer items than its length");
+            hb.add_leaf_with(key, |buf| encode(&item, buf));
+        }
+    }
+
+    hb.root()
+}
+
+/// Compute a trie root of the collection of rlp encodable items, like [`ordered_trie_root`].
+///
+/// The keys of the trie are the RLP encoded indexes of the items, which are at most 3 bytes long
+/// for up to `0x10000` items. The trie is built directly from the keys kept in fixed size arrays
+/// instead of going through the [`HashBuilder`], which is faster for large collections such as
+/// the receipts of a block. Larger collections fall back to [`ordered_trie_root`].
+/// This function does not support private nodes.
+pub fn indexed_trie_root<T: Encodable>(items: &[T]) -> B256 {
+    indexed_trie_root_with_privacy_encoder(items, |item, buf| {
+        item.encode(buf);
+        false
+    })
+}
+
+/// Compute a trie root of the collection of items with a custom encoder that also returns
+/// whether the encoded item should be stored in a private leaf.
+/// See [`indexed_trie_root`] for more info.
+/// SEISMIC WARNING: Ensure that shielded items are flagged correctly when calling
+pub fn indexed_trie_root_with_privacy_encoder<T, F>(items: &[T], encode: F) -> B256
+where
+    F: FnMut(&T, &mut Vec<u8>) -> bool,
+{
+    match items.len() {
+        0 => EMPTY_ROOT_HASH,
+        // Indexes up to `0x7f` are encoded as a single byte.
+        1..=0x80 => IndexedTrie::<2>::new(items, encode).root(),
+        // Indexes up to `0xff` are encoded as the length prefix followed by one byte.
+        0x81..=0x100 => IndexedTrie::<4>::new(items, encode).root(),
+        // Indexes up to `0xffff` are encoded as the length prefix followed by two bytes.
+        0x101..=0x10000 => IndexedTrie::<6>::new(items, encode).root(),
+        _ => ordered_trie_root_with_privacy_encoder(items, encode),
+    }
+}
+
+/// Trie over the items keyed by their RLP encoded indexes of at most `N` nibbles.
+struct IndexedTrie<const N: usize> {
+    /// The leaves sorted by key.
+    leaves: Vec<IndexedLeaf<N>>,
+    /// The encoded values of all leaves.
+    values: Vec<u8>,
+}
+
+/// Leaf of the [IndexedTrie].
+struct IndexedLeaf<const N: usize> {
+    /// The nibbles of the key, only the first `key_len` of which are set.
+    key: [u8; N],
+    /// The number of nibbles of the key.
+    key_len: usize,
+    /// The range of the encoded value in [IndexedTrie::values].
+    value: Range<usize>,
+    /// Whether the leaf is private.
+    is_private: bool,
+}
+
+impl<const N: usize> IndexedTrie<N> {
+    /// Encodes the items into leaves sorted by key.
+    fn new<T, F>(items: &[T], mut encode: F) -> Self
+    where
+        F: FnMut(&T, &mut Vec<u8>) -> bool,
+    {
+        let mut leaves = Vec::with_capacity(items.len());
+        let mut values = Vec::new();
+        for i in 0..items.len() {
+            let index = adjust_index_for_rlp(i, items.len());
+            let index_buffer = alloy_rlp::encode_fixed_size(&index);
 
-        let index_buffer = alloy_rlp::encode_fixed_size(&index);
+            let mut key = [0; N];
+            for (j, byte) in index_buffer.iter().enumerate() {
+                key[2 * j] = byte >> 4;
+                key[2 * j + 1] = byte & 0x0f;
+            }
 
-        value_buffer.clear();
-        encode(&items[index], &mut value_buffer);
+            let start = values.len();
+            let is_private = encode(&items[index], &mut values);
+            leaves.push(IndexedLeaf {
+                key,
+                key_len: 2 * index_buffer.len(),
+                value: start..values.len(),
+                is_private,
+            });
+        }
+        Self { leaves, values }
+    }
+
+    /// Computes the root hash of the trie.
+    fn root(&self) -> B256 {
+        let mut rlp_buf = Vec::new();
+        let root = self.node(&self.leaves, 0, &mut rlp_buf);
+        root.as_hash().unwrap_or_else(|| keccak256(&root))
+    }
+
+    /// Returns the node of the subtrie of the given leaves, sharing the first `depth` nibbles.
+    fn node(&self, leaves: &[IndexedLeaf<N>], depth: usize, rlp_buf: &mut Vec<u8>) -> RlpNode {
+        if let [leaf] = leaves {
+            let key = Nibbles::from_nibbles_unchecked(&leaf.key[depth..leaf.key_len]);
+            rlp_buf.clear();
+            return LeafNodeRef::new(&key, &self.values[leaf.value.clone()], &leaf.is_private)
+                .rlp(rlp_buf);
+        }
 
-        let is_private = false; // TODO: fix
-        hb.add_leaf(Nibbles::unpack(&index_buffer), &value_buffer, is_private);
+        // The leaves are sorted, so the first and the last ones share the common prefix.
+        let (first, last) = (&leaves[0], &leaves[leaves.len() - 1]);
+        let prefix_len = first.key[depth..first.key_len]
+            .iter()
+            .zip(&last.key[depth..last.key_len])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if prefix_len == 0 {
+            return self.branch(leaves, depth, rlp_buf);
+        }
+
+        let child = self.branch(leaves, depth + prefix_len, rlp_buf);
+        let key = Nibbles::from_nibbles_unchecked(&first.key[depth..depth + prefix_len]);
+        rlp_buf.clear();
+        ExtensionNodeRef::new(&key, &child).rlp(rlp_buf)
+    }
+
+    /// Returns the branch node of the given leaves, which diverge at the nibble at `depth`.
+    fn branch(
+        &self,
+        mut leaves: &[IndexedLeaf<N>],
+        depth: usize,
+        rlp_buf: &mut Vec<u8>,
+    ) -> RlpNode {
+        let mut children = ArrayVec::<RlpNode, 16>::new();
+        let mut state_mask = TrieMask::default();
+        while let Some(first) = leaves.first() {
+            let nibble = first.key[depth];
+            let (group, rest) =
+                leaves.split_at(leaves.partition_point(|leaf| leaf.key[depth] == nibble));
+            children.push(self.node(group, depth + 1, rlp_buf));
+            state_mask.set_bit(nibble);
+            leaves = rest;
+        }
+        rlp_buf.clear();
+        BranchNodeRef::new(&children, state_mask).rlp(rlp_buf)
     }
+}
 
+/// Computes the root of the subtrie under the given prefix from the sorted leaves of a trie, e.g.
+/// to compute the roots of the shards of a sharded trie separately, or to verify a partial
+/// snapshot against [`subtree_root_from_proof`](crate::proof::subtree_root_from_proof).
+///
+/// The leaves outside of the prefix are skipped, and the prefix is stripped from the keys of the
+/// others, so the result is the hash of the node at the prefix in the full trie, if the prefix
+/// ends at a node rather than within the key of an extension or a leaf. Returns
+/// [EMPTY_ROOT_HASH] if there are no leaves under the prefix.
+/// SEISMIC WARNING: Ensure that the leaves are flagged correctly when calling
+///
+/// # Panics
+///
+/// If the leaves under the prefix are not in sorted order.
+pub fn subtrie_root<V: AsRef<[u8]>>(
+    prefix: &Nibbles,
+    items: impl IntoIterator<Item = (Nibbles, V, bool)>,
+) -> B256 {
+    let mut hb = HashBuilder::default();
+    for (key, value, is_private) in items {
+        if key.starts_with(prefix) {
+            hb.add_leaf(key.slice(prefix.len()..), value.as_ref(), is_private);
+        }
+    }
     hb.root()
 }
 
@@ -62,6 +333,10 @@ mod ethereum {
     /// Trait for storage values that can be marked as public or private
     /// Useful for making the following functions generic over the type of storage value.
     /// to avoid breaking changes in the API for downstream repos
+    ///
+    /// The trait is intentionally not sealed, so that downstream slot types, e.g. carrying a
+    /// ciphertext along with the privacy flag, can implement it and override
+    /// [`FlaggedStorageValue::encode_value`] to control what is stored in the leaves.
     pub trait FlaggedStorageValue {
         /// returns whether the value is private
         fn is_private(&self) -> bool {
@@ -69,6 +344,12 @@ mod ethereum {
         }
         /// returns the underlying value
         fn value(&self) -> &U256;
+        /// encodes the value stored in the leaf, the RLP encoding of [`Self::value`] by default
+        ///
+        /// The encoding must be deterministic since it determines the storage root.
+        fn encode_value(&self, out: &mut Vec<u8>) {
+            self.value().encode(out);
+        }
     }
 
     impl FlaggedStorageValue for U256 {
@@ -84,32 +365,227 @@ mod ethereum {
             &self.0
         }
     }
+    impl<T: FlaggedStorageValue> FlaggedStorageValue for &T {
+        fn is_private(&self) -> bool {
+            (**self).is_private()
+        }
+        fn value(&self) -> &U256 {
+            (**self).value()
+        }
+        fn encode_value(&self, out: &mut Vec<u8>) {
+            (**self).encode_value(out)
+        }
+    }
 
     use super::*;
-    use crate::TrieAccount;
+    use crate::{
+        key_hasher::{KeccakKeyHasher, KeyHasher},
+        nibbles::unpack_b256,
+        proof::{ProofRetainer, StorageProof},
+        GenesisAccount, StateRootError, TrieAccount,
+    };
+    use alloc::collections::BTreeMap;
     use alloy_primitives::{keccak256, Address};
 
+    use crate::HashMap;
+
+    /// An [EIP-2718](https://eips.ethereum.org/EIPS/eip-2718) typed envelope, such as a receipt
+    /// or a transaction.
+    pub trait TypedEnvelope {
+        /// Returns the type byte of the envelope, or [None] for legacy items.
+        fn type_flag(&self) -> Option<u8>;
+
+        /// RLP-encodes the item without the type byte.
+        fn encode_payload(&self, out: &mut Vec<u8>);
+
+        /// Encodes the item as `type_flag || rlp(payload)`, or as `rlp(payload)` for legacy
+        /// items.
+        fn encode_2718(&self, out: &mut Vec<u8>) {
+            if let Some(type_flag) = self.type_flag() {
+                out.push(type_flag);
+            }
+            self.encode_payload(out);
+        }
+    }
+
+    /// Calculates the receipts root of a block from its receipts encoded as EIP-2718 envelopes.
+    pub fn receipts_root<R: TypedEnvelope>(receipts: &[R]) -> B256 {
+        ordered_trie_root_with_encoder(receipts, |receipt, buf| receipt.encode_2718(buf))
+    }
+
+    /// Calculates the transactions root of a block from its transactions encoded as EIP-2718
+    /// envelopes.
+    pub fn transactions_root<T: TypedEnvelope>(transactions: &[T]) -> B256 {
+        ordered_trie_root_with_encoder(transactions, |transaction, buf| {
+            transaction.encode_2718(buf)
+        })
+    }
+
+    /// Calculates the withdrawals root of a block.
+    ///
+    /// Withdrawals are encoded as the [EIP-4895](https://eips.ethereum.org/EIPS/eip-4895) RLP list
+    /// `[index, validator_index, address, amount]`, with the amount in Gwei.
+    pub fn withdrawals_root<W: Encodable>(withdrawals: &[W]) -> B256 {
+        ordered_trie_root(withdrawals)
+    }
+
+    /// Calculates the requests root of a block.
+    ///
+    /// Every [EIP-7685](https://eips.ethereum.org/EIPS/eip-7685) execution layer request is given
+    /// in its opaque encoding `request_type || request_data` and inserted into the trie as is.
+    pub fn requests_root<R: AsRef<[u8]>>(requests: &[R]) -> B256 {
+        ordered_trie_root_with_encoder(requests, |request, buf| {
+            buf.extend_from_slice(request.as_ref())
+        })
+    }
+
     /// Hashes storage keys, sorts them and them calculates the root hash of the storage trie.
     /// See [`storage_root_unsorted`] for more info.
     /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
     pub fn storage_root_unhashed<T: FlaggedStorageValue>(
         storage: impl IntoIterator<Item = (B256, T)>,
     ) -> B256 {
-        storage_root_unsorted(storage.into_iter().map(|(slot, value)| (keccak256(slot), value)))
+        storage_root_unhashed_with_key_hasher(storage, &mut KeccakKeyHasher)
+    }
+
+    /// Hashes storage keys with the given [KeyHasher], e.g. a [`HashedKeyCache`], sorts them and
+    /// calculates the root hash of the storage trie.
+    /// See [`storage_root_unsorted`] for more info.
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    ///
+    /// [`HashedKeyCache`]: crate::key_hasher::HashedKeyCache
+    pub fn storage_root_unhashed_with_key_hasher<T: FlaggedStorageValue>(
+        storage: impl IntoIterator<Item = (B256, T)>,
+        key_hasher: &mut impl KeyHasher,
+    ) -> B256 {
+        storage_root_unsorted(
+            storage.into_iter().map(|(slot, value)| (key_hasher.hash_slot(&slot), value)),
+        )
+    }
+
+    /// Hashes the `U256` storage keys of the `(slot, value, is_private)` items, as output by
+    /// execution, sorts them and calculates the root hash of the storage trie.
+    /// See [`storage_root_unsorted`] for more info.
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn storage_root_unhashed_slots(
+        storage: impl IntoIterator<Item = (U256, U256, bool)>,
+    ) -> B256 {
+        storage_root_unhashed(
+            storage
+                .into_iter()
+                .map(|(slot, value, is_private)| (B256::from(slot), (value, is_private))),
+        )
+    }
+
+    /// Hashes storage keys, sorts them and calculates the root hash of the storage trie along with
+    /// the [StorageProof]s of the target slots, in the order of the targets.
+    ///
+    /// The proofs are verified against the RLP encoding of the slot values, so the values must use
+    /// the default [`FlaggedStorageValue::encode_value`] and the zero-valued slots must be pruned.
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn storage_root_with_proofs<T: FlaggedStorageValue>(
+        storage: impl IntoIterator<Item = (B256, T)>,
+        targets: &[B256],
+    ) -> (B256, Vec<StorageProof>) {
+        let mut storage =
+            Vec::from_iter(storage.into_iter().map(|(slot, value)| (keccak256(slot), value)));
+        storage.sort_unstable_by_key(|(key, _)| *key);
+        let hashed_targets = Vec::from_iter(targets.iter().map(keccak256));
+
+        let mut hb = HashBuilder::default()
+            .with_proof_retainer(ProofRetainer::from_iter(hashed_targets.iter().map(unpack_b256)));
+        let mut value_buf = Vec::new();
+        for (hashed_slot, value) in &storage {
+            value_buf.clear();
+            value.encode_value(&mut value_buf);
+            hb.add_leaf(unpack_b256(hashed_slot), &value_buf, value.is_private());
+        }
+        let root = hb.root();
+        let proof_nodes = hb.take_proof_nodes();
+
+        let proofs = targets
+            .iter()
+            .zip(&hashed_targets)
+            .map(|(slot, hashed_slot)| {
+                let mut proof = StorageProof::new(*slot);
+                if let Ok(index) = storage.binary_search_by_key(hashed_slot, |(key, _)| *key) {
+                    let value = &storage[index].1;
+                    proof.value = *value.value();
+                    proof.is_private = value.is_private();
+                }
+                proof.proof = proof_nodes.matching_nodes_for(&unpack_b256(hashed_slot));
+                proof
+            })
+            .collect();
+        (root, proofs)
     }
 
     /// Sorts and calculates the root hash of account storage trie.
     /// See [`storage_root`] for more info.
     /// /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    ///
+    /// # Panics
+    ///
+    /// If a slot is repeated, see [`storage_root_unsorted_with_duplicates`].
     pub fn storage_root_unsorted<T: FlaggedStorageValue>(
         storage: impl IntoIterator<Item = (B256, T)>,
     ) -> B256 {
         // transform the storage keys
         let mut v = Vec::from_iter(storage);
-        v.sort_unstable_by_key(|(key, _)| *key);
+        sort_by_key(&mut v);
         storage_root(v)
     }
 
+    /// Sorts and calculates the root hash of account storage trie, handling the repeated slots as
+    /// specified by [DuplicateKeys].
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn storage_root_unsorted_with_duplicates<T: FlaggedStorageValue>(
+        storage: impl IntoIterator<Item = (B256, T)>,
+        duplicates: DuplicateKeys,
+    ) -> Result<B256, StateRootError> {
+        try_storage_root(sort_with_duplicates(storage, duplicates))
+    }
+
+    /// How the repeated keys are handled when sorting the items, e.g. the slots written by several
+    /// transactions of a block.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub enum DuplicateKeys {
+        /// Returns a [StateRootError] if a key is repeated.
+        #[default]
+        Reject,
+        /// Keeps the last value of a repeated key.
+        LastWins,
+    }
+
+    /// Sorts the items by key, handling the repeated keys as specified.
+    fn sort_with_duplicates<V>(
+        items: impl IntoIterator<Item = (B256, V)>,
+        duplicates: DuplicateKeys,
+    ) -> Vec<(B256, V)> {
+        let mut items = Vec::from_iter(items);
+        match duplicates {
+            DuplicateKeys::Reject => sort_by_key(&mut items),
+            DuplicateKeys::LastWins => {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::debug_span!(target: "trie::root", "sort", items = items.len())
+                    .entered();
+                // The stable sort keeps the reversed values of a key in order, the last one first.
+                items.reverse();
+                items.sort_by_key(|(key, _)| *key);
+                items.dedup_by_key(|(key, _)| *key);
+            }
+        }
+        items
+    }
+
+    /// Sorts the items by key, in a `sort` span with the `tracing` feature.
+    fn sort_by_key<V>(items: &mut [(B256, V)]) {
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::debug_span!(target: "trie::root", "sort", items = items.len()).entered();
+        items.sort_unstable_by_key(|(key, _)| *key);
+    }
+
     /// Calculates the root hash of account storage trie.
     ///
     /// # Panics
@@ -121,15 +597,124 @@ mod ethereum {
     ) -> B256 {
         let mut hb = HashBuilder::default();
         for (hashed_slot, value) in storage {
-            hb.add_leaf(
-                Nibbles::unpack(hashed_slot),
-                alloy_rlp::encode_fixed_size(value.value()).as_ref(),
-                value.is_private(),
-            );
+            hb.add_leaf_with(unpack_b256(&hashed_slot), |buf| {
+                value.encode_value(buf);
+                value.is_private()
+            });
         }
         hb.root()
     }
 
+    /// Calculates the root hash of account storage trie like [`storage_root`], returning an error
+    /// instead of panicking if the items are not in sorted order or a slot is repeated.
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn try_storage_root<T: FlaggedStorageValue>(
+        storage: impl IntoIterator<Item = (B256, T)>,
+    ) -> Result<B256, StateRootError> {
+        let mut hb = HashBuilder::default();
+        let mut value_buf = Vec::new();
+        for (hashed_slot, value) in storage {
+            value_buf.clear();
+            value.encode_value(&mut value_buf);
+            hb.try_add_leaf(unpack_b256(&hashed_slot), &value_buf, value.is_private())?;
+        }
+        Ok(hb.root())
+    }
+
+    /// The roots of a storage trie computed by [`storage_root_split`].
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct StorageRoots {
+        /// The canonical root of the storage trie, committing to all the slots.
+        pub full: B256,
+        /// The root of the public view of the storage trie, which only contains the public slots
+        /// and can be checked by verifiers that do not see the private ones.
+        pub public_only: B256,
+    }
+
+    /// Calculates the canonical root hash of account storage trie along with the root hash of its
+    /// public view in one pass.
+    ///
+    /// # Panics
+    ///
+    /// If the items are not in sorted order.
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn storage_root_split<T: FlaggedStorageValue>(
+        storage: impl IntoIterator<Item = (B256, T)>,
+    ) -> StorageRoots {
+        let mut hb = HashBuilder::default().with_public_root(true);
+        for (hashed_slot, value) in storage {
+            hb.add_leaf_with(unpack_b256(&hashed_slot), |buf| {
+                value.encode_value(buf);
+                value.is_private()
+            });
+        }
+        let full = hb.root();
+        let public_only = hb.public_root().expect("public view is computed from the leaves");
+        StorageRoots { full, public_only }
+    }
+
+    /// Hashes storage keys, sorts them and calculates the root hash of the storage trie, skipping
+    /// the public zero-valued slots like geth does.
+    /// See [`storage_root_prune_zero`] for more info.
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn storage_root_unhashed_prune_zero<T: FlaggedStorageValue>(
+        storage: impl IntoIterator<Item = (B256, T)>,
+    ) -> B256 {
+        storage_root_unhashed(storage.into_iter().filter(|(_, value)| !is_pruned(value)))
+    }
+
+    /// Calculates the root hash of account storage trie, skipping the public zero-valued slots.
+    ///
+    /// Private zero-valued slots are still committed to, since whether a private slot is zero
+    /// must not be observable from the trie.
+    ///
+    /// # Panics
+    ///
+    /// If the items are not in sorted order.
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn storage_root_prune_zero<T: FlaggedStorageValue>(
+        storage: impl IntoIterator<Item = (B256, T)>,
+    ) -> B256 {
+        storage_root(storage.into_iter().filter(|(_, value)| !is_pruned(value)))
+    }
+
+    #[inline]
+    fn is_pruned<T: FlaggedStorageValue>(value: &T) -> bool {
+        value.value().is_zero() && !value.is_private()
+    }
+
+    /// Calculates the root hash of account storage trie from a map that is already sorted by
+    /// hashed slot, without collecting and sorting the entries first.
+    /// See [`storage_root`] for more info.
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn storage_root_from_map<T: FlaggedStorageValue>(storage: &BTreeMap<B256, T>) -> B256 {
+        storage_root(storage.iter().map(|(hashed_slot, value)| (*hashed_slot, value)))
+    }
+
+    /// Calculates the storage roots of many accounts concurrently on the rayon thread pool.
+    ///
+    /// The storage entries of every account are keyed by hashed slot and do not need to be sorted.
+    /// Returns the storage roots indexed by hashed address.
+    /// See [`storage_root_unsorted`] for more info.
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    #[cfg(feature = "parallel")]
+    pub fn storage_roots_parallel<S, T>(
+        accounts: impl IntoIterator<Item = (B256, S)>,
+    ) -> HashMap<B256, B256>
+    where
+        S: IntoIterator<Item = (B256, T)> + Send,
+        T: FlaggedStorageValue,
+    {
+        use rayon::prelude::*;
+
+        let accounts = Vec::from_iter(accounts);
+        let roots: Vec<(B256, B256)> = accounts
+            .into_par_iter()
+            .map(|(hashed_address, storage)| (hashed_address, storage_root_unsorted(storage)))
+            .collect();
+        HashMap::from_iter(roots)
+    }
+
     /// Hashes and sorts account keys, then proceeds to calculating the root hash of the state
     /// represented as MPT.
     /// See [`state_root_unsorted`] for more info.
@@ -141,27 +726,77 @@ mod ethereum {
         )
     }
 
+    /// Hashes and sorts account keys, then proceeds to calculating the root hash of the state
+    /// represented as MPT, converting the borrowed accounts with the given function instead of
+    /// cloning them.
+    /// See [`state_root_unsorted`] for more info.
+    pub fn state_root_ref_unhashed_with<'a, A: 'a>(
+        state: impl IntoIterator<Item = (&'a Address, &'a A)>,
+        to_account: impl Fn(&A) -> TrieAccount,
+    ) -> B256 {
+        state_root_unsorted(
+            state.into_iter().map(|(address, account)| (keccak256(address), to_account(account))),
+        )
+    }
+
+    /// Calculates the state root of a genesis allocation, hashing the addresses and computing the
+    /// storage root of every account from its flagged storage slots.
+    /// See [`state_root_unsorted`] for more info.
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    pub fn genesis_state_root<'a, T: FlaggedStorageValue + 'a>(
+        alloc: impl IntoIterator<Item = (&'a Address, &'a GenesisAccount<T>)>,
+    ) -> B256 {
+        state_root_ref_unhashed_with(alloc, GenesisAccount::trie_account)
+    }
+
     /// Hashes and sorts account keys, then proceeds to calculating the root hash of the state
     /// represented as MPT.
     /// See [`state_root_unsorted`] for more info.
     pub fn state_root_unhashed<A: Into<TrieAccount>>(
         state: impl IntoIterator<Item = (Address, A)>,
+    ) -> B256 {
+        state_root_unhashed_with_key_hasher(state, &mut KeccakKeyHasher)
+    }
+
+    /// Hashes addresses with the given [KeyHasher], e.g. a [`HashedKeyCache`], sorts them and
+    /// calculates the root hash of the state.
+    /// See [`state_root_unsorted`] for more info.
+    ///
+    /// [`HashedKeyCache`]: crate::key_hasher::HashedKeyCache
+    pub fn state_root_unhashed_with_key_hasher<A: Into<TrieAccount>>(
+        state: impl IntoIterator<Item = (Address, A)>,
+        key_hasher: &mut impl KeyHasher,
     ) -> B256 {
         state_root_unsorted(
-            state.into_iter().map(|(address, account)| (keccak256(address), account)),
+            state
+                .into_iter()
+                .map(|(address, account)| (key_hasher.hash_address(&address), account)),
         )
     }
 
     /// Sorts the hashed account keys and calculates the root hash of the state represented as MPT.
     /// See [`state_root`] for more info.
+    ///
+    /// # Panics
+    ///
+    /// If an account is repeated, see [`state_root_unsorted_with_duplicates`].
     pub fn state_root_unsorted<A: Into<TrieAccount>>(
         state: impl IntoIterator<Item = (B256, A)>,
     ) -> B256 {
         let mut vec = Vec::from_iter(state);
-        vec.sort_unstable_by_key(|(key, _)| *key);
+        sort_by_key(&mut vec);
         state_root(vec)
     }
 
+    /// Sorts the hashed account keys and calculates the root hash of the state represented as MPT,
+    /// handling the repeated accounts as specified by [DuplicateKeys].
+    pub fn state_root_unsorted_with_duplicates<A: Into<TrieAccount>>(
+        state: impl IntoIterator<Item = (B256, A)>,
+        duplicates: DuplicateKeys,
+    ) -> Result<B256, StateRootError> {
+        try_state_root(sort_with_duplicates(state, duplicates))
+    }
+
     /// Calculates the root hash of the state represented as MPT.
     ///
     /// Corresponds to [geth's `deriveHash`](https://github.com/ethereum/go-ethereum/blob/6c149fd4ad063f7c24d726a73bc0546badd1bc73/core/genesis.go#L119).
@@ -170,14 +805,774 @@ mod ethereum {
     ///
     /// If the items are not in sorted order.
     pub fn state_root<A: Into<TrieAccount>>(state: impl IntoIterator<Item = (B256, A)>) -> B256 {
+        let mut hb = HashBuilder::default();
+        for (hashed_key, account) in state {
+            hb.add_leaf_with(unpack_b256(&hashed_key), |buf| {
+                account.into().encode_into(buf);
+                false // account nodes are always public
+            });
+        }
+        hb.root()
+    }
+
+    /// Calculates the root hash of the state represented as MPT like [`state_root`], returning an
+    /// error instead of panicking if the items are not in sorted order or an account is repeated.
+    pub fn try_state_root<A: Into<TrieAccount>>(
+        state: impl IntoIterator<Item = (B256, A)>,
+    ) -> Result<B256, StateRootError> {
         let mut hb = HashBuilder::default();
         let mut account_rlp_buf = Vec::new();
-        let is_private = false; // account nodes are always public
         for (hashed_key, account) in state {
             account_rlp_buf.clear();
-            account.into().encode(&mut account_rlp_buf);
-            hb.add_leaf(Nibbles::unpack(hashed_key), &account_rlp_buf, is_private);
+            account.into().encode_into(&mut account_rlp_buf);
+            hb.try_add_leaf(unpack_b256(&hashed_key), &account_rlp_buf, false)?;
+        }
+        Ok(hb.root())
+    }
+
+    /// Calculates the root hash of the state from a map that is already sorted by hashed address,
+    /// without collecting and sorting the accounts first.
+    /// See [`state_root`] for more info.
+    pub fn state_root_from_map<A: Into<TrieAccount> + Clone>(state: &BTreeMap<B256, A>) -> B256 {
+        state_root(state.iter().map(|(hashed_key, account)| (*hashed_key, account.clone())))
+    }
+
+    /// Calculates the storage root of every account and the root hash of the state in a single
+    /// pass, returning the state root along with the storage roots indexed by hashed address.
+    ///
+    /// The storage entries of every account are keyed by hashed slot and do not need to be sorted,
+    /// and the storage root of the given account is replaced with the computed one.
+    /// See [`state_root`] and [`storage_root_unsorted`] for more info.
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    ///
+    /// # Panics
+    ///
+    /// If the accounts are not in sorted order.
+    pub fn state_root_with_storage_roots<A, S, T>(
+        state_with_storages: impl IntoIterator<Item = (B256, (A, S))>,
+    ) -> (B256, HashMap<B256, B256>)
+    where
+        A: Into<TrieAccount>,
+        S: IntoIterator<Item = (B256, T)>,
+        T: FlaggedStorageValue,
+    {
+        let mut hb = HashBuilder::default();
+        let mut storage_roots = HashMap::default();
+        for (hashed_key, (account, storage)) in state_with_storages {
+            let storage_root = storage_root_unsorted(storage);
+            storage_roots.insert(hashed_key, storage_root);
+            hb.add_leaf_with(unpack_b256(&hashed_key), |buf| {
+                TrieAccount { storage_root, ..account.into() }.encode_into(buf);
+                false // account nodes are always public
+            });
+        }
+        (hb.root(), storage_roots)
+    }
+
+    /// Hashes storage keys on the rayon thread pool, sorts them and calculates the root hash of the
+    /// storage trie. Computes the same root as [`storage_root_unhashed`].
+    /// SEISMIC WARNING: Ensure that the storage values are flagged correctly when calling
+    #[cfg(feature = "parallel")]
+    pub fn storage_root_unhashed_parallel<T: FlaggedStorageValue + Send>(
+        storage: impl IntoIterator<Item = (B256, T)>,
+    ) -> B256 {
+        storage_root(hash_keys_parallel(storage))
+    }
+
+    /// Hashes addresses on the rayon thread pool, sorts them and calculates the root hash of the
+    /// state with [`state_root_parallel`]. Computes the same root as [`state_root_unhashed`].
+    #[cfg(feature = "parallel")]
+    pub fn state_root_unhashed_parallel<A: Into<TrieAccount> + Send>(
+        state: impl IntoIterator<Item = (Address, A)>,
+    ) -> B256 {
+        state_root_parallel(hash_keys_parallel(state))
+    }
+
+    /// Hashes the keys of the items on the rayon thread pool and sorts the items by hashed key.
+    #[cfg(feature = "parallel")]
+    fn hash_keys_parallel<K, V>(items: impl IntoIterator<Item = (K, V)>) -> Vec<(B256, V)>
+    where
+        K: AsRef<[u8]> + Send,
+        V: Send,
+    {
+        use rayon::prelude::*;
+
+        let mut hashed: Vec<(B256, V)> = Vec::from_iter(items)
+            .into_par_iter()
+            .map(|(key, value)| (keccak256(key), value))
+            .collect();
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::debug_span!(target: "trie::root", "sort", items = hashed.len()).entered();
+        hashed.par_sort_unstable_by_key(|(hashed_key, _)| *hashed_key);
+        hashed
+    }
+
+    /// Calculates the root hash of the state represented as MPT on the rayon thread pool.
+    ///
+    /// The accounts are sharded by the top nibble of their hashed keys and the 16 subtries are
+    /// hashed concurrently, then merged into the root branch node. Computes the same root as
+    /// [`state_root`].
+    ///
+    /// # Panics
+    ///
+    /// If the items are not in sorted order.
+    #[cfg(feature = "parallel")]
+    pub fn state_root_parallel<A: Into<TrieAccount> + Send>(
+        state: impl IntoIterator<Item = (B256, A)>,
+    ) -> B256 {
+        use rayon::prelude::*;
+
+        let mut shards: Vec<Vec<(B256, A)>> = (0..16).map(|_| Vec::new()).collect();
+        for (hashed_key, account) in state {
+            shards[(hashed_key[0] >> 4) as usize].push((hashed_key, account));
+        }
+
+        // A trie with less than two subtries has no root branch node to merge into.
+        if shards.iter().filter(|shard| !shard.is_empty()).count() < 2 {
+            return state_root(shards.into_iter().flatten());
+        }
+
+        let subtrie_roots: Vec<(u8, B256)> = shards
+            .into_par_iter()
+            .enumerate()
+            .filter(|(_, shard)| !shard.is_empty())
+            .map(|(nibble, shard)| {
+                let mut hb = HashBuilder::default();
+                let mut account_rlp_buf = Vec::new();
+                for (hashed_key, account) in shard {
+                    account_rlp_buf.clear();
+                    account.into().encode_into(&mut account_rlp_buf);
+                    // strip the top nibble, the subtrie is rooted at the root branch child
+                    let key = unpack_b256(&hashed_key).slice(1..);
+                    hb.add_leaf(key, &account_rlp_buf, false);
+                }
+                (nibble as u8, hb.root())
+            })
+            .collect();
+
+        let mut hb = HashBuilder::default();
+        for (nibble, subtrie_root) in subtrie_roots {
+            hb.add_branch(Nibbles::from_nibbles_unchecked([nibble]), subtrie_root, false);
         }
         hb.root()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::triehash_trie_root;
+
+    #[test]
+    fn ordered_trie_root_matches_triehash() {
+        let items = (0..200u64).collect::<Vec<_>>();
+        let expected =
+            triehash_trie_root(items.iter().enumerate().map(|(i, item)| {
+                (alloy_rlp::encode_fixed_size(&i).to_vec(), alloy_rlp::encode(item))
+            }));
+        assert_eq!(ordered_trie_root(&items), expected);
+        assert_eq!(ordered_trie_root_with_privacy(&items, |_| false), expected);
+    }
+
+    #[test]
+    fn adjusted_indices_around_single_byte_boundary() {
+        for len in [0usize, 1, 2, 0x7e, 0x7f, 0x80, 0x81, 0x82, 0xff, 0x100, 0x101] {
+            let indices = ordered_trie_root_indices(len);
+            assert_eq!(indices.len(), len);
+            for (i, index) in indices.iter().enumerate() {
+                assert_eq!(adjust_index_for_rlp(i, len), *index, "{len} {i}");
+                assert_eq!(checked_adjust_index_for_rlp(i, len), Some(*index));
+            }
+            assert_eq!(checked_adjust_index_for_rlp(len, len), None);
+
+            // The items are permuted into the order of their encoded indexes.
+            let mut sorted = indices.clone();
+            sorted.sort_unstable();
+            assert_eq!(sorted, Vec::from_iter(0..len), "{len}");
+            let keys = Vec::from_iter((0..len).map(|i| rlp_index_nibbles(i, len)));
+            for (key, index) in keys.iter().zip(&indices) {
+                assert_eq!(*key, Nibbles::unpack(alloy_rlp::encode_fixed_size(index)));
+            }
+            assert!(keys.windows(2).all(|pair| pair[0] < pair[1]), "{len}");
+        }
+    }
+
+    #[test]
+    fn indexed_trie_root_matches_ordered() {
+        for len in [0u64, 1, 2, 0x7f, 0x80, 0x81, 0x100, 0x101, 1000] {
+            let items = Vec::from_iter(0..len);
+            assert_eq!(indexed_trie_root(&items), ordered_trie_root(&items), "{len}");
+            assert_eq!(
+                indexed_trie_root_with_privacy_encoder(&items, |item, buf| {
+                    item.encode(buf);
+                    *item % 3 == 0
+                }),
+                ordered_trie_root_with_privacy(&items, |item| *item % 3 == 0),
+                "{len}"
+            );
+        }
+    }
+
+    #[test]
+    fn ordered_trie_root_from_iter_matches_slice() {
+        for len in [0u64, 1, 2, 0x7f, 0x80, 0x81, 300] {
+            let items = Vec::from_iter(0..len);
+            assert_eq!(ordered_trie_root_from_iter(items.iter()), ordered_trie_root(&items));
+            assert_eq!(
+                ordered_trie_root_from_iter_with_privacy_encoder(items.iter(), |item, buf| {
+                    item.encode(buf);
+                    **item % 3 == 0
+                }),
+                ordered_trie_root_with_privacy(&items, |item| *item % 3 == 0)
+            );
+        }
+    }
+
+    #[test]
+    fn ordered_trie_root_with_private_items() {
+        let items = [1u64, 2, 3];
+        let public = ordered_trie_root(&items);
+        let all_private = ordered_trie_root_with_privacy(&items, |_| true);
+        let mixed = ordered_trie_root_with_privacy_encoder(&items, |item, buf| {
+            item.encode(buf);
+            *item == 2
+        });
+        assert_ne!(public, all_private);
+        assert_ne!(public, mixed);
+        assert_ne!(all_private, mixed);
+    }
+
+    #[cfg(feature = "ethereum")]
+    struct TestReceipt {
+        ty: Option<u8>,
+        status: bool,
+        cumulative_gas_used: u64,
+    }
+
+    #[cfg(feature = "ethereum")]
+    impl TypedEnvelope for TestReceipt {
+        fn type_flag(&self) -> Option<u8> {
+            self.ty
+        }
+
+        fn encode_payload(&self, out: &mut Vec<u8>) {
+            let payload_length = self.status.length() + self.cumulative_gas_used.length();
+            alloy_rlp::Header { list: true, payload_length }.encode(out);
+            self.status.encode(out);
+            self.cumulative_gas_used.encode(out);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn receipts_root_prefixes_typed_receipts() {
+        let receipts = [
+            TestReceipt { ty: None, status: true, cumulative_gas_used: 21_000 },
+            TestReceipt { ty: Some(2), status: false, cumulative_gas_used: 42_000 },
+        ];
+        let mut legacy = Vec::new();
+        receipts[0].encode_payload(&mut legacy);
+        let mut typed = vec![2u8];
+        receipts[1].encode_payload(&mut typed);
+
+        let expected = triehash_trie_root([
+            (alloy_rlp::encode_fixed_size(&0usize).to_vec(), legacy),
+            (alloy_rlp::encode_fixed_size(&1usize).to_vec(), typed),
+        ]);
+        assert_eq!(receipts_root(&receipts), expected);
+        assert_eq!(receipts_root::<TestReceipt>(&[]), EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn transactions_and_withdrawals_roots() {
+        use alloy_primitives::Address;
+
+        #[derive(alloy_rlp::RlpEncodable)]
+        struct Withdrawal {
+            index: u64,
+            validator_index: u64,
+            address: Address,
+            amount: u64,
+        }
+
+        // The transaction envelopes are encoded the same way as the receipt ones.
+        let transactions = [TestReceipt { ty: Some(0x4a), status: true, cumulative_gas_used: 1 }];
+        assert_eq!(transactions_root(&transactions), receipts_root(&transactions));
+
+        let withdrawals = Vec::from_iter((0..20u64).map(|i| Withdrawal {
+            index: i,
+            validator_index: i * 2,
+            address: Address::with_last_byte(i as u8),
+            amount: 32_000_000_000,
+        }));
+        let expected = triehash_trie_root(withdrawals.iter().enumerate().map(|(i, withdrawal)| {
+            (alloy_rlp::encode_fixed_size(&i).to_vec(), alloy_rlp::encode(withdrawal))
+        }));
+        assert_eq!(withdrawals_root(&withdrawals), expected);
+        assert_eq!(withdrawals_root::<u64>(&[]), EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn requests_root_uses_opaque_requests() {
+        let requests = Vec::from_iter((0..3u8).map(|ty| [&[ty][..], &[0xaa; 40]].concat()));
+        let expected = triehash_trie_root(
+            requests
+                .iter()
+                .enumerate()
+                .map(|(i, request)| (alloy_rlp::encode_fixed_size(&i).to_vec(), request)),
+        );
+        assert_eq!(requests_root(&requests), expected);
+        assert_eq!(requests_root::<Vec<u8>>(&[]), EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn root_from_map_matches_unsorted() {
+        use crate::TrieAccount;
+        use alloc::collections::BTreeMap;
+        use alloy_primitives::{keccak256, U256};
+
+        let storage =
+            BTreeMap::from_iter((0..100u64).map(|slot| {
+                (keccak256(slot.to_be_bytes()), (U256::from(slot + 1), slot % 3 == 0))
+            }));
+        assert_eq!(storage_root_from_map(&storage), storage_root_unsorted(storage.clone()));
+        assert_eq!(storage_root_from_map(&BTreeMap::<B256, U256>::new()), EMPTY_ROOT_HASH);
+
+        let state =
+            BTreeMap::from_iter((0..100u64).map(|i| {
+                (keccak256(i.to_be_bytes()), TrieAccount { nonce: i, ..Default::default() })
+            }));
+        assert_eq!(state_root_from_map(&state), state_root_unsorted(state.clone()));
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn state_root_ref_unhashed_without_clone() {
+        use crate::TrieAccount;
+        use alloy_primitives::{Address, U256};
+
+        /// Account that is neither `Clone` nor convertible into a [TrieAccount].
+        struct GenesisAccount {
+            balance: U256,
+            _code: Vec<u8>,
+        }
+
+        let accounts = (0..50u8)
+            .map(|i| {
+                (Address::repeat_byte(i), GenesisAccount { balance: U256::from(i), _code: vec![i] })
+            })
+            .collect::<Vec<_>>();
+        let to_account = |account: &GenesisAccount| TrieAccount {
+            balance: account.balance,
+            ..Default::default()
+        };
+        let expected = state_root_unhashed(
+            accounts.iter().map(|(address, account)| (*address, to_account(account))),
+        );
+        assert_eq!(
+            state_root_ref_unhashed_with(
+                accounts.iter().map(|(address, account)| (address, account)),
+                to_account
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn genesis_state_root_matches_accounts() {
+        use crate::{GenesisAccount, TrieAccount};
+        use alloc::collections::BTreeMap;
+        use alloy_primitives::{keccak256, Address, Bytes, U256};
+
+        let genesis = BTreeMap::from_iter((0..20u8).map(|i| {
+            let storage =
+                (i % 2 == 0).then(|| {
+                    BTreeMap::from_iter((1..=i).map(|slot| {
+                        (B256::with_last_byte(slot), (U256::from(slot), slot % 3 == 0))
+                    }))
+                });
+            let account = GenesisAccount {
+                nonce: (i % 3 == 0).then_some(i as u64),
+                balance: U256::from(i),
+                code: (i % 4 == 0).then(|| Bytes::from(vec![i; 10])),
+                storage,
+            };
+            (Address::repeat_byte(i), account)
+        }));
+
+        let expected = state_root_unsorted(genesis.iter().map(|(address, account)| {
+            let trie_account = TrieAccount {
+                nonce: account.nonce.unwrap_or_default(),
+                balance: account.balance,
+                storage_root: account
+                    .storage
+                    .as_ref()
+                    .map_or(EMPTY_ROOT_HASH, |storage| storage_root_unhashed(storage.clone())),
+                code_hash: account.code.as_ref().map_or(crate::KECCAK_EMPTY, keccak256),
+                #[cfg(feature = "account-extensions")]
+                extension: Default::default(),
+            };
+            (keccak256(address), trie_account)
+        }));
+        assert_eq!(genesis_state_root(&genesis), expected);
+        assert_eq!(
+            genesis_state_root(&BTreeMap::<Address, GenesisAccount>::new()),
+            EMPTY_ROOT_HASH
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn storage_root_with_slot_proofs() {
+        use crate::proof::ProofVerificationOutcome;
+        use alloy_primitives::U256;
+
+        let storage = Vec::from_iter(
+            (1..=50u8).map(|i| (B256::with_last_byte(i), (U256::from(i), i % 3 == 0))),
+        );
+        let targets = [B256::with_last_byte(2), B256::with_last_byte(3), B256::with_last_byte(99)];
+        let (root, proofs) = storage_root_with_proofs(storage.iter().copied(), &targets);
+        assert_eq!(root, storage_root_unhashed(storage));
+        assert_eq!(proofs.len(), targets.len());
+
+        assert_eq!(proofs[0].key, targets[0]);
+        assert_eq!(proofs[0].value, U256::from(2));
+        assert_eq!(proofs[0].verify(root), Ok(ProofVerificationOutcome::Included));
+        assert_eq!(proofs[1].value, U256::from(3));
+        assert!(proofs[1].is_private);
+        assert_eq!(proofs[1].verify(root), Ok(ProofVerificationOutcome::IncludedPrivate));
+        assert_eq!(proofs[2].value, U256::ZERO);
+        assert_eq!(proofs[2].verify(root), Ok(ProofVerificationOutcome::Excluded));
+
+        let (empty_root, proofs) = storage_root_with_proofs(Vec::<(B256, U256)>::new(), &targets);
+        assert_eq!(empty_root, EMPTY_ROOT_HASH);
+        assert!(proofs.iter().all(|proof| proof.verify(empty_root).is_ok()));
+    }
+
+    #[test]
+    fn subtrie_roots_match_proofs() {
+        use crate::proof::{subtree_root_from_proof, ProofRetainer};
+
+        let mut leaves = Vec::from_iter((0..200u64).map(|i| {
+            (Nibbles::unpack(keccak256(i.to_be_bytes())), alloy_rlp::encode(i), i % 2 == 0)
+        }));
+        leaves.sort_unstable_by(|(a, ..), (b, ..)| a.cmp(b));
+        let target = leaves[100].0.clone();
+
+        let mut hb =
+            HashBuilder::default().with_proof_retainer(ProofRetainer::from_iter([target.clone()]));
+        for (key, value, is_private) in &leaves {
+            hb.add_leaf(key.clone(), value, *is_private);
+        }
+        let root = hb.root();
+        let proof = hb.take_proof_nodes().matching_nodes_for(&target);
+
+        assert_eq!(subtrie_root(&Nibbles::default(), leaves.iter().cloned()), root);
+        for len in 1..3 {
+            let prefix = target.slice(..len);
+            assert_eq!(
+                Some(subtrie_root(&prefix, leaves.iter().cloned())),
+                subtree_root_from_proof(&proof, &prefix).unwrap()
+            );
+        }
+        assert_eq!(
+            subtrie_root(&target.slice(..10), Vec::<(_, Vec<u8>, _)>::new()),
+            EMPTY_ROOT_HASH
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn try_roots_reject_invalid_items() {
+        use crate::{StateRootError, TrieAccount};
+        use alloy_primitives::U256;
+
+        let slot = |i: u8| B256::with_last_byte(i);
+        let storage = [(slot(1), (U256::from(1), false)), (slot(2), (U256::from(2), true))];
+        assert_eq!(try_storage_root(storage), Ok(storage_root(storage)));
+        assert_eq!(
+            try_storage_root([storage[1], storage[0]]),
+            Err(StateRootError::UnsortedKey {
+                key: Nibbles::unpack(slot(1)),
+                previous: Nibbles::unpack(slot(2)),
+            })
+        );
+        assert_eq!(
+            try_storage_root([storage[0], (slot(1), (U256::from(3), true))]),
+            Err(StateRootError::InvalidPrivacyTransition {
+                key: Nibbles::unpack(slot(1)),
+                was_private: false
+            })
+        );
+
+        let state = [(slot(1), TrieAccount::default()), (slot(2), TrieAccount::default())];
+        assert_eq!(try_state_root(state), Ok(state_root(state)));
+        assert_eq!(
+            try_state_root([state[0], state[0]]),
+            Err(StateRootError::DuplicateKey { key: Nibbles::unpack(slot(1)) })
+        );
+        assert_eq!(try_state_root(Vec::<(B256, TrieAccount)>::new()), Ok(EMPTY_ROOT_HASH));
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn unsorted_roots_with_duplicates() {
+        use crate::{StateRootError, TrieAccount};
+        use alloy_primitives::{keccak256, U256};
+
+        let slot = |i: u8| keccak256([i]);
+        let storage = [
+            (slot(2), U256::from(1)),
+            (slot(1), U256::from(2)),
+            (slot(2), U256::from(3)),
+            (slot(2), U256::from(4)),
+        ];
+        assert_eq!(
+            storage_root_unsorted_with_duplicates(storage, DuplicateKeys::LastWins),
+            Ok(storage_root_unsorted([storage[1], storage[3]]))
+        );
+        assert_eq!(
+            storage_root_unsorted_with_duplicates(storage, DuplicateKeys::Reject),
+            Err(StateRootError::DuplicateKey { key: Nibbles::unpack(slot(2)) })
+        );
+        assert_eq!(
+            storage_root_unsorted_with_duplicates(storage[..2].to_vec(), DuplicateKeys::Reject),
+            Ok(storage_root_unsorted(storage[..2].to_vec()))
+        );
+
+        let account = |nonce| TrieAccount { nonce, ..Default::default() };
+        let state = [(slot(1), account(1)), (slot(1), account(2)), (slot(3), account(3))];
+        assert_eq!(
+            state_root_unsorted_with_duplicates(state, DuplicateKeys::LastWins),
+            Ok(state_root_unsorted([state[1], state[2]]))
+        );
+        assert!(state_root_unsorted_with_duplicates(state, DuplicateKeys::Reject).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn storage_root_from_u256_slots() {
+        use alloy_primitives::U256;
+
+        let slots = (0..20u64).map(|i| (U256::from(i), U256::from(i + 1), i % 3 == 0));
+        assert_eq!(
+            storage_root_unhashed_slots(slots.clone()),
+            storage_root_unhashed(slots.map(|(slot, value, is_private)| {
+                (B256::from(slot.to_be_bytes::<32>()), (value, is_private))
+            }))
+        );
+        assert_eq!(storage_root_unhashed_slots([]), EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn split_storage_roots() {
+        use alloy_primitives::U256;
+
+        let mut storage =
+            Vec::from_iter((1..=20u8).map(|i| (keccak256([i]), (U256::from(i), i % 3 == 0))));
+        storage.sort_unstable_by_key(|(hashed_slot, _)| *hashed_slot);
+
+        let roots = storage_root_split(storage.iter().copied());
+        assert_eq!(roots.full, storage_root(storage.iter().copied()));
+        assert_eq!(
+            roots.public_only,
+            storage_root(storage.iter().copied().filter(|(_, (_, is_private))| !is_private))
+        );
+        assert_ne!(roots.full, roots.public_only);
+
+        let public = Vec::from_iter(storage.iter().map(|(slot, (value, _))| (*slot, *value)));
+        let roots = storage_root_split(public.iter().copied());
+        assert_eq!(roots.full, roots.public_only);
+        assert_eq!(
+            storage_root_split(Vec::<(B256, U256)>::new()),
+            StorageRoots { full: EMPTY_ROOT_HASH, public_only: EMPTY_ROOT_HASH }
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn storage_root_prune_zero_slots() {
+        use alloy_primitives::{keccak256, U256};
+
+        let slot = |i: u8| B256::with_last_byte(i);
+        let storage = [
+            (slot(1), (U256::from(1), false)),
+            (slot(2), (U256::ZERO, false)),
+            (slot(3), (U256::ZERO, true)),
+            (slot(4), (U256::from(4), true)),
+        ];
+        let expected = storage_root_unhashed([storage[0], storage[2], storage[3]]);
+        assert_eq!(storage_root_unhashed_prune_zero(storage), expected);
+        assert_ne!(storage_root_unhashed(storage), expected);
+
+        let mut hashed = storage.map(|(slot, value)| (keccak256(slot), value));
+        hashed.sort_unstable_by_key(|(hashed_slot, _)| *hashed_slot);
+        assert_eq!(storage_root_prune_zero(hashed), expected);
+        assert_eq!(storage_root_prune_zero([(slot(1), U256::ZERO)]), EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn custom_storage_value_encoding() {
+        use alloy_primitives::{keccak256, U256};
+
+        struct EncryptedSlot {
+            value: U256,
+            ciphertext: Vec<u8>,
+        }
+
+        impl FlaggedStorageValue for EncryptedSlot {
+            fn is_private(&self) -> bool {
+                true
+            }
+            fn value(&self) -> &U256 {
+                &self.value
+            }
+            fn encode_value(&self, out: &mut Vec<u8>) {
+                self.ciphertext.as_slice().encode(out);
+            }
+        }
+
+        let slots = (1..50u64).map(|slot| (keccak256(slot.to_be_bytes()), slot));
+        let encrypted = storage_root_unsorted(slots.clone().map(|(hashed_slot, slot)| {
+            (
+                hashed_slot,
+                EncryptedSlot { value: U256::from(slot), ciphertext: vec![slot as u8; 48] },
+            )
+        }));
+
+        let mut sorted = Vec::from_iter(slots);
+        sorted.sort_unstable();
+        let mut hb = HashBuilder::default();
+        for (hashed_slot, slot) in sorted {
+            hb.add_leaf(
+                Nibbles::unpack(hashed_slot),
+                &alloy_rlp::encode(&[slot as u8; 48][..]),
+                true,
+            );
+        }
+        assert_eq!(encrypted, hb.root());
+    }
+
+    #[test]
+    #[cfg(all(feature = "ethereum", feature = "parallel"))]
+    fn state_root_parallel_matches_sequential() {
+        use crate::TrieAccount;
+        use alloy_primitives::{keccak256, U256};
+
+        for len in [0u64, 1, 2, 1000] {
+            let mut state = Vec::from_iter((0..len).map(|i| {
+                let account =
+                    TrieAccount { nonce: i, balance: U256::from(i), ..Default::default() };
+                (keccak256(i.to_be_bytes()), account)
+            }));
+            state.sort_unstable_by_key(|(key, _)| *key);
+            assert_eq!(state_root_parallel(state.clone()), state_root(state));
+        }
+
+        // all accounts share the same top nibble
+        let state =
+            Vec::from_iter((0..3u8).map(|i| (B256::with_last_byte(i), TrieAccount::default())));
+        assert_eq!(state_root_parallel(state.clone()), state_root(state));
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn state_root_with_storage_roots_matches_separate_passes() {
+        use crate::TrieAccount;
+        use alloy_primitives::{keccak256, U256};
+
+        let mut state = Vec::from_iter((0..50u64).map(|i| {
+            let storage = Vec::from_iter((0..i % 5).map(|slot| {
+                (keccak256(slot.to_be_bytes()), (U256::from(i + slot), slot % 2 == 0))
+            }));
+            (keccak256(i.to_be_bytes()), (TrieAccount { nonce: i, ..Default::default() }, storage))
+        }));
+        state.sort_unstable_by_key(|(key, _)| *key);
+
+        let (root, storage_roots) = state_root_with_storage_roots(state.clone());
+        assert_eq!(storage_roots.len(), state.len());
+        let expected = state_root(state.into_iter().map(|(hashed_key, (account, storage))| {
+            let storage_root = storage_root_unsorted(storage);
+            assert_eq!(storage_roots[&hashed_key], storage_root);
+            (hashed_key, TrieAccount { storage_root, ..account })
+        }));
+        assert_eq!(root, expected);
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn unhashed_roots_with_key_cache() {
+        use crate::{key_hasher::HashedKeyCache, TrieAccount};
+        use alloy_primitives::{Address, U256};
+
+        let storage = Vec::from_iter(
+            (0..20u64).map(|i| (B256::from(U256::from(i)), (U256::from(i + 1), i % 2 == 0))),
+        );
+        let state = Vec::from_iter((0..20u64).map(|i| {
+            (Address::with_last_byte(i as u8), TrieAccount { nonce: i, ..Default::default() })
+        }));
+
+        let mut cache = HashedKeyCache::new(1000);
+        for _ in 0..2 {
+            assert_eq!(
+                storage_root_unhashed_with_key_hasher(storage.clone(), &mut cache),
+                storage_root_unhashed(storage.clone())
+            );
+            assert_eq!(
+                state_root_unhashed_with_key_hasher(state.clone(), &mut cache),
+                state_root_unhashed(state.clone())
+            );
+        }
+        assert_eq!((cache.hits(), cache.misses()), (40, 40));
+    }
+
+    #[test]
+    #[cfg(all(feature = "ethereum", feature = "parallel"))]
+    fn unhashed_roots_parallel_match_sequential() {
+        use crate::TrieAccount;
+        use alloy_primitives::{Address, U256};
+
+        for len in [0u64, 1, 2, 1000] {
+            let storage = Vec::from_iter(
+                (0..len).map(|i| (B256::from(U256::from(i)), (U256::from(i + 1), i % 2 == 0))),
+            );
+            assert_eq!(
+                storage_root_unhashed_parallel(storage.clone()),
+                storage_root_unhashed(storage)
+            );
+
+            let state = Vec::from_iter((0..len).map(|i| {
+                let account = TrieAccount { nonce: i, ..Default::default() };
+                (Address::with_last_byte(i as u8).create(i), account)
+            }));
+            assert_eq!(state_root_unhashed_parallel(state.clone()), state_root_unhashed(state));
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "ethereum", feature = "parallel"))]
+    fn storage_roots_parallel_matches_sequential() {
+        use alloy_primitives::{keccak256, U256};
+
+        let accounts = Vec::from_iter((0..50u64).map(|i| {
+            let storage = Vec::from_iter((0..i).map(|slot| {
+                (keccak256(slot.to_be_bytes()), (U256::from(slot + 1), slot % 2 == 0))
+            }));
+            (keccak256(i.to_be_bytes()), storage)
+        }));
+
+        let roots = storage_roots_parallel(accounts.clone());
+        assert_eq!(roots.len(), accounts.len());
+        for (hashed_address, storage) in accounts {
+            assert_eq!(roots[&hashed_address], storage_root_unsorted(storage));
+        }
+        assert_eq!(roots[&keccak256(0u64.to_be_bytes())], EMPTY_ROOT_HASH);
+    }
+}
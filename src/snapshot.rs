@@ -0,0 +1,312 @@
+//! Flat snapshots of a trie, streaming its sorted leaves along with the root they commit to.
+//!
+//! A snapshot starts with a header made of the [MAGIC] bytes, the format [VERSION] and the root of
+//! the trie, followed by one record per leaf in increasing key order until the end of the stream:
+//!
+//! | field  | size | description                                     |
+//! |--------|------|-------------------------------------------------|
+//! | key    | 32   | the hashed key of the leaf                      |
+//! | flags  | 1    | `0x01` if the leaf is private, other bits unset |
+//! | length | 4    | the length of the value, big endian             |
+//! | value  | *    | the value stored in the leaf                    |
+//!
+//! The root in the header is checked against the leaves both on [export] and on [import], so a
+//! snapshot can be verified from its content only.
+
+use crate::{
+    cursor::{DatabaseError, HashedCursor},
+    HashBuilder, Nibbles,
+};
+use alloc::vec::Vec;
+use alloy_primitives::B256;
+use core::fmt;
+use std::io::{self, Read, Write};
+
+/// The magic bytes every snapshot starts with.
+pub const MAGIC: [u8; 4] = *b"STSN";
+
+/// The version of the snapshot format written by [export].
+pub const VERSION: u8 = 1;
+
+/// The flag set on the records of private leaves.
+const PRIVATE_FLAG: u8 = 0x01;
+
+/// Error while exporting or importing a snapshot.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// Error reading from or writing to the snapshot stream.
+    Io(io::Error),
+    /// Error reading the leaves from the cursor.
+    Database(DatabaseError),
+    /// The stream does not start with the [MAGIC] bytes.
+    InvalidMagic([u8; 4]),
+    /// The snapshot format version is not supported.
+    UnsupportedVersion(u8),
+    /// The record has unknown flags set.
+    InvalidFlags {
+        /// Key of the record.
+        key: B256,
+        /// Flags of the record.
+        flags: u8,
+    },
+    /// The record is not sorted after the previous one.
+    UnsortedKey {
+        /// Key of the record.
+        key: B256,
+    },
+    /// The root of the leaves does not match the expected root.
+    RootMismatch {
+        /// Root of the leaves.
+        got: B256,
+        /// Expected root.
+        expected: B256,
+    },
+}
+
+impl std::error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Database(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => fmt::Display::fmt(error, f),
+            Self::Database(error) => fmt::Display::fmt(error, f),
+            Self::InvalidMagic(magic) => write!(f, "invalid snapshot magic {magic:?}"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {version}")
+            }
+            Self::InvalidFlags { key, flags } => {
+                write!(f, "invalid flags {flags:#04x} for key {key}")
+            }
+            Self::UnsortedKey { key } => write!(f, "key {key} is not sorted"),
+            Self::RootMismatch { got, expected } => {
+                write!(f, "root mismatch. got: {got}. expected: {expected}")
+            }
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(source: io::Error) -> Self {
+        Self::Io(source)
+    }
+}
+
+impl From<DatabaseError> for SnapshotError {
+    fn from(source: DatabaseError) -> Self {
+        Self::Database(source)
+    }
+}
+
+/// Writes the snapshot of the trie with the given root, reading its leaves from the cursor and
+/// encoding their values with the encoder, which returns whether the leaf is private, e.g.
+/// `|slot, out| { slot.encode_value(out); slot.is_private() }` for
+/// [storage slots](crate::root::FlaggedStorageValue). Returns the number of records written.
+///
+/// The root of the leaves is computed along the way, and [SnapshotError::RootMismatch] is returned
+/// after the last record if it does not match the root in the header, in which case the written
+/// snapshot must be discarded.
+///
+/// SEISMIC WARNING: Ensure that the leaves are flagged correctly when calling
+pub fn export<C, F, W>(
+    root: B256,
+    cursor: &mut C,
+    mut encode: F,
+    writer: &mut W,
+) -> Result<u64, SnapshotError>
+where
+    C: HashedCursor,
+    F: FnMut(&C::Value, &mut Vec<u8>) -> bool,
+    W: Write,
+{
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&[VERSION])?;
+    writer.write_all(root.as_slice())?;
+
+    let mut hb = HashBuilder::default();
+    let mut value = Vec::new();
+    let mut records = 0;
+    let mut entry = cursor.seek(B256::ZERO)?;
+    while let Some((key, leaf)) = entry {
+        value.clear();
+        let is_private = encode(&leaf, &mut value);
+        let length = u32::try_from(value.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "value too long"))?;
+        writer.write_all(key.as_slice())?;
+        writer.write_all(&[if is_private { PRIVATE_FLAG } else { 0 }])?;
+        writer.write_all(&length.to_be_bytes())?;
+        writer.write_all(&value)?;
+
+        hb.add_leaf(Nibbles::unpack(key), &value, is_private);
+        records += 1;
+        entry = cursor.next()?;
+    }
+    writer.flush()?;
+
+    let got = hb.root();
+    if got != root {
+        return Err(SnapshotError::RootMismatch { got, expected: root });
+    }
+    Ok(records)
+}
+
+/// Reads a snapshot, passing every record to the callback as the key, the value and whether the
+/// leaf is private, and returns the root in its header once checked against the leaves.
+///
+/// The records are passed as they are read, so the caller must discard them if an error is
+/// returned, e.g. by writing them in a transaction committed on success only.
+pub fn import<R, F>(reader: &mut R, mut on_record: F) -> Result<B256, SnapshotError>
+where
+    R: Read,
+    F: FnMut(B256, Vec<u8>, bool),
+{
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(SnapshotError::InvalidMagic(magic));
+    }
+    let mut version = [0; 1];
+    reader.read_exact(&mut version)?;
+    if version[0] != VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version[0]));
+    }
+    let mut root = B256::ZERO;
+    reader.read_exact(root.as_mut_slice())?;
+
+    let mut hb = HashBuilder::default();
+    let mut last_key = None;
+    while let Some(key) = read_key(reader)? {
+        let mut flags = [0; 1];
+        reader.read_exact(&mut flags)?;
+        if flags[0] & !PRIVATE_FLAG != 0 {
+            return Err(SnapshotError::InvalidFlags { key, flags: flags[0] });
+        }
+        let mut length = [0; 4];
+        reader.read_exact(&mut length)?;
+        let length = u32::from_be_bytes(length);
+        // Read through `take` rather than allocating the untrusted length upfront.
+        let mut value = Vec::new();
+        reader.by_ref().take(length.into()).read_to_end(&mut value)?;
+        if value.len() != length as usize {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        if last_key.is_some_and(|last_key| key <= last_key) {
+            return Err(SnapshotError::UnsortedKey { key });
+        }
+        last_key = Some(key);
+
+        let is_private = flags[0] & PRIVATE_FLAG != 0;
+        hb.add_leaf(Nibbles::unpack(key), &value, is_private);
+        on_record(key, value, is_private);
+    }
+
+    let got = hb.root();
+    if got != root {
+        return Err(SnapshotError::RootMismatch { got, expected: root });
+    }
+    Ok(root)
+}
+
+/// Reads the key of the next record, or [None] at the end of the stream.
+fn read_key<R: Read>(reader: &mut R) -> io::Result<Option<B256>> {
+    let mut key = B256::ZERO;
+    let mut read = 0;
+    while read < key.len() {
+        match reader.read(&mut key[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => read += n,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(Some(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cursor::in_memory::InMemoryHashedCursor, EMPTY_ROOT_HASH};
+    use alloc::collections::BTreeMap;
+    use alloy_primitives::keccak256;
+
+    fn encode(value: &(Vec<u8>, bool), out: &mut Vec<u8>) -> bool {
+        out.extend_from_slice(&value.0);
+        value.1
+    }
+
+    #[test]
+    fn export_import_roundtrip() {
+        let leaves = (0..100u64)
+            .map(|i| (keccak256(i.to_be_bytes()), (alloy_rlp::encode(i), i % 3 == 0)))
+            .collect::<BTreeMap<_, _>>();
+        let mut hb = HashBuilder::default();
+        for (key, (value, is_private)) in &leaves {
+            hb.add_leaf(Nibbles::unpack(key), value, *is_private);
+        }
+        let root = hb.root();
+
+        let mut snapshot = Vec::new();
+        let mut cursor = InMemoryHashedCursor::new(Some(&leaves));
+        assert_eq!(export(root, &mut cursor, encode, &mut snapshot).unwrap(), 100);
+
+        let mut imported = BTreeMap::new();
+        let imported_root = import(&mut snapshot.as_slice(), |key, value, is_private| {
+            imported.insert(key, (value, is_private));
+        });
+        assert_eq!(imported_root.unwrap(), root);
+        assert_eq!(imported, leaves);
+
+        // The root is checked on export.
+        let mut cursor = InMemoryHashedCursor::new(Some(&leaves));
+        assert!(matches!(
+            export(EMPTY_ROOT_HASH, &mut cursor, encode, &mut Vec::new()),
+            Err(SnapshotError::RootMismatch { got, expected: EMPTY_ROOT_HASH }) if got == root
+        ));
+
+        // A flipped privacy flag, a truncated stream and a dropped record are detected on import.
+        let mut flipped = snapshot.clone();
+        flipped[37 + 32] ^= PRIVATE_FLAG;
+        assert!(matches!(
+            import(&mut flipped.as_slice(), |_, _, _| {}),
+            Err(SnapshotError::RootMismatch { .. })
+        ));
+        assert!(matches!(
+            import(&mut &snapshot[..snapshot.len() - 1], |_, _, _| {}),
+            Err(SnapshotError::Io(error)) if error.kind() == io::ErrorKind::UnexpectedEof
+        ));
+        let last_record = 32 + 1 + 4 + leaves.values().last().unwrap().0.len();
+        assert!(matches!(
+            import(&mut &snapshot[..snapshot.len() - last_record], |_, _, _| {}),
+            Err(SnapshotError::RootMismatch { .. })
+        ));
+
+        let mut unsupported = snapshot;
+        unsupported[4] = VERSION + 1;
+        assert!(matches!(
+            import(&mut unsupported.as_slice(), |_, _, _| {}),
+            Err(SnapshotError::UnsupportedVersion(version)) if version == VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn empty_snapshot() {
+        let mut snapshot = Vec::new();
+        let mut cursor = InMemoryHashedCursor::<(Vec<u8>, bool)>::new(None);
+        assert_eq!(export(EMPTY_ROOT_HASH, &mut cursor, encode, &mut snapshot).unwrap(), 0);
+        assert_eq!(snapshot.len(), 37);
+        assert_eq!(import(&mut snapshot.as_slice(), |_, _, _| {}).unwrap(), EMPTY_ROOT_HASH);
+        assert!(matches!(
+            import(&mut &b"snapshot"[..], |_, _, _| {}),
+            Err(SnapshotError::InvalidMagic(_))
+        ));
+    }
+}
@@ -0,0 +1,217 @@
+use super::SparseTrieError;
+use crate::{
+    nodes::{LeafValue, RlpNode, TrieNode},
+    HashMap, Nibbles, EMPTY_ROOT_HASH,
+};
+use alloc::{sync::Arc, vec::Vec};
+use alloy_primitives::{keccak256, Bytes, B256};
+use alloy_rlp::Decodable;
+
+/// A read-only trie reassembled from a bag of RLP encoded nodes, e.g. the witness of a block
+/// consumed by a stateless executor.
+///
+/// The nodes are looked up by hash and decoded on access. Subtries whose nodes are not in the bag
+/// are blinded, and reading through them returns [SparseTrieError::BlindedNode].
+#[derive(Clone, Debug)]
+pub struct DecodedTrie {
+    root: B256,
+    nodes: Arc<HashMap<B256, Bytes>>,
+}
+
+impl DecodedTrie {
+    /// Reassembles the trie with the given root from the RLP encoded nodes.
+    ///
+    /// The nodes of other tries, e.g. the storage tries of a witness, are kept so that they can be
+    /// read with [DecodedTrie::with_root].
+    pub fn from_nodes(root: B256, nodes: impl IntoIterator<Item = Bytes>) -> Self {
+        let nodes = nodes.into_iter().map(|node| (keccak256(&node), node)).collect();
+        Self { root, nodes: Arc::new(nodes) }
+    }
+
+    /// Returns the root hash of the trie.
+    pub const fn root(&self) -> B256 {
+        self.root
+    }
+
+    /// Returns the trie with the given root sharing the nodes of this one, e.g. the storage trie
+    /// of an account read from the state trie.
+    pub fn with_root(&self, root: B256) -> Self {
+        Self { root, nodes: Arc::clone(&self.nodes) }
+    }
+
+    /// Returns the value and the privacy of the leaf with the given key.
+    pub fn get(&self, key: &Nibbles) -> Result<Option<LeafValue>, SparseTrieError> {
+        let mut path = Nibbles::default();
+        let mut node = self.resolve(&path, &RlpNode::word_rlp(&self.root))?;
+        loop {
+            let remaining = &key[path.len()..];
+            let child = match node {
+                TrieNode::EmptyRoot => return Ok(None),
+                TrieNode::Leaf(leaf) => {
+                    return Ok((leaf.key[..] == *remaining)
+                        .then(|| LeafValue::new(leaf.value, leaf.is_private)))
+                }
+                TrieNode::Extension(extension) => {
+                    if !remaining.starts_with(&extension.key[..]) {
+                        return Ok(None);
+                    }
+                    path.extend_from_slice(&extension.key);
+                    extension.child
+                }
+                TrieNode::Branch(branch) => {
+                    let Some(&nibble) = remaining.first() else { return Ok(None) };
+                    let branch = branch.as_ref();
+                    let child = branch.children().find(|(index, _)| *index == nibble);
+                    let Some((_, Some(child))) = child else { return Ok(None) };
+                    path.push(nibble);
+                    child.clone()
+                }
+            };
+            node = self.resolve(&path, &child)?;
+        }
+    }
+
+    /// Returns an iterator over the leaves of the trie in key order.
+    ///
+    /// Every blinded subtrie is reported as a [SparseTrieError::BlindedNode] in place of its
+    /// leaves, and the iteration continues after it.
+    pub fn iter(&self) -> DecodedTrieIter<'_> {
+        DecodedTrieIter {
+            trie: self,
+            stack: Vec::from([(Nibbles::default(), RlpNode::word_rlp(&self.root))]),
+        }
+    }
+
+    /// Decodes the node referenced by its parent at the given path.
+    fn resolve(&self, path: &Nibbles, child: &RlpNode) -> Result<TrieNode, SparseTrieError> {
+        let Some(hash) = child.as_hash() else {
+            return Ok(TrieNode::decode(&mut &child[..])?);
+        };
+        if hash == EMPTY_ROOT_HASH {
+            return Ok(TrieNode::EmptyRoot);
+        }
+        let node = self
+            .nodes
+            .get(&hash)
+            .ok_or_else(|| SparseTrieError::BlindedNode { path: path.clone(), hash })?;
+        Ok(TrieNode::decode(&mut &node[..])?)
+    }
+}
+
+/// Iterator over the leaves of a [DecodedTrie], see [DecodedTrie::iter].
+#[derive(Debug)]
+pub struct DecodedTrieIter<'a> {
+    trie: &'a DecodedTrie,
+    /// The paths and references of the nodes left to visit, the next one last.
+    stack: Vec<(Nibbles, RlpNode)>,
+}
+
+impl Iterator for DecodedTrieIter<'_> {
+    type Item = Result<(Nibbles, LeafValue), SparseTrieError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((path, child)) = self.stack.pop() {
+            let node = match self.trie.resolve(&path, &child) {
+                Ok(node) => node,
+                Err(error) => return Some(Err(error)),
+            };
+            match node {
+                TrieNode::EmptyRoot => {}
+                TrieNode::Leaf(leaf) => {
+                    let value = LeafValue::new(leaf.value, leaf.is_private);
+                    return Some(Ok((path.join(&leaf.key), value)));
+                }
+                TrieNode::Extension(extension) => {
+                    self.stack.push((path.join(&extension.key), extension.child));
+                }
+                TrieNode::Branch(branch) => {
+                    let children =
+                        Vec::from_iter(branch.as_ref().children().filter_map(|(nibble, child)| {
+                            child.map(|child| (nibble, child.clone()))
+                        }));
+                    for (nibble, child) in children.into_iter().rev() {
+                        let mut child_path = path.clone();
+                        child_path.push(nibble);
+                        self.stack.push((child_path, child));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{proof::ProofRetainer, HashBuilder};
+
+    fn trie_nodes(leaves: &[(Nibbles, Vec<u8>, bool)]) -> (B256, Vec<Bytes>) {
+        let retainer = ProofRetainer::from_iter(leaves.iter().map(|(key, ..)| key.clone()));
+        let mut hb = HashBuilder::default().with_proof_retainer(retainer);
+        for (key, value, is_private) in leaves {
+            hb.add_leaf(key.clone(), value, *is_private);
+        }
+        let root = hb.root();
+        (root, hb.take_proof_nodes().into_inner().into_values().collect())
+    }
+
+    #[test]
+    fn read_reassembled_trie() {
+        let leaves = (0..100u64)
+            .map(|i| {
+                (Nibbles::unpack(keccak256(i.to_be_bytes())), (alloy_rlp::encode(i), i % 4 == 0))
+            })
+            .collect::<alloc::collections::BTreeMap<_, _>>()
+            .into_iter()
+            .map(|(key, (value, is_private))| (key, value, is_private))
+            .collect::<Vec<_>>();
+        let (root, nodes) = trie_nodes(&leaves);
+
+        // A second trie whose nodes are in the same bag.
+        let other_leaves = [(Nibbles::unpack(B256::with_last_byte(1)), b"other".to_vec(), true)];
+        let (other_root, other_nodes) = trie_nodes(&other_leaves);
+
+        let trie = DecodedTrie::from_nodes(root, nodes.iter().chain(&other_nodes).cloned());
+        for (key, value, is_private) in &leaves {
+            assert_eq!(trie.get(key), Ok(Some(LeafValue::new(value.clone(), *is_private))));
+        }
+        assert_eq!(trie.get(&Nibbles::unpack(B256::ZERO)), Ok(None));
+        assert_eq!(
+            Vec::from_iter(trie.iter().map(Result::unwrap)),
+            Vec::from_iter(leaves.iter().map(|(key, value, is_private)| {
+                (key.clone(), LeafValue::new(value.clone(), *is_private))
+            }))
+        );
+
+        let other = trie.with_root(other_root);
+        assert_eq!(
+            other.get(&other_leaves[0].0),
+            Ok(Some(LeafValue::new(b"other".to_vec(), true)))
+        );
+        assert_eq!(other.iter().count(), 1);
+
+        let empty = trie.with_root(EMPTY_ROOT_HASH);
+        assert_eq!(empty.get(&other_leaves[0].0), Ok(None));
+        assert_eq!(empty.iter().count(), 0);
+    }
+
+    #[test]
+    fn blinded_subtries() {
+        let leaves = (0..16u8)
+            .map(|i| (Nibbles::unpack(B256::repeat_byte(i * 0x11)), vec![i; 32], false))
+            .collect::<Vec<_>>();
+        let (root, nodes) = trie_nodes(&leaves);
+
+        // Only the root branch node is revealed.
+        let root_node = nodes.into_iter().find(|node| keccak256(node) == root).unwrap();
+        let trie = DecodedTrie::from_nodes(root, [root_node]);
+        assert!(matches!(
+            trie.get(&leaves[3].0),
+            Err(SparseTrieError::BlindedNode { path, .. }) if path == Nibbles::from_nibbles_unchecked([3])
+        ));
+        let items = Vec::from_iter(trie.iter());
+        assert_eq!(items.len(), 16);
+        assert!(items.iter().all(|item| matches!(item, Err(SparseTrieError::BlindedNode { .. }))));
+    }
+}
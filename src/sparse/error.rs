@@ -0,0 +1,75 @@
+use alloy_primitives::B256;
+use core::fmt;
+use nybbles::Nibbles;
+
+/// Error during sparse trie operations.
+#[derive(PartialEq, Eq, Debug)]
+pub enum SparseTrieError {
+    /// Encountered a node that has not been revealed yet.
+    BlindedNode {
+        /// Path of the blinded node.
+        path: Nibbles,
+        /// Hash of the blinded node.
+        hash: B256,
+    },
+    /// The revealed node does not match the hash of the blinded node it replaces.
+    NodeHashMismatch {
+        /// Path of the blinded node.
+        path: Nibbles,
+        /// Hash of the revealed node.
+        got: B256,
+        /// Hash of the blinded node.
+        expected: B256,
+    },
+    /// The path of the revealed node cannot be reached from the root.
+    UnreachablePath {
+        /// Path of the revealed node.
+        path: Nibbles,
+    },
+    /// The inserted key is a prefix of a stored path or a stored leaf key is a prefix of it, i.e.
+    /// the keys have different lengths.
+    InvalidKey {
+        /// The inserted key.
+        key: Nibbles,
+    },
+    /// Error during RLP decoding of trie node.
+    Rlp(alloy_rlp::Error),
+}
+
+/// Enable Error trait implementation when core is stabilized.
+/// <https://github.com/rust-lang/rust/issues/103765>
+#[cfg(feature = "std")]
+impl std::error::Error for SparseTrieError {
+    fn source(&self) -> ::core::option::Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Rlp(error) => std::error::Error::source(error as &dyn std::error::Error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SparseTrieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BlindedNode { path, hash } => {
+                write!(f, "blinded node at path {path:?} with hash {hash}")
+            }
+            Self::NodeHashMismatch { path, got, expected } => {
+                write!(f, "node hash mismatch at path {path:?}. got: {got}. expected: {expected}")
+            }
+            Self::UnreachablePath { path } => {
+                write!(f, "path {path:?} is not reachable from the root")
+            }
+            Self::InvalidKey { key } => {
+                write!(f, "key {key:?} does not have the length of the stored keys")
+            }
+            Self::Rlp(error) => fmt::Display::fmt(error, f),
+        }
+    }
+}
+
+impl From<alloy_rlp::Error> for SparseTrieError {
+    fn from(source: alloy_rlp::Error) -> Self {
+        Self::Rlp(source)
+    }
+}
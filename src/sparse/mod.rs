@@ -0,0 +1,734 @@
+//! In-memory sparse trie supporting incremental updates.
+
+use crate::{
+    nodes::{BranchNodeRef, ExtensionNodeRef, LeafNodeRef, RlpNode, TrieNode},
+    proof::ProofNodes,
+    Nibbles, TrieMask, EMPTY_ROOT_HASH,
+};
+use alloc::{boxed::Box, vec::Vec};
+use alloy_primitives::{keccak256, B256};
+use alloy_rlp::Decodable;
+
+mod decoded;
+pub use decoded::{DecodedTrie, DecodedTrieIter};
+
+mod error;
+pub use error::SparseTrieError;
+
+mod provider;
+pub use provider::BlindedProvider;
+
+/// An in-memory Merkle Patricia Trie that only holds the revealed parts of the trie.
+///
+/// Unlike the [`HashBuilder`](crate::HashBuilder), which requires the leaves to be added in
+/// sorted order, the sparse trie supports arbitrary insertions and removals. The RLP encoding of
+/// every node is cached and only the nodes on the paths touched since the last call to
+/// [SparseTrie::root] are re-hashed.
+///
+/// Subtries that have not been revealed are represented by their hashes. Operations that need to
+/// walk through such a subtrie return [SparseTrieError::BlindedNode]. Use
+/// [SparseTrie::reveal_node] or [SparseTrie::reveal_proof_nodes] to reveal them.
+///
+/// NOTE: All keys in the trie are expected to have the same length, since branch node values are
+/// not supported.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SparseTrie {
+    root: SparseNode,
+}
+
+impl SparseTrie {
+    /// Creates a sparse trie with a blinded root node of the given hash.
+    pub fn blind(root: B256) -> Self {
+        if root == EMPTY_ROOT_HASH {
+            return Self::default();
+        }
+        Self { root: SparseNode::new(SparseNodeKind::Hash(root)) }
+    }
+
+    /// Creates a sparse trie from the given root hash and the proof nodes revealing it.
+    pub fn from_proof_nodes(root: B256, proof_nodes: &ProofNodes) -> Result<Self, SparseTrieError> {
+        let mut trie = Self::blind(root);
+        trie.reveal_proof_nodes(proof_nodes)?;
+        Ok(trie)
+    }
+
+    /// Returns `true` if the trie is empty.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_empty()
+    }
+
+    /// Reveals all proof nodes, replacing the corresponding blinded nodes.
+    pub fn reveal_proof_nodes(&mut self, proof_nodes: &ProofNodes) -> Result<(), SparseTrieError> {
+        // Sorting guarantees that parent nodes are revealed before their children.
+        for (path, node) in proof_nodes.nodes_sorted() {
+            self.reveal_node(path, TrieNode::decode(&mut &node[..])?)?;
+        }
+        Ok(())
+    }
+
+    /// Reveals the node at the given path, replacing the blinded node.
+    ///
+    /// The hash of the revealed node must match the hash of the blinded node. Revealing a node
+    /// that was already revealed is a no-op.
+    pub fn reveal_node(&mut self, path: Nibbles, node: TrieNode) -> Result<(), SparseTrieError> {
+        reveal(&mut self.root, &path, 0, node)
+    }
+
+    /// Returns the value and the privacy flag of the leaf with the given key.
+    pub fn get(&self, key: &Nibbles) -> Result<Option<(&[u8], bool)>, SparseTrieError> {
+        let mut node = &self.root;
+        let mut depth = 0;
+        loop {
+            let remaining = &key[depth..];
+            match &node.kind {
+                SparseNodeKind::Empty => return Ok(None),
+                SparseNodeKind::Hash(hash) => {
+                    return Err(SparseTrieError::BlindedNode {
+                        path: key.slice(..depth),
+                        hash: *hash,
+                    })
+                }
+                SparseNodeKind::Leaf { key: leaf_key, value, is_private } => {
+                    return Ok((leaf_key[..] == *remaining).then_some((&value[..], *is_private)))
+                }
+                SparseNodeKind::Extension { key: ext_key, child } => {
+                    if !remaining.starts_with(&ext_key[..]) {
+                        return Ok(None);
+                    }
+                    depth += ext_key.len();
+                    node = &**child;
+                }
+                SparseNodeKind::Branch { children } => {
+                    let Some(nibble) = remaining.first() else { return Ok(None) };
+                    depth += 1;
+                    node = &children[*nibble as usize];
+                }
+            }
+        }
+    }
+
+    /// Inserts or updates the leaf with the given key.
+    ///
+    /// Returns [SparseTrieError::InvalidKey] if the key does not have the length of the keys
+    /// already in the trie.
+    ///
+    /// SEISMIC WARNING: Ensure that the leaf is flagged correctly when calling
+    pub fn insert_leaf(
+        &mut self,
+        key: Nibbles,
+        value: Vec<u8>,
+        is_private: bool,
+    ) -> Result<(), SparseTrieError> {
+        insert(&mut self.root, &key, 0, value, is_private)
+    }
+
+    /// Removes the leaf with the given key.
+    ///
+    /// Returns `true` if the leaf was present in the trie.
+    pub fn remove_leaf(&mut self, key: &Nibbles) -> Result<bool, SparseTrieError> {
+        if self.get(key)?.is_none() {
+            return Ok(false);
+        }
+        remove(&mut self.root, key, 0)?;
+        Ok(true)
+    }
+
+    /// Reveals the blinded nodes on the path to the given key using the provider.
+    pub fn reveal_path<P: BlindedProvider>(
+        &mut self,
+        key: &Nibbles,
+        provider: &P,
+    ) -> Result<(), SparseTrieError> {
+        self.with_provider(provider, |trie| trie.get(key).map(drop))
+    }
+
+    /// Inserts or updates the leaf with the given key, revealing the blinded nodes on its path
+    /// using the provider.
+    ///
+    /// SEISMIC WARNING: Ensure that the leaf is flagged correctly when calling
+    pub fn insert_leaf_with_provider<P: BlindedProvider>(
+        &mut self,
+        key: Nibbles,
+        value: Vec<u8>,
+        is_private: bool,
+        provider: &P,
+    ) -> Result<(), SparseTrieError> {
+        self.reveal_path(&key, provider)?;
+        self.insert_leaf(key, value, is_private)
+    }
+
+    /// Removes the leaf with the given key, revealing the blinded nodes on its path and the
+    /// siblings it collapses into using the provider.
+    ///
+    /// Returns `true` if the leaf was present in the trie.
+    pub fn remove_leaf_with_provider<P: BlindedProvider>(
+        &mut self,
+        key: &Nibbles,
+        provider: &P,
+    ) -> Result<bool, SparseTrieError> {
+        self.with_provider(provider, |trie| trie.remove_leaf(key))
+    }
+
+    /// Runs the operation, revealing the blinded node it stopped at and retrying until it
+    /// succeeds or the provider does not have the node.
+    fn with_provider<P, R>(
+        &mut self,
+        provider: &P,
+        mut f: impl FnMut(&mut Self) -> Result<R, SparseTrieError>,
+    ) -> Result<R, SparseTrieError>
+    where
+        P: BlindedProvider,
+    {
+        loop {
+            match f(self) {
+                Err(SparseTrieError::BlindedNode { path, hash }) => {
+                    let Some(node) = provider.node(&path) else {
+                        return Err(SparseTrieError::BlindedNode { path, hash });
+                    };
+                    self.reveal_node(path, TrieNode::decode(&mut &node[..])?)?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns the root hash of the trie, re-hashing only the nodes that changed since the last
+    /// call.
+    pub fn root(&mut self) -> B256 {
+        match self.root.kind {
+            SparseNodeKind::Empty => EMPTY_ROOT_HASH,
+            SparseNodeKind::Hash(hash) => hash,
+            _ => {
+                let rlp = self.root.rlp(&mut Vec::new());
+                rlp.as_hash().unwrap_or_else(|| keccak256(&rlp))
+            }
+        }
+    }
+}
+
+/// A node of the [SparseTrie] along with its cached RLP encoding.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct SparseNode {
+    kind: SparseNodeKind,
+    /// Cached `rlp(node)` or `rlp(keccak(rlp(node)))`. Cleared when the subtrie is modified.
+    rlp: Option<RlpNode>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+enum SparseNodeKind {
+    #[default]
+    Empty,
+    /// Blinded node that has not been revealed yet.
+    Hash(B256),
+    Leaf {
+        key: Nibbles,
+        value: Vec<u8>,
+        is_private: bool,
+    },
+    Extension {
+        key: Nibbles,
+        child: Box<SparseNode>,
+    },
+    Branch {
+        children: Box<[SparseNode; 16]>,
+    },
+}
+
+impl SparseNode {
+    const fn new(kind: SparseNodeKind) -> Self {
+        Self { kind, rlp: None }
+    }
+
+    const fn is_empty(&self) -> bool {
+        matches!(self.kind, SparseNodeKind::Empty)
+    }
+
+    /// Converts the decoded trie node into a sparse node, revealing all in-place encoded children.
+    fn from_trie_node(node: TrieNode) -> Result<Self, alloy_rlp::Error> {
+        let kind = match node {
+            TrieNode::EmptyRoot => SparseNodeKind::Empty,
+            TrieNode::Leaf(leaf) => SparseNodeKind::Leaf {
+                key: leaf.key,
+                value: leaf.value,
+                is_private: leaf.is_private,
+            },
+            TrieNode::Extension(extension) => SparseNodeKind::Extension {
+                key: extension.key,
+                child: Box::new(Self::from_child(&extension.child)?),
+            },
+            TrieNode::Branch(branch) => {
+                let mut children = Box::<[Self; 16]>::default();
+                for (index, child) in branch.as_ref().children() {
+                    if let Some(child) = child {
+                        children[index as usize] = Self::from_child(child)?;
+                    }
+                }
+                SparseNodeKind::Branch { children }
+            }
+        };
+        Ok(Self::new(kind))
+    }
+
+    /// Converts the child pointer into either a blinded or an in-place revealed node.
+    fn from_child(rlp: &RlpNode) -> Result<Self, alloy_rlp::Error> {
+        let mut node = if let Some(hash) = rlp.as_hash() {
+            Self::new(SparseNodeKind::Hash(hash))
+        } else {
+            Self::from_trie_node(TrieNode::decode(&mut &rlp[..])?)?
+        };
+        node.rlp = Some(rlp.clone());
+        Ok(node)
+    }
+
+    /// Returns either `rlp(node)` or `rlp(keccak(rlp(node)))`, computing and caching it if the
+    /// node was modified.
+    fn rlp(&mut self, buf: &mut Vec<u8>) -> RlpNode {
+        if let Some(rlp) = &self.rlp {
+            return rlp.clone();
+        }
+
+        let rlp = match &mut self.kind {
+            SparseNodeKind::Empty => RlpNode::from_rlp(&[alloy_rlp::EMPTY_STRING_CODE]),
+            SparseNodeKind::Hash(hash) => RlpNode::word_rlp(hash),
+            SparseNodeKind::Leaf { key, value, is_private } => {
+                buf.clear();
+                LeafNodeRef::new(key, value, is_private).rlp(buf)
+            }
+            SparseNodeKind::Extension { key, child } => {
+                let child = child.rlp(buf);
+                buf.clear();
+                ExtensionNodeRef::new(key, &child).rlp(buf)
+            }
+            SparseNodeKind::Branch { children } => {
+                let mut stack = Vec::with_capacity(16);
+                let mut state_mask = TrieMask::default();
+                for (index, child) in children.iter_mut().enumerate() {
+                    if !child.is_empty() {
+                        stack.push(child.rlp(buf));
+                        state_mask.set_bit(index as u8);
+                    }
+                }
+                buf.clear();
+                BranchNodeRef::new(&stack, state_mask).rlp(buf)
+            }
+        };
+        self.rlp = Some(rlp.clone());
+        rlp
+    }
+}
+
+fn reveal(
+    node: &mut SparseNode,
+    path: &Nibbles,
+    depth: usize,
+    revealed: TrieNode,
+) -> Result<(), SparseTrieError> {
+    let remaining = &path[depth..];
+    match &mut node.kind {
+        SparseNodeKind::Hash(hash) if remaining.is_empty() => {
+            let got = revealed.hash();
+            if got != *hash {
+                return Err(SparseTrieError::NodeHashMismatch {
+                    path: path.clone(),
+                    got,
+                    expected: *hash,
+                });
+            }
+            let rlp = node.rlp.take();
+            *node = SparseNode::from_trie_node(revealed)?;
+            node.rlp = rlp;
+            Ok(())
+        }
+        // The node was already revealed.
+        _ if remaining.is_empty() => Ok(()),
+        SparseNodeKind::Hash(hash) => {
+            Err(SparseTrieError::BlindedNode { path: path.slice(..depth), hash: *hash })
+        }
+        SparseNodeKind::Extension { key, child } if remaining.starts_with(&key[..]) => {
+            let len = key.len();
+            reveal(child, path, depth + len, revealed)
+        }
+        SparseNodeKind::Branch { children } => {
+            reveal(&mut children[remaining[0] as usize], path, depth + 1, revealed)
+        }
+        _ => Err(SparseTrieError::UnreachablePath { path: path.clone() }),
+    }
+}
+
+fn insert(
+    node: &mut SparseNode,
+    key: &Nibbles,
+    depth: usize,
+    value: Vec<u8>,
+    is_private: bool,
+) -> Result<(), SparseTrieError> {
+    let remaining = &key[depth..];
+    match &mut node.kind {
+        SparseNodeKind::Hash(hash) => {
+            return Err(SparseTrieError::BlindedNode { path: key.slice(..depth), hash: *hash })
+        }
+        SparseNodeKind::Branch { children } => {
+            let Some(&nibble) = remaining.first() else {
+                return Err(SparseTrieError::InvalidKey { key: key.clone() });
+            };
+            node.rlp = None;
+            return insert(&mut children[nibble as usize], key, depth + 1, value, is_private);
+        }
+        SparseNodeKind::Extension { key: ext_key, child }
+            if remaining.starts_with(&ext_key[..]) =>
+        {
+            node.rlp = None;
+            let len = ext_key.len();
+            return insert(child, key, depth + len, value, is_private);
+        }
+        SparseNodeKind::Leaf { key: leaf_key, value: leaf_value, is_private: leaf_is_private }
+            if leaf_key[..] == *remaining =>
+        {
+            node.rlp = None;
+            *leaf_value = value;
+            *leaf_is_private = is_private;
+            return Ok(());
+        }
+        // The key must diverge from the leaf key or the extension key before either ends.
+        SparseNodeKind::Leaf { key: prefix, .. }
+        | SparseNodeKind::Extension { key: prefix, .. } => {
+            let common = prefix.common_prefix_length(remaining);
+            if common == prefix.len() || common == remaining.len() {
+                return Err(SparseTrieError::InvalidKey { key: key.clone() });
+            }
+        }
+        SparseNodeKind::Empty => {}
+    }
+
+    // The remaining cases restructure the node in place.
+    node.rlp = None;
+    node.kind = match core::mem::take(&mut node.kind) {
+        SparseNodeKind::Empty => SparseNodeKind::Leaf {
+            key: Nibbles::from_nibbles_unchecked(remaining),
+            value,
+            is_private,
+        },
+        SparseNodeKind::Leaf { key: leaf_key, value: leaf_value, is_private: leaf_is_private } => {
+            let common = leaf_key.common_prefix_length(remaining);
+            let mut children = Box::<[SparseNode; 16]>::default();
+            children[leaf_key[common] as usize] = SparseNode::new(SparseNodeKind::Leaf {
+                key: Nibbles::from_nibbles_unchecked(&leaf_key[common + 1..]),
+                value: leaf_value,
+                is_private: leaf_is_private,
+            });
+            children[remaining[common] as usize] = SparseNode::new(SparseNodeKind::Leaf {
+                key: Nibbles::from_nibbles_unchecked(&remaining[common + 1..]),
+                value,
+                is_private,
+            });
+            branch_with_prefix(&remaining[..common], children)
+        }
+        SparseNodeKind::Extension { key: ext_key, child } => {
+            let common = ext_key.common_prefix_length(remaining);
+            let mut children = Box::<[SparseNode; 16]>::default();
+            let ext_rest = &ext_key[common + 1..];
+            children[ext_key[common] as usize] = if ext_rest.is_empty() {
+                *child
+            } else {
+                SparseNode::new(SparseNodeKind::Extension {
+                    key: Nibbles::from_nibbles_unchecked(ext_rest),
+                    child,
+                })
+            };
+            children[remaining[common] as usize] = SparseNode::new(SparseNodeKind::Leaf {
+                key: Nibbles::from_nibbles_unchecked(&remaining[common + 1..]),
+                value,
+                is_private,
+            });
+            branch_with_prefix(&remaining[..common], children)
+        }
+        SparseNodeKind::Hash(_) | SparseNodeKind::Branch { .. } => {
+            unreachable!("handled above")
+        }
+    };
+    Ok(())
+}
+
+/// Removes the leaf with the given key, collapsing the nodes on its path.
+///
+/// The leaf must be present in the trie.
+fn remove(node: &mut SparseNode, key: &Nibbles, depth: usize) -> Result<(), SparseTrieError> {
+    node.rlp = None;
+    if matches!(node.kind, SparseNodeKind::Leaf { .. }) {
+        node.kind = SparseNodeKind::Empty;
+        return Ok(());
+    }
+
+    match &mut node.kind {
+        SparseNodeKind::Extension { key: ext_key, child } => {
+            let len = ext_key.len();
+            remove(child, key, depth + len)?;
+        }
+        SparseNodeKind::Branch { children } => {
+            let nibble = key[depth] as usize;
+            // If the branch is left with a single blinded child, we cannot tell whether it has to
+            // be merged with the parent without revealing it first.
+            if matches!(children[nibble].kind, SparseNodeKind::Leaf { .. }) {
+                let mut siblings = children
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, child)| *index != nibble && !child.is_empty());
+                if let (Some((index, sibling)), None) = (siblings.next(), siblings.next()) {
+                    if let SparseNodeKind::Hash(hash) = sibling.kind {
+                        let mut path = key.slice(..depth);
+                        path.push(index as u8);
+                        return Err(SparseTrieError::BlindedNode { path, hash });
+                    }
+                }
+            }
+            remove(&mut children[nibble], key, depth + 1)?;
+        }
+        SparseNodeKind::Empty | SparseNodeKind::Hash(_) | SparseNodeKind::Leaf { .. } => {
+            unreachable!("leaf must be present")
+        }
+    }
+
+    node.kind = match core::mem::take(&mut node.kind) {
+        SparseNodeKind::Extension { key: ext_key, child } => {
+            let child = *child;
+            match child.kind {
+                SparseNodeKind::Leaf { key: leaf_key, value, is_private } => {
+                    SparseNodeKind::Leaf { key: ext_key.join(&leaf_key), value, is_private }
+                }
+                SparseNodeKind::Extension { key: child_key, child } => {
+                    SparseNodeKind::Extension { key: ext_key.join(&child_key), child }
+                }
+                kind => SparseNodeKind::Extension {
+                    key: ext_key,
+                    child: Box::new(SparseNode { kind, rlp: child.rlp }),
+                },
+            }
+        }
+        SparseNodeKind::Branch { mut children } => {
+            let only_child = {
+                let mut remaining =
+                    children.iter().enumerate().filter(|(_, child)| !child.is_empty());
+                match (remaining.next(), remaining.next()) {
+                    (Some((index, _)), None) => Some(index),
+                    _ => None,
+                }
+            };
+            match only_child {
+                Some(index) => {
+                    let child = core::mem::take(&mut children[index]);
+                    let prefix = Nibbles::from_nibbles_unchecked([index as u8]);
+                    match child.kind {
+                        SparseNodeKind::Leaf { key: leaf_key, value, is_private } => {
+                            SparseNodeKind::Leaf { key: prefix.join(&leaf_key), value, is_private }
+                        }
+                        SparseNodeKind::Extension { key: child_key, child } => {
+                            SparseNodeKind::Extension { key: prefix.join(&child_key), child }
+                        }
+                        kind => SparseNodeKind::Extension {
+                            key: prefix,
+                            child: Box::new(SparseNode { kind, rlp: child.rlp }),
+                        },
+                    }
+                }
+                None => SparseNodeKind::Branch { children },
+            }
+        }
+        kind => kind,
+    };
+    Ok(())
+}
+
+/// Returns a branch node with the given children, wrapped into an extension node if the prefix
+/// is not empty.
+fn branch_with_prefix(prefix: &[u8], children: Box<[SparseNode; 16]>) -> SparseNodeKind {
+    let branch = SparseNodeKind::Branch { children };
+    if prefix.is_empty() {
+        branch
+    } else {
+        SparseNodeKind::Extension {
+            key: Nibbles::from_nibbles_unchecked(prefix),
+            child: Box::new(SparseNode::new(branch)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{proof::ProofRetainer, HashBuilder};
+    use alloc::collections::BTreeMap;
+    use alloy_primitives::U256;
+
+    fn hash_builder_root(leaves: &BTreeMap<B256, (Vec<u8>, bool)>) -> B256 {
+        let mut hb = HashBuilder::default();
+        for (key, (value, is_private)) in leaves {
+            hb.add_leaf(Nibbles::unpack(key), value, *is_private);
+        }
+        hb.root()
+    }
+
+    fn leaves(range: core::ops::Range<u64>) -> BTreeMap<B256, (Vec<u8>, bool)> {
+        range
+            .map(|i| {
+                let key = keccak256(i.to_be_bytes());
+                (key, (alloy_rlp::encode(U256::from(i)), i % 3 == 0))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty() {
+        let mut trie = SparseTrie::default();
+        assert_eq!(trie.root(), EMPTY_ROOT_HASH);
+        assert_eq!(SparseTrie::blind(EMPTY_ROOT_HASH), trie);
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let mut expected = leaves(0..256);
+        let mut trie = SparseTrie::default();
+        // Insert in reverse order to make sure the order does not matter.
+        for (key, (value, is_private)) in expected.iter().rev() {
+            trie.insert_leaf(Nibbles::unpack(key), value.clone(), *is_private).unwrap();
+        }
+        assert_eq!(trie.root(), hash_builder_root(&expected));
+
+        for (key, (value, is_private)) in &expected {
+            assert_eq!(trie.get(&Nibbles::unpack(key)), Ok(Some((&value[..], *is_private))));
+        }
+        assert_eq!(trie.get(&Nibbles::unpack(B256::ZERO)), Ok(None));
+
+        // Update some leaves.
+        for (key, (value, is_private)) in expected.iter_mut().step_by(7) {
+            *value = vec![0x42];
+            *is_private = !*is_private;
+            trie.insert_leaf(Nibbles::unpack(key), value.clone(), *is_private).unwrap();
+        }
+        assert_eq!(trie.root(), hash_builder_root(&expected));
+
+        // Remove all leaves one by one.
+        while let Some((key, _)) = expected.pop_first() {
+            assert_eq!(trie.remove_leaf(&Nibbles::unpack(key)), Ok(true));
+            assert_eq!(trie.remove_leaf(&Nibbles::unpack(key)), Ok(false));
+            if expected.len() % 16 == 0 {
+                assert_eq!(trie.root(), hash_builder_root(&expected));
+            }
+        }
+        assert!(trie.is_empty());
+        assert_eq!(trie.root(), EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    fn reveal_and_update() {
+        let mut expected = leaves(0..128);
+        let targets = expected.keys().take(3).copied().collect::<Vec<_>>();
+
+        let retainer = ProofRetainer::from_iter(targets.iter().map(Nibbles::unpack));
+        let mut hb = HashBuilder::default().with_proof_retainer(retainer);
+        for (key, (value, is_private)) in &expected {
+            hb.add_leaf(Nibbles::unpack(key), value, *is_private);
+        }
+        let root = hb.root();
+        let proof_nodes = hb.take_proof_nodes();
+
+        let mut trie = SparseTrie::from_proof_nodes(root, &proof_nodes).unwrap();
+        assert_eq!(trie.root(), root);
+
+        for target in &targets {
+            let (value, is_private) = &expected[target];
+            assert_eq!(trie.get(&Nibbles::unpack(target)), Ok(Some((&value[..], *is_private))));
+        }
+
+        let new_value = vec![0x42];
+        trie.insert_leaf(Nibbles::unpack(targets[0]), new_value.clone(), true).unwrap();
+        expected.insert(targets[0], (new_value, true));
+        assert_eq!(trie.root(), hash_builder_root(&expected));
+
+        // Blinded subtries cannot be walked.
+        let blinded = expected.keys().last().copied().unwrap();
+        assert!(matches!(
+            trie.get(&Nibbles::unpack(blinded)),
+            Err(SparseTrieError::BlindedNode { .. })
+        ));
+    }
+
+    #[test]
+    fn reveal_with_provider() {
+        let mut expected = leaves(0..128);
+        let retainer = ProofRetainer::from_iter(expected.keys().map(Nibbles::unpack));
+        let mut hb = HashBuilder::default().with_proof_retainer(retainer);
+        for (key, (value, is_private)) in &expected {
+            hb.add_leaf(Nibbles::unpack(key), value, *is_private);
+        }
+        let root = hb.root();
+        let provider = hb.take_proof_nodes();
+
+        // Only the nodes on the touched paths are revealed.
+        let mut trie = SparseTrie::blind(root);
+        let keys = expected.keys().copied().step_by(5).collect::<Vec<_>>();
+        trie.reveal_path(&Nibbles::unpack(keys[0]), &provider).unwrap();
+        let (value, is_private) = &expected[&keys[0]];
+        assert_eq!(trie.get(&Nibbles::unpack(keys[0])), Ok(Some((&value[..], *is_private))));
+        assert!(matches!(
+            trie.get(&Nibbles::unpack(keys[1])),
+            Err(SparseTrieError::BlindedNode { .. })
+        ));
+
+        for (i, key) in keys.iter().enumerate() {
+            if i % 2 == 0 {
+                expected.insert(*key, (vec![0x42], true));
+                trie.insert_leaf_with_provider(Nibbles::unpack(key), vec![0x42], true, &provider)
+                    .unwrap();
+            } else {
+                expected.remove(key);
+                assert_eq!(
+                    trie.remove_leaf_with_provider(&Nibbles::unpack(key), &provider),
+                    Ok(true)
+                );
+            }
+        }
+        assert_eq!(trie.root(), hash_builder_root(&expected));
+
+        // Without the nodes the blinded paths cannot be revealed.
+        let mut trie = SparseTrie::blind(root);
+        assert!(matches!(
+            trie.reveal_path(&Nibbles::unpack(keys[0]), &ProofNodes::default()),
+            Err(SparseTrieError::BlindedNode { .. })
+        ));
+    }
+
+    #[test]
+    fn reveal_hash_mismatch() {
+        let mut trie = SparseTrie::blind(B256::repeat_byte(1));
+        let node = TrieNode::Leaf(crate::nodes::LeafNode::new(
+            Nibbles::unpack(B256::ZERO),
+            vec![1],
+            false,
+        ));
+        assert!(matches!(
+            trie.reveal_node(Nibbles::default(), node),
+            Err(SparseTrieError::NodeHashMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn insert_invalid_keys() {
+        let expected = leaves(0..16);
+        let mut trie = SparseTrie::default();
+        for (key, (value, is_private)) in &expected {
+            trie.insert_leaf(Nibbles::unpack(key), value.clone(), *is_private).unwrap();
+        }
+        let root = trie.root();
+
+        let key = Nibbles::unpack(expected.keys().next().unwrap());
+        let mut longer = key.clone();
+        longer.push(0);
+        for key in [Nibbles::default(), key.slice(..1), key.slice(..10), longer] {
+            assert_eq!(
+                trie.insert_leaf(key.clone(), vec![1], false),
+                Err(SparseTrieError::InvalidKey { key })
+            );
+        }
+        assert_eq!(trie.root(), root);
+    }
+}
@@ -0,0 +1,24 @@
+use crate::{proof::ProofNodes, Nibbles};
+use alloy_primitives::Bytes;
+
+/// Provider of the nodes that are blinded in a [SparseTrie](super::SparseTrie), such as a
+/// database of trie nodes or a peer serving them.
+///
+/// The nodes are revealed on demand, so that tries too large to be held in memory can be updated
+/// and proven by revealing only the paths that are touched.
+pub trait BlindedProvider {
+    /// Returns the RLP encoded node at the given path, or [None] if it is not available.
+    fn node(&self, path: &Nibbles) -> Option<Bytes>;
+}
+
+impl<T: BlindedProvider + ?Sized> BlindedProvider for &T {
+    fn node(&self, path: &Nibbles) -> Option<Bytes> {
+        (**self).node(path)
+    }
+}
+
+impl BlindedProvider for ProofNodes {
+    fn node(&self, path: &Nibbles) -> Option<Bytes> {
+        self.get(path).cloned()
+    }
+}
@@ -0,0 +1,759 @@
+//! Incremental state and storage root computation on top of stored trie nodes.
+
+use crate::{
+    cursor::{
+        post_state::HashedPostStateCursorFactory, DatabaseError, HashedCursorFactory,
+        HashedStorageCursor, TrieCursor, TrieCursorFactory,
+    },
+    hash_builder::TrieObserver,
+    hashed_state::{HashedPostState, HashedStorage},
+    nibbles::unpack_b256,
+    node_iter::{TrieElement, TrieNodeIter},
+    prefix_set::{PrefixSet, TriePrefixSets},
+    updates::{StateTrieUpdates, TrieUpdates},
+    walker::{CursorSubNode, TrieWalker},
+    HashBuilder, Nibbles, EMPTY_ROOT_HASH,
+};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use alloy_primitives::B256;
+
+/// Computes the state root from the hashed state and the stored account and storage trie nodes.
+///
+/// Only the subtries containing keys from the prefix sets are re-hashed, the hashes of all other
+/// subtries are taken from the stored branch nodes. The stored nodes must be consistent with the
+/// hashed state prior to the changes recorded in the prefix sets.
+#[derive(Clone, Debug)]
+pub struct StateRoot<T, H> {
+    /// Factory of cursors over the stored trie nodes.
+    trie_cursor_factory: T,
+    /// Factory of cursors over the hashed state.
+    hashed_cursor_factory: H,
+    /// The prefix sets of the changed keys.
+    prefix_sets: TriePrefixSets,
+    /// Observer of the hashing work.
+    observer: Option<Arc<dyn TrieObserver>>,
+    /// The maximum number of account trie elements to process before pausing.
+    node_budget: Option<usize>,
+    /// The checkpoint of a paused computation to resume from.
+    checkpoint: Option<StateRootCheckpoint>,
+}
+
+/// The progress of a state root computation, see [StateRoot::root_with_progress].
+#[derive(Debug)]
+pub enum StateRootProgress {
+    /// The computation is complete, with the state root and the remaining updates.
+    Complete(B256, StateTrieUpdates),
+    /// The node budget was exhausted. The computation is resumed with
+    /// [StateRoot::with_checkpoint], the updates so far can be applied in the meantime.
+    Progress(Box<StateRootCheckpoint>, StateTrieUpdates),
+}
+
+/// The intermediate state of a paused state root computation.
+#[derive(Clone, Debug)]
+pub struct StateRootCheckpoint {
+    /// The hash builder with the accounts and subtries added so far.
+    pub hash_builder: HashBuilder,
+    /// The stack of the walker over the stored account trie nodes.
+    pub walker_stack: Vec<CursorSubNode>,
+    /// The hashed address of the last account added to the hash builder.
+    pub last_account_key: B256,
+}
+
+impl<T, H> StateRoot<T, H>
+where
+    T: TrieCursorFactory + Clone,
+    H: HashedCursorFactory + Clone,
+{
+    /// Creates a new state root computer.
+    ///
+    /// With no prefix sets set, the stored nodes are assumed to be up to date.
+    pub fn new(trie_cursor_factory: T, hashed_cursor_factory: H) -> Self {
+        Self {
+            trie_cursor_factory,
+            hashed_cursor_factory,
+            prefix_sets: TriePrefixSets::default(),
+            observer: None,
+            node_budget: None,
+            checkpoint: None,
+        }
+    }
+
+    /// Sets the prefix sets of the changed keys.
+    pub fn with_prefix_sets(mut self, prefix_sets: TriePrefixSets) -> Self {
+        self.prefix_sets = prefix_sets;
+        self
+    }
+
+    /// Sets the observer of the hashing work of the account and storage tries.
+    pub fn with_observer(mut self, observer: Arc<dyn TrieObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Sets the maximum number of account trie elements, accounts and skipped subtries, to process
+    /// before [StateRoot::root_with_progress] pauses the computation.
+    ///
+    /// The budget is checked after every account, so that the memory and time spent per run are
+    /// bounded, e.g. for pipelined sync stages.
+    pub const fn with_node_budget(mut self, node_budget: usize) -> Self {
+        self.node_budget = Some(node_budget);
+        self
+    }
+
+    /// Resumes the computation from the checkpoint of a previous run.
+    ///
+    /// The stored nodes, the hashed state and the prefix sets must be the same as in the previous
+    /// run, except for the updates it returned.
+    pub fn with_checkpoint(mut self, checkpoint: StateRootCheckpoint) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
+    /// Computes the state root.
+    pub fn root(mut self) -> Result<B256, DatabaseError> {
+        self.node_budget = None;
+        match self.calculate(false)? {
+            StateRootProgress::Complete(root, _) => Ok(root),
+            StateRootProgress::Progress(..) => unreachable!("no node budget"),
+        }
+    }
+
+    /// Computes the state root along with the updates of the stored account and storage trie
+    /// nodes.
+    pub fn root_with_updates(mut self) -> Result<(B256, StateTrieUpdates), DatabaseError> {
+        self.node_budget = None;
+        match self.calculate(true)? {
+            StateRootProgress::Complete(root, updates) => Ok((root, updates)),
+            StateRootProgress::Progress(..) => unreachable!("no node budget"),
+        }
+    }
+
+    /// Computes the state root along with the updates of the stored account and storage trie
+    /// nodes, pausing once the node budget is exhausted.
+    ///
+    /// Applying the updates of all the runs in order is equivalent to applying the updates of
+    /// [StateRoot::root_with_updates].
+    pub fn root_with_progress(self) -> Result<StateRootProgress, DatabaseError> {
+        self.calculate(true)
+    }
+
+    fn calculate(self, retain_updates: bool) -> Result<StateRootProgress, DatabaseError> {
+        let TriePrefixSets { account_prefix_set, mut storage_prefix_sets } = self.prefix_sets;
+        let mut updates = StateTrieUpdates::default();
+
+        let trie_cursor = self.trie_cursor_factory.account_trie_cursor()?;
+        let hashed_account_cursor = self.hashed_cursor_factory.hashed_account_cursor()?;
+        let (walker, mut hash_builder, last_account_key) = match self.checkpoint {
+            Some(checkpoint) => (
+                TrieWalker::from_stack(trie_cursor, checkpoint.walker_stack, account_prefix_set),
+                checkpoint.hash_builder,
+                Some(checkpoint.last_account_key),
+            ),
+            None => (
+                TrieWalker::new(trie_cursor, account_prefix_set)?,
+                HashBuilder::default().with_updates(retain_updates),
+                None,
+            ),
+        };
+        let walker = walker.with_deletions_retained(retain_updates);
+        let mut account_node_iter =
+            TrieNodeIter::new(walker, hashed_account_cursor).with_last_hashed_key(last_account_key);
+        hash_builder.observer = self.observer.clone();
+        let mut account_rlp = Vec::new();
+        let mut processed = 0usize;
+
+        while let Some(element) = account_node_iter.try_next()? {
+            processed += 1;
+            match element {
+                TrieElement::Branch { key, hash, children_are_in_trie, is_private } => {
+                    hash_builder.add_branch_with_privacy(
+                        key,
+                        hash,
+                        children_are_in_trie,
+                        is_private,
+                    );
+                }
+                TrieElement::Leaf(hashed_address, mut account) => {
+                    let mut storage_root = StorageRoot::new(
+                        self.trie_cursor_factory.clone(),
+                        self.hashed_cursor_factory.clone(),
+                        hashed_address,
+                    )
+                    .with_prefix_set(
+                        storage_prefix_sets.remove(&hashed_address).unwrap_or_default(),
+                    );
+                    storage_root.observer = self.observer.clone();
+                    account.storage_root = if retain_updates {
+                        let (root, storage_updates) = storage_root.root_with_updates()?;
+                        if !storage_updates.is_empty() {
+                            updates.storage_tries.insert(hashed_address, storage_updates);
+                        }
+                        root
+                    } else {
+                        storage_root.root()?
+                    };
+
+                    account_rlp.clear();
+                    account.encode_into(&mut account_rlp);
+                    // account nodes are always public
+                    hash_builder.add_leaf(unpack_b256(&hashed_address), &account_rlp, false);
+
+                    if self.node_budget.is_some_and(|budget| processed >= budget) {
+                        updates.account_nodes.finalize(
+                            hash_builder.take_updates().updated_nodes,
+                            account_node_iter.walker.take_removed_keys(),
+                        );
+                        let checkpoint = StateRootCheckpoint {
+                            hash_builder,
+                            walker_stack: account_node_iter.walker.stack().to_vec(),
+                            last_account_key: hashed_address,
+                        };
+                        return Ok(StateRootProgress::Progress(Box::new(checkpoint), updates));
+                    }
+                }
+            }
+        }
+
+        let (root, hash_builder_updates) = hash_builder.root_with_updates();
+        updates.account_nodes.finalize(
+            hash_builder_updates.updated_nodes,
+            account_node_iter.walker.take_removed_keys(),
+        );
+
+        Ok(StateRootProgress::Complete(root, updates))
+    }
+}
+
+/// Computes the state root after applying the given in-memory state changes on top of the stored
+/// trie nodes and hashed state, without persisting the changes first.
+///
+/// The stored nodes must be consistent with the hashed state of the cursor factory.
+pub fn state_root_with_overlay<T, H>(
+    trie_cursor_factory: T,
+    hashed_cursor_factory: H,
+    post_state: HashedPostState,
+) -> Result<B256, DatabaseError>
+where
+    T: TrieCursorFactory + Clone,
+    H: HashedCursorFactory + Clone,
+{
+    let prefix_sets = post_state.construct_prefix_sets().freeze();
+    let post_state = post_state.into_sorted();
+    StateRoot::new(
+        trie_cursor_factory,
+        HashedPostStateCursorFactory::new(hashed_cursor_factory, &post_state),
+    )
+    .with_prefix_sets(prefix_sets)
+    .root()
+}
+
+/// Computes the storage root of the account before the changes recorded by the reverse changeset,
+/// from its current stored storage trie nodes and hashed storage, e.g. to roll back a reorged
+/// block without replaying the history.
+///
+/// The reverse changeset holds the previous values of the changed slots along with their privacy
+/// flag, zero values marking the slots that did not exist. Only the subtries of the changed slots
+/// are re-hashed.
+pub fn reverted_storage_root<T, H>(
+    trie_cursor_factory: T,
+    hashed_cursor_factory: H,
+    hashed_address: B256,
+    reverts: HashedStorage,
+) -> Result<B256, DatabaseError>
+where
+    T: TrieCursorFactory,
+    H: HashedCursorFactory,
+{
+    let prefix_set = reverts.construct_prefix_set().freeze();
+    let post_state = HashedPostState::default().with_storages([(hashed_address, reverts)]);
+    let post_state = post_state.into_sorted();
+    StorageRoot::new(
+        trie_cursor_factory,
+        HashedPostStateCursorFactory::new(hashed_cursor_factory, &post_state),
+        hashed_address,
+    )
+    .with_prefix_set(prefix_set)
+    .root()
+}
+
+/// Computes the storage root of a single account from its hashed storage and its stored storage
+/// trie nodes.
+///
+/// SEISMIC WARNING: Ensure that the storage values are flagged correctly by the hashed storage
+/// cursor.
+#[derive(Clone, Debug)]
+pub struct StorageRoot<T, H> {
+    /// Factory of cursors over the stored trie nodes.
+    trie_cursor_factory: T,
+    /// Factory of cursors over the hashed state.
+    hashed_cursor_factory: H,
+    /// The hashed address of the account.
+    hashed_address: B256,
+    /// The prefix set of the changed storage keys.
+    prefix_set: PrefixSet,
+    /// Observer of the hashing work.
+    observer: Option<Arc<dyn TrieObserver>>,
+}
+
+impl<T, H> StorageRoot<T, H>
+where
+    T: TrieCursorFactory,
+    H: HashedCursorFactory,
+{
+    /// Creates a new storage root computer for the account with the given hashed address.
+    pub fn new(trie_cursor_factory: T, hashed_cursor_factory: H, hashed_address: B256) -> Self {
+        Self {
+            trie_cursor_factory,
+            hashed_cursor_factory,
+            hashed_address,
+            prefix_set: PrefixSet::default(),
+            observer: None,
+        }
+    }
+
+    /// Sets the prefix set of the changed storage keys.
+    pub fn with_prefix_set(mut self, prefix_set: PrefixSet) -> Self {
+        self.prefix_set = prefix_set;
+        self
+    }
+
+    /// Sets the observer of the hashing work of the storage trie.
+    pub fn with_observer(mut self, observer: Arc<dyn TrieObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Computes the storage root.
+    pub fn root(self) -> Result<B256, DatabaseError> {
+        self.calculate(false).map(|(root, _)| root)
+    }
+
+    /// Computes the storage root along with the updates of the stored storage trie nodes.
+    pub fn root_with_updates(self) -> Result<(B256, TrieUpdates), DatabaseError> {
+        self.calculate(true)
+    }
+
+    fn calculate(self, retain_updates: bool) -> Result<(B256, TrieUpdates), DatabaseError> {
+        let mut updates = TrieUpdates::default();
+        let mut trie_cursor = self.trie_cursor_factory.storage_trie_cursor(self.hashed_address)?;
+        let mut hashed_storage_cursor =
+            self.hashed_cursor_factory.hashed_storage_cursor(self.hashed_address)?;
+
+        // Short circuit on wiped storage, all stored nodes are removed.
+        if hashed_storage_cursor.is_storage_empty()? {
+            if retain_updates {
+                let mut entry = trie_cursor.seek(Nibbles::default())?;
+                while let Some((path, _)) = entry {
+                    updates.insert_removed(path);
+                    entry = trie_cursor.next()?;
+                }
+            }
+            if let Some(observer) = &self.observer {
+                observer.on_storage_root_computed();
+            }
+            return Ok((EMPTY_ROOT_HASH, updates));
+        }
+
+        let walker =
+            TrieWalker::new(trie_cursor, self.prefix_set)?.with_deletions_retained(retain_updates);
+        let mut storage_node_iter = TrieNodeIter::new(walker, hashed_storage_cursor);
+        let mut hash_builder = HashBuilder::default().with_updates(retain_updates);
+        hash_builder.observer = self.observer.clone();
+
+        while let Some(element) = storage_node_iter.try_next()? {
+            match element {
+                TrieElement::Branch { key, hash, children_are_in_trie, is_private } => {
+                    hash_builder.add_branch_with_privacy(
+                        key,
+                        hash,
+                        children_are_in_trie,
+                        is_private,
+                    );
+                }
+                TrieElement::Leaf(hashed_slot, (value, is_private)) => {
+                    hash_builder.add_leaf(
+                        unpack_b256(&hashed_slot),
+                        alloy_rlp::encode_fixed_size(&value).as_ref(),
+                        is_private,
+                    );
+                }
+            }
+        }
+
+        let (root, hash_builder_updates) = hash_builder.root_with_updates();
+        updates.finalize(
+            hash_builder_updates.updated_nodes,
+            storage_node_iter.walker.take_removed_keys(),
+        );
+        if let Some(observer) = &self.observer {
+            observer.on_storage_root_computed();
+        }
+
+        Ok((root, updates))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cursor::in_memory::{InMemoryHashedState, InMemoryTrieNodes},
+        prefix_set::{PrefixSetMut, TriePrefixSetsMut},
+        root::{state_root, storage_root},
+        TrieAccount,
+    };
+    use alloc::collections::BTreeMap;
+    use alloy_primitives::U256;
+
+    fn apply_updates(nodes: &mut InMemoryTrieNodes, updates: StateTrieUpdates) {
+        for path in updates.account_nodes.removed_nodes {
+            nodes.account_nodes.remove(&path);
+        }
+        nodes.account_nodes.extend(updates.account_nodes.updated_nodes);
+        for (hashed_address, storage_updates) in updates.storage_tries {
+            let storage_nodes = nodes.storage_nodes.entry(hashed_address).or_default();
+            for path in storage_updates.removed_nodes {
+                storage_nodes.remove(&path);
+            }
+            storage_nodes.extend(storage_updates.updated_nodes);
+        }
+    }
+
+    fn expected_root(state: &InMemoryHashedState) -> B256 {
+        state_root(state.accounts.iter().map(|(hashed_address, account)| {
+            let storage = state.storages.get(hashed_address).cloned().unwrap_or_default();
+            (*hashed_address, TrieAccount { storage_root: storage_root(storage), ..*account })
+        }))
+    }
+
+    #[test]
+    fn incremental_state_root() {
+        let mut state = InMemoryHashedState::default();
+        for i in 0..200u64 {
+            let hashed_address = alloy_primitives::keccak256(i.to_be_bytes());
+            let account = TrieAccount { nonce: i, ..Default::default() };
+            state.accounts.insert(hashed_address, account);
+            if i % 3 == 0 {
+                let storage = BTreeMap::from_iter((0..20u64).map(|slot| {
+                    (
+                        alloy_primitives::keccak256(slot.to_be_bytes()),
+                        (U256::from(i + slot), slot % 2 == 0),
+                    )
+                }));
+                state.storages.insert(hashed_address, storage);
+            }
+        }
+
+        // Compute the root from scratch and store the nodes.
+        let mut nodes = InMemoryTrieNodes::default();
+        let (root, updates) = StateRoot::new(&nodes, &state).root_with_updates().unwrap();
+        assert_eq!(root, expected_root(&state));
+        assert!(!updates.account_nodes.is_empty());
+        apply_updates(&mut nodes, updates);
+        assert_eq!(StateRoot::new(&nodes, &state).root().unwrap(), root);
+
+        // Change an account and a storage slot of another account.
+        let changed_account = alloy_primitives::keccak256(1u64.to_be_bytes());
+        state.accounts.get_mut(&changed_account).unwrap().balance = U256::from(1);
+        let changed_storage = alloy_primitives::keccak256(3u64.to_be_bytes());
+        let changed_slot = alloy_primitives::keccak256(5u64.to_be_bytes());
+        state
+            .storages
+            .get_mut(&changed_storage)
+            .unwrap()
+            .insert(changed_slot, (U256::from(7), true));
+
+        let mut prefix_sets = TriePrefixSetsMut::default();
+        prefix_sets.account_prefix_set.insert(Nibbles::unpack(changed_account));
+        prefix_sets.account_prefix_set.insert(Nibbles::unpack(changed_storage));
+        prefix_sets
+            .storage_prefix_sets
+            .insert(changed_storage, PrefixSetMut::from_iter([Nibbles::unpack(changed_slot)]));
+
+        let (new_root, updates) = StateRoot::new(&nodes, &state)
+            .with_prefix_sets(prefix_sets.freeze())
+            .root_with_updates()
+            .unwrap();
+        assert_ne!(new_root, root);
+        assert_eq!(new_root, expected_root(&state));
+        apply_updates(&mut nodes, updates);
+
+        // The private masks of the stored nodes match the ones of a full recomputation.
+        let (_, full_updates) =
+            StorageRoot::new(&InMemoryTrieNodes::default(), &state, changed_storage)
+                .root_with_updates()
+                .unwrap();
+        let stored_root_node = &nodes.storage_nodes[&changed_storage][&Nibbles::default()];
+        assert!(!stored_root_node.private_mask.is_empty());
+        assert_eq!(
+            stored_root_node.private_mask,
+            full_updates.updated_nodes[&Nibbles::default()].private_mask
+        );
+        assert_eq!(StateRoot::new(&nodes, &state).root().unwrap(), new_root);
+
+        // Without prefix sets the stale stored nodes are reused.
+        state.accounts.get_mut(&changed_account).unwrap().nonce += 1;
+        assert_eq!(StateRoot::new(&nodes, &state).root().unwrap(), new_root);
+    }
+
+    fn root_in_steps(
+        nodes: &InMemoryTrieNodes,
+        state: &InMemoryHashedState,
+        prefix_sets: &TriePrefixSets,
+        budget: usize,
+    ) -> (B256, StateTrieUpdates) {
+        let mut updates = StateTrieUpdates::default();
+        let mut checkpoint = None;
+        loop {
+            let mut state_root = StateRoot::new(nodes, state)
+                .with_prefix_sets(prefix_sets.clone())
+                .with_node_budget(budget);
+            if let Some(checkpoint) = checkpoint.take() {
+                state_root = state_root.with_checkpoint(checkpoint);
+            }
+            match state_root.root_with_progress().unwrap() {
+                StateRootProgress::Complete(root, step_updates) => {
+                    updates.extend(step_updates);
+                    return (root, updates);
+                }
+                StateRootProgress::Progress(next, step_updates) => {
+                    updates.extend(step_updates);
+                    checkpoint = Some(*next);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn resume_state_root_from_checkpoint() {
+        let mut state = InMemoryHashedState::default();
+        for i in 0..100u64 {
+            let hashed_address = alloy_primitives::keccak256(i.to_be_bytes());
+            state.accounts.insert(hashed_address, TrieAccount { nonce: i, ..Default::default() });
+            if i % 4 == 0 {
+                let storage = BTreeMap::from_iter((0..5u64).map(|slot| {
+                    (
+                        alloy_primitives::keccak256(slot.to_be_bytes()),
+                        (U256::from(slot + 1), i % 8 == 0),
+                    )
+                }));
+                state.storages.insert(hashed_address, storage);
+            }
+        }
+
+        // From scratch.
+        let empty = InMemoryTrieNodes::default();
+        let (root, updates) = StateRoot::new(&empty, &state).root_with_updates().unwrap();
+        let mut nodes = empty.clone();
+        apply_updates(&mut nodes, updates);
+        for budget in [1, 7, 1000] {
+            let (step_root, step_updates) =
+                root_in_steps(&empty, &state, &TriePrefixSets::default(), budget);
+            assert_eq!(step_root, root);
+            let mut step_nodes = empty.clone();
+            apply_updates(&mut step_nodes, step_updates);
+            assert_eq!(step_nodes, nodes);
+        }
+
+        // Incrementally, reusing the stored nodes of the unchanged subtries.
+        let mut prefix_sets = TriePrefixSetsMut::default();
+        for i in [3u64, 40, 41, 97] {
+            let hashed_address = alloy_primitives::keccak256(i.to_be_bytes());
+            state.accounts.get_mut(&hashed_address).unwrap().balance = U256::from(i);
+            prefix_sets.account_prefix_set.insert(Nibbles::unpack(hashed_address));
+        }
+        let prefix_sets = prefix_sets.freeze();
+        let (root, updates) = StateRoot::new(&nodes, &state)
+            .with_prefix_sets(prefix_sets.clone())
+            .root_with_updates()
+            .unwrap();
+        assert_eq!(root, expected_root(&state));
+        let mut expected_nodes = nodes.clone();
+        apply_updates(&mut expected_nodes, updates);
+        for budget in [1, 2, 7] {
+            let (step_root, step_updates) = root_in_steps(&nodes, &state, &prefix_sets, budget);
+            assert_eq!(step_root, root);
+            let mut step_nodes = nodes.clone();
+            apply_updates(&mut step_nodes, step_updates);
+            assert_eq!(step_nodes, expected_nodes);
+        }
+    }
+
+    #[test]
+    fn state_root_with_post_state_overlay() {
+        let mut state = InMemoryHashedState::default();
+        for i in 0..100u64 {
+            let hashed_address = alloy_primitives::keccak256(i.to_be_bytes());
+            state.accounts.insert(hashed_address, TrieAccount { nonce: i, ..Default::default() });
+            state.storages.insert(
+                hashed_address,
+                BTreeMap::from_iter((0..10u64).map(|slot| {
+                    (alloy_primitives::keccak256(slot.to_be_bytes()), (U256::from(slot + 1), false))
+                })),
+            );
+        }
+        let mut nodes = InMemoryTrieNodes::default();
+        let (root, updates) = StateRoot::new(&nodes, &state).root_with_updates().unwrap();
+        apply_updates(&mut nodes, updates);
+
+        let destroyed = alloy_primitives::keccak256(1u64.to_be_bytes());
+        let changed = alloy_primitives::keccak256(2u64.to_be_bytes());
+        let created = B256::with_last_byte(1);
+        let recreated = alloy_primitives::keccak256(4u64.to_be_bytes());
+        let (removed_slot, private_slot) = (
+            alloy_primitives::keccak256(3u64.to_be_bytes()),
+            alloy_primitives::keccak256(100u64.to_be_bytes()),
+        );
+        let post_state = HashedPostState::default()
+            .with_accounts([
+                (destroyed, None),
+                (changed, Some(TrieAccount { nonce: 1000, ..Default::default() })),
+                (created, Some(TrieAccount::default())),
+            ])
+            .with_storages([
+                (
+                    changed,
+                    HashedStorage::from_iter([
+                        (removed_slot, (U256::ZERO, false)),
+                        (private_slot, (U256::from(1), true)),
+                    ]),
+                ),
+                (
+                    recreated,
+                    HashedStorage {
+                        wiped: true,
+                        ..HashedStorage::from_iter([(private_slot, (U256::from(2), true))])
+                    },
+                ),
+            ]);
+
+        let mut expected = state.clone();
+        expected.accounts.remove(&destroyed);
+        expected.accounts.get_mut(&changed).unwrap().nonce = 1000;
+        expected.accounts.insert(created, TrieAccount::default());
+        let storage = expected.storages.get_mut(&changed).unwrap();
+        storage.remove(&removed_slot);
+        storage.insert(private_slot, (U256::from(1), true));
+        expected
+            .storages
+            .insert(recreated, BTreeMap::from_iter([(private_slot, (U256::from(2), true))]));
+
+        let overlay_root = state_root_with_overlay(&nodes, &state, post_state).unwrap();
+        assert_ne!(overlay_root, root);
+        assert_eq!(overlay_root, expected_root(&expected));
+        assert_eq!(state_root_with_overlay(&nodes, &state, HashedPostState::default()), Ok(root));
+    }
+
+    #[test]
+    fn observe_root_computation() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Debug, Default)]
+        struct Counters {
+            leaves: AtomicUsize,
+            branches: AtomicUsize,
+            bytes: AtomicUsize,
+            storage_roots: AtomicUsize,
+        }
+
+        impl TrieObserver for Counters {
+            fn on_leaf_added(&self) {
+                self.leaves.fetch_add(1, Ordering::Relaxed);
+            }
+            fn on_branch_hashed(&self) {
+                self.branches.fetch_add(1, Ordering::Relaxed);
+            }
+            fn on_bytes_hashed(&self, len: usize) {
+                self.bytes.fetch_add(len, Ordering::Relaxed);
+            }
+            fn on_storage_root_computed(&self) {
+                self.storage_roots.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let mut state = InMemoryHashedState::default();
+        for i in 0..20u64 {
+            let hashed_address = alloy_primitives::keccak256(i.to_be_bytes());
+            state.accounts.insert(hashed_address, TrieAccount::default());
+            if i % 2 == 0 {
+                state.storages.insert(
+                    hashed_address,
+                    BTreeMap::from_iter([(B256::with_last_byte(1), (U256::from(1), true))]),
+                );
+            }
+        }
+
+        let counters = Arc::new(Counters::default());
+        let root = StateRoot::new(&InMemoryTrieNodes::default(), &state)
+            .with_observer(counters.clone())
+            .root()
+            .unwrap();
+        assert_eq!(root, expected_root(&state));
+        assert_eq!(counters.leaves.load(Ordering::Relaxed), 30);
+        assert_eq!(counters.storage_roots.load(Ordering::Relaxed), 20);
+        assert!(counters.branches.load(Ordering::Relaxed) > 0);
+        assert!(counters.bytes.load(Ordering::Relaxed) > 0);
+    }
+
+    #[test]
+    fn wiped_storage_root() {
+        let hashed_address = B256::with_last_byte(1);
+        let mut state = InMemoryHashedState::default();
+        state.storages.insert(
+            hashed_address,
+            BTreeMap::from_iter((0..50u64).map(|slot| {
+                (alloy_primitives::keccak256(slot.to_be_bytes()), (U256::from(slot + 1), false))
+            })),
+        );
+
+        let mut nodes = InMemoryTrieNodes::default();
+        let (root, updates) =
+            StorageRoot::new(&nodes, &state, hashed_address).root_with_updates().unwrap();
+        assert_eq!(root, storage_root(state.storages[&hashed_address].clone()));
+        assert!(!updates.updated_nodes.is_empty());
+        nodes.storage_nodes.insert(hashed_address, BTreeMap::from_iter(updates.updated_nodes));
+
+        state.storages.remove(&hashed_address);
+        let (root, updates) =
+            StorageRoot::new(&nodes, &state, hashed_address).root_with_updates().unwrap();
+        assert_eq!(root, EMPTY_ROOT_HASH);
+        assert_eq!(updates.removed_nodes.len(), nodes.storage_nodes[&hashed_address].len());
+    }
+
+    #[test]
+    fn revert_storage_root() {
+        let hashed_address = B256::with_last_byte(1);
+        let slot = |i: u64| alloy_primitives::keccak256(i.to_be_bytes());
+        let mut state = InMemoryHashedState::default();
+        state.storages.insert(
+            hashed_address,
+            BTreeMap::from_iter((0..50u64).map(|i| (slot(i), (U256::from(i + 1), i % 3 == 0)))),
+        );
+        let previous_root = storage_root(state.storages[&hashed_address].clone());
+
+        // Change, remove and create slots, then store the nodes of the current storage.
+        let storage = state.storages.get_mut(&hashed_address).unwrap();
+        storage.insert(slot(1), (U256::from(100), true));
+        storage.remove(&slot(2));
+        storage.insert(slot(100), (U256::from(1), false));
+        let mut nodes = InMemoryTrieNodes::default();
+        let (root, updates) =
+            StorageRoot::new(&nodes, &state, hashed_address).root_with_updates().unwrap();
+        assert_ne!(root, previous_root);
+        nodes.storage_nodes.insert(hashed_address, BTreeMap::from_iter(updates.updated_nodes));
+
+        let reverts = HashedStorage::from_iter([
+            (slot(1), (U256::from(2), false)),
+            (slot(2), (U256::from(3), false)),
+            (slot(100), (U256::ZERO, false)),
+        ]);
+        assert_eq!(
+            reverted_storage_root(&nodes, &state, hashed_address, reverts),
+            Ok(previous_root)
+        );
+        assert_eq!(
+            reverted_storage_root(&nodes, &state, hashed_address, HashedStorage::default()),
+            Ok(root)
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! [proptest] strategies for generating trie inputs, including the privacy flags of the storage
+//! values.
+
+use alloc::collections::BTreeMap;
+use alloy_primitives::{B256, U256};
+use proptest::{collection::btree_map, prelude::*};
+
+#[cfg(feature = "ethereum")]
+use crate::{cursor::in_memory::InMemoryHashedState, TrieAccount};
+
+/// Returns a strategy for a non-zero storage value flagged as private with the given probability.
+pub fn flagged_value(private_probability: f64) -> impl Strategy<Value = (U256, bool)> {
+    (
+        any::<U256>().prop_map(|value| value.max(U256::from(1))),
+        prop::bool::weighted(private_probability),
+    )
+}
+
+/// Returns a strategy for the sorted hashed storage of an account with up to `max_slots` slots,
+/// each of them flagged as private with the given probability.
+pub fn flagged_storage(
+    max_slots: usize,
+    private_probability: f64,
+) -> impl Strategy<Value = BTreeMap<B256, (U256, bool)>> {
+    btree_map(any::<B256>(), flagged_value(private_probability), 0..=max_slots)
+}
+
+/// Returns a strategy for a hashed state with up to `max_accounts` accounts with up to
+/// `max_slots` storage slots each, flagged as private with the given probability.
+///
+/// The storage roots of the accounts are arbitrary, since they are recomputed from the storage.
+#[cfg(feature = "ethereum")]
+pub fn hashed_state(
+    max_accounts: usize,
+    max_slots: usize,
+    private_probability: f64,
+) -> impl Strategy<Value = InMemoryHashedState> {
+    btree_map(
+        any::<B256>(),
+        (any::<TrieAccount>(), flagged_storage(max_slots, private_probability)),
+        0..=max_accounts,
+    )
+    .prop_map(|accounts| {
+        let mut state = InMemoryHashedState::default();
+        for (hashed_address, (account, storage)) in accounts {
+            state.accounts.insert(hashed_address, account);
+            if !storage.is_empty() {
+                state.storages.insert(hashed_address, storage);
+            }
+        }
+        state
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        #[cfg_attr(miri, ignore = "no proptest")]
+        fn private_probability_bounds(
+            public in flagged_storage(32, 0.0),
+            private in flagged_storage(32, 1.0),
+        ) {
+            prop_assert!(public.values().all(|(value, is_private)| !value.is_zero() && !is_private));
+            prop_assert!(private.values().all(|(value, is_private)| !value.is_zero() && *is_private));
+        }
+
+        #[test]
+        #[cfg(feature = "ethereum")]
+        #[cfg_attr(miri, ignore = "no proptest")]
+        fn state_root_of_hashed_state(state in hashed_state(16, 16, 0.5)) {
+            use crate::{cursor::in_memory::InMemoryTrieNodes, root, StateRoot};
+
+            let expected = root::state_root(state.accounts.iter().map(|(hashed_address, account)| {
+                let storage = state.storages.get(hashed_address).cloned().unwrap_or_default();
+                (*hashed_address, TrieAccount { storage_root: root::storage_root(storage), ..*account })
+            }));
+            let nodes = InMemoryTrieNodes::default();
+            prop_assert_eq!(StateRoot::new(&nodes, &state).root().unwrap(), expected);
+        }
+    }
+}
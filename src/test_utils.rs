@@ -0,0 +1,364 @@
+//! Test utilities for cross-checking trie root computations.
+//!
+//! Provides [`triehash_reference`], a naive recursive root computation that does not share any
+//! logic with the [`HashBuilder`](crate::HashBuilder) besides the node encoding, and loaders for
+//! the `TrieTests` JSON vectors of the [Ethereum tests](https://github.com/ethereum/tests).
+//!
+//! Also provides the deterministic inputs of the benchmarks, so that downstream crates can track
+//! their performance against the same inputs.
+
+use crate::{
+    nodes::{BranchNodeRef, ExtensionNodeRef, LeafNodeRef, RlpNode},
+    Nibbles, TrieMask, EMPTY_ROOT_HASH,
+};
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+use alloy_primitives::{hex, keccak256, B256, U256};
+use alloy_rlp::Encodable;
+use core::fmt;
+use serde_json::Value;
+
+/// Computes the root of the trie with the given public key-value pairs.
+///
+/// See [`triehash_reference_with_privacy`] for more information.
+pub fn triehash_reference<I, K, V>(iter: I) -> B256
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    triehash_reference_with_privacy(iter.into_iter().map(|(key, value)| (key, value, false)))
+}
+
+/// Computes the root of the trie with the given key-value pairs and their privacy flags by
+/// recursively building every node from the full set of leaves.
+///
+/// Later duplicates of a key replace the earlier ones. The values are stored as-is in the leaves.
+///
+/// # Panics
+///
+/// If a key is a prefix of another key, since the branch nodes do not store values.
+pub fn triehash_reference_with_privacy<I, K, V>(iter: I) -> B256
+where
+    I: IntoIterator<Item = (K, V, bool)>,
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    let leaves = iter
+        .into_iter()
+        .map(|(key, value, is_private)| {
+            (Nibbles::unpack(key), (value.as_ref().to_vec(), is_private))
+        })
+        .collect::<BTreeMap<_, _>>();
+    if leaves.is_empty() {
+        return EMPTY_ROOT_HASH;
+    }
+
+    let leaves = leaves
+        .iter()
+        .map(|(key, (value, is_private))| (key, value.as_slice(), *is_private))
+        .collect::<Vec<_>>();
+    keccak256(encode_node(&leaves, 0))
+}
+
+/// RLP-encodes the node rooted at `depth` that contains the given sorted leaves.
+fn encode_node(leaves: &[(&Nibbles, &[u8], bool)], depth: usize) -> Vec<u8> {
+    let mut rlp = Vec::new();
+    if let [(key, value, is_private)] = leaves {
+        LeafNodeRef::new(&key.slice(depth..), value, is_private).encode(&mut rlp);
+        return rlp;
+    }
+
+    let (first, last) = (leaves[0].0, leaves[leaves.len() - 1].0);
+    let shared = first[depth..].iter().zip(&last[depth..]).take_while(|(a, b)| a == b).count();
+    if shared > 0 {
+        let child = RlpNode::from_rlp(&encode_node(leaves, depth + shared));
+        ExtensionNodeRef::new(&first.slice(depth..depth + shared), &child).encode(&mut rlp);
+        return rlp;
+    }
+
+    assert!(
+        leaves.iter().all(|(key, ..)| key.len() > depth),
+        "trie keys must not be prefixes of each other"
+    );
+    let mut stack = Vec::new();
+    let mut state_mask = TrieMask::default();
+    for nibble in 0..16 {
+        let children =
+            leaves.iter().filter(|(key, ..)| key[depth] == nibble).copied().collect::<Vec<_>>();
+        if !children.is_empty() {
+            stack.push(RlpNode::from_rlp(&encode_node(&children, depth + 1)));
+            state_mask.set_bit(nibble);
+        }
+    }
+    BranchNodeRef::new(&stack, state_mask).encode(&mut rlp);
+    rlp
+}
+
+/// Returns `len` distinct hashed keys, the hashes of the big-endian item indexes, in index order.
+pub fn hashed_keys(len: usize) -> Vec<B256> {
+    (0..len as u64).map(|i| keccak256(i.to_be_bytes())).collect()
+}
+
+/// Returns `len` items of `item_len` bytes for ordered trie roots, such as transactions or
+/// receipts.
+pub fn ordered_items(len: usize, item_len: usize) -> Vec<Vec<u8>> {
+    (0..len).map(|i| vec![i as u8; item_len]).collect()
+}
+
+/// Returns `len` storage slots sorted by hashed slot, along with their privacy flags. Every third
+/// slot is private.
+pub fn hashed_storage(len: usize) -> Vec<(B256, (U256, bool))> {
+    let mut storage = hashed_keys(len)
+        .into_iter()
+        .enumerate()
+        .map(|(i, hashed_slot)| (hashed_slot, (U256::from(i + 1), i % 3 == 0)))
+        .collect::<Vec<_>>();
+    storage.sort_unstable_by_key(|(hashed_slot, _)| *hashed_slot);
+    storage
+}
+
+/// Returns `len` accounts sorted by hashed address.
+#[cfg(feature = "ethereum")]
+pub fn hashed_accounts(len: usize) -> Vec<(B256, crate::TrieAccount)> {
+    let mut accounts = hashed_keys(len)
+        .into_iter()
+        .enumerate()
+        .map(|(i, hashed_address)| {
+            let account = crate::TrieAccount {
+                nonce: i as u64,
+                balance: U256::from(i),
+                ..Default::default()
+            };
+            (hashed_address, account)
+        })
+        .collect::<Vec<_>>();
+    accounts.sort_unstable_by_key(|(hashed_address, _)| *hashed_address);
+    accounts
+}
+
+/// A single test case of the `TrieTests` JSON vectors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrieTest {
+    /// The name of the test case.
+    pub name: String,
+    /// The updates applied to the trie in order. A missing value deletes the key.
+    pub input: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    /// The expected root of the trie.
+    pub root: B256,
+}
+
+impl TrieTest {
+    /// Returns the key-value pairs of the trie after applying all the updates.
+    ///
+    /// Empty values delete the key, as in the reference implementations.
+    pub fn final_state(&self) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        let mut state = BTreeMap::new();
+        for (key, value) in &self.input {
+            match value {
+                Some(value) if !value.is_empty() => {
+                    state.insert(key.clone(), value.clone());
+                }
+                _ => {
+                    state.remove(key);
+                }
+            }
+        }
+        state
+    }
+
+    /// Returns the final state with the keys hashed, as used by the secure trie vectors.
+    pub fn secure_final_state(&self) -> BTreeMap<B256, Vec<u8>> {
+        self.final_state().into_iter().map(|(key, value)| (keccak256(key), value)).collect()
+    }
+
+    /// Returns `true` if no key of the final state is a prefix of another key.
+    ///
+    /// Vectors with prefix keys require values in branch nodes, which are not supported.
+    pub fn is_prefix_free(&self) -> bool {
+        let state = self.final_state();
+        let keys = state.keys().collect::<Vec<_>>();
+        keys.windows(2).all(|pair| !pair[1].starts_with(pair[0]))
+    }
+}
+
+/// Error returned when loading the `TrieTests` JSON vectors.
+#[derive(Debug)]
+pub enum TrieTestError {
+    /// The input is not valid JSON.
+    Json(serde_json::Error),
+    /// The test case with the given name is malformed.
+    Malformed(String),
+}
+
+impl fmt::Display for TrieTestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(err) => write!(f, "invalid trie test JSON: {err}"),
+            Self::Malformed(name) => write!(f, "malformed trie test {name}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TrieTestError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Json(err) => Some(err),
+            Self::Malformed(_) => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for TrieTestError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+/// Loads the test cases from a `TrieTests` JSON file, e.g. `trietest.json` or
+/// `trieanyorder.json`.
+///
+/// The updates are given either as a list of `[key, value]` pairs or as a map from keys to values.
+/// Keys and values prefixed with `0x` are hex-decoded, others are taken as raw bytes.
+pub fn load_trie_tests(json: &str) -> Result<Vec<TrieTest>, TrieTestError> {
+    let tests: BTreeMap<String, Value> = serde_json::from_str(json)?;
+    tests.into_iter().map(|(name, test)| parse_trie_test(name, &test)).collect()
+}
+
+fn parse_trie_test(name: String, test: &Value) -> Result<TrieTest, TrieTestError> {
+    let input = match test.get("in") {
+        Some(Value::Array(pairs)) => pairs
+            .iter()
+            .map(|pair| match pair.as_array().map(Vec::as_slice) {
+                Some([key, value]) => Some((parse_bytes(key)?, parse_value(value)?)),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>(),
+        Some(Value::Object(pairs)) => pairs
+            .iter()
+            .map(|(key, value)| Some((decode_str(key)?, parse_value(value)?)))
+            .collect::<Option<Vec<_>>>(),
+        _ => None,
+    };
+    let root = test.get("root").and_then(Value::as_str).and_then(|root| root.parse().ok());
+    match (input, root) {
+        (Some(input), Some(root)) => Ok(TrieTest { name, input, root }),
+        _ => Err(TrieTestError::Malformed(name)),
+    }
+}
+
+fn parse_value(value: &Value) -> Option<Option<Vec<u8>>> {
+    if value.is_null() {
+        Some(None)
+    } else {
+        parse_bytes(value).map(Some)
+    }
+}
+
+fn parse_bytes(value: &Value) -> Option<Vec<u8>> {
+    value.as_str().and_then(decode_str)
+}
+
+fn decode_str(s: &str) -> Option<Vec<u8>> {
+    match s.strip_prefix("0x") {
+        Some(s) => hex::decode(s).ok(),
+        None => Some(s.as_bytes().to_vec()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{triehash_trie_root, HashBuilder};
+    use proptest::{collection::btree_map, prelude::*};
+
+    const TRIE_TESTS: &str = r#"{
+        "branchingTests": {
+            "in": [
+                ["0x04110d816c380812a427968ece99b1c963dfbce6", "something"],
+                ["0x095e7baea6a6c7c4c2dfeb977efac326af552d87", "something"],
+                ["0x0a517d755cebbf66312b30fff713666a9cb917e0", "something"],
+                ["0x04110d816c380812a427968ece99b1c963dfbce6", null],
+                ["0x095e7baea6a6c7c4c2dfeb977efac326af552d87", null],
+                ["0x0a517d755cebbf66312b30fff713666a9cb917e0", ""]
+            ],
+            "root": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+        },
+        "dogs": {
+            "in": { "doe": "reindeer", "dog": "puppy", "dogglesworth": "cat" },
+            "root": "0x8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3"
+        },
+        "hexAnyOrder": {
+            "in": { "0x0c": "hello", "0x0a": "0x0b" },
+            "root": "0x618d98a3b1e9f99f0568f0e73b0d56ac1f480b2958a065e8864bd2dd6b3601f7"
+        }
+    }"#;
+
+    #[test]
+    fn load_trie_test_vectors() {
+        let tests = load_trie_tests(TRIE_TESTS).unwrap();
+        assert_eq!(
+            tests.iter().map(|test| test.name.as_str()).collect::<Vec<_>>(),
+            ["branchingTests", "dogs", "hexAnyOrder"]
+        );
+
+        let branching = &tests[0];
+        assert_eq!(branching.input.len(), 6);
+        assert_eq!(branching.input[3].1, None);
+        assert!(branching.final_state().is_empty());
+
+        assert!(!tests[1].is_prefix_free());
+        assert_eq!(
+            tests[2].final_state(),
+            BTreeMap::from([(vec![0x0a], vec![0x0b]), (vec![0x0c], b"hello".to_vec())])
+        );
+
+        for test in tests.iter().filter(|test| test.is_prefix_free()) {
+            let state = test.final_state();
+            assert_eq!(triehash_reference(&state), test.root, "{}", test.name);
+            assert_eq!(triehash_trie_root(&state), test.root, "{}", test.name);
+        }
+
+        assert!(matches!(
+            load_trie_tests(r#"{ "broken": { "in": [["0x01"]], "root": "0x00" } }"#),
+            Err(TrieTestError::Malformed(name)) if name == "broken"
+        ));
+    }
+
+    #[test]
+    fn bench_inputs() {
+        let storage = hashed_storage(300);
+        assert!(storage.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        let mut hb = HashBuilder::default();
+        for (hashed_slot, (value, is_private)) in &storage {
+            hb.add_leaf(Nibbles::unpack(hashed_slot), &alloy_rlp::encode(value), *is_private);
+        }
+        let expected = triehash_reference_with_privacy(
+            storage
+                .iter()
+                .map(|(key, (value, is_private))| (key, alloy_rlp::encode(value), *is_private)),
+        );
+        assert_eq!(hb.root(), expected);
+        assert_eq!(ordered_items(128, 10).len(), 128);
+    }
+
+    proptest! {
+        #[test]
+        #[cfg_attr(miri, ignore = "no proptest")]
+        fn reference_matches_hash_builder(
+            state in btree_map(any::<B256>(), (any::<Vec<u8>>(), any::<bool>()), 0..64)
+        ) {
+            let public = state.iter().map(|(key, (value, _))| (key, value));
+            prop_assert_eq!(triehash_reference(public.clone()), triehash_trie_root(public));
+
+            let mut hb = HashBuilder::default();
+            for (key, (value, is_private)) in &state {
+                hb.add_leaf(Nibbles::unpack(key), value, *is_private);
+            }
+            let expected = triehash_reference_with_privacy(
+                state.iter().map(|(key, (value, is_private))| (key, value, *is_private)),
+            );
+            prop_assert_eq!(hb.root(), expected);
+        }
+    }
+}
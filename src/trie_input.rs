@@ -0,0 +1,117 @@
+//! Aggregated inputs for computing roots on top of a chain of in-memory blocks.
+
+use crate::{hashed_state::HashedPostState, prefix_set::TriePrefixSetsMut, StateTrieUpdates};
+use core::mem;
+
+/// The trie node updates, the hashed state changes and the prefix sets of a chain of in-memory
+/// blocks, aggregated so that the root of the chain tip can be computed on top of the persisted
+/// trie nodes and hashed state.
+///
+/// The prefix sets contain the keys changed by all aggregated blocks, since the stored trie nodes
+/// of none of those subtries can be reused.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrieInput {
+    /// The aggregated trie node updates.
+    pub nodes: StateTrieUpdates,
+    /// The aggregated hashed state changes.
+    pub state: HashedPostState,
+    /// The prefix sets of the keys changed by the aggregated state.
+    pub prefix_sets: TriePrefixSetsMut,
+}
+
+impl TrieInput {
+    /// Creates a new input from the trie node updates, the hashed state changes and the prefix
+    /// sets.
+    pub const fn new(
+        nodes: StateTrieUpdates,
+        state: HashedPostState,
+        prefix_sets: TriePrefixSetsMut,
+    ) -> Self {
+        Self { nodes, state, prefix_sets }
+    }
+
+    /// Creates an input aggregating the trie node updates and the hashed state changes of the
+    /// blocks, ordered from the oldest to the newest.
+    pub fn from_blocks<I>(blocks: I) -> Self
+    where
+        I: IntoIterator<Item = (StateTrieUpdates, HashedPostState)>,
+    {
+        let mut input = Self::default();
+        for (nodes, state) in blocks {
+            input.extend(nodes, state);
+        }
+        input
+    }
+
+    /// Extends the input with the trie node updates and the hashed state changes of a later
+    /// block, the latter taking precedence.
+    pub fn extend(&mut self, nodes: StateTrieUpdates, state: HashedPostState) {
+        self.prefix_sets.extend(state.construct_prefix_sets());
+        self.nodes.extend(nodes);
+        self.state.extend(state);
+    }
+
+    /// Prepends the trie node updates and the hashed state changes of an earlier block, the
+    /// changes of the input taking precedence.
+    pub fn prepend(&mut self, mut nodes: StateTrieUpdates, mut state: HashedPostState) {
+        self.prefix_sets.extend(state.construct_prefix_sets());
+        nodes.extend(mem::take(&mut self.nodes));
+        self.nodes = nodes;
+        state.extend(mem::take(&mut self.state));
+        self.state = state;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hashed_state::HashedStorage, nibbles::unpack_b256, Nibbles, TrieAccount};
+    use alloy_primitives::{B256, U256};
+
+    fn block(i: u8) -> (StateTrieUpdates, HashedPostState) {
+        let mut nodes = StateTrieUpdates::default();
+        nodes.account_nodes.insert_removed(Nibbles::from_nibbles_unchecked([i % 2]));
+        let state = HashedPostState::default()
+            .with_accounts([
+                (
+                    B256::with_last_byte(i),
+                    Some(TrieAccount { nonce: i.into(), ..Default::default() }),
+                ),
+                (
+                    B256::with_last_byte(0xff),
+                    Some(TrieAccount { nonce: i.into(), ..Default::default() }),
+                ),
+            ])
+            .with_storages([(
+                B256::with_last_byte(0xff),
+                HashedStorage::from_iter([(B256::with_last_byte(i), (U256::from(i), i % 2 == 0))]),
+            )]);
+        (nodes, state)
+    }
+
+    #[test]
+    fn aggregate_blocks() {
+        let input = TrieInput::from_blocks((1..=3).map(block));
+        assert_eq!(input.state.accounts[&B256::with_last_byte(0xff)].unwrap().nonce, 3);
+        assert_eq!(input.state.storages[&B256::with_last_byte(0xff)].storage.len(), 3);
+        let mut account_prefix_set = input.prefix_sets.account_prefix_set.clone().freeze();
+        for i in [1, 2, 3, 0xff] {
+            assert!(account_prefix_set.contains(&unpack_b256(&B256::with_last_byte(i))));
+        }
+
+        // Prepending the earlier blocks in reverse yields the same aggregation.
+        let mut prepended = TrieInput::default();
+        for i in (1..=3).rev() {
+            let (nodes, state) = block(i);
+            prepended.prepend(nodes, state);
+        }
+        assert_eq!(prepended.nodes, input.nodes);
+        assert_eq!(prepended.state, input.state);
+        let mut storage_prefix_set =
+            prepended.prefix_sets.storage_prefix_sets[&B256::with_last_byte(0xff)].clone().freeze();
+        for i in 1..=3 {
+            assert!(storage_prefix_set.contains(&unpack_b256(&B256::with_last_byte(i))));
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Intermediate trie node updates collected during root computation.
+
+use crate::{BranchNodeCompact, HashMap, Nibbles};
+use alloc::vec::Vec;
+use alloy_primitives::{map::HashSet, B256};
+
+/// The aggregation of trie updates.
+///
+/// Contains the branch nodes created or updated during root computation, keyed by their full
+/// path, together with the paths of the branch nodes that should be removed from the database.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrieUpdates {
+    /// Collection of updated intermediate nodes indexed by full path.
+    pub updated_nodes: HashMap<Nibbles, BranchNodeCompact>,
+    /// Collection of removed intermediate nodes indexed by full path.
+    pub removed_nodes: HashSet<Nibbles>,
+}
+
+impl TrieUpdates {
+    /// Returns `true` if the updates are empty.
+    pub fn is_empty(&self) -> bool {
+        self.updated_nodes.is_empty() && self.removed_nodes.is_empty()
+    }
+
+    /// Returns the number of updated and removed nodes.
+    pub fn len(&self) -> usize {
+        self.updated_nodes.len() + self.removed_nodes.len()
+    }
+
+    /// Inserts the updated branch node at the given path, discarding any prior removal of it.
+    pub fn insert_updated(&mut self, path: Nibbles, node: BranchNodeCompact) {
+        self.removed_nodes.remove(&path);
+        self.updated_nodes.insert(path, node);
+    }
+
+    /// Marks the branch node at the given path as removed, discarding any prior update of it.
+    pub fn insert_removed(&mut self, path: Nibbles) {
+        self.updated_nodes.remove(&path);
+        self.removed_nodes.insert(path);
+    }
+
+    /// Extends the updates with another set of updates, the latter taking precedence.
+    pub fn extend(&mut self, other: Self) {
+        for path in other.removed_nodes {
+            self.insert_removed(path);
+        }
+        for (path, node) in other.updated_nodes {
+            self.insert_updated(path, node);
+        }
+    }
+
+    /// Finalizes the updates with the branch nodes collected by the
+    /// [`HashBuilder`](crate::HashBuilder) and the paths of the nodes that were visited but not
+    /// retained in the new trie.
+    ///
+    /// Removed keys that were updated are not considered removed.
+    pub fn finalize(
+        &mut self,
+        updated_nodes: HashMap<Nibbles, BranchNodeCompact>,
+        removed_keys: impl IntoIterator<Item = Nibbles>,
+    ) {
+        self.removed_nodes
+            .extend(removed_keys.into_iter().filter(|path| !updated_nodes.contains_key(path)));
+        for (path, node) in updated_nodes {
+            self.insert_updated(path, node);
+        }
+    }
+
+    /// Returns the updated nodes sorted by path.
+    pub fn updated_nodes_sorted(&self) -> Vec<(Nibbles, BranchNodeCompact)> {
+        let mut nodes =
+            Vec::from_iter(self.updated_nodes.iter().map(|(k, v)| (k.clone(), v.clone())));
+        nodes.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        nodes
+    }
+
+    /// Returns the removed node paths in sorted order.
+    pub fn removed_nodes_sorted(&self) -> Vec<Nibbles> {
+        let mut paths = Vec::from_iter(self.removed_nodes.iter().cloned());
+        paths.sort_unstable();
+        paths
+    }
+}
+
+/// The aggregation of the account trie updates and the storage trie updates of the accounts.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateTrieUpdates {
+    /// The account trie updates.
+    pub account_nodes: TrieUpdates,
+    /// The storage trie updates indexed by hashed address.
+    pub storage_tries: HashMap<B256, TrieUpdates>,
+}
+
+impl StateTrieUpdates {
+    /// Returns `true` if the updates are empty.
+    pub fn is_empty(&self) -> bool {
+        self.account_nodes.is_empty() && self.storage_tries.values().all(TrieUpdates::is_empty)
+    }
+
+    /// Extends the updates with another set of updates, the latter taking precedence.
+    pub fn extend(&mut self, other: Self) {
+        self.account_nodes.extend(other.account_nodes);
+        for (hashed_address, storage_updates) in other.storage_tries {
+            self.storage_tries.entry(hashed_address).or_default().extend(storage_updates);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HashBuilder, TrieMask};
+    use alloy_primitives::hex;
+
+    #[test]
+    fn hash_builder_updates() {
+        let mut hb = HashBuilder::default().with_updates(true);
+        for key in [
+            hex!("1000000000000000000000000000000000000000000000000000000000000000"),
+            hex!("1100000000000000000000000000000000000000000000000000000000000000"),
+            hex!("1110000000000000000000000000000000000000000000000000000000000000"),
+            hex!("1200000000000000000000000000000000000000000000000000000000000000"),
+        ] {
+            hb.add_leaf(Nibbles::unpack(key), &[], false);
+        }
+        hb.root();
+
+        let updates = hb.take_updates();
+        let node = &updates.updated_nodes[&Nibbles::from_nibbles_unchecked([0x1])];
+        assert_eq!(node.state_mask, TrieMask::new(0b0111));
+        assert_eq!(node.hash_mask, TrieMask::new(0b0010));
+        assert!(updates.removed_nodes.is_empty());
+
+        // The hash builder keeps collecting updates after they were taken.
+        assert_eq!(hb.updates_len(), 0);
+        assert!(hb.updated_branch_nodes.is_some());
+    }
+
+    #[test]
+    fn extend_and_finalize() {
+        let path = Nibbles::from_nibbles_unchecked([0x1]);
+        let other_path = Nibbles::from_nibbles_unchecked([0x2]);
+
+        let mut updates = TrieUpdates::default();
+        updates.insert_updated(path.clone(), BranchNodeCompact::default());
+        updates.extend(TrieUpdates {
+            removed_nodes: HashSet::from_iter([path.clone()]),
+            ..Default::default()
+        });
+        assert!(updates.updated_nodes.is_empty());
+        assert_eq!(updates.removed_nodes_sorted(), vec![path.clone()]);
+
+        updates.finalize(
+            HashMap::from_iter([(path.clone(), BranchNodeCompact::default())]),
+            [path.clone(), other_path.clone()],
+        );
+        assert_eq!(updates.updated_nodes_sorted(), vec![(path, BranchNodeCompact::default())]);
+        assert_eq!(updates.removed_nodes_sorted(), vec![other_path]);
+        assert_eq!(updates.len(), 2);
+    }
+}
@@ -0,0 +1,355 @@
+//! Walker over the stored branch nodes of a trie.
+
+use crate::{
+    cursor::{DatabaseError, TrieCursor},
+    nibbles::pack_b256,
+    prefix_set::PrefixSet,
+    BranchNodeCompact, Nibbles,
+};
+use alloc::vec::Vec;
+use alloy_primitives::{map::HashSet, B256};
+use tracing::trace;
+
+/// Walks the stored branch nodes of a trie in key order.
+///
+/// Subtries that are not covered by the prefix set of changed keys and whose hashes are stored
+/// in the parent node, as indicated by its hash mask, can be skipped. Combine it with a hashed
+/// cursor in a [`TrieNodeIter`](crate::TrieNodeIter) to get the elements to feed into the
+/// [`HashBuilder`](crate::HashBuilder).
+#[derive(Debug)]
+pub struct TrieWalker<C> {
+    /// Cursor over the stored branch nodes.
+    cursor: C,
+    /// The stack of the visited nodes, the last one being the current position.
+    stack: Vec<CursorSubNode>,
+    /// Whether the subtrie at the current position can be skipped.
+    can_skip_current_node: bool,
+    /// The changed keys.
+    changes: PrefixSet,
+    /// The paths of the stored nodes that were visited and must be rebuilt, if retained.
+    removed_keys: Option<HashSet<Nibbles>>,
+}
+
+impl<C: TrieCursor> TrieWalker<C> {
+    /// Creates a new walker positioned at the root of the trie.
+    pub fn new(mut cursor: C, changes: PrefixSet) -> Result<Self, DatabaseError> {
+        let root = cursor.seek_exact(Nibbles::default())?;
+        Ok(Self::with_root(cursor, root, changes))
+    }
+
+    /// Advances the walker to the next position.
+    pub fn advance(&mut self) -> Result<(), DatabaseError> {
+        let mut seek = self.start_advance();
+        while let Some(key) = seek {
+            let entry = self.cursor.seek(key)?;
+            seek = self.consume_node(entry);
+        }
+        self.update_skip_node();
+        Ok(())
+    }
+}
+
+impl<C> TrieWalker<C> {
+    /// Creates a new walker positioned at the given stored root node, if any.
+    pub(crate) fn with_root(
+        cursor: C,
+        root: Option<(Nibbles, BranchNodeCompact)>,
+        changes: PrefixSet,
+    ) -> Self {
+        let root = match root {
+            Some((key, node)) => CursorSubNode::new(key, Some(node)),
+            None => CursorSubNode::default(),
+        };
+        Self::from_stack(cursor, vec![root], changes)
+    }
+
+    /// Creates a new walker resuming from the stack of a previous walker, see [TrieWalker::stack].
+    pub fn from_stack(cursor: C, stack: Vec<CursorSubNode>, changes: PrefixSet) -> Self {
+        let mut this =
+            Self { cursor, stack, can_skip_current_node: false, changes, removed_keys: None };
+        this.update_skip_node();
+        this
+    }
+
+    /// Returns the stack of the visited nodes, to resume the walk later with
+    /// [TrieWalker::from_stack].
+    pub fn stack(&self) -> &[CursorSubNode] {
+        &self.stack
+    }
+
+    /// Returns the cursor over the stored branch nodes.
+    #[cfg(all(feature = "async", feature = "ethereum"))]
+    pub(crate) fn cursor_mut(&mut self) -> &mut C {
+        &mut self.cursor
+    }
+
+    /// Retains the paths of the visited stored nodes that must be rebuilt.
+    pub fn with_deletions_retained(mut self, retained: bool) -> Self {
+        if retained {
+            self.removed_keys = Some(HashSet::default());
+        }
+        self
+    }
+
+    /// Takes the paths of the visited stored nodes that must be rebuilt.
+    pub fn take_removed_keys(&mut self) -> HashSet<Nibbles> {
+        self.removed_keys.as_mut().map(core::mem::take).unwrap_or_default()
+    }
+
+    /// Returns the key of the current position.
+    pub fn key(&self) -> Option<&Nibbles> {
+        self.stack.last().map(|node| &node.full_key)
+    }
+
+    /// Returns the stored hash of the subtrie at the current position.
+    pub fn hash(&self) -> Option<B256> {
+        self.stack.last().and_then(CursorSubNode::hash)
+    }
+
+    /// Returns `true` if the children of the current position are stored in the trie.
+    pub fn children_are_in_trie(&self) -> bool {
+        self.stack.last().is_some_and(CursorSubNode::tree_flag)
+    }
+
+    /// Returns `true` if the subtrie at the current position contains private leaves according to
+    /// the stored private mask.
+    pub fn is_private(&self) -> bool {
+        self.stack.last().is_some_and(CursorSubNode::private_flag)
+    }
+
+    /// Returns `true` if the subtrie at the current position can be skipped.
+    pub const fn can_skip_current_node(&self) -> bool {
+        self.can_skip_current_node
+    }
+
+    /// Returns the first hashed key that has not been covered by the walker yet.
+    pub fn next_unprocessed_key(&self) -> Option<B256> {
+        self.key().and_then(|key| {
+            if self.can_skip_current_node {
+                key.increment().map(|inc| pack_b256(&inc))
+            } else {
+                Some(pack_b256(key))
+            }
+        })
+    }
+
+    /// Starts advancing the walker to the next position.
+    ///
+    /// Returns the key to seek the next stored node at, whose result is passed to
+    /// [TrieWalker::consume_node] until no more seeks are needed. The advance is completed with
+    /// [TrieWalker::update_skip_node]. The cursor is left to the caller, so that the same steps
+    /// drive both the synchronous and the async cursors.
+    pub(crate) fn start_advance(&mut self) -> Option<Nibbles> {
+        let nibble = self.stack.last()?.nibble;
+        if !self.can_skip_current_node && self.children_are_in_trie() {
+            match nibble {
+                -1 => self.move_to_next_sibling(true),
+                _ => self.key().cloned(),
+            }
+        } else {
+            self.move_to_next_sibling(false)
+        }
+    }
+
+    /// Pushes the stored node found at or after the current position to the stack.
+    ///
+    /// Returns the key to seek the next stored node at if the walker moved on instead.
+    pub(crate) fn consume_node(
+        &mut self,
+        entry: Option<(Nibbles, BranchNodeCompact)>,
+    ) -> Option<Nibbles> {
+        let Some((key, node)) = entry else {
+            self.stack.clear();
+            return None;
+        };
+
+        // Sync the root position with the first nibble of the retrieved node.
+        if !key.is_empty() && !self.stack.is_empty() {
+            self.stack[0].set_nibble(key[0] as i8);
+        }
+
+        // The retrieved node must be a descendant of the current position, otherwise the tree
+        // mask was incorrect and the walker moves on to the next sibling.
+        if let Some(subnode) = self.stack.last() {
+            if !key.starts_with(&subnode.full_key) {
+                return self.move_to_next_sibling(false);
+            }
+        }
+
+        let subnode = CursorSubNode::new(key.clone(), Some(node));
+        let nibble = subnode.nibble;
+        self.stack.push(subnode);
+        self.update_skip_node();
+
+        // The node is rebuilt unless its subtrie is skipped as a whole.
+        if !self.can_skip_current_node || nibble != -1 {
+            if let Some(keys) = self.removed_keys.as_mut() {
+                keys.insert(key);
+            }
+        }
+
+        None
+    }
+
+    /// Moves to the next sibling with state, popping the exhausted nodes from the stack.
+    ///
+    /// Returns the key to seek the next stored node at if the sibling is not stored in the
+    /// current node, see [TrieWalker::consume_node].
+    fn move_to_next_sibling(&mut self, allow_root_to_child_nibble: bool) -> Option<Nibbles> {
+        let subnode = self.stack.last_mut()?;
+
+        if subnode.nibble >= 0xf || (subnode.nibble < 0 && !allow_root_to_child_nibble) {
+            self.stack.pop();
+            return self.move_to_next_sibling(false);
+        }
+
+        subnode.set_nibble(subnode.nibble + 1);
+
+        if subnode.node.is_none() {
+            return Some(subnode.full_key.clone());
+        }
+
+        loop {
+            if subnode.state_flag() {
+                trace!(target: "trie::walker", key = ?subnode.full_key, "found next sibling with state");
+                return None;
+            }
+            if subnode.nibble == 0xf {
+                trace!(target: "trie::walker", key = ?subnode.key, "checked all siblings, popping the stack");
+                self.stack.pop();
+                return self.move_to_next_sibling(false);
+            }
+            subnode.set_nibble(subnode.nibble + 1);
+        }
+    }
+
+    /// Updates whether the subtrie at the current position can be skipped.
+    pub(crate) fn update_skip_node(&mut self) {
+        self.can_skip_current_node = self
+            .stack
+            .last()
+            .is_some_and(|node| node.hash_flag() && !self.changes.contains(&node.full_key));
+    }
+}
+
+/// A stored branch node on the walker stack along with the child nibble being visited.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CursorSubNode {
+    /// The path of the node.
+    key: Nibbles,
+    /// The visited child nibble, or `-1` if the node itself is visited.
+    pub(crate) nibble: i8,
+    /// The stored node, if any.
+    pub(crate) node: Option<BranchNodeCompact>,
+    /// The path of the node extended with the visited child nibble.
+    pub(crate) full_key: Nibbles,
+}
+
+impl Default for CursorSubNode {
+    fn default() -> Self {
+        Self::new(Nibbles::default(), None)
+    }
+}
+
+impl CursorSubNode {
+    pub(crate) fn new(key: Nibbles, node: Option<BranchNodeCompact>) -> Self {
+        // Nodes without a stored root hash are visited starting from their first child.
+        let nibble = node
+            .as_ref()
+            .filter(|node| node.root_hash.is_none())
+            .and_then(|node| node.state_mask.first_set_bit_index())
+            .map_or(-1, |nibble| nibble as i8);
+        let mut this = Self { full_key: key.clone(), key, nibble: -1, node };
+        this.set_nibble(nibble);
+        this
+    }
+
+    pub(crate) fn set_nibble(&mut self, nibble: i8) {
+        self.nibble = nibble;
+        self.full_key = self.key.clone();
+        if nibble >= 0 {
+            self.full_key.push(nibble as u8);
+        }
+    }
+
+    pub(crate) fn state_flag(&self) -> bool {
+        self.node
+            .as_ref()
+            .map_or(true, |node| self.nibble < 0 || node.state_mask.is_bit_set(self.nibble as u8))
+    }
+
+    pub(crate) fn tree_flag(&self) -> bool {
+        self.node
+            .as_ref()
+            .map_or(true, |node| self.nibble < 0 || node.tree_mask.is_bit_set(self.nibble as u8))
+    }
+
+    pub(crate) fn hash_flag(&self) -> bool {
+        self.node.as_ref().is_some_and(|node| match self.nibble {
+            -1 => node.root_hash.is_some(),
+            nibble => node.hash_mask.is_bit_set(nibble as u8),
+        })
+    }
+
+    pub(crate) fn private_flag(&self) -> bool {
+        self.node.as_ref().is_some_and(|node| match self.nibble {
+            -1 => !node.private_mask.is_empty(),
+            nibble => node.private_mask.is_bit_set(nibble as u8),
+        })
+    }
+
+    pub(crate) fn hash(&self) -> Option<B256> {
+        let node = self.node.as_ref()?;
+        match self.nibble {
+            -1 => node.root_hash,
+            nibble => {
+                node.hash_mask.is_bit_set(nibble as u8).then(|| node.hash_for_nibble(nibble as u8))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cursor::in_memory::InMemoryTrieCursor, prefix_set::PrefixSetMut, HashBuilder};
+    use alloc::collections::BTreeMap;
+    use alloy_primitives::keccak256;
+
+    #[test]
+    fn walk_stored_nodes() {
+        let mut hb = HashBuilder::default().with_updates(true);
+        let mut keys =
+            Vec::from_iter((0..100u64).map(|i| Nibbles::unpack(keccak256(i.to_be_bytes()))));
+        keys.sort_unstable();
+        for key in keys {
+            hb.add_leaf(key, &[0x01], false);
+        }
+        let root = hb.root();
+        let (_, updates) = hb.split();
+        let nodes = BTreeMap::from_iter(updates);
+
+        // The unchanged trie is skipped as a whole.
+        let walker =
+            TrieWalker::new(InMemoryTrieCursor::new(Some(&nodes)), PrefixSet::default()).unwrap();
+        assert_eq!(walker.key(), Some(&Nibbles::default()));
+        assert!(walker.can_skip_current_node());
+        assert_eq!(walker.hash(), Some(root));
+        assert_eq!(walker.next_unprocessed_key(), None);
+
+        // Every stored node is visited in order when all keys changed.
+        let mut walker =
+            TrieWalker::new(InMemoryTrieCursor::new(Some(&nodes)), PrefixSetMut::all().freeze())
+                .unwrap()
+                .with_deletions_retained(true);
+        let mut visited = Vec::new();
+        while let Some(key) = walker.key() {
+            assert!(!walker.can_skip_current_node());
+            visited.push(key.clone());
+            walker.advance().unwrap();
+        }
+        assert!(visited.windows(2).all(|pair| pair[0] < pair[1]));
+        // The root node is rebuilt as well, but is read when creating the walker.
+        assert_eq!(walker.take_removed_keys().len() + 1, nodes.len());
+    }
+}
@@ -0,0 +1,236 @@
+//! Witness generation for stateless execution.
+
+use crate::{
+    cursor::{DatabaseError, HashedCursorFactory, TrieCursorFactory},
+    node_iter::{TrieElement, TrieNodeIter},
+    prefix_set::{PrefixSet, PrefixSetMut},
+    proof::ProofNodes,
+    state_root::StorageRoot,
+    walker::TrieWalker,
+    HashBuilder, HashMap, Nibbles,
+};
+use alloc::vec::Vec;
+use alloy_primitives::{keccak256, map::HashSet, Bytes, B256};
+
+/// Collects the trie node preimages needed to recompute the state root after touching the target
+/// accounts and storage slots, e.g. to re-execute a block statelessly.
+///
+/// The witness contains the RLP encoded nodes on the paths from the state root to the target
+/// accounts and from their storage roots to the target slots, keyed by their hashes. Storage
+/// leaves are kept as encoded, including their privacy flag, so the root recomputed from the
+/// witness is identical.
+///
+/// Removing a key may collapse its parent branch node into the remaining sibling, whose node is
+/// only part of the witness if it is targeted as well.
+#[derive(Clone, Debug)]
+pub struct TrieWitness<T, H> {
+    /// Factory of cursors over the stored trie nodes.
+    trie_cursor_factory: T,
+    /// Factory of cursors over the hashed state.
+    hashed_cursor_factory: H,
+    /// The collected node preimages keyed by their hashes.
+    witness: HashMap<B256, Bytes>,
+}
+
+impl<T, H> TrieWitness<T, H>
+where
+    T: TrieCursorFactory + Clone,
+    H: HashedCursorFactory + Clone,
+{
+    /// Creates a new witness builder. The stored trie nodes must be consistent with the hashed
+    /// state.
+    pub fn new(trie_cursor_factory: T, hashed_cursor_factory: H) -> Self {
+        Self { trie_cursor_factory, hashed_cursor_factory, witness: HashMap::default() }
+    }
+
+    /// Computes the witness for the target hashed accounts and their target hashed slots.
+    ///
+    /// The root node of the storage trie of every target account is included, even if none of
+    /// its slots are targeted.
+    pub fn compute(
+        mut self,
+        targets: HashMap<B256, HashSet<B256>>,
+    ) -> Result<HashMap<B256, Bytes>, DatabaseError> {
+        let account_targets = Vec::from_iter(targets.keys().map(Nibbles::unpack));
+        let walker = TrieWalker::new(
+            self.trie_cursor_factory.account_trie_cursor()?,
+            expand_paths_to(&account_targets),
+        )?;
+        let mut account_node_iter =
+            TrieNodeIter::new(walker, self.hashed_cursor_factory.hashed_account_cursor()?);
+        let mut hash_builder = HashBuilder::default().with_proof_retainer(account_targets);
+        let mut account_rlp = Vec::new();
+
+        while let Some(element) = account_node_iter.try_next()? {
+            match element {
+                TrieElement::Branch { key, hash, children_are_in_trie, is_private } => {
+                    hash_builder.add_branch_with_privacy(
+                        key,
+                        hash,
+                        children_are_in_trie,
+                        is_private,
+                    );
+                }
+                TrieElement::Leaf(hashed_address, mut account) => {
+                    account.storage_root = match targets.get(&hashed_address) {
+                        Some(slots) => self.storage_witness(hashed_address, slots)?,
+                        None => StorageRoot::new(
+                            self.trie_cursor_factory.clone(),
+                            self.hashed_cursor_factory.clone(),
+                            hashed_address,
+                        )
+                        .root()?,
+                    };
+
+                    account_rlp.clear();
+                    account.encode_into(&mut account_rlp);
+                    // account nodes are always public
+                    hash_builder.add_leaf(Nibbles::unpack(hashed_address), &account_rlp, false);
+                }
+            }
+        }
+
+        hash_builder.root();
+        self.extend_witness(hash_builder.take_proof_nodes());
+
+        Ok(self.witness)
+    }
+
+    /// Collects the storage trie nodes on the paths to the target slots and returns the storage
+    /// root.
+    fn storage_witness(
+        &mut self,
+        hashed_address: B256,
+        slots: &HashSet<B256>,
+    ) -> Result<B256, DatabaseError> {
+        let slot_targets = Vec::from_iter(slots.iter().map(Nibbles::unpack));
+        let walker = TrieWalker::new(
+            self.trie_cursor_factory.storage_trie_cursor(hashed_address)?,
+            expand_paths_to(&slot_targets),
+        )?;
+        let mut storage_node_iter = TrieNodeIter::new(
+            walker,
+            self.hashed_cursor_factory.hashed_storage_cursor(hashed_address)?,
+        );
+        let mut hash_builder = HashBuilder::default().with_proof_retainer(slot_targets);
+
+        while let Some(element) = storage_node_iter.try_next()? {
+            match element {
+                TrieElement::Branch { key, hash, children_are_in_trie, is_private } => {
+                    hash_builder.add_branch_with_privacy(
+                        key,
+                        hash,
+                        children_are_in_trie,
+                        is_private,
+                    );
+                }
+                TrieElement::Leaf(hashed_slot, (value, is_private)) => {
+                    hash_builder.add_leaf(
+                        Nibbles::unpack(hashed_slot),
+                        alloy_rlp::encode_fixed_size(&value).as_ref(),
+                        is_private,
+                    );
+                }
+            }
+        }
+
+        let root = hash_builder.root();
+        self.extend_witness(hash_builder.take_proof_nodes());
+
+        Ok(root)
+    }
+
+    fn extend_witness(&mut self, proof_nodes: ProofNodes) {
+        for (_, node) in proof_nodes.into_inner() {
+            self.witness.insert(keccak256(&node), node);
+        }
+    }
+}
+
+/// Returns the prefix set that makes the walker expand the stored nodes on the paths to the
+/// targets. The root node is always expanded, so it is rebuilt and retained.
+pub(crate) fn expand_paths_to(targets: &[Nibbles]) -> PrefixSet {
+    let mut prefix_set = PrefixSetMut::from_iter(targets.iter().cloned());
+    prefix_set.insert(Nibbles::default());
+    prefix_set.freeze()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cursor::in_memory::{InMemoryHashedState, InMemoryTrieNodes},
+        proof::{MultiProof, StorageMultiProof},
+        root::storage_root,
+        state_root::StateRoot,
+        TrieAccount,
+    };
+    use alloc::collections::BTreeMap;
+    use alloy_primitives::U256;
+
+    #[test]
+    fn witness_contains_target_proofs() {
+        let mut state = InMemoryHashedState::default();
+        for i in 0..100u64 {
+            let hashed_address = keccak256(i.to_be_bytes());
+            state.accounts.insert(hashed_address, TrieAccount { nonce: i, ..Default::default() });
+            state.storages.insert(
+                hashed_address,
+                BTreeMap::from_iter((0..20u64).map(|slot| {
+                    (keccak256(slot.to_be_bytes()), (U256::from(i + slot + 1), slot % 2 == 0))
+                })),
+            );
+        }
+
+        let mut nodes = InMemoryTrieNodes::default();
+        let (root, updates) = StateRoot::new(&nodes, &state).root_with_updates().unwrap();
+        nodes.account_nodes.extend(updates.account_nodes.updated_nodes);
+        for (hashed_address, storage_updates) in updates.storage_tries {
+            nodes
+                .storage_nodes
+                .entry(hashed_address)
+                .or_default()
+                .extend(storage_updates.updated_nodes);
+        }
+
+        let target_account = keccak256(7u64.to_be_bytes());
+        let target_slots = [keccak256(2u64.to_be_bytes()), keccak256(3u64.to_be_bytes())];
+        let untouched_account = keccak256(8u64.to_be_bytes());
+        let witness = TrieWitness::new(&nodes, &state)
+            .compute(HashMap::from_iter([
+                (target_account, HashSet::from_iter(target_slots)),
+                (untouched_account, HashSet::default()),
+            ]))
+            .unwrap();
+        assert!(witness.iter().all(|(hash, node)| keccak256(node) == *hash));
+        assert!(witness.contains_key(&root));
+
+        let accounts = Vec::from_iter(state.accounts.iter().map(|(hashed_address, account)| {
+            let storage_root = storage_root(state.storages[hashed_address].clone());
+            (*hashed_address, alloy_rlp::encode(TrieAccount { storage_root, ..*account }))
+        }));
+        let (expected_root, account_proof) =
+            MultiProof::from_sorted_accounts(accounts, [target_account, untouched_account]);
+        assert_eq!(expected_root, root);
+        for (_, node) in account_proof.account_subtree.iter() {
+            assert_eq!(witness.get(&keccak256(node)), Some(node));
+        }
+
+        let storage_proof = StorageMultiProof::from_sorted_leaves(
+            state.storages[&target_account].iter().map(|(hashed_slot, (value, is_private))| {
+                (*hashed_slot, alloy_rlp::encode_fixed_size(value), *is_private)
+            }),
+            target_slots,
+        );
+        for (_, node) in storage_proof.subtree.iter() {
+            assert_eq!(witness.get(&keccak256(node)), Some(node));
+        }
+        let untouched_storage_root = storage_root(state.storages[&untouched_account].clone());
+        assert!(witness.contains_key(&untouched_storage_root));
+
+        // Storage nodes of accounts that are not targeted are left out.
+        let other_storage_root =
+            storage_root(state.storages[&keccak256(9u64.to_be_bytes())].clone());
+        assert!(!witness.contains_key(&other_storage_root));
+    }
+}